@@ -0,0 +1,169 @@
+//! Measures the overhead of erasing keys behind the obj traits, compared with
+//! using the concrete types directly.
+//!
+//! Uses only the standard library, so it runs on stable with
+//! `cargo bench --bench erasure`. Pass a substring to only run the matching
+//! benchmarks, as in `cargo bench --bench erasure -- map`.
+
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hash},
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use object_safe::{
+    collections::DynHashMap,
+    hasher::{hash_one, FxBuildHasher},
+    EqObj, HashObj, Obj, TypeTagged,
+};
+
+trait Key: HashObj + EqObj {}
+impl<T: Hash + Eq + 'static> Key for T {}
+
+const KEYS: usize = 10_000;
+
+/// Runs `f` repeatedly for about half a second, and prints the average time
+/// per call.
+fn bench(filter: &Option<String>, name: &str, mut f: impl FnMut()) {
+    if filter.as_ref().is_some_and(|filter| !name.contains(filter)) {
+        return;
+    }
+    let budget = Duration::from_millis(500);
+    f();
+    let start = Instant::now();
+    let mut iterations = 0u32;
+    while start.elapsed() < budget {
+        f();
+        iterations += 1;
+    }
+    let per_call = start.elapsed() / iterations;
+    println!("{name:<40} {:>12.1} µs", per_call.as_secs_f64() * 1e6);
+}
+
+fn strings() -> Vec<String> {
+    (0..KEYS).map(|i| format!("key-{i}")).collect()
+}
+
+fn boxed_keys() -> Vec<Box<dyn Key>> {
+    strings()
+        .into_iter()
+        .map(|s| Box::new(s) as Box<dyn Key>)
+        .collect()
+}
+
+fn hashing(filter: &Option<String>) {
+    let state = RandomState::new();
+    let strings = strings();
+    let boxed = boxed_keys();
+    let objs: Vec<_> = boxed_keys().into_iter().map(Obj).collect();
+    let tagged: Vec<_> = boxed_keys().into_iter().map(TypeTagged).collect();
+    bench(filter, "hash/direct", || {
+        for s in &strings {
+            black_box(state.hash_one(s));
+        }
+    });
+    bench(filter, "hash/dyn HashObj", || {
+        for key in &boxed {
+            black_box(hash_one(&state, key.as_hash_object()));
+        }
+    });
+    bench(filter, "hash/Obj<Box<dyn Key>>", || {
+        for obj in &objs {
+            black_box(state.hash_one(obj));
+        }
+    });
+    bench(filter, "hash/TypeTagged<Box<dyn Key>>", || {
+        for tagged in &tagged {
+            black_box(state.hash_one(tagged));
+        }
+    });
+}
+
+fn equality(filter: &Option<String>) {
+    // Each key is compared with an equal copy, and with the next key.
+    let (strings, copies) = (strings(), strings());
+    let (boxed, boxed_copies) = (boxed_keys(), boxed_keys());
+    let objs: Vec<_> = boxed_keys().into_iter().map(Obj).collect();
+    let obj_copies: Vec<_> = boxed_keys().into_iter().map(Obj).collect();
+    bench(filter, "eq/direct", || {
+        for i in 1..KEYS {
+            black_box(strings[i] == copies[i]);
+            black_box(strings[i] == copies[i - 1]);
+        }
+    });
+    bench(filter, "eq/dyn EqObj", || {
+        for i in 1..KEYS {
+            black_box(boxed[i].eq_object(boxed_copies[i].as_partial_eq_object()));
+            black_box(boxed[i].eq_object(boxed_copies[i - 1].as_partial_eq_object()));
+        }
+    });
+    bench(filter, "eq/Obj<Box<dyn Key>>", || {
+        for i in 1..KEYS {
+            black_box(objs[i] == obj_copies[i]);
+            black_box(objs[i] == obj_copies[i - 1]);
+        }
+    });
+}
+
+fn maps(filter: &Option<String>) {
+    let strings = strings();
+    bench(filter, "map insert/HashMap<String>", || {
+        let map: HashMap<_, _> = strings.iter().cloned().zip(0..).collect();
+        black_box(map);
+    });
+    bench(filter, "map insert/HashMap<Obj<Box<dyn Key>>>", || {
+        let map: HashMap<_, _> = boxed_keys().into_iter().map(Obj).zip(0..).collect();
+        black_box(map);
+    });
+    bench(filter, "map insert/DynHashMap", || {
+        let map: DynHashMap<dyn Key, _> = boxed_keys().into_iter().zip(0..).collect();
+        black_box(map);
+    });
+
+    let direct: HashMap<_, _> = strings.iter().cloned().zip(0..).collect();
+    let obj: HashMap<_, _> = boxed_keys().into_iter().map(Obj).zip(0..).collect();
+    let probes: Vec<_> = boxed_keys().into_iter().map(Obj).collect();
+    let tagged: HashMap<_, _> = boxed_keys().into_iter().map(TypeTagged).zip(0..).collect();
+    let tagged_probes: Vec<_> = boxed_keys().into_iter().map(TypeTagged).collect();
+    let dyn_map: DynHashMap<dyn Key, _> = boxed_keys().into_iter().zip(0..).collect();
+    let fx_map: DynHashMap<dyn Key, _, FxBuildHasher> = boxed_keys().into_iter().zip(0..).collect();
+    bench(filter, "map lookup/HashMap<String>", || {
+        for s in &strings {
+            black_box(direct.get(s));
+        }
+    });
+    bench(filter, "map lookup/HashMap<Obj<Box<dyn Key>>>", || {
+        for probe in &probes {
+            black_box(obj.get(probe));
+        }
+    });
+    bench(filter, "map lookup/HashMap<TypeTagged<..>>", || {
+        for probe in &tagged_probes {
+            black_box(tagged.get(probe));
+        }
+    });
+    bench(filter, "map lookup/DynHashMap", || {
+        for s in &strings {
+            black_box(dyn_map.get(s));
+        }
+    });
+    bench(filter, "map lookup/DynHashMap get_by", || {
+        for s in &strings {
+            black_box(dyn_map.get_by(s));
+        }
+    });
+    bench(filter, "map lookup/DynHashMap<FxBuildHasher>", || {
+        for s in &strings {
+            black_box(fx_map.get(s));
+        }
+    });
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`, which is not a filter.
+    let filter = std::env::args().skip(1).find(|arg| !arg.starts_with("--"));
+    hashing(&filter);
+    equality(&filter);
+    maps(&filter);
+}
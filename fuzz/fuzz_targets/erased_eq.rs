@@ -0,0 +1,131 @@
+//! Builds values of a few types from the fuzz input, erases them through
+//! several wrapper stacks, and checks that erasure never changes how they
+//! compare or hash.
+//!
+//! Run with `cargo fuzz run erased_eq` from the repository root.
+
+#![no_main]
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash},
+    ops::Deref,
+    rc::Rc,
+};
+
+use libfuzzer_sys::fuzz_target;
+use object_safe::{hasher::hash_one, DynKey, EqObj, HashObj, Obj, PartialEqObj};
+
+trait Key: HashObj + EqObj + PartialEqObj {}
+impl<T: Hash + Eq + 'static> Key for T {}
+
+object_safe::impl_hash!(dyn Key);
+object_safe::impl_eq!(dyn Key);
+object_safe::impl_partial_eq!(dyn Key);
+
+/// A value of one of the fuzzed types, kept concrete so the erased results
+/// can be checked against the real ones.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+enum Value {
+    U8(u8),
+    U32(u32),
+    I64(i64),
+    Bool(bool),
+    Str(String),
+    Pair(u8, u8),
+}
+
+impl Value {
+    fn decode(data: &mut &[u8]) -> Option<Value> {
+        let (&tag, rest) = data.split_first()?;
+        *data = rest;
+        let mut take = |n: usize| -> Option<&[u8]> {
+            let (head, rest) = data.split_at_checked(n)?;
+            *data = rest;
+            Some(head)
+        };
+        Some(match tag % 6 {
+            0 => Value::U8(take(1)?[0]),
+            1 => Value::U32(u32::from_le_bytes(take(4)?.try_into().ok()?)),
+            2 => Value::I64(i64::from_le_bytes(take(8)?.try_into().ok()?)),
+            3 => Value::Bool(take(1)?[0] % 2 == 0),
+            4 => {
+                let len = usize::from(take(1)?[0] % 8);
+                Value::Str(String::from_utf8_lossy(take(len)?).into_owned())
+            }
+            _ => {
+                let pair = take(2)?;
+                Value::Pair(pair[0], pair[1])
+            }
+        })
+    }
+
+    fn erase(&self) -> Box<dyn Key> {
+        match self.clone() {
+            Value::U8(x) => Box::new(x),
+            Value::U32(x) => Box::new(x),
+            Value::I64(x) => Box::new(x),
+            Value::Bool(x) => Box::new(x),
+            Value::Str(x) => Box::new(x),
+            Value::Pair(a, b) => Box::new((a, b)),
+        }
+    }
+
+    fn hash_concrete(&self, state: &RandomState) -> u64 {
+        match self {
+            Value::U8(x) => state.hash_one(x),
+            Value::U32(x) => state.hash_one(x),
+            Value::I64(x) => state.hash_one(x),
+            Value::Bool(x) => state.hash_one(x),
+            Value::Str(x) => state.hash_one(x),
+            Value::Pair(a, b) => state.hash_one((a, b)),
+        }
+    }
+
+    fn to_key(&self) -> DynKey {
+        match self.clone() {
+            Value::U8(x) => DynKey::new(x),
+            Value::U32(x) => DynKey::new(x),
+            Value::I64(x) => DynKey::new(x),
+            Value::Bool(x) => DynKey::new(x),
+            Value::Str(x) => DynKey::new(x),
+            Value::Pair(a, b) => DynKey::new((a, b)),
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut data = data;
+    let mut values = Vec::new();
+    while let Some(value) = Value::decode(&mut data) {
+        values.push(value);
+    }
+    let state = RandomState::new();
+    let boxed: Vec<Box<dyn Key>> = values.iter().map(Value::erase).collect();
+    let objs: Vec<Obj<Rc<dyn Key>>> = values.iter().map(|v| Obj(Rc::from(v.erase()))).collect();
+    let keys: Vec<DynKey> = values.iter().map(Value::to_key).collect();
+
+    for (i, a) in values.iter().enumerate() {
+        let hash = a.hash_concrete(&state);
+        assert_eq!(hash_one(&state, boxed[i].as_hash_object()), hash);
+        assert_eq!(state.hash_one(&objs[i]), hash);
+        assert_eq!(state.hash_one(&keys[i]), hash);
+
+        for (j, b) in values.iter().enumerate() {
+            let expected = a == b;
+            let erased = *boxed[i] == *boxed[j];
+            assert_eq!(erased, expected, "{a:?} == {b:?}");
+            assert_eq!(*boxed[j] == *boxed[i], erased, "symmetry");
+            assert_eq!(objs[i] == objs[j], expected, "Obj<Rc<dyn Key>>");
+            assert_eq!(keys[i] == keys[j], expected, "DynKey");
+            assert_eq!(
+                boxed[i].eq_object(boxed[j].as_partial_eq_object()),
+                expected,
+                "eq_object"
+            );
+            if erased {
+                assert_eq!(hash, b.hash_concrete(&state), "equal values hash alike");
+            }
+        }
+    }
+});
@@ -0,0 +1,127 @@
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use crate::Obj;
+
+/// Wrapper that compares and hashes the object a pointer points to by its
+/// address, instead of its value. Use it with `Rc<dyn MyTrait>`,
+/// `Arc<dyn MyTrait>` or `&dyn MyTrait` when it matters whether two pointers
+/// refer to the same object, rather than to equal ones.
+///
+/// Only the address is compared, not the vtable, so the same object seen
+/// through two different trait objects is still the same object. Zero-sized
+/// values may share an address with other values, so they are not reliably
+/// distinguished.
+///
+/// ```rust ignore
+/// let a: Rc<dyn MyTrait> = Rc::new(1);
+/// let b = ByAddressObj(a.clone());
+/// assert!(b == ByAddressObj(a));
+/// assert!(b != ByAddressObj(Rc::new(1)));
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct ByAddressObj<P>(pub P);
+
+impl<P> ByAddressObj<P> {
+    pub fn new(pointer: P) -> Self {
+        ByAddressObj(pointer)
+    }
+
+    pub fn into_inner(self) -> P {
+        self.0
+    }
+
+    /// Converts to `Obj`, which compares the object by value instead.
+    pub fn into_obj(self) -> Obj<P> {
+        Obj(self.0)
+    }
+}
+
+impl<P: Deref> ByAddressObj<P> {
+    /// The address of the object that the pointer points to.
+    pub fn addr(&self) -> *const () {
+        let target: &P::Target = &self.0;
+        (target as *const P::Target).cast()
+    }
+}
+
+impl<P> From<Obj<P>> for ByAddressObj<P> {
+    fn from(obj: Obj<P>) -> Self {
+        ByAddressObj(obj.0)
+    }
+}
+
+impl<P> Deref for ByAddressObj<P> {
+    type Target = P;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<P: fmt::Debug> fmt::Debug for ByAddressObj<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ByAddressObj").field(&self.0).finish()
+    }
+}
+
+impl<P: Deref> PartialEq for ByAddressObj<P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr() == other.addr()
+    }
+}
+
+impl<P: Deref> Eq for ByAddressObj<P> {}
+
+impl<P: Deref> PartialOrd for ByAddressObj<P> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<P: Deref> Ord for ByAddressObj<P> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.addr().cmp(&other.addr())
+    }
+}
+
+impl<P: Deref> Hash for ByAddressObj<P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr().hash(state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashSet, rc::Rc};
+
+    use crate::*;
+
+    #[test]
+    fn identity() {
+        let a: Rc<dyn PartialEqObj> = Rc::new(1);
+        let b: Rc<dyn PartialEqObj> = Rc::new(1);
+        assert!(*a == *b);
+        assert!(ByAddressObj(a.clone()) == ByAddressObj(a.clone()));
+        assert!(ByAddressObj(a.clone()) != ByAddressObj(b.clone()));
+        let set: HashSet<_> = [&a, &b, &a]
+            .into_iter()
+            .cloned()
+            .map(ByAddressObj)
+            .collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.iter().all(|x| x.clone().into_obj() == Obj(a.clone())));
+    }
+
+    #[test]
+    fn same_object_through_different_traits() {
+        let value = 5u32;
+        let eq: &dyn PartialEqObj = &value;
+        let hash: &dyn HashObj = &value;
+        assert!(ByAddressObj(eq).addr() == ByAddressObj(hash).addr());
+    }
+}
@@ -0,0 +1,77 @@
+use crate::{EqObj, HashObj, OrdObj};
+
+/// Everything a trait object needs to be a key in a `HashMap` or `HashSet`:
+/// `HashObj`, `EqObj`, and through it `PartialEqObj`. This is automatically
+/// implemented for every type that implements those traits, so a custom
+/// trait can extend it instead of listing all three.
+///
+/// ```rust ignore
+/// pub trait MyKey: ObjectSafeKey {}
+/// impl_key!(dyn MyKey);
+/// ```
+pub trait ObjectSafeKey: HashObj + EqObj {}
+
+impl<T: HashObj + EqObj> ObjectSafeKey for T {}
+
+/// `ObjectSafeKey` with `OrdObj`, for objects that are also keys in a
+/// `BTreeMap` or `BTreeSet`. Use `impl_full!` to implement the std traits for
+/// a trait extending it.
+pub trait FullObj: ObjectSafeKey + OrdObj {}
+
+impl<T: ObjectSafeKey + OrdObj> FullObj for T {}
+
+crate::impl_with_auto_traits!(crate::impl_key: dyn ObjectSafeKey);
+crate::impl_with_auto_traits!(crate::impl_full: dyn FullObj);
+
+/// Implements `PartialEq`, `Eq` and `Hash` with one call, for the same types
+/// and with the same syntax as `impl_hash!`, typically a trait object whose
+/// trait extends `ObjectSafeKey`.
+#[macro_export]
+macro_rules! impl_key {
+    ($($input:tt)*) => {
+        $crate::impl_partial_eq! { $($input)* }
+        $crate::impl_eq! { $($input)* }
+        $crate::impl_hash! { $($input)* }
+    };
+}
+
+/// Implements `PartialEq`, `Eq`, `Hash`, `PartialOrd` and `Ord` with one
+/// call, like `impl_key!`, typically for a trait object whose trait extends
+/// `FullObj`.
+#[macro_export]
+macro_rules! impl_full {
+    ($($input:tt)*) => {
+        $crate::impl_key! { $($input)* }
+        $crate::impl_partial_ord! { $($input)* }
+        $crate::impl_ord! { $($input)* }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeSet, HashSet};
+
+    use crate::*;
+
+    trait MyKey: ObjectSafeKey {}
+    impl<T: ObjectSafeKey> MyKey for T {}
+    impl_key!(dyn MyKey);
+
+    trait Sorted: FullObj {}
+    impl<T: FullObj> Sorted for T {}
+    impl_full!(dyn Sorted);
+
+    #[test]
+    fn one_supertrait() {
+        let set: HashSet<Box<dyn MyKey>> = [1, 2, 1].map(|x| Box::new(x) as _).into();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&(Box::new(2) as Box<dyn MyKey>)));
+
+        let set: BTreeSet<Box<dyn Sorted>> = ["b", "a", "b"].map(|x| Box::new(x) as _).into();
+        let first = set.first().unwrap();
+        assert_eq!((**first).as_any().downcast_ref(), Some(&"a"));
+
+        let keys: HashSet<Box<dyn ObjectSafeKey + Send>> = [1, 1].map(|x| Box::new(x) as _).into();
+        assert_eq!(keys.len(), 1);
+    }
+}
@@ -0,0 +1,243 @@
+use alloc::{
+    alloc::{alloc, dealloc, handle_alloc_error},
+    vec::Vec,
+};
+use core::{
+    alloc::Layout,
+    cell::{Cell, RefCell},
+    fmt, mem,
+    ptr::{self, NonNull},
+};
+
+use crate::{KeyObj, Obj};
+
+/// Reference to a value allocated in an `ObjArena`, usually as a trait
+/// object, wrapped in `Obj` to implement the standard traits.
+pub type ArenaObj<'a, X = dyn KeyObj> = Obj<&'a X>;
+
+/// Arena that allocates values of any type next to each other in large
+/// chunks, rather than boxing each of them, and drops them all at once when
+/// the arena is dropped.
+///
+/// The returned references can be converted to trait objects and wrapped in
+/// `Obj`, as an `ArenaObj`, to compare and hash them.
+///
+/// ```rust ignore
+/// let arena = ObjArena::new();
+/// let a: ArenaObj<dyn MyTrait> = Obj(arena.alloc(1u32));
+/// let b: ArenaObj<dyn MyTrait> = Obj(arena.alloc("one"));
+/// assert!(a != b);
+/// ```
+pub struct ObjArena {
+    chunks: RefCell<Vec<Chunk>>,
+    /// Values that need to be dropped, in the order they were allocated.
+    drops: RefCell<Vec<PendingDrop>>,
+    len: Cell<usize>,
+}
+
+/// A block of memory that values are allocated from, front to back.
+struct Chunk {
+    start: NonNull<u8>,
+    layout: Layout,
+    used: usize,
+}
+
+/// A value in the arena that must be dropped with it.
+struct PendingDrop {
+    value: *mut u8,
+    drop: unsafe fn(*mut u8),
+}
+
+/// # Safety
+///
+/// `value` must point to a valid `T`, which must not be used afterwards.
+unsafe fn drop_value<T>(value: *mut u8) {
+    ptr::drop_in_place(value as *mut T)
+}
+
+/// Size of the first chunk. Each chunk after it is twice as large as the
+/// previous one, unless a value needs an even larger one.
+const FIRST_CHUNK: usize = 4096;
+
+/// Minimum alignment of chunks.
+const CHUNK_ALIGN: usize = 16;
+
+impl ObjArena {
+    pub fn new() -> Self {
+        Self {
+            chunks: RefCell::new(Vec::new()),
+            drops: RefCell::new(Vec::new()),
+            len: Cell::new(0),
+        }
+    }
+
+    /// Moves the value into the arena, and returns a reference to it that
+    /// lives as long as the arena.
+    #[allow(clippy::mut_from_ref)] // Every value gets its own memory.
+    pub fn alloc<T: 'static>(&self, value: T) -> &mut T {
+        let ptr = match mem::size_of::<T>() {
+            0 => NonNull::dangling(),
+            _ => self.alloc_layout(Layout::new::<T>()).cast::<T>(),
+        };
+        // SAFETY: `ptr` is valid for writes of a `T`, and is not used for
+        // anything else while the arena lives.
+        unsafe { ptr.as_ptr().write(value) };
+        if mem::needs_drop::<T>() {
+            self.drops.borrow_mut().push(PendingDrop {
+                value: ptr.as_ptr().cast(),
+                drop: drop_value::<T>,
+            });
+        }
+        self.len.set(self.len.get() + 1);
+        // SAFETY: The value was just written, and no other reference to it
+        // exists. It is only dropped when the arena is, which can't happen
+        // while the returned reference borrows the arena. Values are
+        // `'static`, so they can't borrow the arena themselves.
+        unsafe { &mut *ptr.as_ptr() }
+    }
+
+    /// Number of values allocated in the arena.
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Total size of the chunks allocated by the arena, in bytes.
+    pub fn allocated_bytes(&self) -> usize {
+        self.chunks.borrow().iter().map(|c| c.layout.size()).sum()
+    }
+
+    /// Allocates a block with a nonzero size.
+    fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
+        let mut chunks = self.chunks.borrow_mut();
+        if let Some(chunk) = chunks.last_mut() {
+            if let Some(ptr) = chunk.bump(layout) {
+                return ptr;
+            }
+        }
+        let previous = chunks.last().map_or(FIRST_CHUNK / 2, |c| c.layout.size());
+        let size = (previous * 2).max(layout.size());
+        let chunk_layout = Layout::from_size_align(size, layout.align().max(CHUNK_ALIGN))
+            .expect("arena chunk too large");
+        // SAFETY: The layout has a nonzero size.
+        let start = NonNull::new(unsafe { alloc(chunk_layout) })
+            .unwrap_or_else(|| handle_alloc_error(chunk_layout));
+        let mut chunk = Chunk {
+            start,
+            layout: chunk_layout,
+            used: 0,
+        };
+        let ptr = chunk.bump(layout).expect("fresh chunk fits the value");
+        chunks.push(chunk);
+        ptr
+    }
+}
+
+impl Chunk {
+    /// Allocates a block with the layout from the free end of the chunk, if
+    /// it fits.
+    fn bump(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let address = self.start.as_ptr() as usize + self.used;
+        let padding = address.wrapping_neg() & (layout.align() - 1);
+        let offset = self.used.checked_add(padding)?;
+        let end = offset.checked_add(layout.size())?;
+        if end > self.layout.size() {
+            return None;
+        }
+        self.used = end;
+        // SAFETY: `offset` is within the chunk, so the pointer is not null.
+        Some(unsafe { NonNull::new_unchecked(self.start.as_ptr().add(offset)) })
+    }
+}
+
+impl Default for ObjArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ObjArena {
+    fn drop(&mut self) {
+        for entry in self.drops.get_mut().drain(..) {
+            // SAFETY: Each entry was pushed for a value of the type `drop` was
+            // created for, which is valid until now and never used again.
+            unsafe { (entry.drop)(entry.value) };
+        }
+        for chunk in self.chunks.get_mut().drain(..) {
+            // SAFETY: The chunk was allocated with this layout, and no value
+            // in it is used after this.
+            unsafe { dealloc(chunk.start.as_ptr(), chunk.layout) };
+        }
+    }
+}
+
+impl fmt::Debug for ObjArena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObjArena")
+            .field("len", &self.len())
+            .field("allocated_bytes", &self.allocated_bytes())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::{BTreeSet, HashSet},
+        rc::Rc,
+    };
+
+    use std::hash::Hash;
+
+    use super::*;
+    use crate::{HashObj, OrdObj};
+
+    trait Key: HashObj + OrdObj {}
+    impl<T: Hash + Ord + 'static> Key for T {}
+
+    #[test]
+    fn allocates_and_compares() {
+        let arena = ObjArena::new();
+        let mut set: HashSet<ArenaObj<dyn Key>> = HashSet::new();
+        for i in 0..10_000u32 {
+            set.insert(Obj(arena.alloc(i % 100)));
+            set.insert(Obj(arena.alloc(String::from("a"))));
+        }
+        assert_eq!(set.len(), 101);
+        assert_eq!(arena.len(), 20_000);
+        assert!(set.contains(&Obj(&7u32 as &dyn Key)));
+        let sorted: BTreeSet<_> = set.iter().copied().collect();
+        let ints = sorted
+            .iter()
+            .filter_map(|k| k.as_any().downcast_ref::<u32>());
+        assert!(ints.copied().eq(0..100));
+
+        let aligned = arena.alloc(Aligned(3));
+        assert_eq!(aligned as *const Aligned as usize % 64, 0);
+        assert_eq!(aligned.0, 3);
+        arena.alloc(());
+        assert_eq!(arena.len(), 20_002);
+    }
+
+    #[repr(align(64))]
+    struct Aligned(u8);
+
+    #[test]
+    fn drops_values() {
+        let shared = Rc::new(());
+        {
+            let arena = ObjArena::new();
+            for _ in 0..1000 {
+                arena.alloc(Rc::clone(&shared));
+            }
+            let value = arena.alloc(vec![1, 2, 3]);
+            value.push(4);
+            assert_eq!(value.len(), 4);
+            assert_eq!(Rc::strong_count(&shared), 1001);
+        }
+        assert_eq!(Rc::strong_count(&shared), 1);
+    }
+}
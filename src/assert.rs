@@ -0,0 +1,169 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+
+/// Asserts that two trait objects are equal, comparing them with
+/// `PartialEqObj`.
+///
+/// Each argument is dereferenced once, so it may be a reference to a trait
+/// object, like `&dyn MyTrait`, or a pointer to one, like `Box<dyn MyTrait>`.
+/// On failure, the panic message includes the concrete type of each object,
+/// the reason they differ, and their `Debug` output, if the trait object
+/// implements `Debug`.
+///
+/// ```rust ignore
+/// let a: Box<dyn MyTrait> = Box::new(1);
+/// let b: Box<dyn MyTrait> = Box::new(1u64);
+/// assert_dyn_eq!(a, b, "comparing {}", "numbers");
+/// // panics with:
+/// // assertion `left == right` failed: comparing numbers
+/// // objects have different types: i32 and u64
+/// //   left: 1 (i32)
+/// //  right: 1 (u64)
+/// ```
+#[macro_export]
+macro_rules! assert_dyn_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert_dyn_eq!($left, $right, "")
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&*$left, &*$right) {
+            (left, right) => {
+                if let Err(reason) = $crate::ExplainEqObj::eq_object_explain(
+                    left,
+                    $crate::PartialEqObj::as_partial_eq_object(right),
+                ) {
+                    $crate::__private::assert_failed(
+                        "==",
+                        &$crate::__private::format!($($arg)+),
+                        &reason,
+                        $crate::__describe!(left),
+                        $crate::__describe!(right),
+                    );
+                }
+            }
+        }
+    };
+}
+
+/// Asserts that two trait objects are not equal, comparing them with
+/// `PartialEqObj`. See `assert_dyn_eq` for details.
+#[macro_export]
+macro_rules! assert_dyn_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::assert_dyn_ne!($left, $right, "")
+    };
+    ($left:expr, $right:expr, $($arg:tt)+) => {
+        match (&*$left, &*$right) {
+            (left, right) => {
+                if $crate::PartialEqObj::eq_object(
+                    left,
+                    $crate::PartialEqObj::as_partial_eq_object(right),
+                ) {
+                    $crate::__private::assert_failed(
+                        "!=",
+                        &$crate::__private::format!($($arg)+),
+                        &"objects are equal",
+                        $crate::__describe!(left),
+                        $crate::__describe!(right),
+                    );
+                }
+            }
+        }
+    };
+}
+
+/// Formats an object with `Debug` if its static type implements `Debug`, and
+/// includes its concrete type name either way.
+///
+/// This uses autoref-based dispatch, so the `Debug` implementation is selected
+/// at the call site, where the static type is known.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __describe {
+    ($object:expr) => {{
+        #[allow(unused_imports)]
+        use $crate::__private::{DescribeDebug as _, DescribeFallback as _};
+        (
+            (&$crate::__private::Describe($object)).describe(),
+            $crate::AsAny::type_name($object),
+        )
+    }};
+}
+pub struct Describe<'a, T: ?Sized>(pub &'a T);
+
+pub trait DescribeDebug {
+    fn describe(&self) -> String;
+}
+
+impl<T: core::fmt::Debug + ?Sized> DescribeDebug for Describe<'_, T> {
+    fn describe(&self) -> String {
+        format!("{:?}", self.0)
+    }
+}
+
+pub trait DescribeFallback {
+    fn describe(&self) -> String;
+}
+
+impl<T: ?Sized> DescribeFallback for &Describe<'_, T> {
+    fn describe(&self) -> String {
+        "<no Debug>".to_string()
+    }
+}
+
+#[track_caller]
+pub fn assert_failed(
+    op: &str,
+    message: &str,
+    reason: &dyn core::fmt::Display,
+    (left, left_type): (String, &str),
+    (right, right_type): (String, &str),
+) -> ! {
+    let separator = if message.is_empty() { "" } else { ": " };
+    panic!(
+        "assertion `left {op} right` failed{separator}{message}\n\
+         {reason}\n  \
+         left: {left} ({left_type})\n \
+         right: {right} ({right_type})"
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    trait Value: PartialEqObj + std::fmt::Debug {}
+    impl<T: PartialEq + std::fmt::Debug + 'static> Value for T {}
+
+    #[test]
+    fn assert_dyn_eq_passes() {
+        let a: Box<dyn Value> = Box::new(1);
+        let b: &dyn Value = &1;
+        assert_dyn_eq!(a, b);
+        assert_dyn_ne!(a, &2 as &dyn Value, "with {}", "message");
+    }
+
+    #[test]
+    #[should_panic(expected = "objects have different types: i32 and u64\n  \
+                               left: 1 (i32)\n right: 1 (u64)")]
+    fn assert_dyn_eq_reports_types() {
+        let a: Box<dyn Value> = Box::new(1);
+        let b: Box<dyn Value> = Box::new(1u64);
+        assert_dyn_eq!(a, b);
+    }
+
+    #[test]
+    #[should_panic(expected = "failed: context\nobjects have different values\n  \
+                               left: <no Debug> (i32)\n right: <no Debug> (i32)")]
+    fn assert_dyn_eq_without_debug() {
+        assert_dyn_eq!(&1 as &dyn PartialEqObj, &2 as &dyn PartialEqObj, "context");
+    }
+
+    #[test]
+    #[should_panic(expected = "assertion `left != right` failed")]
+    fn assert_dyn_ne_fails() {
+        assert_dyn_ne!(&1 as &dyn Value, &1 as &dyn Value);
+    }
+}
@@ -0,0 +1,184 @@
+use core::{
+    any::Any,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use crate::AsAny;
+
+macro_rules! key_adapter {
+    ($($(#[$attr:meta])* $Name:ident),*) => {$(
+        $(#[$attr])*
+        #[derive(Clone, Copy)]
+        pub struct $Name<T, F> {
+            inner: T,
+            key: F,
+        }
+
+        impl<T, F> $Name<T, F> {
+            pub fn new(inner: T, key: F) -> Self {
+                Self { inner, key }
+            }
+
+            pub fn into_inner(self) -> T {
+                self.inner
+            }
+        }
+
+        impl<T, F, K, X> $Name<T, F>
+        where
+            T: Deref<Target = X>,
+            X: AsAny + ?Sized,
+            F: Fn(&dyn Any) -> K,
+        {
+            /// The key that this value is compared by.
+            pub fn key(&self) -> K {
+                let target: &X = &self.inner;
+                (self.key)(target.as_any())
+            }
+        }
+
+        impl<T, F> Deref for $Name<T, F> {
+            type Target = T;
+
+            fn deref(&self) -> &Self::Target {
+                &self.inner
+            }
+        }
+
+        impl<T: fmt::Debug, F> fmt::Debug for $Name<T, F> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_tuple(stringify!($Name)).field(&self.inner).finish()
+            }
+        }
+
+        impl<T, F, K, X> PartialEq for $Name<T, F>
+        where
+            T: Deref<Target = X>,
+            X: AsAny + ?Sized,
+            F: Fn(&dyn Any) -> K,
+            K: PartialEq,
+        {
+            fn eq(&self, other: &Self) -> bool {
+                self.key() == other.key()
+            }
+        }
+
+        impl<T, F, K, X> Eq for $Name<T, F>
+        where
+            T: Deref<Target = X>,
+            X: AsAny + ?Sized,
+            F: Fn(&dyn Any) -> K,
+            K: Eq,
+        {
+        }
+    )*};
+}
+
+key_adapter! {
+    /// Adapter that implements `PartialEq` and `Eq`, and therefore
+    /// `PartialEqObj` and `EqObj`, by comparing a key extracted from the
+    /// erased object, rather than using the object's own `PartialEq`.
+    ///
+    /// ```rust ignore
+    /// let by_id = EqBy::new(obj, |any: &dyn Any| any.downcast_ref::<Foo>().map(|f| f.id));
+    /// ```
+    EqBy,
+
+    /// Like `EqBy`, but also implements `Hash`, and therefore `HashObj`, by
+    /// hashing the extracted key, so it can be used in hashed collections.
+    HashBy,
+
+    /// Like `EqBy`, but also implements `PartialOrd` and `Ord`, and therefore
+    /// `PartialOrdObj` and `OrdObj`, by comparing the extracted key, so it can
+    /// be used in ordered collections.
+    OrdBy
+}
+
+impl<T, F, K, X> Hash for HashBy<T, F>
+where
+    T: Deref<Target = X>,
+    X: AsAny + ?Sized,
+    F: Fn(&dyn Any) -> K,
+    K: Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl<T, F, K, X> PartialOrd for OrdBy<T, F>
+where
+    T: Deref<Target = X>,
+    X: AsAny + ?Sized,
+    F: Fn(&dyn Any) -> K,
+    K: Ord,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, F, K, X> Ord for OrdBy<T, F>
+where
+    T: Deref<Target = X>,
+    X: AsAny + ?Sized,
+    F: Fn(&dyn Any) -> K,
+    K: Ord,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeSet, HashSet};
+
+    use crate::*;
+
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    struct Doc {
+        id: u32,
+        revision: u32,
+    }
+
+    type ById = fn(&dyn Any) -> Option<u32>;
+
+    fn by_id(any: &dyn Any) -> Option<u32> {
+        any.downcast_ref::<Doc>().map(|doc| doc.id)
+    }
+
+    fn doc(id: u32, revision: u32) -> Box<dyn EqObj> {
+        Box::new(Doc { id, revision })
+    }
+
+    #[test]
+    fn eq_by_key() {
+        let a = EqBy::new(doc(1, 1), by_id as ById);
+        assert!(a == EqBy::new(doc(1, 2), by_id as ById));
+        assert!(a != EqBy::new(doc(2, 1), by_id as ById));
+        let erased: Box<dyn EqObj> = Box::new(a);
+        assert!(*erased == *(Box::new(EqBy::new(doc(1, 3), by_id as ById)) as Box<dyn EqObj>));
+    }
+
+    #[test]
+    fn hash_and_ord_by_key() {
+        let set: HashSet<_> = [doc(1, 1), doc(1, 2), doc(2, 1)]
+            .into_iter()
+            .map(|d| HashBy::new(d, by_id as ById))
+            .collect();
+        assert_eq!(set.len(), 2);
+
+        let set: BTreeSet<_> = [doc(2, 1), doc(1, 2), doc(1, 1)]
+            .into_iter()
+            .map(|d| OrdBy::new(d, by_id as ById))
+            .collect();
+        assert_eq!(
+            set.iter().map(OrdBy::key).collect::<Vec<_>>(),
+            [Some(1), Some(2)]
+        );
+    }
+}
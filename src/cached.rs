@@ -0,0 +1,188 @@
+use core::{
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
+use std::sync::OnceLock;
+
+use crate::{EqObj, HashObj, ObjRefMut, PartialEqObj, Refresh};
+
+/// Wrapper that hashes the object it points to once, with `hash_object_u64`,
+/// and then feeds the hasher only the cached 64-bit hash. The cache is cleared
+/// whenever the wrapper is mutably dereferenced, so mutating the object
+/// through the wrapper never leaves a stale hash behind.
+///
+/// This saves rehashing a large key every time it is inserted or looked up,
+/// at the cost of hashing differently from the bare object. Only compare
+/// `CachedHashObj` keys with other `CachedHashObj` keys in hashed collections.
+///
+/// ```rust ignore
+/// let key = CachedHashObj::new(Box::new(large_document) as Box<dyn Key>);
+/// for map in &mut shards {
+///     map.get(&key); // only hashed the first time
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct CachedHashObj<T> {
+    inner: T,
+    hash: OnceLock<u64>,
+}
+
+impl<T> CachedHashObj<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            hash: OnceLock::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Returns the cached hash, or None if it has not been computed since the
+    /// wrapper was created or last mutably dereferenced.
+    pub fn cached_hash(&self) -> Option<u64> {
+        self.hash.get().copied()
+    }
+
+    /// Gives mutable access to the pointer, and clears the cache when the
+    /// guard is dropped, like `DerefMut` does when it is called.
+    pub fn get_mut(&mut self) -> ObjRefMut<'_, Self> {
+        ObjRefMut::new(self)
+    }
+}
+
+/// Clears the cache.
+impl<T> Refresh for CachedHashObj<T> {
+    type Target = T;
+
+    fn target(&self) -> &T {
+        &self.inner
+    }
+
+    fn target_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    fn refresh(&mut self) {
+        self.hash.take();
+    }
+}
+
+impl<T, X> CachedHashObj<T>
+where
+    T: Deref<Target = X>,
+    X: HashObj + ?Sized,
+{
+    /// Returns the hash of the object, computing it if it is not cached.
+    pub fn hash_u64(&self) -> u64 {
+        *self.hash.get_or_init(|| self.inner.hash_object_u64())
+    }
+}
+
+impl<T: Clone> Clone for CachedHashObj<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            hash: self.hash.clone(),
+        }
+    }
+}
+
+impl<T> Deref for CachedHashObj<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for CachedHashObj<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.hash.take();
+        &mut self.inner
+    }
+}
+
+impl<T, X> Hash for CachedHashObj<T>
+where
+    T: Deref<Target = X>,
+    X: HashObj + ?Sized,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash_u64());
+    }
+}
+
+impl<T, X> PartialEq for CachedHashObj<T>
+where
+    T: Deref<Target = X>,
+    X: PartialEqObj + ?Sized,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b): (&X, &X) = (&self.inner, &other.inner);
+        a.eq_object(b.as_partial_eq_object())
+    }
+}
+
+impl<T, X> Eq for CachedHashObj<T>
+where
+    T: Deref<Target = X>,
+    X: EqObj + ?Sized,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, collections::HashSet};
+
+    use super::*;
+
+    thread_local! {
+        static CALLS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn calls() -> usize {
+        CALLS.with(Cell::take)
+    }
+
+    #[derive(PartialEq, Eq)]
+    struct Counted(u32);
+
+    impl Hash for Counted {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            CALLS.with(|calls| calls.set(calls.get() + 1));
+            self.0.hash(state);
+        }
+    }
+
+    #[test]
+    // The lazily initialized cache trips the lint, but it is only ever set to
+    // the hash of the current object.
+    #[allow(clippy::mutable_key_type)]
+    fn hashes_once() {
+        let key = CachedHashObj::new(Box::new(Counted(1)));
+        assert_eq!(key.cached_hash(), None);
+        let mut set = HashSet::new();
+        set.insert(CachedHashObj::new(Box::new(Counted(1))));
+        calls();
+        for _ in 0..3 {
+            assert!(set.contains(&key));
+        }
+        assert_eq!(calls(), 1);
+        assert_eq!(key.cached_hash(), Some(Counted(1).hash_object_u64()));
+    }
+
+    #[test]
+    fn mutation_invalidates() {
+        let mut key = CachedHashObj::new(Box::new(Counted(1)));
+        let before = key.hash_u64();
+        key.0 = 2;
+        assert_eq!(key.cached_hash(), None);
+        assert_ne!(key.hash_u64(), before);
+        assert_eq!(key.hash_u64(), Counted(2).hash_object_u64());
+        assert_eq!(calls(), 3);
+        key.get_mut().0 = 3;
+        assert_eq!(key.cached_hash(), None);
+    }
+}
@@ -0,0 +1,200 @@
+//! `CloneObj`, the object-safe version of `Clone`, and `impl_clone!`.
+
+use alloc::{
+    alloc::{alloc, handle_alloc_error},
+    boxed::Box,
+    rc::Rc,
+    sync::Arc,
+};
+use core::{alloc::Layout, ptr};
+
+use crate::impl_with_auto_traits;
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl<T: Clone> Sealed for T {}
+}
+
+/// Object-safe version of `Clone`
+///
+/// This is implemented for every `'static` type that implements `Clone`, and
+/// can't be implemented otherwise, since `clone_boxed` relies on it. Use
+/// `impl_clone!` to implement `Clone` for boxes of a trait extending it:
+///
+/// ```rust ignore
+/// pub trait Shape: CloneObj + HashObj {}
+/// impl_clone!(dyn Shape);
+///
+/// let shapes: Vec<Box<dyn Shape>> = vec![Box::new(Circle(1.0))];
+/// let copies = shapes.clone();
+/// ```
+///
+/// `Obj` derives `Clone`, so `Obj<Box<dyn Shape>>` is `Clone` as soon as the
+/// box is, and structs holding such fields can derive it too. Shared pointers
+/// such as `Rc<dyn Shape>` are already `Clone`, but share the object: use
+/// `clone_rc` and `clone_arc` to clone the object itself.
+pub trait CloneObj: sealed::Sealed {
+    fn clone_box(&self) -> Box<dyn CloneObj>;
+
+    /// Writes a clone of the value to `dst`.
+    ///
+    /// # Safety
+    ///
+    /// `dst` must be valid for writes of a value with the layout of `self`.
+    #[doc(hidden)]
+    unsafe fn clone_to_uninit(&self, dst: *mut u8);
+}
+
+impl<T: Clone + 'static> CloneObj for T {
+    fn clone_box(&self) -> Box<dyn CloneObj> {
+        Box::new(self.clone())
+    }
+
+    unsafe fn clone_to_uninit(&self, dst: *mut u8) {
+        // SAFETY: The caller guarantees that `dst` is valid for a `T`.
+        unsafe { dst.cast::<T>().write(self.clone()) };
+    }
+}
+
+/// Clones an object into a new box of the same type, typically a trait
+/// object such as `dyn MyTrait` where `MyTrait` extends `CloneObj`. This is
+/// the `Clone` impl generated by `impl_clone!`.
+pub fn clone_boxed<X: CloneObj + ?Sized>(value: &X) -> Box<X> {
+    let layout = Layout::for_value(value);
+    let data = if layout.size() == 0 {
+        // SAFETY: The alignment is a power of two, so it is not zero.
+        unsafe { ptr::NonNull::new_unchecked(layout.align() as *mut u8) }.as_ptr()
+    } else {
+        // SAFETY: The layout has a nonzero size.
+        let data = unsafe { alloc(layout) };
+        if data.is_null() {
+            handle_alloc_error(layout);
+        }
+        data
+    };
+    // SAFETY: `data` was allocated for the layout of the value, and a value of
+    // the same concrete type is written to it, since only `T: Clone` can
+    // implement `CloneObj`.
+    unsafe { value.clone_to_uninit(data) };
+    let mut clone = value as *const X as *mut X;
+    // SAFETY: This replaces the address of the pointer, which is its first
+    // field, and keeps its metadata, which is valid for the clone since it
+    // has the same concrete type. The clone was allocated with the global
+    // allocator and the layout of its type, so the box can own it.
+    unsafe {
+        ptr::addr_of_mut!(clone).cast::<*mut u8>().write(data);
+        Box::from_raw(clone)
+    }
+}
+
+/// Clones an object into a new `Rc`, rather than sharing it like
+/// `Rc::clone`. The clone is moved from a box, so this allocates twice.
+pub fn clone_rc<X: CloneObj + ?Sized>(value: &X) -> Rc<X> {
+    Rc::from(clone_boxed(value))
+}
+
+/// Clones an object into a new `Arc`, like `clone_rc`.
+pub fn clone_arc<X: CloneObj + ?Sized>(value: &X) -> Arc<X> {
+    Arc::from(clone_boxed(value))
+}
+
+impl_with_auto_traits!(crate::impl_clone: dyn CloneObj);
+
+/// Implements `Clone` for boxes of trait objects whose trait extends
+/// `CloneObj`, such as `Box<dyn MyTrait>`, with `clone_boxed`. List the
+/// objects with `impl_with_auto_traits!` to cover `Box<dyn MyTrait + Send>`
+/// and so on too.
+///
+/// ```rust ignore
+/// impl_clone!(dyn MyTrait, dyn AnotherTrait);
+/// ```
+#[macro_export]
+macro_rules! impl_clone {
+    ($($Type:ty),*$(,)?) => {$(
+        impl Clone for $crate::__private::Box<$Type> {
+            fn clone(&self) -> Self {
+                $crate::clone_boxed(&**self)
+            }
+        }
+    )*};
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::*;
+
+    trait Shape: CloneObj + PartialEqObj {
+        fn area(&self) -> u32;
+    }
+
+    impl_clone!(dyn Shape);
+    impl_partial_eq!(dyn Shape);
+
+    #[derive(Clone, PartialEq)]
+    struct Square(u32);
+
+    impl Shape for Square {
+        fn area(&self) -> u32 {
+            self.0 * self.0
+        }
+    }
+
+    #[derive(Clone, PartialEq)]
+    struct Point;
+
+    impl Shape for Point {
+        fn area(&self) -> u32 {
+            0
+        }
+    }
+
+    #[test]
+    fn clone_boxes() {
+        let shapes: Vec<Box<dyn Shape>> = vec![Box::new(Square(2)), Box::new(Point)];
+        let copies = shapes.clone();
+        assert!(shapes == copies);
+        assert_eq!(copies[0].area(), 4);
+        assert!(!ptr::addr_eq(&*shapes[0], &*copies[0]));
+
+        let wrapped = Obj(copies.into_iter().next().unwrap());
+        assert!(wrapped.clone() == wrapped);
+
+        let erased: Box<dyn CloneObj + Send> = Box::new(String::from("a"));
+        let copy = erased.clone();
+        drop(erased);
+        let copy: &dyn CloneObj = &*copy;
+        drop((*copy).clone_box());
+
+        let shared = clone_rc(&*shapes[0]);
+        assert_eq!(shared.area(), 4);
+        assert_eq!(clone_arc(&*shapes[1]).area(), 0);
+    }
+
+    #[test]
+    fn clones_drop_once() {
+        thread_local! {
+            static DROPS: Cell<usize> = const { Cell::new(0) };
+        }
+
+        #[derive(Clone)]
+        struct Counted(#[allow(dead_code)] Rc<()>);
+
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPS.with(|drops| drops.set(drops.get() + 1));
+            }
+        }
+
+        let shared = Rc::new(());
+        let original: Box<dyn CloneObj> = Box::new(Counted(shared.clone()));
+        let copy = original.clone();
+        assert_eq!(Rc::strong_count(&shared), 3);
+        drop((original, copy));
+        assert_eq!(Rc::strong_count(&shared), 1);
+        assert_eq!(DROPS.with(Cell::get), 2);
+    }
+}
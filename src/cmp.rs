@@ -0,0 +1,659 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::{cmp::Ordering, hash::Hasher, ops::Deref};
+
+#[cfg(feature = "alloc")]
+use crate::Obj;
+use crate::{Error, HashObj, OrdObj, PartialEqObj, PartialOrdObj, Result};
+
+/// Compares two objects that may be behind different trait objects, such as
+/// `&dyn Render` and `&dyn Persist`, where both traits extend `PartialEqObj`.
+/// They are equal if they have the same concrete type and equal values, or if
+/// a comparator registered in the [`registry`](crate::registry) says they are.
+/// Either way, `obj_eq(a, b) == obj_eq(b, a)`.
+///
+/// Unlike `==` on trait objects, which never consults the registry, this is
+/// not consistent with the order or the hash of the objects.
+pub fn obj_eq<A, B>(a: &A, b: &B) -> bool
+where
+    A: PartialEqObj + ?Sized,
+    B: PartialEqObj + ?Sized,
+{
+    dyn_eq(a.as_partial_eq_object(), b.as_partial_eq_object()).unwrap_or(false)
+}
+
+/// Compares two erased values, returning None if they have different concrete
+/// types and no comparator is registered for them in the
+/// [`registry`](crate::registry), rather than false like `obj_eq`.
+pub fn dyn_eq(a: &dyn PartialEqObj, b: &dyn PartialEqObj) -> Option<bool> {
+    if a.as_any().type_id() == b.as_any().type_id() {
+        Some(a.eq_object(b))
+    } else {
+        crate::registry::cross_eq(a.as_any(), b.as_any())
+    }
+}
+
+/// Orders two erased values, returning None if they have different concrete
+/// types, rather than ordering them by type like `cmp_object`.
+pub fn dyn_cmp(a: &dyn OrdObj, b: &dyn OrdObj) -> Option<Ordering> {
+    (a.as_any().type_id() == b.as_any().type_id()).then(|| a.cmp_object(b))
+}
+
+/// Hashes an erased value with `DefaultHasher`, like `hash_object_u64`.
+#[cfg(feature = "std")]
+pub fn dyn_hash(a: &dyn HashObj) -> u64 {
+    a.hash_object_u64()
+}
+
+/// Returns true if the object is a `T` equal to `probe`, by downcasting it
+/// rather than erasing the probe, for hot paths that check a key against a
+/// known value.
+///
+/// ```rust ignore
+/// if matches_concrete(&*key, &"admin") { ... }
+/// ```
+///
+/// Unlike `obj_eq`, this never consults the [`registry`](crate::registry), so
+/// objects of other types never match.
+#[inline]
+pub fn matches_concrete<T: PartialEq + 'static>(obj: &dyn PartialEqObj, probe: &T) -> bool {
+    obj.as_any().downcast_ref::<T>() == Some(probe)
+}
+
+/// Hashes a concrete value exactly like `hash_object_u64` hashes an object
+/// holding it, to compare with the stored hash of an object without erasing
+/// the value.
+#[cfg(feature = "std")]
+#[inline]
+pub fn hash_concrete<T: core::hash::Hash + ?Sized>(probe: &T) -> u64 {
+    use core::hash::BuildHasher;
+
+    core::hash::BuildHasherDefault::<std::collections::hash_map::DefaultHasher>::default()
+        .hash_one(probe)
+}
+
+/// Hashes an object exactly like the `Hash` impls generated by `impl_hash!`,
+/// for hand-written impls where the macros cannot be used.
+///
+/// ```rust ignore
+/// impl<T: 'static> Hash for dyn MyTrait<T> {
+///     fn hash<H: Hasher>(&self, state: &mut H) {
+///         object_safe::hash_object_into(self, state)
+///     }
+/// }
+/// ```
+///
+/// `obj_eq`, `obj_partial_cmp` and `obj_cmp` do the same for the other
+/// traits, so the delegation stays in this crate. For a trait extending
+/// `OrdObj`, implement `eq` with `obj_cmp(a, b).is_eq()` instead, like the
+/// impls generated by `impl_full!`, so equality agrees with the order.
+#[inline]
+pub fn hash_object_into<X: HashObj + ?Sized, H: Hasher>(object: &X, state: &mut H) {
+    crate::hash_object(object.as_hash_object(), state)
+}
+
+/// Compares two objects like the `PartialOrd` impls generated by
+/// `impl_partial_ord!`, which may be behind different trait objects, like
+/// `obj_eq`.
+#[inline]
+pub fn obj_partial_cmp<A, B>(a: &A, b: &B) -> Option<Ordering>
+where
+    A: PartialOrdObj + ?Sized,
+    B: PartialOrdObj + ?Sized,
+{
+    a.partial_cmp_object(b.as_partial_ord_object())
+}
+
+/// Compares two objects like the `Ord` impls generated by `impl_ord!`, which
+/// may be behind different trait objects, like `obj_eq`. Unlike `dyn_cmp`,
+/// objects of different types are ordered by type.
+#[inline]
+pub fn obj_cmp<A, B>(a: &A, b: &B) -> Ordering
+where
+    A: OrdObj + ?Sized,
+    B: OrdObj + ?Sized,
+{
+    a.cmp_object(b.as_ord_object())
+}
+
+fn cmp<T: OrdObj + ?Sized>(a: &T, b: &T) -> Ordering {
+    a.cmp_object(b.as_ord_object())
+}
+
+/// Returns the greater of two objects, or `b` if they are equal, like
+/// `Ord::max`. Works with references and boxes alike, such as `&dyn OrdObj`,
+/// `Box<dyn OrdObj>`, or `Rc<dyn MyTrait>` where `MyTrait` extends `OrdObj`.
+pub fn max_obj<P, T>(a: P, b: P) -> P
+where
+    P: Deref<Target = T>,
+    T: OrdObj + ?Sized,
+{
+    match cmp(&*a, &*b) {
+        Ordering::Greater => a,
+        Ordering::Less | Ordering::Equal => b,
+    }
+}
+
+/// Returns the lesser of two objects, or `a` if they are equal, like
+/// `Ord::min`. See `max_obj` for the supported pointer types.
+pub fn min_obj<P, T>(a: P, b: P) -> P
+where
+    P: Deref<Target = T>,
+    T: OrdObj + ?Sized,
+{
+    match cmp(&*a, &*b) {
+        Ordering::Greater => b,
+        Ordering::Less | Ordering::Equal => a,
+    }
+}
+
+/// Restricts an object to the interval `[min, max]`, like `Ord::clamp`. See
+/// `max_obj` for the supported pointer types.
+///
+/// # Panics
+///
+/// Panics if `min > max`. Use `try_clamp_obj` to handle that instead.
+pub fn clamp_obj<P, T>(value: P, min: P, max: P) -> P
+where
+    P: Deref<Target = T>,
+    T: OrdObj + ?Sized,
+{
+    try_clamp_obj(value, min, max).expect("clamp_obj called with min > max")
+}
+
+/// Like `clamp_obj`, but returns `Error::InvalidRange` instead of panicking
+/// if `min > max`.
+pub fn try_clamp_obj<P, T>(value: P, min: P, max: P) -> Result<P>
+where
+    P: Deref<Target = T>,
+    T: OrdObj + ?Sized,
+{
+    if cmp(&*min, &*max) == Ordering::Greater {
+        Err(Error::InvalidRange)
+    } else if cmp(&*value, &*min) == Ordering::Less {
+        Ok(min)
+    } else if cmp(&*value, &*max) == Ordering::Greater {
+        Ok(max)
+    } else {
+        Ok(value)
+    }
+}
+
+/// Returns a comparator for pointers to objects, for use with methods like
+/// `sort_by` and `binary_search_by`, so you don't have to dereference both
+/// sides yourself.
+///
+/// ```rust ignore
+/// let mut objects: Vec<Box<dyn MyTrait>> = ...;
+/// objects.sort_by(by_obj_order());
+/// ```
+pub fn by_obj_order<P, T>() -> impl FnMut(&P, &P) -> Ordering + Copy
+where
+    P: Deref<Target = T>,
+    T: OrdObj + ?Sized,
+{
+    |a, b| cmp(&**a, &**b)
+}
+
+/// Sorts a slice of pointers to objects, like `slice::sort`.
+#[cfg(feature = "alloc")]
+pub fn sort_objs<P, T>(slice: &mut [P])
+where
+    P: Deref<Target = T>,
+    T: OrdObj + ?Sized,
+{
+    slice.sort_by(by_obj_order());
+}
+
+/// Sorts a slice by an erased key, such as a `Box<dyn OrdObj>`, that `key`
+/// extracts once per element, like `slice::sort_by_cached_key`. This suits
+/// elements behind trait objects whose key is expensive to compute, since
+/// `sort_by_key` would call `key`, and so dispatch dynamically, twice per
+/// comparison.
+///
+/// ```rust ignore
+/// let mut documents: Vec<Box<dyn Document>> = ...;
+/// sort_objs_by_cached_key(&mut documents, |doc| doc.sort_key());
+/// ```
+///
+/// The keys are compared with `cmp_object`, so keys of different types are
+/// ordered by type.
+#[cfg(feature = "alloc")]
+pub fn sort_objs_by_cached_key<E, Q, K>(slice: &mut [E], mut key: impl FnMut(&E) -> Q)
+where
+    Q: Deref<Target = K>,
+    K: OrdObj + ?Sized,
+{
+    slice.sort_by_cached_key(|element| Obj(key(element)));
+}
+
+/// Sorts a slice of pointers to objects without preserving the order of equal
+/// elements, like `slice::sort_unstable`.
+pub fn sort_unstable_objs<P, T>(slice: &mut [P])
+where
+    P: Deref<Target = T>,
+    T: OrdObj + ?Sized,
+{
+    slice.sort_unstable_by(by_obj_order());
+}
+
+/// Methods for slices of pointers to objects, such as `[Box<dyn MyTrait>]`,
+/// that compare the objects with `cmp_object`.
+pub trait ObjSliceExt<T: ?Sized> {
+    /// Sorts the slice, like `slice::sort`.
+    #[cfg(feature = "alloc")]
+    fn sort_objs(&mut self);
+    /// Sorts the slice without preserving the order of equal elements, like
+    /// `slice::sort_unstable`.
+    fn sort_unstable_objs(&mut self);
+    /// Binary searches the sorted slice for an object, like
+    /// `slice::binary_search`.
+    fn binary_search_obj(&self, object: &T) -> Result<usize, usize>;
+}
+
+impl<P, T> ObjSliceExt<T> for [P]
+where
+    P: Deref<Target = T>,
+    T: OrdObj + ?Sized,
+{
+    #[cfg(feature = "alloc")]
+    fn sort_objs(&mut self) {
+        sort_objs(self)
+    }
+
+    fn sort_unstable_objs(&mut self) {
+        sort_unstable_objs(self)
+    }
+
+    fn binary_search_obj(&self, object: &T) -> Result<usize, usize> {
+        self.binary_search_by(|x| cmp(&**x, object))
+    }
+}
+
+/// Methods for vectors of pointers to objects, such as
+/// `Vec<Box<dyn MyTrait>>`, that compare the objects with `eq_object`.
+#[cfg(feature = "alloc")]
+pub trait ObjVecExt<T: ?Sized> {
+    /// Removes consecutive equal objects, like `Vec::dedup`.
+    fn dedup_objs(&mut self);
+}
+
+#[cfg(feature = "alloc")]
+impl<P, T> ObjVecExt<T> for Vec<P>
+where
+    P: Deref<Target = T>,
+    T: PartialEqObj + ?Sized,
+{
+    fn dedup_objs(&mut self) {
+        self.dedup_by(|a, b| a.eq_object(b.as_partial_eq_object()))
+    }
+}
+
+/// Compares two sequences of pointers to objects lexicographically, like
+/// `Ord` for slices: element by element, and then by length.
+pub fn cmp_slices<P, T>(a: &[P], b: &[P]) -> Ordering
+where
+    P: Deref<Target = T>,
+    T: OrdObj + ?Sized,
+{
+    cmp_iters(a, b)
+}
+
+/// Compares two iterators of references to pointers to objects, such as
+/// `slice.iter()` over a `[Box<dyn MyTrait>]`, lexicographically, like
+/// `Iterator::cmp`. See `cmp_obj_iters` for iterators whose items point to
+/// the objects directly.
+pub fn cmp_iters<I, J, P, T>(a: I, b: J) -> Ordering
+where
+    I: IntoIterator,
+    J: IntoIterator,
+    I::Item: Deref<Target = P>,
+    J::Item: Deref<Target = P>,
+    P: Deref<Target = T>,
+    T: OrdObj + ?Sized,
+{
+    cmp_obj_iters(a.into_iter().map(Twice), b.into_iter().map(Twice))
+}
+
+/// Tests two iterators of references to pointers to objects, such as
+/// `slice.iter()` over a `[Box<dyn MyTrait>]`, for equality, like
+/// `Iterator::eq`: they are equal if they have the same length and all their
+/// elements are equal. See `eq_obj_iters` for iterators whose items point to
+/// the objects directly.
+pub fn eq_iters<I, J, P, T>(a: I, b: J) -> bool
+where
+    I: IntoIterator,
+    J: IntoIterator,
+    I::Item: Deref<Target = P>,
+    J::Item: Deref<Target = P>,
+    P: Deref<Target = T>,
+    T: PartialEqObj + ?Sized,
+{
+    eq_obj_iters(a.into_iter().map(Twice), b.into_iter().map(Twice))
+}
+
+/// Tests two streams of objects for equality, like `eq_iters`, for
+/// iterators whose items point to the objects directly, such as
+/// `&dyn PartialEqObj`, or `Box<dyn MyTrait>` yielded by value. The elements
+/// are compared as they are produced, and the comparison stops at the first
+/// difference, so neither stream needs to be collected.
+///
+/// ```rust ignore
+/// // decode yields a `Box<dyn Record>` per record
+/// let equal = eq_obj_iters(left.decode(), right.decode());
+/// ```
+pub fn eq_obj_iters<I, J, T>(a: I, b: J) -> bool
+where
+    I: IntoIterator,
+    J: IntoIterator,
+    I::Item: Deref<Target = T>,
+    J::Item: Deref<Target = T>,
+    T: PartialEqObj + ?Sized,
+{
+    let (mut a, mut b) = (a.into_iter(), b.into_iter());
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(x), Some(y)) if x.eq_object(y.as_partial_eq_object()) => {}
+            _ => return false,
+        }
+    }
+}
+
+/// Compares two streams of objects lexicographically, like `cmp_iters`, for
+/// iterators whose items point to the objects directly. See `eq_obj_iters`.
+pub fn cmp_obj_iters<I, J, T>(a: I, b: J) -> Ordering
+where
+    I: IntoIterator,
+    J: IntoIterator,
+    I::Item: Deref<Target = T>,
+    J::Item: Deref<Target = T>,
+    T: OrdObj + ?Sized,
+{
+    partial_cmp_iter_by(a, b, |x, y| Some(cmp(x, y))).expect("cmp_object is total")
+}
+
+/// Compares two streams of objects lexicographically, like
+/// `Iterator::partial_cmp`, returning None as soon as two elements are not
+/// comparable. See `eq_obj_iters`.
+pub fn partial_cmp_obj_iters<I, J, T>(a: I, b: J) -> Option<Ordering>
+where
+    I: IntoIterator,
+    J: IntoIterator,
+    I::Item: Deref<Target = T>,
+    J::Item: Deref<Target = T>,
+    T: PartialOrdObj + ?Sized,
+{
+    partial_cmp_iter_by(a, b, |x, y| x.partial_cmp_object(y.as_partial_ord_object()))
+}
+
+/// A reference to a pointer, dereferencing straight to the object, so the
+/// `_iters` functions can be implemented by the `_obj_iters` ones.
+struct Twice<Q>(Q);
+
+impl<Q: Deref> Deref for Twice<Q>
+where
+    Q::Target: Deref,
+{
+    type Target = <Q::Target as Deref>::Target;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+fn partial_cmp_iter_by<I, J, T>(
+    a: I,
+    b: J,
+    mut compare: impl FnMut(&T, &T) -> Option<Ordering>,
+) -> Option<Ordering>
+where
+    I: IntoIterator,
+    J: IntoIterator,
+    I::Item: Deref<Target = T>,
+    J::Item: Deref<Target = T>,
+    T: ?Sized,
+{
+    let (mut a, mut b) = (a.into_iter(), b.into_iter());
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return Some(Ordering::Equal),
+            (None, Some(_)) => return Some(Ordering::Less),
+            (Some(_), None) => return Some(Ordering::Greater),
+            (Some(x), Some(y)) => match compare(&x, &y)? {
+                Ordering::Equal => {}
+                unequal => return Some(unequal),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn match_concrete_values() {
+        let key: &dyn PartialEqObj = &"admin";
+        assert!(matches_concrete(key, &"admin"));
+        assert!(!matches_concrete(key, &"user"));
+        assert!(!matches_concrete(key, &String::from("admin")));
+        #[cfg(feature = "std")]
+        assert_eq!(hash_concrete(&"admin"), "admin".hash_object_u64());
+    }
+
+    fn int(x: &dyn OrdObj) -> i32 {
+        *x.as_any().downcast_ref().unwrap()
+    }
+
+    #[test]
+    fn eq_across_trait_objects() {
+        trait Render: PartialEqObj {}
+        trait Persist: PartialEqObj {}
+        impl<T: PartialEq + 'static> Render for T {}
+        impl<T: PartialEq + 'static> Persist for T {}
+
+        let render: Box<dyn Render> = Box::new("a".to_string());
+        let persist: Box<dyn Persist> = Box::new("a".to_string());
+        assert!(obj_eq(&*render, &*persist));
+        assert!(obj_eq(&*persist, &*render));
+        assert!(!obj_eq(
+            &*render,
+            &*(Box::new("b".to_string()) as Box<dyn Persist>)
+        ));
+        assert!(!obj_eq(&*render, &*(Box::new("a") as Box<dyn Persist>)));
+    }
+
+    #[test]
+    fn fallible_clamp() {
+        let (one, two, three): (&dyn OrdObj, &dyn OrdObj, &dyn OrdObj) = (&1, &2, &3);
+        assert_eq!(int(try_clamp_obj(three, one, two).unwrap()), 2);
+        assert_eq!(
+            try_clamp_obj(one, three, two).err(),
+            Some(Error::InvalidRange)
+        );
+    }
+
+    #[test]
+    fn hand_written_impls() {
+        trait Shape<T>: OrdObj + HashObj {}
+        impl<T: 'static, S: Ord + core::hash::Hash + 'static> Shape<T> for S {}
+
+        impl<T: 'static> core::hash::Hash for dyn Shape<T> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                hash_object_into(self, state)
+            }
+        }
+        impl<T: 'static> PartialEq for dyn Shape<T> {
+            fn eq(&self, other: &Self) -> bool {
+                obj_cmp(self, other).is_eq()
+            }
+        }
+        impl<T: 'static> Eq for dyn Shape<T> {}
+        impl<T: 'static> PartialOrd for dyn Shape<T> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl<T: 'static> Ord for dyn Shape<T> {
+            fn cmp(&self, other: &Self) -> Ordering {
+                obj_cmp(self, other)
+            }
+        }
+
+        use std::hash::BuildHasher;
+
+        let set: std::collections::BTreeSet<Box<dyn Shape<u8>>> =
+            [3, 1, 3].map(|x| Box::new(x) as _).into();
+        assert_eq!(set.len(), 2);
+        let first: &dyn Shape<u8> = &**set.first().unwrap();
+        assert!(*first == *(Box::new(1) as Box<dyn Shape<u8>>));
+        let state = std::hash::RandomState::new();
+        assert_eq!(state.hash_one(first), state.hash_one(1));
+        let nan: &dyn PartialOrdObj = &f64::NAN;
+        assert_eq!(obj_partial_cmp(nan, nan), None);
+        assert_eq!(obj_partial_cmp(nan, &1.0), None);
+    }
+
+    #[test]
+    fn erased_values() {
+        assert_eq!(dyn_eq(&1, &1), Some(true));
+        assert_eq!(dyn_eq(&1, &2), Some(false));
+        assert_eq!(dyn_eq(&1, &"1"), None);
+        assert_eq!(dyn_cmp(&1, &2), Some(Ordering::Less));
+        assert_eq!(dyn_cmp(&1, &'a'), None);
+        #[cfg(feature = "std")]
+        assert_eq!(dyn_hash(&"a"), "a".hash_object_u64());
+    }
+
+    #[test]
+    fn min_max_refs() {
+        let (one, two): (&dyn OrdObj, &dyn OrdObj) = (&1, &2);
+        assert_eq!(int(max_obj(one, two)), 2);
+        assert_eq!(int(min_obj(two, one)), 1);
+        let other_one: &dyn OrdObj = &1;
+        let (max, min): (&dyn OrdObj, &dyn OrdObj) =
+            (max_obj(one, other_one), min_obj(one, other_one));
+        assert!(core::ptr::addr_eq(max, other_one));
+        assert!(core::ptr::addr_eq(min, one));
+    }
+
+    #[test]
+    fn clamp_boxes() {
+        let boxed = |x: i32| -> Box<dyn OrdObj> { Box::new(x) };
+        assert_eq!(int(&*clamp_obj(boxed(5), boxed(1), boxed(3))), 3);
+        assert_eq!(int(&*clamp_obj(boxed(0), boxed(1), boxed(3))), 1);
+        assert_eq!(int(&*clamp_obj(boxed(2), boxed(1), boxed(3))), 2);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn sort() {
+        trait MyTrait: OrdObj {}
+        impl<T: Ord + 'static> MyTrait for T {}
+        let ints = |objects: &[Box<dyn MyTrait>]| -> Vec<i32> {
+            objects.iter().map(|x| int(x.as_ord_object())).collect()
+        };
+
+        let mut objects: Vec<Box<dyn MyTrait>> = vec![Box::new(3), Box::new(1), Box::new(2)];
+        objects.sort_by(by_obj_order());
+        assert_eq!(ints(&objects), [1, 2, 3]);
+        assert_eq!(
+            objects.binary_search_by(|x| cmp(&**x, &2 as &dyn MyTrait)),
+            Ok(1)
+        );
+
+        objects.reverse();
+        sort_objs(&mut objects);
+        assert_eq!(ints(&objects), [1, 2, 3]);
+        objects.reverse();
+        sort_unstable_objs(&mut objects);
+        assert_eq!(ints(&objects), [1, 2, 3]);
+
+        let mut calls = 0;
+        sort_objs_by_cached_key(&mut objects, |x| {
+            calls += 1;
+            Box::new(-int(x.as_ord_object())) as Box<dyn OrdObj>
+        });
+        assert_eq!(ints(&objects), [3, 2, 1]);
+        assert_eq!(calls, 3);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn extension_traits() {
+        let mut objects: Vec<Box<dyn OrdObj>> = [3, 1, 3, 2, 1]
+            .map(|x| Box::new(x) as Box<dyn OrdObj>)
+            .into();
+        objects.sort_objs();
+        objects.dedup_objs();
+        assert_eq!(
+            objects.iter().map(|x| int(&**x)).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert_eq!(objects.binary_search_obj(&2), Ok(1));
+        assert_eq!(objects.binary_search_obj(&4), Err(3));
+        objects.reverse();
+        objects[..2].sort_unstable_objs();
+        assert_eq!(
+            objects.iter().map(|x| int(&**x)).collect::<Vec<_>>(),
+            [2, 3, 1]
+        );
+    }
+
+    #[test]
+    fn lexicographic() {
+        let row = |xs: &[i32]| -> Vec<Box<dyn OrdObj>> {
+            xs.iter().map(|&x| Box::new(x) as Box<dyn OrdObj>).collect()
+        };
+        assert_eq!(cmp_slices(&row(&[1, 2]), &row(&[1, 2])), Ordering::Equal);
+        assert_eq!(cmp_slices(&row(&[1, 2]), &row(&[1, 3])), Ordering::Less);
+        assert_eq!(cmp_slices(&row(&[2]), &row(&[1, 3])), Ordering::Greater);
+        assert_eq!(cmp_slices(&row(&[1]), &row(&[1, 0])), Ordering::Less);
+        assert_eq!(cmp_slices(&row(&[]), &row(&[])), Ordering::Equal);
+        assert_eq!(cmp_iters(&row(&[1, 2]), &row(&[1])), Ordering::Greater);
+        assert!(eq_iters(&row(&[1, 2]), &row(&[1, 2])));
+        assert!(!eq_iters(&row(&[1, 2]), &row(&[1])));
+        assert!(!eq_iters(&row(&[1, 2]), &row(&[1, 3])));
+        let mixed: Vec<Box<dyn OrdObj>> = vec![Box::new(1), Box::new("a")];
+        assert!(eq_iters(&mixed, &mixed));
+        assert_eq!(cmp_slices(&mixed, &mixed), Ordering::Equal);
+    }
+
+    #[test]
+    fn streams() {
+        let boxed = |xs: [i32; 3]| xs.into_iter().map(|x| Box::new(x) as Box<dyn OrdObj>);
+        assert!(eq_obj_iters(boxed([1, 2, 3]), boxed([1, 2, 3])));
+        assert!(!eq_obj_iters(boxed([1, 2, 3]), boxed([1, 2, 4])));
+        assert_eq!(
+            cmp_obj_iters(boxed([1, 2, 3]), boxed([1, 3, 0])),
+            Ordering::Less
+        );
+        assert_eq!(
+            cmp_obj_iters(boxed([1, 2, 3]), boxed([1, 2, 3]).take(2)),
+            Ordering::Greater
+        );
+
+        let mut produced = 0;
+        let endless = (0..).map(|x| {
+            produced += 1;
+            Box::new(x) as Box<dyn OrdObj>
+        });
+        assert!(!eq_obj_iters(endless, boxed([0, 5, 0])));
+        assert_eq!(produced, 2);
+
+        let floats: [&dyn PartialOrdObj; 2] = [&1.0, &f64::NAN];
+        assert_eq!(partial_cmp_obj_iters(floats, floats), None);
+        assert_eq!(
+            partial_cmp_obj_iters(floats.into_iter().take(1), floats),
+            Some(Ordering::Less)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn clamp_invalid_range() {
+        let (one, two): (&dyn OrdObj, &dyn OrdObj) = (&1, &2);
+        clamp_obj(one, two, one);
+    }
+}
@@ -0,0 +1,290 @@
+//! A set of boxed trait objects that switches from a linear scan to hashing
+//! as it grows.
+
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    mem,
+};
+use std::collections::hash_map::RandomState;
+
+use super::{hash_set, vec_set, DynHashSet, DynVecSet};
+use crate::{EqObj, HashObj};
+
+/// Default number of keys above which a `DynAdaptiveSet` switches to hashing.
+pub const DEFAULT_THRESHOLD: usize = 16;
+
+/// Set of boxed trait objects, such as `DynAdaptiveSet<dyn MyKey>` where
+/// `MyKey` extends `HashObj` and `EqObj`, which is stored as a `DynVecSet`
+/// while it is small, and as a `DynHashSet` once it holds more keys than its
+/// threshold. It never switches back, even if keys are removed.
+pub struct DynAdaptiveSet<K: ?Sized, S = RandomState> {
+    repr: Repr<K, S>,
+    threshold: usize,
+}
+
+enum Repr<K: ?Sized, S> {
+    /// The hasher is kept until the set switches to hashing.
+    Vec(DynVecSet<K>, Option<S>),
+    Hash(DynHashSet<K, S>),
+}
+
+impl<K: HashObj + EqObj + ?Sized> DynAdaptiveSet<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty set that switches to hashing once it holds more than
+    /// `threshold` keys.
+    pub fn with_threshold(threshold: usize) -> Self {
+        Self::with_threshold_and_hasher(threshold, RandomState::new())
+    }
+}
+
+impl<K: ?Sized, S> DynAdaptiveSet<K, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::with_threshold_and_hasher(DEFAULT_THRESHOLD, hasher)
+    }
+
+    pub fn with_threshold_and_hasher(threshold: usize, hasher: S) -> Self {
+        Self {
+            repr: Repr::Vec(DynVecSet::new(), Some(hasher)),
+            threshold,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.repr {
+            Repr::Vec(set, _) => set.len(),
+            Repr::Hash(set) => set.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the keys are currently stored in a hash set.
+    pub fn is_hashed(&self) -> bool {
+        matches!(self.repr, Repr::Hash(_))
+    }
+
+    pub fn iter(&self) -> Iter<'_, K> {
+        match &self.repr {
+            Repr::Vec(set, _) => Iter::Vec(set.iter()),
+            Repr::Hash(set) => Iter::Hash(set.iter()),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        match &mut self.repr {
+            Repr::Vec(set, _) => set.clear(),
+            Repr::Hash(set) => set.clear(),
+        }
+    }
+}
+
+impl<K, S> DynAdaptiveSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    /// Adds a key to the set, returning false if an equal key was already
+    /// present, in which case the set is not modified.
+    pub fn insert(&mut self, key: Box<K>) -> bool {
+        let inserted = match &mut self.repr {
+            Repr::Vec(set, _) => set.insert(key),
+            Repr::Hash(set) => return set.insert(key),
+        };
+        if self.len() > self.threshold {
+            self.switch_to_hash();
+        }
+        inserted
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        match &self.repr {
+            Repr::Vec(set, _) => set.contains(key),
+            Repr::Hash(set) => set.contains(key),
+        }
+    }
+
+    /// Returns the key in the set that is equal to `key`.
+    pub fn get(&self, key: &K) -> Option<&K> {
+        match &self.repr {
+            Repr::Vec(set, _) => set.get(key),
+            Repr::Hash(set) => set.get(key),
+        }
+    }
+
+    /// Removes the key that is equal to `key`, returning whether it was
+    /// present.
+    pub fn remove(&mut self, key: &K) -> bool {
+        match &mut self.repr {
+            Repr::Vec(set, _) => set.remove(key),
+            Repr::Hash(set) => set.remove(key),
+        }
+    }
+
+    /// Removes and returns the key that is equal to `key`.
+    pub fn take(&mut self, key: &K) -> Option<Box<K>> {
+        match &mut self.repr {
+            Repr::Vec(set, _) => set.take(key),
+            Repr::Hash(set) => set.take(key),
+        }
+    }
+
+    /// Returns true if the set contains a key of type `T` equal to `value`.
+    /// Only keys of type `T` can match.
+    pub fn contains_by<T: Hash + PartialEq + 'static>(&self, value: &T) -> bool {
+        match &self.repr {
+            Repr::Vec(set, _) => set.contains_by(value),
+            Repr::Hash(set) => set.contains_by(value),
+        }
+    }
+
+    /// Keeps only the keys for which `f` returns true.
+    pub fn retain(&mut self, f: impl FnMut(&K) -> bool) {
+        match &mut self.repr {
+            Repr::Vec(set, _) => set.retain(f),
+            Repr::Hash(set) => set.retain(f),
+        }
+    }
+
+    fn switch_to_hash(&mut self) {
+        if let Repr::Vec(keys, hasher) = &mut self.repr {
+            let keys = mem::take(keys);
+            let hasher = hasher.take().expect("the hasher is kept until switching");
+            let mut set = DynHashSet::with_capacity_and_hasher(keys.len(), hasher);
+            set.extend(keys);
+            self.repr = Repr::Hash(set);
+        }
+    }
+}
+
+impl<K: ?Sized, S: Default> Default for DynAdaptiveSet<K, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K: fmt::Debug + ?Sized, S> fmt::Debug for DynAdaptiveSet<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Sets are equal if they contain the same keys, regardless of how they are
+/// stored.
+impl<K, S> PartialEq for DynAdaptiveSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|key| other.contains(key))
+    }
+}
+
+impl<K, S> Eq for DynAdaptiveSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+}
+
+impl<K, S> FromIterator<Box<K>> for DynAdaptiveSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = Box<K>>>(iter: I) -> Self {
+        let mut set = Self::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<K, S> Extend<Box<K>> for DynAdaptiveSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = Box<K>>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+impl<'a, K: ?Sized, S> IntoIterator for &'a DynAdaptiveSet<K, S> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the keys of a `DynAdaptiveSet`.
+pub enum Iter<'a, K: ?Sized> {
+    Vec(vec_set::Iter<'a, K>),
+    Hash(hash_set::Iter<'a, K>),
+}
+
+impl<'a, K: ?Sized> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Vec(iter) => iter.next(),
+            Iter::Hash(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Iter::Vec(iter) => iter.size_hint(),
+            Iter::Hash(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<K: ?Sized> ExactSizeIterator for Iter<'_, K> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::Key;
+
+    #[test]
+    fn switches_to_hashing_above_threshold() {
+        let mut set = DynAdaptiveSet::<dyn Key>::with_threshold(3);
+        for i in 0..3u32 {
+            assert!(set.insert(Box::new(i)));
+        }
+        assert!(!set.insert(Box::new(0u32)));
+        assert!(!set.is_hashed());
+        assert!(set.insert(Box::new("a")));
+        assert!(set.is_hashed());
+        assert_eq!(set.len(), 4);
+        for i in 0..3u32 {
+            assert!(set.contains(&i));
+        }
+        assert!(set.contains_by(&"a"));
+        assert!(set.remove(&"a"));
+        assert!(set.is_hashed());
+        assert_eq!(set.iter().len(), 3);
+    }
+
+    #[test]
+    fn equality_across_representations() {
+        let keys = || (0..4u32).map(|i| Box::new(i) as Box<dyn Key>);
+        let small: DynAdaptiveSet<dyn Key> = keys().collect();
+        let mut large = DynAdaptiveSet::<dyn Key>::with_threshold(1);
+        large.extend(keys());
+        assert!(!small.is_hashed());
+        assert!(large.is_hashed());
+        assert_eq!(small, large);
+    }
+}
@@ -0,0 +1,216 @@
+//! A map holding at most one value of each concrete type.
+
+use core::{
+    any::{Any, TypeId},
+    fmt,
+};
+use std::collections::{hash_map, HashMap};
+
+use crate::{AsAny, PartialEqObj};
+
+/// Container holding at most one value of each concrete type, like an
+/// "anymap", where every value is stored as a `Box<X>`, such as
+/// `Box<dyn Resource>` where `Resource` extends the object-safe traits you
+/// need. Values can be retrieved by their concrete type, or enumerated as
+/// `&X` to process all of them generically.
+///
+/// ```rust ignore
+/// let mut resources = ObjAnyMap::<dyn Resource>::new();
+/// resources.insert(Box::new(Gravity(9.8)));
+/// resources.insert(Box::new(Time(0)));
+/// resources.get_mut::<Time>().unwrap().0 += 1;
+/// for resource in resources.values() {
+///     hash.write_u64(resource.hash_object_u64());
+/// }
+/// ```
+pub struct ObjAnyMap<X: ?Sized> {
+    inner: HashMap<TypeId, Box<X>>,
+}
+
+impl<X: AsAny + ?Sized> ObjAnyMap<X> {
+    pub fn new() -> Self {
+        Self {
+            inner: HashMap::new(),
+        }
+    }
+
+    /// Stores a value, returning the previous value of the same concrete
+    /// type, if any.
+    pub fn insert(&mut self, value: Box<X>) -> Option<Box<X>> {
+        self.inner.insert((*value).as_any().type_id(), value)
+    }
+
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.get_dyn(TypeId::of::<T>())?.as_any().downcast_ref()
+    }
+
+    pub fn get_mut<T: Any>(&mut self) -> Option<&mut T> {
+        let value: &mut X = self.inner.get_mut(&TypeId::of::<T>())?;
+        value.as_any_mut().downcast_mut()
+    }
+
+    /// Returns the value with the given concrete type.
+    pub fn get_dyn(&self, type_id: TypeId) -> Option<&X> {
+        self.inner.get(&type_id).map(|value| &**value)
+    }
+
+    pub fn contains<T: Any>(&self) -> bool {
+        self.inner.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn remove<T: Any>(&mut self) -> Option<Box<X>> {
+        self.inner.remove(&TypeId::of::<T>())
+    }
+}
+
+impl<X: ?Sized> ObjAnyMap<X> {
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Iterates over all values, in arbitrary order.
+    pub fn values(&self) -> Values<'_, X> {
+        Values(self.inner.values())
+    }
+
+    /// Iterates over all values mutably, in arbitrary order.
+    pub fn values_mut(&mut self) -> ValuesMut<'_, X> {
+        ValuesMut(self.inner.values_mut())
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+}
+
+impl<X: AsAny + ?Sized> Default for ObjAnyMap<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: fmt::Debug + ?Sized> fmt::Debug for ObjAnyMap<X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.values()).finish()
+    }
+}
+
+/// Maps are equal if they hold values of the same types, and the values of
+/// each type are equal.
+impl<X: PartialEqObj + ?Sized> PartialEq for ObjAnyMap<X> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.inner.iter().all(|(type_id, value)| {
+                other
+                    .get_dyn(*type_id)
+                    .is_some_and(|other| value.eq_object(other.as_partial_eq_object()))
+            })
+    }
+}
+
+impl<X: AsAny + ?Sized> Extend<Box<X>> for ObjAnyMap<X> {
+    fn extend<I: IntoIterator<Item = Box<X>>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl<X: AsAny + ?Sized> FromIterator<Box<X>> for ObjAnyMap<X> {
+    fn from_iter<I: IntoIterator<Item = Box<X>>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+/// Iterator over the values of an `ObjAnyMap`.
+pub struct Values<'a, X: ?Sized>(hash_map::Values<'a, TypeId, Box<X>>);
+
+impl<'a, X: ?Sized> Iterator for Values<'a, X> {
+    type Item = &'a X;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|value| &**value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<X: ?Sized> ExactSizeIterator for Values<'_, X> {}
+
+/// Mutable iterator over the values of an `ObjAnyMap`.
+pub struct ValuesMut<'a, X: ?Sized>(hash_map::ValuesMut<'a, TypeId, Box<X>>);
+
+impl<'a, X: ?Sized> Iterator for ValuesMut<'a, X> {
+    type Item = &'a mut X;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|value| &mut **value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<X: ?Sized> ExactSizeIterator for ValuesMut<'_, X> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{fixture::Key as Resource, HashObj};
+
+    #[derive(Debug, Hash, PartialEq, Eq)]
+    struct Gravity(u32);
+
+    #[derive(Debug, Hash, PartialEq, Eq)]
+    struct Time(u64);
+
+    fn resources() -> ObjAnyMap<dyn Resource> {
+        [
+            Box::new(Gravity(10)) as Box<dyn Resource>,
+            Box::new(Time(0)),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    #[test]
+    fn typed_access() {
+        let mut map = resources();
+        assert_eq!(map.get::<Gravity>(), Some(&Gravity(10)));
+        map.get_mut::<Time>().unwrap().0 += 1;
+        assert_eq!(map.get::<Time>(), Some(&Time(1)));
+        assert!(map.insert(Box::new(Gravity(9))).is_some());
+        assert_eq!(map.len(), 2);
+        assert!(map.remove::<Gravity>().is_some());
+        assert!(!map.contains::<Gravity>());
+        assert_eq!(map.get::<u8>(), None);
+    }
+
+    #[test]
+    fn enumerate_and_compare() {
+        let mut a = resources();
+        let b = resources();
+        assert!(a == b);
+        let hash: u64 = a
+            .values()
+            .map(|r| r.hash_object_u64())
+            .fold(0, |x, y| x ^ y);
+        assert_eq!(
+            hash,
+            Gravity(10).hash_object_u64() ^ Time(0).hash_object_u64()
+        );
+        a.get_mut::<Time>().unwrap().0 = 5;
+        assert!(a != b);
+        a.remove::<Time>();
+        assert!(a != b);
+    }
+}
@@ -0,0 +1,469 @@
+//! An ordered map keyed by boxed trait objects, and its iterators.
+
+use core::{fmt, mem, ops::Index, ops::RangeBounds};
+use std::collections::{btree_map, BTreeMap};
+
+use super::key::{
+    check_bounds, concrete_bounds, concrete_probes, probe, probe_bounds, Probe, Stored,
+};
+use crate::{OrdObj, Result};
+
+/// Ordered map keyed by boxed trait objects, such as `DynBTreeMap<dyn MyKey,
+/// V>` where `MyKey` extends `OrdObj`.
+///
+/// Like `DynBTreeSet`, keys are ordered with `cmp_object`, and lookups take a
+/// plain `&K` without allocating.
+pub struct DynBTreeMap<K: ?Sized, V> {
+    inner: BTreeMap<Stored<K>, V>,
+}
+
+impl<K: ?Sized, V> DynBTreeMap<K, V> {
+    pub fn new() -> Self {
+        Self {
+            inner: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Iterates over the entries in ascending order of their keys.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.inner.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut(self.inner.iter_mut())
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys(self.inner.keys())
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values(self.inner.values())
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut(self.inner.values_mut())
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+
+    /// Removes all the entries, and returns them in ascending order of their
+    /// keys. The map is empty afterwards, even if the iterator is dropped
+    /// early.
+    pub fn drain(&mut self) -> IntoIter<K, V> {
+        IntoIter(core::mem::take(&mut self.inner).into_iter())
+    }
+}
+
+impl<K: OrdObj + ?Sized, V> DynBTreeMap<K, V> {
+    /// Inserts a value, returning the value previously stored for an equal
+    /// key, if any. In that case the key is not updated.
+    pub fn insert(&mut self, key: Box<K>, value: V) -> Option<V> {
+        self.inner.insert(Stored(key), value)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.inner.get(probe(&key))
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.inner.get_mut(probe(&key))
+    }
+
+    /// Returns the stored key that is equal to `key`, and its value.
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        self.inner
+            .get_key_value(probe(&key))
+            .map(|(stored, value)| (&*stored.0, value))
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(probe(&key))
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.inner.remove(probe(&key))
+    }
+
+    pub fn remove_entry(&mut self, key: &K) -> Option<(Box<K>, V)> {
+        self.inner
+            .remove_entry(probe(&key))
+            .map(|(stored, value)| (stored.0, value))
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.inner
+            .first_key_value()
+            .map(|(stored, value)| (&*stored.0, value))
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.inner
+            .last_key_value()
+            .map(|(stored, value)| (&*stored.0, value))
+    }
+
+    pub fn pop_first(&mut self) -> Option<(Box<K>, V)> {
+        self.inner
+            .pop_first()
+            .map(|(stored, value)| (stored.0, value))
+    }
+
+    pub fn pop_last(&mut self) -> Option<(Box<K>, V)> {
+        self.inner
+            .pop_last()
+            .map(|(stored, value)| (stored.0, value))
+    }
+
+    /// Iterates over the entries whose keys are within `range`, in ascending
+    /// order. The bounds are borrowed keys, such as `&a..&b`, or `..=&b`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than the end, like
+    /// `BTreeMap::range`. Use `try_range` to handle that instead.
+    pub fn range<'a, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        R: RangeBounds<&'a K>,
+        K: 'a,
+    {
+        Range(self.inner.range::<dyn Probe<K>, _>(probe_bounds(&range)))
+    }
+
+    /// Like `range`, but returns `Error::InvalidRange` instead of panicking
+    /// if the start of the range is greater than the end.
+    pub fn try_range<'a, R>(&self, range: R) -> Result<Range<'_, K, V>>
+    where
+        R: RangeBounds<&'a K>,
+        K: 'a,
+    {
+        let bounds = probe_bounds(&range);
+        check_bounds(&bounds)?;
+        Ok(Range(self.inner.range::<dyn Probe<K>, _>(bounds)))
+    }
+
+    /// Like `range`, but with mutable access to the values.
+    pub fn range_mut<'a, R>(&mut self, range: R) -> RangeMut<'_, K, V>
+    where
+        R: RangeBounds<&'a K>,
+        K: 'a,
+    {
+        RangeMut(
+            self.inner
+                .range_mut::<dyn Probe<K>, _>(probe_bounds(&range)),
+        )
+    }
+
+    /// Like `range`, but the bounds are values of a concrete type, such as
+    /// `Priority(1)..Priority(3)`, which do not need to be converted to `&K`.
+    ///
+    /// Every value of type `T` is ordered like a key holding it would be, so
+    /// a range with both bounds only includes keys of type `T`. An unbounded
+    /// side extends past them to the keys of other types, which are ordered
+    /// arbitrarily relative to `T`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than the end, like
+    /// `range`.
+    pub fn range_by<T, R>(&self, range: R) -> Range<'_, K, V>
+    where
+        T: Ord + 'static,
+        R: RangeBounds<T>,
+    {
+        let bounds = concrete_bounds(&range);
+        Range(
+            self.inner
+                .range::<dyn Probe<K>, _>(concrete_probes(&bounds)),
+        )
+    }
+
+    /// Like `range_by`, but with mutable access to the values.
+    pub fn range_mut_by<T, R>(&mut self, range: R) -> RangeMut<'_, K, V>
+    where
+        T: Ord + 'static,
+        R: RangeBounds<T>,
+    {
+        let bounds = concrete_bounds(&range);
+        RangeMut(
+            self.inner
+                .range_mut::<dyn Probe<K>, _>(concrete_probes(&bounds)),
+        )
+    }
+
+    /// Keeps only the entries for which `f` returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        self.inner.retain(|stored, value| f(&stored.0, value))
+    }
+
+    /// Keeps every entry whose key's concrete type is not `T`, and the entries
+    /// with a key of type `T` for which `f` returns true.
+    pub fn retain_by<T: 'static>(&mut self, mut f: impl FnMut(&T, &mut V) -> bool) {
+        self.retain(|key, value| key.as_any().downcast_ref().is_none_or(|key| f(key, value)))
+    }
+
+    /// Removes the entries for which `f` returns true, and returns them in
+    /// ascending order of their keys, like `retain` with the opposite
+    /// condition, but without dropping the removed entries. Unlike
+    /// `HashMap::extract_if`, every matching entry is removed right away,
+    /// even if the iterator is dropped early, since `BTreeMap::extract_if`
+    /// needs a newer Rust than this crate supports.
+    pub fn extract_if(
+        &mut self,
+        mut f: impl FnMut(&K, &mut V) -> bool,
+    ) -> impl Iterator<Item = (Box<K>, V)> {
+        let mut extracted = Vec::new();
+        self.inner = mem::take(&mut self.inner)
+            .into_iter()
+            .filter_map(|(stored, mut value)| {
+                if f(&stored.0, &mut value) {
+                    extracted.push((stored.0, value));
+                    None
+                } else {
+                    Some((stored, value))
+                }
+            })
+            .collect();
+        extracted.into_iter()
+    }
+}
+
+impl<K: ?Sized, V> Default for DynBTreeMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: fmt::Debug + ?Sized, V: fmt::Debug> fmt::Debug for DynBTreeMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: OrdObj + ?Sized, V: PartialEq> PartialEq for DynBTreeMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<K: OrdObj + ?Sized, V: Eq> Eq for DynBTreeMap<K, V> {}
+
+impl<K: OrdObj + ?Sized, V> Index<&K> for DynBTreeMap<K, V> {
+    type Output = V;
+
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the map.
+    fn index(&self, key: &K) -> &V {
+        self.get(key).expect("key not found in DynBTreeMap")
+    }
+}
+
+impl<K: OrdObj + ?Sized, V> FromIterator<(Box<K>, V)> for DynBTreeMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (Box<K>, V)>>(iter: I) -> Self {
+        Self {
+            inner: iter
+                .into_iter()
+                .map(|(key, value)| (Stored(key), value))
+                .collect(),
+        }
+    }
+}
+
+impl<K: OrdObj + ?Sized, V> Extend<(Box<K>, V)> for DynBTreeMap<K, V> {
+    fn extend<I: IntoIterator<Item = (Box<K>, V)>>(&mut self, iter: I) {
+        self.inner
+            .extend(iter.into_iter().map(|(key, value)| (Stored(key), value)))
+    }
+}
+
+impl<'a, K: ?Sized, V> IntoIterator for &'a DynBTreeMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: ?Sized, V> IntoIterator for &'a mut DynBTreeMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K: ?Sized, V> IntoIterator for DynBTreeMap<K, V> {
+    type Item = (Box<K>, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.inner.into_iter())
+    }
+}
+
+iterator! {
+    /// Iterator over the entries of a `DynBTreeMap`, in ascending order.
+    Iter<'a, K, V>(btree_map::Iter<'a, Stored<K>, V>) -> (&'a K, &'a V),
+    |(key, value)| (&*key.0, value)
+}
+
+iterator! {
+    /// Mutable iterator over the entries of a `DynBTreeMap`, in ascending
+    /// order.
+    IterMut<'a, K, V>(btree_map::IterMut<'a, Stored<K>, V>) -> (&'a K, &'a mut V),
+    |(key, value)| (&*key.0, value)
+}
+
+iterator! {
+    /// Iterator over the keys of a `DynBTreeMap`, in ascending order.
+    Keys<'a, K, V>(btree_map::Keys<'a, Stored<K>, V>) -> &'a K,
+    |key| &*key.0
+}
+
+iterator! {
+    /// Iterator over the values of a `DynBTreeMap`, in ascending order of
+    /// their keys.
+    Values<'a, K, V>(btree_map::Values<'a, Stored<K>, V>) -> &'a V,
+    |value| value
+}
+
+iterator! {
+    /// Mutable iterator over the values of a `DynBTreeMap`, in ascending
+    /// order of their keys.
+    ValuesMut<'a, K, V>(btree_map::ValuesMut<'a, Stored<K>, V>) -> &'a mut V,
+    |value| value
+}
+
+iterator! {
+    /// Iterator over a range of entries of a `DynBTreeMap`.
+    Range<'a, K, V>(btree_map::Range<'a, Stored<K>, V>) -> (&'a K, &'a V),
+    |(key, value)| (&*key.0, value)
+}
+
+iterator! {
+    /// Mutable iterator over a range of entries of a `DynBTreeMap`.
+    RangeMut<'a, K, V>(btree_map::RangeMut<'a, Stored<K>, V>) -> (&'a K, &'a mut V),
+    |(key, value)| (&*key.0, value)
+}
+
+iterator! {
+    /// Owning iterator over the entries of a `DynBTreeMap`, in ascending
+    /// order.
+    IntoIter<K, V>(btree_map::IntoIter<Stored<K>, V>) -> (Box<K>, V),
+    |(key, value)| (key.0, value)
+}
+
+exact_size!(
+    Iter<'a, K, V>,
+    IterMut<'a, K, V>,
+    Keys<'a, K, V>,
+    Values<'a, K, V>,
+    ValuesMut<'a, K, V>,
+    IntoIter<K, V>
+);
+double_ended!(Iter<'a, K, V>, |(key, value)| (&*key.0, value));
+double_ended!(IterMut<'a, K, V>, |(key, value)| (&*key.0, value));
+double_ended!(Keys<'a, K, V>, |key| &*key.0);
+double_ended!(Values<'a, K, V>, |value| value);
+double_ended!(ValuesMut<'a, K, V>, |value| value);
+double_ended!(Range<'a, K, V>, |(key, value)| (&*key.0, value));
+double_ended!(RangeMut<'a, K, V>, |(key, value)| (&*key.0, value));
+double_ended!(IntoIter<K, V>, |(key, value)| (key.0, value));
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    trait Rule: OrdObj + fmt::Debug {}
+    impl<T: Ord + fmt::Debug + 'static> Rule for T {}
+
+    #[test]
+    fn retain_by_type() {
+        let mut rules = DynBTreeMap::<dyn Rule, u32>::new();
+        rules.insert(Box::new(1u8), 1);
+        rules.insert(Box::new(2u8), 2);
+        rules.insert(Box::new(1u16), 3);
+        rules.retain_by(|key: &u8, value| {
+            *value += 10;
+            *key == 2
+        });
+        let values: Vec<u32> = rules.values().copied().collect();
+        assert_eq!(values.len(), 2);
+        assert!(values.contains(&12));
+        assert!(values.contains(&3));
+    }
+
+    #[test]
+    fn extract_and_drain() {
+        let mut rules = DynBTreeMap::<dyn Rule, u32>::new();
+        for (key, value) in [(1u8, 1), (2, 2), (3, 3)] {
+            rules.insert(Box::new(key), value);
+        }
+        let extracted: Vec<u32> = rules
+            .extract_if(|_, value| *value % 2 == 1)
+            .map(|(_, value)| value)
+            .collect();
+        assert_eq!(extracted, [1, 3]);
+        let drained: Vec<_> = rules.drain().collect();
+        assert_eq!(drained.len(), 1);
+        assert!(rules.is_empty());
+    }
+
+    #[test]
+    fn ordered() {
+        let mut rules = DynBTreeMap::<dyn Rule, &str>::new();
+        rules.insert(Box::new(3u8), "c");
+        rules.insert(Box::new(1u8), "a");
+        rules.insert(Box::new(2u8), "b");
+        rules.insert(Box::new("z"), "str");
+        assert_eq!(rules.len(), 4);
+        assert_eq!(rules[&2u8], "b");
+        let values: Vec<_> = rules.values().copied().collect();
+        let strs = values.iter().position(|v| *v == "str").unwrap();
+        let mut ints = values.clone();
+        ints.remove(strs);
+        assert_eq!(ints, ["a", "b", "c"]);
+        let (low, high): (&dyn Rule, &dyn Rule) = (&2u8, &3u8);
+        let range: Vec<_> = rules.range(low..=high).map(|(_, v)| *v).collect();
+        assert_eq!(range, ["b", "c"]);
+        for (_, value) in rules.range_mut(..high) {
+            *value = "low";
+        }
+        assert_eq!(rules[&1u8], "low");
+        assert_eq!(rules.remove(&1u8), Some("low"));
+        assert_eq!(rules.get(&1u8), None);
+        assert!(rules.iter().next_back().is_some());
+    }
+
+    #[test]
+    fn concrete_range() {
+        let mut rules = DynBTreeMap::<dyn Rule, &str>::new();
+        for (key, value) in [(1u8, "a"), (2, "b"), (3, "c"), (4, "d")] {
+            rules.insert(Box::new(key), value);
+        }
+        rules.insert(Box::new(2u16), "other");
+        rules.insert(Box::new("z"), "str");
+        let range: Vec<_> = rules.range_by(2u8..4).map(|(_, v)| *v).collect();
+        assert_eq!(range, ["b", "c"]);
+        for (_, value) in rules.range_mut_by(3u8..=u8::MAX) {
+            *value = "high";
+        }
+        let range: Vec<_> = rules.range_by(0u8..=3).map(|(_, v)| *v).collect();
+        assert_eq!(range, ["a", "b", "high"]);
+        assert_eq!(rules.range_by(u16::MIN..=u16::MAX).count(), 1);
+        assert_eq!(rules.range_by(0u32..10).count(), 0);
+    }
+}
@@ -0,0 +1,276 @@
+//! An ordered set of boxed trait objects, and its iterators.
+
+use core::{fmt, ops::RangeBounds};
+use std::collections::{btree_set, BTreeSet};
+
+use super::key::{
+    check_bounds, concrete_bounds, concrete_probes, probe, probe_bounds, Probe, Stored,
+};
+use crate::{OrdObj, Result};
+
+/// Ordered set of boxed trait objects, such as `DynBTreeSet<dyn MyKey>` where
+/// `MyKey` extends `OrdObj`.
+///
+/// Keys are ordered with `cmp_object`: keys of the same concrete type are
+/// ordered by their own `Ord` implementation, and keys of different types are
+/// grouped by type. Lookups take a plain `&K` without allocating.
+///
+/// ```rust ignore
+/// let mut rules = DynBTreeSet::<dyn Rule>::new();
+/// rules.insert(Box::new(Priority(3)));
+/// rules.insert(Box::new(Priority(1)));
+/// let low: Vec<_> = rules.range(..&Priority(2) as &dyn Rule).collect();
+/// ```
+pub struct DynBTreeSet<K: ?Sized> {
+    inner: BTreeSet<Stored<K>>,
+}
+
+impl<K: ?Sized> DynBTreeSet<K> {
+    pub fn new() -> Self {
+        Self {
+            inner: BTreeSet::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Iterates over the keys in ascending order.
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter(self.inner.iter())
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+}
+
+impl<K: OrdObj + ?Sized> DynBTreeSet<K> {
+    /// Adds a key to the set, returning false if an equal key was already
+    /// present, in which case the set is not modified.
+    pub fn insert(&mut self, key: Box<K>) -> bool {
+        self.inner.insert(Stored(key))
+    }
+
+    /// Adds a key to the set, replacing and returning the equal key that was
+    /// already present, if any.
+    pub fn replace(&mut self, key: Box<K>) -> Option<Box<K>> {
+        self.inner.replace(Stored(key)).map(|stored| stored.0)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.inner.contains(probe(&key))
+    }
+
+    /// Returns the key in the set that is equal to `key`.
+    pub fn get(&self, key: &K) -> Option<&K> {
+        self.inner.get(probe(&key)).map(|stored| &*stored.0)
+    }
+
+    /// Removes the key that is equal to `key`, returning whether it was
+    /// present.
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.inner.remove(probe(&key))
+    }
+
+    /// Removes and returns the key that is equal to `key`.
+    pub fn take(&mut self, key: &K) -> Option<Box<K>> {
+        self.inner.take(probe(&key)).map(|stored| stored.0)
+    }
+
+    pub fn first(&self) -> Option<&K> {
+        self.inner.first().map(|stored| &*stored.0)
+    }
+
+    pub fn last(&self) -> Option<&K> {
+        self.inner.last().map(|stored| &*stored.0)
+    }
+
+    pub fn pop_first(&mut self) -> Option<Box<K>> {
+        self.inner.pop_first().map(|stored| stored.0)
+    }
+
+    pub fn pop_last(&mut self) -> Option<Box<K>> {
+        self.inner.pop_last().map(|stored| stored.0)
+    }
+
+    /// Iterates over the keys within `range`, in ascending order. The bounds
+    /// are borrowed keys, such as `&a..&b`, or `..=&b`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than the end, like
+    /// `BTreeSet::range`. Use `try_range` to handle that instead.
+    pub fn range<'a, R>(&self, range: R) -> Range<'_, K>
+    where
+        R: RangeBounds<&'a K>,
+        K: 'a,
+    {
+        Range(self.inner.range::<dyn Probe<K>, _>(probe_bounds(&range)))
+    }
+
+    /// Like `range`, but returns `Error::InvalidRange` instead of panicking
+    /// if the start of the range is greater than the end.
+    pub fn try_range<'a, R>(&self, range: R) -> Result<Range<'_, K>>
+    where
+        R: RangeBounds<&'a K>,
+        K: 'a,
+    {
+        let bounds = probe_bounds(&range);
+        check_bounds(&bounds)?;
+        Ok(Range(self.inner.range::<dyn Probe<K>, _>(bounds)))
+    }
+
+    /// Like `range`, but the bounds are values of a concrete type, such as
+    /// `Priority(1)..Priority(3)`, like `DynBTreeMap::range_by`. A range with
+    /// both bounds only includes keys of type `T`.
+    pub fn range_by<T, R>(&self, range: R) -> Range<'_, K>
+    where
+        T: Ord + 'static,
+        R: RangeBounds<T>,
+    {
+        let bounds = concrete_bounds(&range);
+        Range(
+            self.inner
+                .range::<dyn Probe<K>, _>(concrete_probes(&bounds)),
+        )
+    }
+
+    /// Keeps only the keys for which `f` returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(&K) -> bool) {
+        self.inner.retain(|stored| f(&stored.0))
+    }
+
+    /// Keeps every key whose concrete type is not `T`, and the keys of type
+    /// `T` for which `f` returns true.
+    pub fn retain_by<T: 'static>(&mut self, mut f: impl FnMut(&T) -> bool) {
+        self.retain(|key| key.as_any().downcast_ref().is_none_or(&mut f))
+    }
+}
+
+impl<K: ?Sized> Default for DynBTreeSet<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: fmt::Debug + ?Sized> fmt::Debug for DynBTreeSet<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K: OrdObj + ?Sized> PartialEq for DynBTreeSet<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<K: OrdObj + ?Sized> Eq for DynBTreeSet<K> {}
+
+impl<K: OrdObj + ?Sized> FromIterator<Box<K>> for DynBTreeSet<K> {
+    fn from_iter<I: IntoIterator<Item = Box<K>>>(iter: I) -> Self {
+        Self {
+            inner: iter.into_iter().map(Stored).collect(),
+        }
+    }
+}
+
+impl<K: OrdObj + ?Sized> Extend<Box<K>> for DynBTreeSet<K> {
+    fn extend<I: IntoIterator<Item = Box<K>>>(&mut self, iter: I) {
+        self.inner.extend(iter.into_iter().map(Stored))
+    }
+}
+
+impl<'a, K: ?Sized> IntoIterator for &'a DynBTreeSet<K> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: ?Sized> IntoIterator for DynBTreeSet<K> {
+    type Item = Box<K>;
+    type IntoIter = IntoIter<K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.inner.into_iter())
+    }
+}
+
+iterator! {
+    /// Iterator over the keys of a `DynBTreeSet`, in ascending order.
+    Iter<'a, K>(btree_set::Iter<'a, Stored<K>>) -> &'a K,
+    |key| &*key.0
+}
+
+iterator! {
+    /// Iterator over a range of keys of a `DynBTreeSet`.
+    Range<'a, K>(btree_set::Range<'a, Stored<K>>) -> &'a K,
+    |key| &*key.0
+}
+
+iterator! {
+    /// Owning iterator over the keys of a `DynBTreeSet`, in ascending order.
+    IntoIter<K>(btree_set::IntoIter<Stored<K>>) -> Box<K>,
+    |key| key.0
+}
+
+exact_size!(Iter<'a, K>, IntoIter<K>);
+double_ended!(Iter<'a, K>, |key| &*key.0);
+double_ended!(Range<'a, K>, |key| &*key.0);
+double_ended!(IntoIter<K>, |key| key.0);
+
+#[cfg(test)]
+mod test {
+    use core::ops::Bound;
+
+    use super::*;
+
+    trait Rule: OrdObj + fmt::Debug {}
+    impl<T: Ord + fmt::Debug + 'static> Rule for T {}
+
+    fn ints<'a>(keys: impl Iterator<Item = &'a dyn Rule>) -> Vec<u32> {
+        keys.map(|k| *k.as_any().downcast_ref::<u32>().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn ordered() {
+        let mut set: DynBTreeSet<dyn Rule> = [5u32, 1, 3]
+            .map(|x| Box::new(x) as Box<dyn Rule>)
+            .into_iter()
+            .collect();
+        assert!(set.insert(Box::new(4u32)));
+        assert!(!set.insert(Box::new(4u32)));
+        assert_eq!(ints(set.iter()), [1, 3, 4, 5]);
+        assert_eq!(ints(set.iter().rev()), [5, 4, 3, 1]);
+        assert!(set.contains(&3u32));
+        assert!(!set.contains(&3u64));
+        let (two, four): (&dyn Rule, &dyn Rule) = (&2u32, &4u32);
+        assert_eq!(ints(set.range(two..four)), [3]);
+        assert_eq!(ints(set.range(two..=four)), [3, 4]);
+        assert_eq!(ints(set.range(..=four)), [1, 3, 4]);
+        assert_eq!(ints(set.range(four..)), [4, 5]);
+        assert_eq!(ints(set.try_range(two..=four).unwrap()), [3, 4]);
+        assert!(set.try_range(four..two).is_err());
+        assert!(set.try_range(four..four).is_ok());
+        assert!(set
+            .try_range((Bound::Excluded(four), Bound::Excluded(four)))
+            .is_err());
+        assert_eq!(ints(set.range_by(2u32..4)), [3]);
+        assert_eq!(ints(set.range_by(4u32..=u32::MAX)), [4, 5]);
+        assert_eq!(set.range_by(2u64..4).count(), 0);
+        assert!(set.remove(&1u32));
+        assert_eq!(ints(set.first().into_iter()), [3]);
+        assert_eq!(ints(set.pop_last().as_deref().into_iter()), [5]);
+        assert_eq!(set.len(), 2);
+    }
+}
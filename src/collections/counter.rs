@@ -0,0 +1,244 @@
+//! A counting multiset of boxed trait objects.
+
+use core::{
+    cmp::Reverse,
+    fmt,
+    hash::{BuildHasher, Hash},
+};
+use std::collections::hash_map::RandomState;
+
+use super::{hash_map, DynHashMap};
+use crate::{EqObj, HashObj};
+
+/// Multiset of boxed trait objects, such as `DynCounter<dyn MyKey>` where
+/// `MyKey` extends `HashObj` and `EqObj`, which counts how many times each
+/// distinct key was inserted.
+///
+/// ```rust ignore
+/// let mut events = DynCounter::<dyn Event>::new();
+/// events.insert(Box::new(Click { button: 1 }));
+/// events.insert(Box::new(Click { button: 1 }));
+/// events.insert(Box::new(Scroll(-3)));
+/// assert_eq!(events.count(&Click { button: 1 }), 2);
+/// ```
+pub struct DynCounter<K: ?Sized, S = RandomState> {
+    inner: DynHashMap<K, usize, S>,
+    total: usize,
+}
+
+impl<K: HashObj + EqObj + ?Sized> DynCounter<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K: ?Sized, S> DynCounter<K, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            inner: DynHashMap::with_hasher(hasher),
+            total: 0,
+        }
+    }
+
+    /// Number of distinct keys.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Sum of the counts of all keys.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Iterates over the distinct keys and their counts, in arbitrary order.
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter(self.inner.iter())
+    }
+
+    /// Returns the distinct keys and their counts, from most to least common.
+    pub fn most_common(&self) -> Vec<(&K, usize)> {
+        let mut counts: Vec<_> = self.iter().collect();
+        counts.sort_by_key(|&(_, count)| Reverse(count));
+        counts
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear();
+        self.total = 0;
+    }
+}
+
+impl<K, S> DynCounter<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    /// Increments the count of `key`, returning the new count.
+    pub fn insert(&mut self, key: Box<K>) -> usize {
+        self.insert_n(key, 1)
+    }
+
+    /// Adds `n` to the count of `key`, returning the new count. Inserting a
+    /// key zero times does not add it to the counter.
+    pub fn insert_n(&mut self, key: Box<K>, n: usize) -> usize {
+        if n == 0 {
+            return self.count(&key);
+        }
+        self.total += n;
+        let count = self.inner.entry(key).or_insert(0);
+        *count += n;
+        *count
+    }
+
+    /// Returns how many times `key` was inserted, or zero if it is absent.
+    pub fn count(&self, key: &K) -> usize {
+        self.inner.get(key).copied().unwrap_or(0)
+    }
+
+    /// Returns the count of the key of type `T` equal to `value`. Only keys of
+    /// type `T` can match.
+    pub fn count_by<T: Hash + PartialEq + 'static>(&self, value: &T) -> usize {
+        self.inner.get_by(value).copied().unwrap_or(0)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Decrements the count of `key`, removing it once the count reaches zero.
+    /// Returns the count before decrementing.
+    pub fn remove(&mut self, key: &K) -> usize {
+        let Some(count) = self.inner.get_mut(key) else {
+            return 0;
+        };
+        *count -= 1;
+        self.total -= 1;
+        if *count == 0 {
+            self.inner.remove(key);
+            return 1;
+        }
+        *count + 1
+    }
+
+    /// Removes `key` entirely, returning its count.
+    pub fn remove_all(&mut self, key: &K) -> usize {
+        let count = self.inner.remove(key).unwrap_or(0);
+        self.total -= count;
+        count
+    }
+}
+
+impl<K: ?Sized, S: Default> Default for DynCounter<K, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K: fmt::Debug + ?Sized, S> fmt::Debug for DynCounter<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, S> PartialEq for DynCounter<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.total == other.total && self.inner == other.inner
+    }
+}
+
+impl<K, S> Eq for DynCounter<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+}
+
+impl<K, S> FromIterator<Box<K>> for DynCounter<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = Box<K>>>(iter: I) -> Self {
+        let mut counter = Self::default();
+        counter.extend(iter);
+        counter
+    }
+}
+
+impl<K, S> Extend<Box<K>> for DynCounter<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = Box<K>>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+impl<'a, K: ?Sized, S> IntoIterator for &'a DynCounter<K, S> {
+    type Item = (&'a K, usize);
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+iterator! {
+    /// Iterator over the distinct keys of a `DynCounter` and their counts.
+    Iter<'a, K>(hash_map::Iter<'a, K, usize>) -> (&'a K, usize),
+    |(key, count)| (key, *count)
+}
+
+exact_size!(Iter<'a, K>);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::Key as Event;
+
+    #[test]
+    fn counts() {
+        let mut events: DynCounter<dyn Event> = [
+            Box::new("click") as Box<dyn Event>,
+            Box::new("click"),
+            Box::new(3u8),
+            Box::new("click"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events.total(), 4);
+        assert_eq!(events.count(&"click"), 3);
+        assert_eq!(events.count_by(&3u8), 1);
+        assert_eq!(events.count(&3u16), 0);
+        assert_eq!(events.insert_n(Box::new(3u8), 4), 5);
+        let most_common: Vec<usize> = events.most_common().iter().map(|x| x.1).collect();
+        assert_eq!(most_common, [5, 3]);
+    }
+
+    #[test]
+    fn remove() {
+        let mut events = DynCounter::<dyn Event>::new();
+        events.insert_n(Box::new('a'), 2);
+        assert_eq!(events.remove(&'a'), 2);
+        assert_eq!(events.remove(&'a'), 1);
+        assert_eq!(events.remove(&'a'), 0);
+        assert!(events.is_empty());
+        events.insert_n(Box::new('b'), 3);
+        assert_eq!(events.remove_all(&'b'), 3);
+        assert_eq!(events.total(), 0);
+        assert_eq!(events.insert_n(Box::new('c'), 0), 0);
+        assert!(!events.contains(&'c'));
+    }
+}
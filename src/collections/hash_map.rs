@@ -0,0 +1,761 @@
+//! A hash map keyed by boxed trait objects, and its iterators.
+
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    ops::Index,
+};
+use std::collections::{hash_map, hash_map::RandomState, HashMap};
+
+use super::key::{EqStats, Hashed, PassThrough, Prehasher};
+use crate::{EqObj, HashError, HashObj, TryHashObj};
+
+/// Hash map keyed by boxed trait objects, such as `DynHashMap<dyn MyKey, V>`
+/// where `MyKey` extends `HashObj` and `EqObj`.
+///
+/// Like `DynHashSet`, keys are compared with `eq_object` and hashed with
+/// `hash_object`, and lookups take a plain `&K` without allocating.
+///
+/// ```rust ignore
+/// let mut map = DynHashMap::<dyn MyKey, &str>::new();
+/// map.insert(Box::new(1), "int");
+/// map.insert(Box::new(1u8), "byte");
+/// assert_eq!(map[&1u8], "byte");
+/// ```
+pub struct DynHashMap<K: ?Sized, V, S = RandomState> {
+    inner: HashMap<Hashed<K>, V, PassThrough>,
+    prehasher: Prehasher<S>,
+}
+
+impl<K: HashObj + EqObj + ?Sized, V> DynHashMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, RandomState::new())
+    }
+}
+
+impl<K: ?Sized, V, S> DynHashMap<K, V, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            inner: HashMap::with_hasher(PassThrough),
+            prehasher: Prehasher::new(hasher),
+        }
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            inner: HashMap::with_capacity_and_hasher(capacity, PassThrough),
+            prehasher: Prehasher::new(hasher),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.inner.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut(self.inner.iter_mut())
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys(self.inner.keys())
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values(self.inner.values())
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut(self.inner.values_mut())
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+
+    /// Removes all the entries, and returns them in an iterator. The map is
+    /// empty afterwards even if the iterator is dropped early.
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        Drain(self.inner.drain())
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.prehasher.build_hasher
+    }
+
+    /// How often lookups had to call `eq_object`, rather than telling keys
+    /// apart by their hash alone, since the map was created or the
+    /// statistics were last reset.
+    pub fn eq_stats(&self) -> EqStats {
+        self.prehasher.counters.stats()
+    }
+
+    pub fn reset_eq_stats(&self) {
+        self.prehasher.counters.reset()
+    }
+}
+
+impl<K, V, S> DynHashMap<K, V, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    /// Inserts a value, returning the value previously stored for an equal
+    /// key, if any. In that case the key is not updated.
+    pub fn insert(&mut self, key: Box<K>, value: V) -> Option<V> {
+        self.inner.insert(self.prehasher.hashed(key), value)
+    }
+
+    /// Gets the entry for `key`, for in-place manipulation. The key is only
+    /// stored if the entry is vacant and a value is inserted.
+    pub fn entry(&mut self, key: Box<K>) -> Entry<'_, K, V> {
+        match self.inner.entry(self.prehasher.hashed(key)) {
+            hash_map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry(entry)),
+            hash_map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry(entry)),
+        }
+    }
+
+    /// Returns the value for `key`, inserting one first if the key is not
+    /// present. Unlike `entry`, this takes a borrowed key, and only calls
+    /// `to_owned` to box it if a value is actually inserted, so nothing is
+    /// allocated when the key is already present.
+    ///
+    /// ```rust ignore
+    /// let count = map.get_or_insert_with(&1u8, |_| Box::new(1u8), || 0);
+    /// *count += 1;
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to_owned` returns a key that is not equal to `key`.
+    pub fn get_or_insert_with(
+        &mut self,
+        key: &K,
+        to_owned: impl FnOnce(&K) -> Box<K>,
+        value: impl FnOnce() -> V,
+    ) -> &mut V {
+        self.get_or_insert_with_hashed(self.hash_key(key), key, to_owned, value)
+    }
+
+    /// The hash that the map computes for `key` with its `BuildHasher`. The
+    /// `*_hashed` methods take it along with the key, so a key that is
+    /// looked up several times, or in several maps sharing a `BuildHasher`,
+    /// is only hashed once.
+    pub fn hash_key(&self, key: &K) -> u64 {
+        self.prehasher.hash(key)
+    }
+
+    /// Like `get`, with the `hash_key` of `key`. Passing any other hash
+    /// makes the lookup miss.
+    pub fn get_hashed(&self, hash: u64, key: &K) -> Option<&V> {
+        self.inner
+            .get(self.prehasher.probe_hashed(hash, key).as_dyn())
+    }
+
+    /// Like `get_mut`, with the `hash_key` of `key`. See `get_hashed`.
+    pub fn get_mut_hashed(&mut self, hash: u64, key: &K) -> Option<&mut V> {
+        self.inner
+            .get_mut(self.prehasher.probe_hashed(hash, key).as_dyn())
+    }
+
+    /// Like `contains_key`, with the `hash_key` of `key`. See `get_hashed`.
+    pub fn contains_key_hashed(&self, hash: u64, key: &K) -> bool {
+        self.inner
+            .contains_key(self.prehasher.probe_hashed(hash, key).as_dyn())
+    }
+
+    /// Like `get_or_insert_with`, with the `hash_key` of `key`, which is
+    /// stored with the key if it is inserted, so `key` is never hashed.
+    /// Passing any other hash stores the key where the other methods cannot
+    /// find it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to_owned` returns a key that is not equal to `key`.
+    pub fn get_or_insert_with_hashed(
+        &mut self,
+        hash: u64,
+        key: &K,
+        to_owned: impl FnOnce(&K) -> Box<K>,
+        value: impl FnOnce() -> V,
+    ) -> &mut V {
+        if !self.contains_key_hashed(hash, key) {
+            let stored = Hashed {
+                hash,
+                key: to_owned(key),
+            };
+            self.inner.insert(stored, value());
+        }
+        self.get_mut_hashed(hash, key)
+            .expect("the key was just inserted")
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.inner.get(self.prehasher.probe(key).as_dyn())
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.inner.get_mut(self.prehasher.probe(key).as_dyn())
+    }
+
+    /// Returns the stored key that is equal to `key`, and its value.
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        self.inner
+            .get_key_value(self.prehasher.probe(key).as_dyn())
+            .map(|(stored, value)| (&*stored.key, value))
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(self.prehasher.probe(key).as_dyn())
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.inner.remove(self.prehasher.probe(key).as_dyn())
+    }
+
+    pub fn remove_entry(&mut self, key: &K) -> Option<(Box<K>, V)> {
+        self.inner
+            .remove_entry(self.prehasher.probe(key).as_dyn())
+            .map(|(stored, value)| (stored.key, value))
+    }
+
+    /// Returns true if the map contains a key of type `T` equal to `value`.
+    ///
+    /// Unlike `contains_key`, this hashes `value` directly, so it works with
+    /// any `value` whose type can be used as a key, even if `&T` cannot be
+    /// converted to `&K`. Comparators in the [`registry`](crate::registry) are
+    /// not used: only keys of type `T` can match.
+    pub fn contains_by<T: Hash + PartialEq + 'static>(&self, value: &T) -> bool {
+        self.inner
+            .contains_key(self.prehasher.probe_concrete(value).as_dyn())
+    }
+
+    /// Returns the value for the key of type `T` equal to `value`. See
+    /// `contains_by`.
+    pub fn get_by<T: Hash + PartialEq + 'static>(&self, value: &T) -> Option<&V> {
+        self.inner
+            .get(self.prehasher.probe_concrete(value).as_dyn())
+    }
+
+    /// Returns the value for the key of type `T` equal to `value`. See
+    /// `contains_by`.
+    pub fn get_mut_by<T: Hash + PartialEq + 'static>(&mut self, value: &T) -> Option<&mut V> {
+        self.inner
+            .get_mut(self.prehasher.probe_concrete(value).as_dyn())
+    }
+
+    /// Removes the key of type `T` equal to `value`, returning its value. See
+    /// `contains_by`.
+    pub fn remove_by<T: Hash + PartialEq + 'static>(&mut self, value: &T) -> Option<V> {
+        self.inner
+            .remove(self.prehasher.probe_concrete(value).as_dyn())
+    }
+
+    /// Keeps only the entries for which `f` returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        self.inner.retain(|stored, value| f(&stored.key, value))
+    }
+
+    /// Keeps every entry whose key's concrete type is not `T`, and the entries
+    /// with a key of type `T` for which `f` returns true.
+    pub fn retain_by<T: 'static>(&mut self, mut f: impl FnMut(&T, &mut V) -> bool) {
+        self.retain(|key, value| key.as_any().downcast_ref().is_none_or(|key| f(key, value)))
+    }
+
+    /// Removes the entries for which `f` returns true, and returns them in an
+    /// iterator, like `retain` with the opposite condition, but without
+    /// dropping the removed entries. If the iterator is dropped early, the
+    /// entries it did not reach are kept.
+    pub fn extract_if<'a>(
+        &'a mut self,
+        mut f: impl FnMut(&K, &mut V) -> bool + 'a,
+    ) -> impl Iterator<Item = (Box<K>, V)> + 'a {
+        self.inner
+            .extract_if(move |stored, value| f(&stored.key, value))
+            .map(|(stored, value)| (stored.key, value))
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional)
+    }
+}
+
+/// Methods for keys that may fail to hash, such as `DynHashMap<dyn MyProxy,
+/// _>` where `MyProxy` extends `TryHashObj` and `EqObj`, which return the
+/// error instead of panicking. Create such a map with `default` or
+/// `with_hasher`.
+impl<K, V, S> DynHashMap<K, V, S>
+where
+    K: TryHashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    /// The hash that the map computes for `key`, or why it cannot be hashed.
+    pub fn try_hash_key(&self, key: &K) -> Result<u64, HashError> {
+        self.prehasher.try_hash(key)
+    }
+
+    /// Inserts a value like `insert`, unless the key cannot be hashed.
+    pub fn try_insert(&mut self, key: Box<K>, value: V) -> Result<Option<V>, HashError> {
+        let hash = self.try_hash_key(&key)?;
+        Ok(self.inner.insert(Hashed { hash, key }, value))
+    }
+
+    /// Like `get`, unless `key` cannot be hashed.
+    pub fn try_get(&self, key: &K) -> Result<Option<&V>, HashError> {
+        let hash = self.try_hash_key(key)?;
+        Ok(self
+            .inner
+            .get(self.prehasher.probe_hashed(hash, key).as_dyn()))
+    }
+
+    /// Like `remove`, unless `key` cannot be hashed.
+    pub fn try_remove(&mut self, key: &K) -> Result<Option<V>, HashError> {
+        let hash = self.try_hash_key(key)?;
+        Ok(self
+            .inner
+            .remove(self.prehasher.probe_hashed(hash, key).as_dyn()))
+    }
+
+    /// Inserts every entry whose key can be hashed, and skips the others,
+    /// which are returned along with their errors.
+    pub fn try_extend(
+        &mut self,
+        entries: impl IntoIterator<Item = (Box<K>, V)>,
+    ) -> Vec<(Box<K>, V, HashError)> {
+        let mut skipped = Vec::new();
+        for (key, value) in entries {
+            match self.try_hash_key(&key) {
+                Ok(hash) => {
+                    self.inner.insert(Hashed { hash, key }, value);
+                }
+                Err(error) => skipped.push((key, value, error)),
+            }
+        }
+        skipped
+    }
+}
+
+impl<K: ?Sized, V, S: Default> Default for DynHashMap<K, V, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K: fmt::Debug + ?Sized, V: fmt::Debug, S> fmt::Debug for DynHashMap<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, S> PartialEq for DynHashMap<K, V, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    V: PartialEq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<K, V, S> Eq for DynHashMap<K, V, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, S> Index<&K> for DynHashMap<K, V, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    type Output = V;
+
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the map.
+    fn index(&self, key: &K) -> &V {
+        self.get(key).expect("key not found in DynHashMap")
+    }
+}
+
+impl<K, V, S> FromIterator<(Box<K>, V)> for DynHashMap<K, V, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (Box<K>, V)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V, S> Extend<(Box<K>, V)> for DynHashMap<K, V, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (Box<K>, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K: ?Sized, V, S> IntoIterator for &'a DynHashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: ?Sized, V, S> IntoIterator for &'a mut DynHashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K: ?Sized, V, S> IntoIterator for DynHashMap<K, V, S> {
+    type Item = (Box<K>, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.inner.into_iter())
+    }
+}
+
+/// A view into a single entry of a `DynHashMap`, which is either vacant or
+/// occupied. Returned by `DynHashMap::entry`.
+pub enum Entry<'a, K: ?Sized, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+/// An occupied entry of a `DynHashMap`.
+pub struct OccupiedEntry<'a, K: ?Sized, V>(hash_map::OccupiedEntry<'a, Hashed<K>, V>);
+
+/// A vacant entry of a `DynHashMap`.
+pub struct VacantEntry<'a, K: ?Sized, V>(hash_map::VacantEntry<'a, Hashed<K>, V>);
+
+impl<'a, K: ?Sized, V> Entry<'a, K, V> {
+    /// Inserts `default` if the entry is vacant, and returns the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    /// Inserts the result of `default` if the entry is vacant, and returns
+    /// the value.
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        self.or_insert_with_key(|_| default())
+    }
+
+    /// Inserts the result of `default`, which is passed the key, if the entry
+    /// is vacant, and returns the value.
+    pub fn or_insert_with_key(self, default: impl FnOnce(&K) -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(entry.key());
+                entry.insert(value)
+            }
+        }
+    }
+
+    /// Calls `f` with the value if the entry is occupied.
+    pub fn and_modify(mut self, f: impl FnOnce(&mut V)) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+impl<'a, K: ?Sized, V: Default> Entry<'a, K, V> {
+    /// Inserts the default value if the entry is vacant, and returns the
+    /// value.
+    pub fn or_default(self) -> &'a mut V {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<'a, K: ?Sized, V> OccupiedEntry<'a, K, V> {
+    /// The key that is stored in the map, rather than the one passed to
+    /// `entry`.
+    pub fn key(&self) -> &K {
+        &self.0.key().key
+    }
+
+    pub fn get(&self) -> &V {
+        self.0.get()
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.0.get_mut()
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.0.into_mut()
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn insert(&mut self, value: V) -> V {
+        self.0.insert(value)
+    }
+
+    pub fn remove(self) -> V {
+        self.0.remove()
+    }
+
+    pub fn remove_entry(self) -> (Box<K>, V) {
+        let (key, value) = self.0.remove_entry();
+        (key.key, value)
+    }
+}
+
+impl<'a, K: ?Sized, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.0.key().key
+    }
+
+    pub fn into_key(self) -> Box<K> {
+        self.0.into_key().key
+    }
+
+    /// Stores the key and `value` in the map, and returns the value.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.0.insert(value)
+    }
+}
+
+iterator! {
+    /// Iterator over the entries of a `DynHashMap`.
+    Iter<'a, K, V>(hash_map::Iter<'a, Hashed<K>, V>) -> (&'a K, &'a V),
+    |(key, value)| (&*key.key, value)
+}
+
+iterator! {
+    /// Mutable iterator over the entries of a `DynHashMap`.
+    IterMut<'a, K, V>(hash_map::IterMut<'a, Hashed<K>, V>) -> (&'a K, &'a mut V),
+    |(key, value)| (&*key.key, value)
+}
+
+iterator! {
+    /// Iterator over the keys of a `DynHashMap`.
+    Keys<'a, K, V>(hash_map::Keys<'a, Hashed<K>, V>) -> &'a K,
+    |key| &*key.key
+}
+
+iterator! {
+    /// Iterator over the values of a `DynHashMap`.
+    Values<'a, K, V>(hash_map::Values<'a, Hashed<K>, V>) -> &'a V,
+    |value| value
+}
+
+iterator! {
+    /// Mutable iterator over the values of a `DynHashMap`.
+    ValuesMut<'a, K, V>(hash_map::ValuesMut<'a, Hashed<K>, V>) -> &'a mut V,
+    |value| value
+}
+
+iterator! {
+    /// Owning iterator over the entries of a `DynHashMap`.
+    IntoIter<K, V>(hash_map::IntoIter<Hashed<K>, V>) -> (Box<K>, V),
+    |(key, value)| (key.key, value)
+}
+
+iterator! {
+    /// Draining iterator over the entries of a `DynHashMap`.
+    Drain<'a, K, V>(hash_map::Drain<'a, Hashed<K>, V>) -> (Box<K>, V),
+    |(key, value)| (key.key, value)
+}
+
+exact_size!(
+    Iter<'a, K, V>,
+    IterMut<'a, K, V>,
+    Keys<'a, K, V>,
+    Values<'a, K, V>,
+    ValuesMut<'a, K, V>,
+    IntoIter<K, V>,
+    Drain<'a, K, V>
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::Key;
+
+    #[test]
+    fn map_operations() {
+        let mut map = DynHashMap::<dyn Key, &str>::new();
+        assert_eq!(map.insert(Box::new(1), "int"), None);
+        assert_eq!(map.insert(Box::new(1u8), "byte"), None);
+        assert_eq!(map.insert(Box::new(1), "i32"), Some("int"));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&1u8], "byte");
+        assert_eq!(map.get(&1), Some(&"i32"));
+        assert_eq!(map.get(&2), None);
+        *map.get_mut(&1u8).unwrap() = "u8";
+        assert_eq!(map.get_key_value(&1u8).map(|(_, v)| *v), Some("u8"));
+        assert!(map.contains_key(&1));
+        assert_eq!(map.remove(&1), Some("i32"));
+        assert!(!map.contains_key(&1));
+        let (key, value) = map.remove_entry(&1u8).unwrap();
+        assert!(key.as_any().is::<u8>());
+        assert_eq!(value, "u8");
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn concrete_lookups() {
+        let mut map = DynHashMap::<dyn Key, u32>::new();
+        map.insert(Box::new(String::from("a")), 1);
+        map.insert(Box::new(2u8), 2);
+        assert_eq!(map.get_by(&String::from("a")), Some(&1));
+        assert!(map.contains_by(&2u8));
+        assert!(!map.contains_by(&2u16));
+        *map.get_mut_by(&2u8).unwrap() += 1;
+        assert_eq!(map.remove_by(&2u8), Some(3));
+        assert_eq!(map.remove_by(&2u8), None);
+    }
+
+    #[test]
+    fn entries() {
+        let mut map = DynHashMap::<dyn Key, u32>::new();
+        *map.entry(Box::new("a")).or_insert(0) += 1;
+        *map.entry(Box::new("a")).or_insert(0) += 1;
+        map.entry(Box::new(1u8)).and_modify(|v| *v = 9).or_default();
+        map.entry(Box::new(1u8)).and_modify(|v| *v = 9).or_default();
+        assert_eq!(map[&"a"], 2);
+        assert_eq!(map[&1u8], 9);
+        match map.entry(Box::new("b")) {
+            Entry::Vacant(entry) => assert!(entry.into_key().as_any().is::<&str>()),
+            Entry::Occupied(_) => panic!("should be vacant"),
+        }
+        match map.entry(Box::new("a")) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 2),
+            Entry::Vacant(_) => panic!("should be occupied"),
+        }
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn get_or_insert_with() {
+        let mut map = DynHashMap::<dyn Key, u32>::new();
+        let mut boxed = 0;
+        for _ in 0..3 {
+            let key: &dyn Key = &"a";
+            *map.get_or_insert_with(
+                key,
+                |_| {
+                    boxed += 1;
+                    Box::new("a")
+                },
+                || 0,
+            ) += 1;
+        }
+        assert_eq!(boxed, 1);
+        assert_eq!(map[&"a"], 3);
+
+        let key: &dyn Key = &"b";
+        let hash = map.hash_key(key);
+        assert!(!map.contains_key_hashed(hash, key));
+        *map.get_or_insert_with_hashed(hash, key, |_| Box::new("b"), || 1) += 1;
+        assert_eq!(map.get_hashed(hash, key), Some(&2));
+        assert_eq!(map[&"b"], 2);
+        assert_eq!(map.get_hashed(hash ^ 1, key), None);
+    }
+
+    #[test]
+    fn iterate_and_collect() {
+        let map: DynHashMap<dyn Key, u32> =
+            [(Box::new("a") as Box<dyn Key>, 1), (Box::new('b'), 2)]
+                .into_iter()
+                .collect();
+        let mut other = DynHashMap::new();
+        other.extend(map.iter().map(|(k, v)| (k.as_any(), *v)).map(|(k, v)| {
+            let key: Box<dyn Key> = match k.downcast_ref::<&str>() {
+                Some(s) => Box::new(*s),
+                None => Box::new(*k.downcast_ref::<char>().unwrap()),
+            };
+            (key, v)
+        }));
+        assert_eq!(map, other);
+        assert_eq!(map.values().sum::<u32>(), 3);
+        assert_eq!(map.keys().count(), 2);
+        for (_, value) in &mut other {
+            *value += 1;
+        }
+        assert_eq!(other.into_iter().map(|(_, v)| v).sum::<u32>(), 5);
+    }
+
+    #[test]
+    fn extract_and_drain() {
+        let mut map = DynHashMap::<dyn Key, u32>::new();
+        map.insert(Box::new(1u8), 1);
+        map.insert(Box::new("a"), 2);
+        map.insert(Box::new('b'), 3);
+        let extracted: Vec<_> = map.extract_if(|_, value| *value > 1).collect();
+        assert_eq!(extracted.len(), 2);
+        assert_eq!(map.len(), 1);
+        let drained: Vec<_> = map.drain().collect();
+        assert_eq!(drained.len(), 1);
+        assert_eq!(drained[0].0.as_any().downcast_ref(), Some(&1u8));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn fallible_hashing() {
+        trait Remote: TryHashObj + EqObj {}
+        impl<T: TryHashObj + Eq + 'static> Remote for T {}
+
+        #[derive(PartialEq, Eq)]
+        struct Poisoned;
+
+        impl TryHashObj for Poisoned {
+            fn try_hash_object(&self, _: &mut dyn core::hash::Hasher) -> Result<(), HashError> {
+                Err(HashError::new::<Self>("poisoned"))
+            }
+        }
+
+        let mut map = DynHashMap::<dyn Remote, u8>::default();
+        assert_eq!(map.try_insert(Box::new("a"), 1), Ok(None));
+        assert!(map.try_insert(Box::new(Poisoned), 2).is_err());
+        assert_eq!(map.try_get(&"a"), Ok(Some(&1)));
+        let skipped = map.try_extend([
+            (Box::new(Poisoned) as Box<dyn Remote>, 3),
+            (Box::new("b"), 4),
+        ]);
+        assert_eq!(skipped.iter().map(|(_, v, _)| *v).collect::<Vec<_>>(), [3]);
+        assert_eq!(map.try_remove(&"b"), Ok(Some(4)));
+        assert_eq!(map.len(), 1);
+    }
+}
@@ -0,0 +1,564 @@
+//! A hash set of boxed trait objects, and its iterators.
+
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+};
+use std::collections::{hash_map::RandomState, hash_set, HashSet};
+
+use super::key::{EqStats, Hashed, PassThrough, Prehasher};
+use crate::{EqObj, HashError, HashObj, TryHashObj};
+
+/// Hash set of boxed trait objects, such as `DynHashSet<dyn MyKey>` where
+/// `MyKey` extends `HashObj` and `EqObj`.
+///
+/// Keys are compared with `eq_object` and hashed with `hash_object`, so you
+/// don't need to wrap them in `Obj`, or to call `impl_hash!` and `impl_eq!`
+/// for the trait. Lookups take a plain `&K`, such as `&5` for a
+/// `DynHashSet<dyn MyKey>`, and never allocate. Each key is hashed once, when
+/// it is inserted, and its hash is stored next to it, so `eq_object` only runs
+/// for keys whose hash matches the probe's. See `eq_stats`.
+///
+/// ```rust ignore
+/// let mut set = DynHashSet::<dyn MyKey>::new();
+/// set.insert(Box::new(1));
+/// set.insert(Box::new("one"));
+/// assert!(set.contains(&1));
+/// ```
+pub struct DynHashSet<K: ?Sized, S = RandomState> {
+    inner: HashSet<Hashed<K>, PassThrough>,
+    prehasher: Prehasher<S>,
+}
+
+impl<K: HashObj + EqObj + ?Sized> DynHashSet<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_hasher(capacity, RandomState::new())
+    }
+}
+
+impl<K: ?Sized, S> DynHashSet<K, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            inner: HashSet::with_hasher(PassThrough),
+            prehasher: Prehasher::new(hasher),
+        }
+    }
+
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            inner: HashSet::with_capacity_and_hasher(capacity, PassThrough),
+            prehasher: Prehasher::new(hasher),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter(self.inner.iter())
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+
+    /// Removes all the keys, and returns them in an iterator. The set is
+    /// empty afterwards even if the iterator is dropped early.
+    pub fn drain(&mut self) -> Drain<'_, K> {
+        Drain(self.inner.drain())
+    }
+
+    pub fn hasher(&self) -> &S {
+        &self.prehasher.build_hasher
+    }
+
+    /// How often lookups had to call `eq_object`, rather than telling keys
+    /// apart by their hash alone, since the set was created or the
+    /// statistics were last reset.
+    pub fn eq_stats(&self) -> EqStats {
+        self.prehasher.counters.stats()
+    }
+
+    pub fn reset_eq_stats(&self) {
+        self.prehasher.counters.reset()
+    }
+}
+
+impl<K, S> DynHashSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    /// Adds a key to the set, returning false if an equal key was already
+    /// present, in which case the set is not modified.
+    pub fn insert(&mut self, key: Box<K>) -> bool {
+        self.inner.insert(self.prehasher.hashed(key))
+    }
+
+    /// Adds a key to the set, replacing and returning the equal key that was
+    /// already present, if any.
+    pub fn replace(&mut self, key: Box<K>) -> Option<Box<K>> {
+        self.inner
+            .replace(self.prehasher.hashed(key))
+            .map(|stored| stored.key)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.inner.contains(self.prehasher.probe(key).as_dyn())
+    }
+
+    /// Returns the key in the set that is equal to `key`.
+    pub fn get(&self, key: &K) -> Option<&K> {
+        self.inner
+            .get(self.prehasher.probe(key).as_dyn())
+            .map(|stored| &*stored.key)
+    }
+
+    /// The hash that the set computes for `key` with its `BuildHasher`, like
+    /// `DynHashMap::hash_key`.
+    pub fn hash_key(&self, key: &K) -> u64 {
+        self.prehasher.hash(key)
+    }
+
+    /// Like `contains`, with the `hash_key` of `key`. See
+    /// `DynHashMap::get_hashed`.
+    pub fn contains_hashed(&self, hash: u64, key: &K) -> bool {
+        self.inner
+            .contains(self.prehasher.probe_hashed(hash, key).as_dyn())
+    }
+
+    /// Like `get`, with the `hash_key` of `key`. See `DynHashMap::get_hashed`.
+    pub fn get_hashed(&self, hash: u64, key: &K) -> Option<&K> {
+        self.inner
+            .get(self.prehasher.probe_hashed(hash, key).as_dyn())
+            .map(|stored| &*stored.key)
+    }
+
+    /// Returns the key in the set that is equal to `key`, inserting the key
+    /// that `to_owned` boxes first if there is none, so at most one box is
+    /// allocated. `hash` is the `hash_key` of `key`, which is stored with
+    /// the new key, like `DynHashMap::get_or_insert_with_hashed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to_owned` returns a key that is not equal to `key`.
+    pub fn get_or_insert_with_hashed(
+        &mut self,
+        hash: u64,
+        key: &K,
+        to_owned: impl FnOnce(&K) -> Box<K>,
+    ) -> &K {
+        if !self.contains_hashed(hash, key) {
+            self.inner.insert(Hashed {
+                hash,
+                key: to_owned(key),
+            });
+        }
+        self.get_hashed(hash, key)
+            .expect("the key was just inserted")
+    }
+
+    /// Removes the key that is equal to `key`, returning whether it was
+    /// present.
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.inner.remove(self.prehasher.probe(key).as_dyn())
+    }
+
+    /// Removes and returns the key that is equal to `key`.
+    pub fn take(&mut self, key: &K) -> Option<Box<K>> {
+        self.inner
+            .take(self.prehasher.probe(key).as_dyn())
+            .map(|stored| stored.key)
+    }
+
+    /// Returns true if the set contains a key of type `T` equal to `value`.
+    ///
+    /// Unlike `contains`, this hashes `value` directly, so it works with any
+    /// `value` whose type can be stored in the set, even if `&T` cannot be
+    /// converted to `&K`. Comparators in the [`registry`](crate::registry) are
+    /// not used: only keys of type `T` can match.
+    pub fn contains_by<T: Hash + PartialEq + 'static>(&self, value: &T) -> bool {
+        self.inner
+            .contains(self.prehasher.probe_concrete(value).as_dyn())
+    }
+
+    /// Returns the key of type `T` equal to `value`. See `contains_by`.
+    pub fn get_by<T: Hash + PartialEq + 'static>(&self, value: &T) -> Option<&K> {
+        self.inner
+            .get(self.prehasher.probe_concrete(value).as_dyn())
+            .map(|stored| &*stored.key)
+    }
+
+    /// Removes the key of type `T` equal to `value`, returning whether it was
+    /// present. See `contains_by`.
+    pub fn remove_by<T: Hash + PartialEq + 'static>(&mut self, value: &T) -> bool {
+        self.inner
+            .remove(self.prehasher.probe_concrete(value).as_dyn())
+    }
+
+    /// Keeps only the keys for which `f` returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(&K) -> bool) {
+        self.inner.retain(|stored| f(&stored.key))
+    }
+
+    /// Keeps every key whose concrete type is not `T`, and the keys of type
+    /// `T` for which `f` returns true.
+    pub fn retain_by<T: 'static>(&mut self, mut f: impl FnMut(&T) -> bool) {
+        self.retain(|key| key.as_any().downcast_ref().is_none_or(&mut f))
+    }
+
+    /// Removes the keys for which `f` returns true, and returns them in an
+    /// iterator, like `retain` with the opposite condition, but without
+    /// dropping the removed keys. If the iterator is dropped early, the keys
+    /// it did not reach are kept.
+    pub fn extract_if<'a>(
+        &'a mut self,
+        mut f: impl FnMut(&K) -> bool + 'a,
+    ) -> impl Iterator<Item = Box<K>> + 'a {
+        self.inner
+            .extract_if(move |stored| f(&stored.key))
+            .map(|stored| stored.key)
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional)
+    }
+}
+
+/// Methods for keys that may fail to hash, such as `DynHashSet<dyn MyProxy>`
+/// where `MyProxy` extends `TryHashObj` and `EqObj`, which return the error
+/// instead of panicking. Create such a set with `default` or `with_hasher`.
+impl<K, S> DynHashSet<K, S>
+where
+    K: TryHashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    /// The hash that the set computes for `key`, or why it cannot be hashed.
+    pub fn try_hash_key(&self, key: &K) -> Result<u64, HashError> {
+        self.prehasher.try_hash(key)
+    }
+
+    /// Adds a key to the set like `insert`, unless it cannot be hashed.
+    pub fn try_insert(&mut self, key: Box<K>) -> Result<bool, HashError> {
+        let hash = self.try_hash_key(&key)?;
+        Ok(self.inner.insert(Hashed { hash, key }))
+    }
+
+    /// Like `contains`, unless `key` cannot be hashed.
+    pub fn try_contains(&self, key: &K) -> Result<bool, HashError> {
+        let hash = self.try_hash_key(key)?;
+        Ok(self
+            .inner
+            .contains(self.prehasher.probe_hashed(hash, key).as_dyn()))
+    }
+
+    /// Like `remove`, unless `key` cannot be hashed.
+    pub fn try_remove(&mut self, key: &K) -> Result<bool, HashError> {
+        let hash = self.try_hash_key(key)?;
+        Ok(self
+            .inner
+            .remove(self.prehasher.probe_hashed(hash, key).as_dyn()))
+    }
+
+    /// Adds every key that can be hashed, and skips the others, which are
+    /// returned along with their errors.
+    pub fn try_extend(
+        &mut self,
+        keys: impl IntoIterator<Item = Box<K>>,
+    ) -> Vec<(Box<K>, HashError)> {
+        let mut skipped = Vec::new();
+        for key in keys {
+            match self.try_hash_key(&key) {
+                Ok(hash) => {
+                    self.inner.insert(Hashed { hash, key });
+                }
+                Err(error) => skipped.push((key, error)),
+            }
+        }
+        skipped
+    }
+}
+
+impl<K: ?Sized, S: Default> Default for DynHashSet<K, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K: fmt::Debug + ?Sized, S> fmt::Debug for DynHashSet<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K, S> PartialEq for DynHashSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|key| other.contains(key))
+    }
+}
+
+impl<K, S> Eq for DynHashSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+}
+
+impl<K, S> FromIterator<Box<K>> for DynHashSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = Box<K>>>(iter: I) -> Self {
+        let mut set = Self::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<K, S> Extend<Box<K>> for DynHashSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = Box<K>>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+impl<'a, K: ?Sized, S> IntoIterator for &'a DynHashSet<K, S> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: ?Sized, S> IntoIterator for DynHashSet<K, S> {
+    type Item = Box<K>;
+    type IntoIter = IntoIter<K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.inner.into_iter())
+    }
+}
+
+iterator! {
+    /// Iterator over the keys of a `DynHashSet`.
+    Iter<'a, K>(hash_set::Iter<'a, Hashed<K>>) -> &'a K,
+    |stored| &*stored.key
+}
+
+iterator! {
+    /// Owning iterator over the keys of a `DynHashSet`.
+    IntoIter<K>(hash_set::IntoIter<Hashed<K>>) -> Box<K>,
+    |stored| stored.key
+}
+
+iterator! {
+    /// Draining iterator over the keys of a `DynHashSet`.
+    Drain<'a, K>(hash_set::Drain<'a, Hashed<K>>) -> Box<K>,
+    |stored| stored.key
+}
+
+exact_size!(Iter<'a, K>, IntoIter<K>, Drain<'a, K>);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::Key;
+
+    #[test]
+    fn stored_key_lookup_skips_comparison() {
+        #[allow(clippy::derived_hash_with_manual_eq)]
+        #[derive(Debug, Hash, Eq)]
+        struct NeverCompared;
+        impl PartialEq for NeverCompared {
+            fn eq(&self, _: &Self) -> bool {
+                panic!("should not be compared")
+            }
+        }
+        let mut set = DynHashSet::<dyn Key>::new();
+        set.insert(Box::new(NeverCompared));
+        let stored = set.iter().next().unwrap();
+        assert!(set.contains(stored));
+        assert_eq!(set.eq_stats().full_eq, 1);
+    }
+
+    #[test]
+    fn interns_with_one_hash() {
+        let mut set = DynHashSet::<dyn Key>::new();
+        let key: &dyn Key = &"a";
+        let hash = set.hash_key(key);
+        let mut boxed = 0;
+        for _ in 0..3 {
+            set.get_or_insert_with_hashed(hash, key, |_| {
+                boxed += 1;
+                Box::new("a")
+            });
+        }
+        assert_eq!(boxed, 1);
+        assert!(set.contains(&"a"));
+        assert!(set.contains_hashed(hash, key));
+        assert!(set.get_hashed(hash, key).unwrap().as_any().is::<&str>());
+    }
+
+    #[test]
+    fn set_operations() {
+        let mut set = DynHashSet::<dyn Key>::new();
+        assert!(set.insert(Box::new(1)));
+        assert!(set.insert(Box::new("one")));
+        assert!(set.insert(Box::new(1u8)));
+        assert!(!set.insert(Box::new(1)));
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&1));
+        assert!(set.contains(&"one"));
+        assert!(!set.contains(&2));
+        assert!(!set.contains(&1u16));
+        assert!(set.get(&1u8).unwrap().as_any().is::<u8>());
+        assert!(set.remove(&1u8));
+        assert!(!set.remove(&1u8));
+        assert!(set.take(&"one").is_some());
+        assert_eq!(set.iter().count(), 1);
+        set.retain(|_| false);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn eq_object_only_runs_on_hash_matches() {
+        let set: DynHashSet<dyn Key> = (0..100u32).map(|i| Box::new(i) as Box<dyn Key>).collect();
+        set.reset_eq_stats();
+        assert!(set.contains(&7u32));
+        assert_eq!(set.eq_stats().full_eq, 1);
+        assert!(set.contains_by(&8u32));
+        assert_eq!(set.eq_stats().full_eq, 2);
+        set.reset_eq_stats();
+        assert_eq!(set.eq_stats(), EqStats::default());
+    }
+
+    #[test]
+    fn eq_across_hashers() {
+        let a: DynHashSet<dyn Key> = (0..10u32).map(|i| Box::new(i) as Box<dyn Key>).collect();
+        let b: DynHashSet<dyn Key> = (0..10u32)
+            .rev()
+            .map(|i| Box::new(i) as Box<dyn Key>)
+            .collect();
+        assert!(a == b);
+    }
+
+    #[test]
+    fn extract_and_drain() {
+        let mut set: DynHashSet<dyn Key> =
+            [Box::new(1u8) as Box<dyn Key>, Box::new(2u8), Box::new("a")]
+                .into_iter()
+                .collect();
+        let extracted: Vec<_> = set.extract_if(|key| key.as_any().is::<u8>()).collect();
+        assert_eq!(extracted.len(), 2);
+        assert!(extracted
+            .iter()
+            .any(|key| key.as_any().downcast_ref() == Some(&2u8)));
+        assert_eq!(set.len(), 1);
+        let drained: Vec<_> = set.drain().collect();
+        assert_eq!(drained[0].as_any().downcast_ref(), Some(&"a"));
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn retain_by_type() {
+        let mut set: DynHashSet<dyn Key> = [
+            Box::new(1u8) as Box<dyn Key>,
+            Box::new(2u8),
+            Box::new(2u16),
+            Box::new("a"),
+        ]
+        .into_iter()
+        .collect();
+        set.retain_by(|x: &u8| *x > 1);
+        assert_eq!(set.len(), 3);
+        assert!(!set.contains_by(&1u8));
+        assert!(set.contains_by(&2u8));
+        assert!(set.contains_by(&2u16));
+        assert!(set.contains_by(&"a"));
+    }
+
+    #[test]
+    fn concrete_lookups() {
+        let mut set = DynHashSet::<dyn Key>::new();
+        set.insert(Box::new(String::from("a")));
+        set.insert(Box::new(1u8));
+        assert!(set.contains_by(&String::from("a")));
+        assert!(!set.contains_by(&"a"));
+        assert!(!set.contains_by(&1u16));
+        assert!(set.get_by(&1u8).unwrap().as_any().is::<u8>());
+        assert!(set.remove_by(&1u8));
+        assert!(!set.remove_by(&1u8));
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn collect_and_compare() {
+        let a: DynHashSet<dyn Key> = vec![Box::new(1) as Box<dyn Key>, Box::new("a"), Box::new(1)]
+            .into_iter()
+            .collect();
+        let mut b = DynHashSet::<dyn Key>::new();
+        b.extend([Box::new("a") as Box<dyn Key>, Box::new(1)]);
+        assert_eq!(a.len(), 2);
+        assert_eq!(a, b);
+        assert_eq!(a.into_iter().count(), 2);
+    }
+
+    #[test]
+    fn fallible_hashing() {
+        trait Remote: TryHashObj + EqObj {}
+        impl<T: TryHashObj + Eq + 'static> Remote for T {}
+
+        #[derive(PartialEq, Eq)]
+        struct Proxy(Option<u32>);
+
+        impl TryHashObj for Proxy {
+            fn try_hash_object(
+                &self,
+                mut state: &mut dyn core::hash::Hasher,
+            ) -> Result<(), HashError> {
+                let id = self.0.ok_or(HashError::new::<Self>("detached"))?;
+                id.hash(&mut state);
+                Ok(())
+            }
+        }
+
+        let mut set = DynHashSet::<dyn Remote>::default();
+        assert_eq!(set.try_insert(Box::new(Proxy(Some(1)))), Ok(true));
+        assert_eq!(set.try_insert(Box::new(1)), Ok(true));
+        assert!(set.try_insert(Box::new(Proxy(None))).is_err());
+        assert_eq!(set.try_contains(&Proxy(Some(1))), Ok(true));
+        assert!(set.try_hash_key(&1).is_ok());
+        assert_eq!(
+            set.try_contains(&Proxy(None)).unwrap_err().reason,
+            "detached"
+        );
+
+        let skipped = set.try_extend([
+            Box::new(Proxy(None)) as Box<dyn Remote>,
+            Box::new(Proxy(Some(2))),
+        ]);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.try_remove(&Proxy(Some(2))), Ok(true));
+    }
+}
@@ -0,0 +1,369 @@
+//! An insertion-ordered set of boxed trait objects, and its iterators.
+
+use core::{
+    fmt,
+    hash::{BuildHasher, Hash},
+    ops::Index,
+};
+use std::collections::{hash_map::RandomState, HashMap};
+
+use crate::{hasher::hash_one, EqObj, HashObj};
+
+/// Hash set of boxed trait objects, such as `DynIndexSet<dyn MyKey>` where
+/// `MyKey` extends `HashObj` and `EqObj`, which iterates over its keys in the
+/// order they were inserted, regardless of how they hash.
+///
+/// Keys are stored in a `Vec`, and can also be accessed by their position.
+/// Like `DynHashSet`, lookups take a plain `&K` and never allocate.
+///
+/// ```rust ignore
+/// let mut set = DynIndexSet::<dyn MyKey>::new();
+/// set.insert(Box::new("b"));
+/// set.insert(Box::new(1u8));
+/// set.insert(Box::new("b"));
+/// assert_eq!(set.get_index_of(&1u8), Some(1));
+/// ```
+pub struct DynIndexSet<K: ?Sized, S = RandomState> {
+    entries: Vec<Box<K>>,
+    /// Positions of the entries, bucketed by the hash of their key.
+    index: HashMap<u64, Vec<usize>>,
+    build_hasher: S,
+}
+
+impl<K: HashObj + EqObj + ?Sized> DynIndexSet<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K: ?Sized, S> DynIndexSet<K, S> {
+    pub fn with_hasher(build_hasher: S) -> Self {
+        Self {
+            entries: Vec::new(),
+            index: HashMap::new(),
+            build_hasher,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates over the keys in insertion order.
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter(self.entries.iter())
+    }
+
+    /// Returns the key at position `index`.
+    pub fn get_index(&self, index: usize) -> Option<&K> {
+        self.entries.get(index).map(|key| &**key)
+    }
+
+    pub fn first(&self) -> Option<&K> {
+        self.entries.first().map(|key| &**key)
+    }
+
+    pub fn last(&self) -> Option<&K> {
+        self.entries.last().map(|key| &**key)
+    }
+
+    /// Slice of the keys in insertion order.
+    pub fn as_slice(&self) -> &[Box<K>] {
+        &self.entries
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+}
+
+impl<K, S> DynIndexSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    /// Adds a key to the end of the set, returning true if it was not already
+    /// present. An existing equal key keeps its position and is not replaced.
+    pub fn insert(&mut self, key: Box<K>) -> bool {
+        self.insert_full(key).1
+    }
+
+    /// Like `insert`, but also returns the position of the key.
+    pub fn insert_full(&mut self, key: Box<K>) -> (usize, bool) {
+        let hash = self.hash(&key);
+        if let Some(i) = self.find(hash, |k| k.eq_object(key.as_partial_eq_object())) {
+            return (i, false);
+        }
+        let i = self.entries.len();
+        self.entries.push(key);
+        self.index.entry(hash).or_default().push(i);
+        (i, true)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.get_index_of(key).is_some()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&K> {
+        self.get_index_of(key).map(|i| &*self.entries[i])
+    }
+
+    /// Returns the position of `key`.
+    pub fn get_index_of(&self, key: &K) -> Option<usize> {
+        self.find(self.hash(key), |k| k.eq_object(key.as_partial_eq_object()))
+    }
+
+    /// Returns true if the set contains a key of type `T` equal to `value`.
+    /// Only keys of type `T` can match.
+    pub fn contains_by<T: Hash + PartialEq + 'static>(&self, value: &T) -> bool {
+        self.get_index_of_by(value).is_some()
+    }
+
+    /// Returns the position of the key of type `T` equal to `value`. See
+    /// `contains_by`.
+    pub fn get_index_of_by<T: Hash + PartialEq + 'static>(&self, value: &T) -> Option<usize> {
+        let hash = self.build_hasher.hash_one(value);
+        self.find(hash, |k| k.as_any().downcast_ref::<T>() == Some(value))
+    }
+
+    /// Removes `key` by swapping it with the last key, which changes the
+    /// position of the last key but takes constant time. Returns the removed
+    /// key.
+    pub fn swap_remove(&mut self, key: &K) -> Option<Box<K>> {
+        let i = self.get_index_of(key)?;
+        Some(self.swap_remove_index(i))
+    }
+
+    /// Removes `key` and shifts all the following keys down by one position,
+    /// which preserves the insertion order but takes linear time. Returns the
+    /// removed key.
+    pub fn shift_remove(&mut self, key: &K) -> Option<Box<K>> {
+        let i = self.get_index_of(key)?;
+        Some(self.shift_remove_index(i))
+    }
+
+    /// Removes and returns the last key.
+    pub fn pop(&mut self) -> Option<Box<K>> {
+        let last = self.entries.len().checked_sub(1)?;
+        Some(self.swap_remove_index(last))
+    }
+
+    /// Keeps only the keys for which `f` returns true, preserving their order.
+    pub fn retain(&mut self, mut f: impl FnMut(&K) -> bool) {
+        self.entries.retain(|key| f(key));
+        self.reindex();
+    }
+
+    fn hash(&self, key: &K) -> u64 {
+        hash_one(&self.build_hasher, key.as_hash_object())
+    }
+
+    fn find(&self, hash: u64, mut eq: impl FnMut(&K) -> bool) -> Option<usize> {
+        self.index
+            .get(&hash)?
+            .iter()
+            .copied()
+            .find(|&i| eq(&self.entries[i]))
+    }
+
+    fn unindex(&mut self, i: usize) {
+        let hash = self.hash(&self.entries[i]);
+        if let Some(bucket) = self.index.get_mut(&hash) {
+            bucket.retain(|&j| j != i);
+            if bucket.is_empty() {
+                self.index.remove(&hash);
+            }
+        }
+    }
+
+    fn swap_remove_index(&mut self, i: usize) -> Box<K> {
+        self.unindex(i);
+        let last = self.entries.len() - 1;
+        if i != last {
+            let hash = self.hash(&self.entries[last]);
+            for j in self.index.get_mut(&hash).into_iter().flatten() {
+                if *j == last {
+                    *j = i;
+                }
+            }
+        }
+        self.entries.swap_remove(i)
+    }
+
+    fn shift_remove_index(&mut self, i: usize) -> Box<K> {
+        self.unindex(i);
+        for j in self.index.values_mut().flatten() {
+            if *j > i {
+                *j -= 1;
+            }
+        }
+        self.entries.remove(i)
+    }
+
+    fn reindex(&mut self) {
+        self.index.clear();
+        for i in 0..self.entries.len() {
+            let hash = self.hash(&self.entries[i]);
+            self.index.entry(hash).or_default().push(i);
+        }
+    }
+}
+
+impl<K: ?Sized, S: Default> Default for DynIndexSet<K, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+impl<K: fmt::Debug + ?Sized, S> fmt::Debug for DynIndexSet<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Sets are equal if they contain the same keys, regardless of their order.
+impl<K, S> PartialEq for DynIndexSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|key| other.contains(key))
+    }
+}
+
+impl<K, S> Eq for DynIndexSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+}
+
+impl<K: ?Sized, S> Index<usize> for DynIndexSet<K, S> {
+    type Output = K;
+
+    fn index(&self, index: usize) -> &K {
+        &self.entries[index]
+    }
+}
+
+impl<K, S> FromIterator<Box<K>> for DynIndexSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = Box<K>>>(iter: I) -> Self {
+        let mut set = Self::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<K, S> Extend<Box<K>> for DynIndexSet<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = Box<K>>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+impl<'a, K: ?Sized, S> IntoIterator for &'a DynIndexSet<K, S> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: ?Sized, S> IntoIterator for DynIndexSet<K, S> {
+    type Item = Box<K>;
+    type IntoIter = IntoIter<K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.entries.into_iter())
+    }
+}
+
+iterator! {
+    /// Iterator over the keys of a `DynIndexSet`, in insertion order.
+    Iter<'a, K>(core::slice::Iter<'a, Box<K>>) -> &'a K,
+    |key| &**key
+}
+
+iterator! {
+    /// Owning iterator over the keys of a `DynIndexSet`, in insertion order.
+    IntoIter<K>(std::vec::IntoIter<Box<K>>) -> Box<K>,
+    |key| key
+}
+
+exact_size!(Iter<'a, K>, IntoIter<K>);
+double_ended!(Iter<'a, K>, |key| &**key);
+double_ended!(IntoIter<K>, |key| key);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::Key;
+
+    fn ints(set: &DynIndexSet<dyn Key>) -> Vec<u32> {
+        set.iter()
+            .map(|key| *key.as_any().downcast_ref::<u32>().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn insertion_order() {
+        let mut set = DynIndexSet::<dyn Key>::new();
+        for i in [5u32, 3, 9, 3, 1, 5] {
+            set.insert(Box::new(i));
+        }
+        assert_eq!(ints(&set), [5, 3, 9, 1]);
+        assert_eq!(set.insert_full(Box::new(9u32)), (2, false));
+        assert_eq!(set.get_index_of(&1u32), Some(3));
+        assert_eq!(set.get_index_of_by(&3u32), Some(1));
+        assert!(!set.contains(&3u8));
+        assert!(set[0].as_any().is::<u32>());
+        let reversed: Vec<_> = set.iter().rev().map(|k| format!("{k:?}")).collect();
+        assert_eq!(reversed, ["1", "9", "3", "5"]);
+    }
+
+    #[test]
+    fn removal_keeps_index_consistent() {
+        let mut set: DynIndexSet<dyn Key> =
+            (0..6u32).map(|i| Box::new(i) as Box<dyn Key>).collect();
+        assert!(set.swap_remove(&1u32).is_some());
+        assert_eq!(ints(&set), [0, 5, 2, 3, 4]);
+        assert!(set.shift_remove(&2u32).is_some());
+        assert_eq!(ints(&set), [0, 5, 3, 4]);
+        for (i, key) in [0u32, 5, 3, 4].iter().enumerate() {
+            assert_eq!(set.get_index_of(key), Some(i));
+        }
+        set.retain(|key| key.as_any().downcast_ref() != Some(&5u32));
+        assert_eq!(ints(&set), [0, 3, 4]);
+        assert_eq!(set.get_index_of(&4u32), Some(2));
+        assert!(set.pop().is_some());
+        assert!(set.swap_remove(&4u32).is_none());
+        assert_eq!(ints(&set), [0, 3]);
+    }
+
+    #[test]
+    fn equality_ignores_order() {
+        let a: DynIndexSet<dyn Key> = [Box::new(1u32) as Box<dyn Key>, Box::new("a")]
+            .into_iter()
+            .collect();
+        let b: DynIndexSet<dyn Key> = [Box::new("a") as Box<dyn Key>, Box::new(1u32)]
+            .into_iter()
+            .collect();
+        assert_eq!(a, b);
+    }
+}
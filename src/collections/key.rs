@@ -0,0 +1,487 @@
+//! Plumbing that lets the collections look up `Box<K>` keys with a borrowed
+//! `&K`, without allocating, and lets the hashed collections hash each key
+//! only once.
+
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{BuildHasher, Hash, Hasher},
+    ops::{Bound, RangeBounds},
+    sync::atomic::{AtomicU64, Ordering::Relaxed},
+};
+
+use crate::{
+    eq_or_same,
+    hasher::{hash_one, FinishableHasher},
+    upcast_any, EqObj, Error, HashError, HashObj, OrdObj, Result, TryHashObj,
+};
+
+/// A key stored in an ordered collection.
+pub(crate) struct Stored<K: ?Sized>(pub Box<K>);
+
+/// Anything that can be compared with a `K`. Both stored keys and lookup
+/// probes are compared through `dyn Probe<K>` in ordered collections, or
+/// `dyn HashProbe<K>` in hashed collections, so that stored keys can be
+/// borrowed as probes.
+pub(crate) trait Probe<K: ?Sized> {
+    /// The probe as a `K`, unless it is a concrete value.
+    fn key(&self) -> Option<&K>;
+    fn eq_key(&self, key: &K) -> bool;
+
+    /// The concrete value, if the probe is one that can be ordered.
+    fn ordered_value(&self) -> Option<&dyn OrdObj> {
+        None
+    }
+}
+
+/// A probe for hashed collections, which carries its hash, as computed by
+/// the collection's `BuildHasher` when the key was stored or the lookup
+/// started.
+pub(crate) trait HashProbe<K: ?Sized>: Probe<K> {
+    fn probe_hash(&self) -> u64;
+
+    /// Where to record how comparisons with this probe went, if anywhere.
+    fn counters(&self) -> Option<&EqCounters> {
+        None
+    }
+}
+
+/// A key stored in a hashed collection, next to its hash, so the collection
+/// never has to hash it again, and can skip `eq_object` when hashes differ.
+pub(crate) struct Hashed<K: ?Sized> {
+    pub hash: u64,
+    pub key: Box<K>,
+}
+
+impl<K: EqObj + ?Sized> Probe<K> for Hashed<K> {
+    fn key(&self) -> Option<&K> {
+        Some(&self.key)
+    }
+
+    fn eq_key(&self, key: &K) -> bool {
+        eq_or_same(&*self.key, key)
+    }
+}
+
+impl<K: EqObj + ?Sized> HashProbe<K> for Hashed<K> {
+    fn probe_hash(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// A lookup probe, along with its hash.
+pub(crate) struct Prehashed<'a, P> {
+    hash: u64,
+    probe: P,
+    counters: &'a EqCounters,
+}
+
+impl<K: ?Sized, P: Probe<K>> Probe<K> for Prehashed<'_, P> {
+    fn key(&self) -> Option<&K> {
+        self.probe.key()
+    }
+
+    fn eq_key(&self, key: &K) -> bool {
+        self.probe.eq_key(key)
+    }
+}
+
+impl<K: ?Sized, P: Probe<K>> HashProbe<K> for Prehashed<'_, P> {
+    fn probe_hash(&self) -> u64 {
+        self.hash
+    }
+
+    fn counters(&self) -> Option<&EqCounters> {
+        Some(self.counters)
+    }
+}
+
+impl<'a, P> Prehashed<'a, P> {
+    pub fn as_dyn<K: ?Sized>(&self) -> &(dyn HashProbe<K> + '_)
+    where
+        P: Probe<K>,
+    {
+        self
+    }
+}
+
+/// Hashes the keys and probes of a hashed collection with its `BuildHasher`,
+/// before they reach the inner `std` collection, and keeps the statistics of
+/// its lookups.
+pub(crate) struct Prehasher<S> {
+    pub build_hasher: S,
+    pub counters: EqCounters,
+}
+
+impl<S> Prehasher<S> {
+    pub fn new(build_hasher: S) -> Self {
+        Self {
+            build_hasher,
+            counters: EqCounters::default(),
+        }
+    }
+}
+
+impl<S: BuildHasher> Prehasher<S> {
+    pub fn hash<K: HashObj + ?Sized>(&self, key: &K) -> u64 {
+        hash_one(&self.build_hasher, key.as_hash_object())
+    }
+
+    /// Hashes a key like `hash`, unless it cannot be hashed.
+    pub fn try_hash<K: TryHashObj + ?Sized>(&self, key: &K) -> Result<u64, HashError> {
+        let mut hasher = FinishableHasher::from_build(&self.build_hasher);
+        key.try_hash_object(hasher.as_dyn())?;
+        Ok(hasher.finish())
+    }
+
+    pub fn hashed<K: HashObj + ?Sized>(&self, key: Box<K>) -> Hashed<K> {
+        Hashed {
+            hash: self.hash(&*key),
+            key,
+        }
+    }
+
+    pub fn probe<'a, K: HashObj + ?Sized>(&'a self, key: &'a K) -> Prehashed<'a, &'a K> {
+        self.probe_hashed(self.hash(key), key)
+    }
+
+    /// A probe for a key whose hash was already computed with `hash`.
+    pub fn probe_hashed<'a, K: ?Sized>(&'a self, hash: u64, key: &'a K) -> Prehashed<'a, &'a K> {
+        Prehashed {
+            hash,
+            probe: key,
+            counters: &self.counters,
+        }
+    }
+
+    /// A probe for a value of a concrete type, which hashes like a `K`
+    /// holding the same value, since `HashObj` feeds the hasher exactly what
+    /// `Hash` does.
+    pub fn probe_concrete<'a, T: Hash>(&'a self, value: &'a T) -> Prehashed<'a, Concrete<'a, T>> {
+        Prehashed {
+            hash: self.build_hasher.hash_one(value),
+            probe: Concrete(value),
+            counters: &self.counters,
+        }
+    }
+}
+
+/// Hasher for the inner `std` collections, whose keys have already been
+/// hashed, and only write their hash.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct PassThrough;
+
+impl BuildHasher for PassThrough {
+    type Hasher = PassThroughHasher;
+
+    fn build_hasher(&self) -> PassThroughHasher {
+        PassThroughHasher(0)
+    }
+}
+
+pub(crate) struct PassThroughHasher(u64);
+
+impl Hasher for PassThroughHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = self.0.rotate_left(8) ^ u64::from(byte);
+        }
+    }
+
+    fn write_u64(&mut self, hash: u64) {
+        self.0 = hash;
+    }
+}
+
+/// How the lookups in a `DynHashSet` or `DynHashMap` compared the probe with
+/// stored keys. Every stored key is hashed once, and its hash is compared
+/// with the probe's before `eq_object`, which downcasts, is called.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EqStats {
+    /// Comparisons with a key of the same hash, which called `eq_object`.
+    pub full_eq: u64,
+    /// Comparisons that were settled by a hash mismatch alone.
+    pub hash_rejected: u64,
+}
+
+/// Counts how often hashed lookups needed to call `eq_object`.
+#[derive(Default)]
+pub(crate) struct EqCounters {
+    full_eq: AtomicU64,
+    hash_rejected: AtomicU64,
+}
+
+impl EqCounters {
+    pub fn stats(&self) -> EqStats {
+        EqStats {
+            full_eq: self.full_eq.load(Relaxed),
+            hash_rejected: self.hash_rejected.load(Relaxed),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.full_eq.store(0, Relaxed);
+        self.hash_rejected.store(0, Relaxed);
+    }
+}
+
+impl<K: EqObj + ?Sized> Probe<K> for Stored<K> {
+    fn key(&self) -> Option<&K> {
+        Some(&self.0)
+    }
+
+    fn eq_key(&self, key: &K) -> bool {
+        eq_or_same(&*self.0, key)
+    }
+}
+
+impl<K: EqObj + ?Sized> Probe<K> for &K {
+    fn key(&self) -> Option<&K> {
+        Some(self)
+    }
+
+    fn eq_key(&self, key: &K) -> bool {
+        eq_or_same(*self, key)
+    }
+}
+
+/// A probe for a value of a concrete type, which only matches keys of the
+/// same type.
+pub(crate) struct Concrete<'a, T>(pub &'a T);
+
+impl<K, T> Probe<K> for Concrete<'_, T>
+where
+    K: EqObj + ?Sized,
+    T: PartialEq + 'static,
+{
+    fn key(&self) -> Option<&K> {
+        None
+    }
+
+    fn eq_key(&self, key: &K) -> bool {
+        key.as_any().downcast_ref::<T>() == Some(self.0)
+    }
+}
+
+/// A probe for a value of a concrete type in an ordered collection, which
+/// orders like a `K` holding the same value: by value against keys of the
+/// same type, and by `TypeId` against the others, like `cmp_object`.
+pub(crate) struct ConcreteOrd<'a, T>(pub &'a T);
+
+impl<K, T> Probe<K> for ConcreteOrd<'_, T>
+where
+    K: EqObj + ?Sized,
+    T: Ord + 'static,
+{
+    fn key(&self) -> Option<&K> {
+        None
+    }
+
+    fn eq_key(&self, key: &K) -> bool {
+        key.as_any().downcast_ref::<T>() == Some(self.0)
+    }
+
+    fn ordered_value(&self) -> Option<&dyn OrdObj> {
+        Some(self.0)
+    }
+}
+
+/// Borrows a key as a probe for ordered lookups.
+pub(crate) fn probe<'a, K: EqObj + ?Sized>(key: &'a &'a K) -> &'a (dyn Probe<K> + 'a) {
+    key
+}
+
+impl<'a, K: EqObj + ?Sized + 'a> Borrow<dyn Probe<K> + 'a> for Stored<K> {
+    fn borrow(&self) -> &(dyn Probe<K> + 'a) {
+        self
+    }
+}
+
+impl<'a, K: EqObj + ?Sized + 'a> Borrow<dyn HashProbe<K> + 'a> for Hashed<K> {
+    fn borrow(&self) -> &(dyn HashProbe<K> + 'a) {
+        self
+    }
+}
+
+fn probe_eq<K: ?Sized>(a: &dyn Probe<K>, b: &dyn Probe<K>) -> bool {
+    match (a.key(), b.key()) {
+        (Some(key), _) => b.eq_key(key),
+        (None, Some(key)) => a.eq_key(key),
+        (None, None) => unreachable!("a stored key is always a `K`"),
+    }
+}
+
+impl<K: ?Sized> PartialEq for dyn Probe<K> + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        probe_eq(self, other)
+    }
+}
+
+impl<K: ?Sized> Eq for dyn Probe<K> + '_ {}
+
+/// Hashes are compared first, so `eq_object`, which downcasts, only runs for
+/// keys with the same hash.
+impl<K: ?Sized> PartialEq for dyn HashProbe<K> + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        let counters = self.counters().or(other.counters());
+        if self.probe_hash() != other.probe_hash() {
+            if let Some(counters) = counters {
+                counters.hash_rejected.fetch_add(1, Relaxed);
+            }
+            return false;
+        }
+        if let Some(counters) = counters {
+            counters.full_eq.fetch_add(1, Relaxed);
+        }
+        #[cfg(feature = "metrics")]
+        crate::metrics::count(&crate::metrics::FULL_EQ_AFTER_HASH);
+        probe_eq(self, other)
+    }
+}
+
+impl<K: ?Sized> Eq for dyn HashProbe<K> + '_ {}
+
+impl<K: ?Sized> Hash for dyn HashProbe<K> + '_ {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.probe_hash());
+    }
+}
+
+impl<K: OrdObj + ?Sized> PartialOrd for dyn Probe<K> + '_ {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn as_ordered<'a, K: OrdObj + ?Sized>(probe: &'a (dyn Probe<K> + '_)) -> &'a dyn OrdObj {
+    probe
+        .key()
+        .map(K::as_ord_object)
+        .or_else(|| probe.ordered_value())
+        .expect("ordered collections are probed with keys or ordered values")
+}
+
+/// Ordered collections are probed with borrowed keys, or with the bounds of
+/// a range of concrete values, which are never compared with each other.
+impl<K: OrdObj + ?Sized> Ord for dyn Probe<K> + '_ {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if let (Some(a), Some(b)) = (self.key(), other.key()) {
+            return a.cmp_object(b.as_ord_object());
+        }
+        let (a, b) = (as_ordered(self), as_ordered(other));
+        // A range of concrete values is expected to span keys of other types,
+        // so they are ordered like `cmp_object` would, without reporting it.
+        let (a_type, b_type) = (upcast_any(a).type_id(), upcast_any(b).type_id());
+        if a_type == b_type {
+            a.cmp_object(b)
+        } else {
+            a_type.cmp(&b_type)
+        }
+    }
+}
+
+impl<K: ?Sized> Hash for Hashed<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl<K: EqObj + ?Sized> PartialEq for Hashed<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash && eq_or_same(&*self.key, &*other.key)
+    }
+}
+
+impl<K: EqObj + ?Sized> Eq for Hashed<K> {}
+
+impl<K: fmt::Debug + ?Sized> fmt::Debug for Hashed<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.key.fmt(f)
+    }
+}
+
+impl<K: EqObj + ?Sized> PartialEq for Stored<K> {
+    fn eq(&self, other: &Self) -> bool {
+        eq_or_same(&*self.0, &*other.0)
+    }
+}
+
+impl<K: EqObj + ?Sized> Eq for Stored<K> {}
+
+impl<K: OrdObj + ?Sized> PartialOrd for Stored<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: OrdObj + ?Sized> Ord for Stored<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp_object(other.0.as_ord_object())
+    }
+}
+
+impl<K: fmt::Debug + ?Sized> fmt::Debug for Stored<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Converts the bounds of a range of borrowed keys into bounds of probes.
+pub(crate) fn probe_bounds<'r, 'a: 'r, K: EqObj + ?Sized + 'a>(
+    range: &'r impl RangeBounds<&'a K>,
+) -> (
+    Bound<&'r (dyn Probe<K> + 'r)>,
+    Bound<&'r (dyn Probe<K> + 'r)>,
+) {
+    (range.start_bound().map(probe), range.end_bound().map(probe))
+}
+
+/// Checks that the start of a range is not greater than its end, which the
+/// `range` methods of the `std` collections panic on.
+pub(crate) fn check_bounds<K: OrdObj + ?Sized>(
+    bounds: &(Bound<&(dyn Probe<K> + '_)>, Bound<&(dyn Probe<K> + '_)>),
+) -> Result<()> {
+    let invalid = match *bounds {
+        (Bound::Excluded(start), Bound::Excluded(end)) => start.cmp(end) != Ordering::Less,
+        (
+            Bound::Included(start) | Bound::Excluded(start),
+            Bound::Included(end) | Bound::Excluded(end),
+        ) => start.cmp(end) == Ordering::Greater,
+        _ => false,
+    };
+    if invalid {
+        Err(Error::InvalidRange)
+    } else {
+        Ok(())
+    }
+}
+
+/// Wraps the bounds of a range of concrete values, to be borrowed as probes
+/// with `concrete_probes`.
+pub(crate) fn concrete_bounds<T>(
+    range: &impl RangeBounds<T>,
+) -> (Bound<ConcreteOrd<'_, T>>, Bound<ConcreteOrd<'_, T>>) {
+    (
+        range.start_bound().map(ConcreteOrd),
+        range.end_bound().map(ConcreteOrd),
+    )
+}
+
+pub(crate) fn concrete_probes<'r, K, T>(
+    (start, end): &'r (Bound<ConcreteOrd<'_, T>>, Bound<ConcreteOrd<'_, T>>),
+) -> (
+    Bound<&'r (dyn Probe<K> + 'r)>,
+    Bound<&'r (dyn Probe<K> + 'r)>,
+)
+where
+    K: EqObj + ?Sized,
+    T: Ord + 'static,
+{
+    let probe = |bound: &'r ConcreteOrd<'_, T>| bound as &dyn Probe<K>;
+    (start.as_ref().map(probe), end.as_ref().map(probe))
+}
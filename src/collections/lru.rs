@@ -0,0 +1,396 @@
+//! A least-recently-used cache keyed by boxed trait objects.
+
+use core::{
+    any::Any,
+    fmt,
+    hash::{BuildHasher, Hash},
+};
+use std::collections::{hash_map::RandomState, HashMap};
+
+use crate::{hasher::hash_one, EqObj, HashObj};
+
+const NIL: usize = usize::MAX;
+
+struct Node<K: ?Sized, V> {
+    key: Box<K>,
+    value: V,
+    /// The next more recently used node.
+    newer: usize,
+    /// The next less recently used node.
+    older: usize,
+}
+
+/// Cache with a fixed capacity, keyed by boxed trait objects, such as
+/// `DynLruCache<dyn MyKey, V>` where `MyKey` extends `HashObj` and `EqObj`.
+/// When the cache is full, inserting a new key evicts the least recently used
+/// entry.
+///
+/// Like `DynHashMap`, lookups take a plain `&K`, or a value of a concrete key
+/// type with the `*_by` methods, and never allocate.
+///
+/// ```rust ignore
+/// let mut cache = DynLruCache::<dyn Description, Texture>::new(2);
+/// cache.put(Box::new(Circle(1)), render(&Circle(1)));
+/// cache.put(Box::new(Square(2)), render(&Square(2)));
+/// cache.get(&Circle(1));
+/// cache.put(Box::new(Square(3)), render(&Square(3))); // evicts Square(2)
+/// ```
+pub struct DynLruCache<K: ?Sized, V, S = RandomState> {
+    nodes: Vec<Option<Node<K, V>>>,
+    free: Vec<usize>,
+    /// Indices of the nodes, bucketed by the hash of their key.
+    index: HashMap<u64, Vec<usize>>,
+    build_hasher: S,
+    newest: usize,
+    oldest: usize,
+    len: usize,
+    capacity: usize,
+}
+
+impl<K: HashObj + EqObj + ?Sized, V> DynLruCache<K, V> {
+    /// Creates an empty cache that can hold `capacity` entries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_hasher(capacity, RandomState::new())
+    }
+}
+
+impl<K: ?Sized, V, S> DynLruCache<K, V, S> {
+    /// Creates an empty cache that can hold `capacity` entries, and hashes
+    /// keys with `build_hasher`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_hasher(capacity: usize, build_hasher: S) -> Self {
+        assert!(capacity > 0, "DynLruCache capacity must be positive");
+        Self {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            index: HashMap::new(),
+            build_hasher,
+            newest: NIL,
+            oldest: NIL,
+            len: 0,
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Iterates over the entries from most to least recently used, without
+    /// updating their recency.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            cache_nodes: &self.nodes,
+            next: self.newest,
+            remaining: self.len,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.free.clear();
+        self.index.clear();
+        self.newest = NIL;
+        self.oldest = NIL;
+        self.len = 0;
+    }
+
+    fn node(&self, i: usize) -> &Node<K, V> {
+        self.nodes[i].as_ref().expect("linked nodes are occupied")
+    }
+
+    fn node_mut(&mut self, i: usize) -> &mut Node<K, V> {
+        self.nodes[i].as_mut().expect("linked nodes are occupied")
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let (newer, older) = {
+            let node = self.node(i);
+            (node.newer, node.older)
+        };
+        match newer {
+            NIL => self.newest = older,
+            newer => self.node_mut(newer).older = older,
+        }
+        match older {
+            NIL => self.oldest = newer,
+            older => self.node_mut(older).newer = newer,
+        }
+    }
+
+    fn push_newest(&mut self, i: usize) {
+        let newest = self.newest;
+        let node = self.node_mut(i);
+        node.newer = NIL;
+        node.older = newest;
+        match newest {
+            NIL => self.oldest = i,
+            newest => self.node_mut(newest).newer = i,
+        }
+        self.newest = i;
+    }
+
+    fn touch(&mut self, i: usize) {
+        if self.newest != i {
+            self.unlink(i);
+            self.push_newest(i);
+        }
+    }
+}
+
+impl<K, V, S> DynLruCache<K, V, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    /// Inserts a value and marks it as the most recently used. Returns the
+    /// value previously stored for an equal key, if any, in which case the key
+    /// is not updated. Otherwise, if the cache is full, the least recently
+    /// used entry is evicted.
+    pub fn put(&mut self, key: Box<K>, value: V) -> Option<V> {
+        self.push(key, value)
+            .and_then(|(old_key, old_value)| match old_key {
+                Some(_) => None,
+                None => Some(old_value),
+            })
+    }
+
+    /// Like `put`, but returns the evicted entry, if any, as
+    /// `(Some(key), value)`, or the replaced value as `(None, value)`.
+    pub fn push(&mut self, key: Box<K>, value: V) -> Option<(Option<Box<K>>, V)> {
+        let hash = hash_one(&self.build_hasher, key.as_hash_object());
+        if let Some(i) = self.find(hash, |k| k.eq_object(key.as_partial_eq_object())) {
+            self.touch(i);
+            let old = core::mem::replace(&mut self.node_mut(i).value, value);
+            return Some((None, old));
+        }
+        let evicted = if self.len == self.capacity {
+            self.pop_lru().map(|(key, value)| (Some(key), value))
+        } else {
+            None
+        };
+        let node = Node {
+            key,
+            value,
+            newer: NIL,
+            older: NIL,
+        };
+        let i = match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = Some(node);
+                i
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        self.index.entry(hash).or_default().push(i);
+        self.push_newest(i);
+        self.len += 1;
+        evicted
+    }
+
+    /// Returns the value for `key`, and marks it as the most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        self.get_mut(key).map(|value| &*value)
+    }
+
+    /// Returns the value for `key`, and marks it as the most recently used.
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let i = self.find_key(key)?;
+        self.touch(i);
+        Some(&mut self.node_mut(i).value)
+    }
+
+    /// Returns the value for `key`, without updating its recency.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.find_key(key).map(|i| &self.node(i).value)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.find_key(key).is_some()
+    }
+
+    /// Removes the entry for `key`, returning its value.
+    pub fn pop(&mut self, key: &K) -> Option<V> {
+        let i = self.find_key(key)?;
+        Some(self.remove_node(i).1)
+    }
+
+    /// Returns the value for the key of type `T` equal to `value`, and marks
+    /// it as the most recently used. Only keys of type `T` can match.
+    pub fn get_by<T: Hash + PartialEq + Any>(&mut self, value: &T) -> Option<&V> {
+        let i = self.find_concrete(value)?;
+        self.touch(i);
+        Some(&self.node(i).value)
+    }
+
+    /// Returns the value for the key of type `T` equal to `value`, without
+    /// updating its recency. Only keys of type `T` can match.
+    pub fn peek_by<T: Hash + PartialEq + Any>(&self, value: &T) -> Option<&V> {
+        self.find_concrete(value).map(|i| &self.node(i).value)
+    }
+
+    /// Returns true if the cache contains a key of type `T` equal to `value`.
+    pub fn contains_by<T: Hash + PartialEq + Any>(&self, value: &T) -> bool {
+        self.find_concrete(value).is_some()
+    }
+
+    /// Removes and returns the least recently used entry.
+    pub fn pop_lru(&mut self) -> Option<(Box<K>, V)> {
+        match self.oldest {
+            NIL => None,
+            oldest => Some(self.remove_node(oldest)),
+        }
+    }
+
+    /// Changes the capacity, evicting the least recently used entries if
+    /// there are more than `capacity`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn resize(&mut self, capacity: usize) {
+        assert!(capacity > 0, "DynLruCache capacity must be positive");
+        while self.len > capacity {
+            self.pop_lru();
+        }
+        self.capacity = capacity;
+    }
+
+    fn find(&self, hash: u64, mut eq: impl FnMut(&K) -> bool) -> Option<usize> {
+        self.index
+            .get(&hash)?
+            .iter()
+            .copied()
+            .find(|&i| eq(&self.node(i).key))
+    }
+
+    fn find_key(&self, key: &K) -> Option<usize> {
+        let hash = hash_one(&self.build_hasher, key.as_hash_object());
+        self.find(hash, |k| k.eq_object(key.as_partial_eq_object()))
+    }
+
+    fn find_concrete<T: Hash + PartialEq + Any>(&self, value: &T) -> Option<usize> {
+        let hash = self.build_hasher.hash_one(value);
+        self.find(hash, |k| k.as_any().downcast_ref::<T>() == Some(value))
+    }
+
+    fn remove_node(&mut self, i: usize) -> (Box<K>, V) {
+        self.unlink(i);
+        let node = self.nodes[i].take().expect("linked nodes are occupied");
+        let hash = hash_one(&self.build_hasher, node.key.as_hash_object());
+        if let Some(bucket) = self.index.get_mut(&hash) {
+            bucket.retain(|&j| j != i);
+            if bucket.is_empty() {
+                self.index.remove(&hash);
+            }
+        }
+        self.free.push(i);
+        self.len -= 1;
+        (node.key, node.value)
+    }
+}
+
+impl<K: fmt::Debug + ?Sized, V: fmt::Debug, S> fmt::Debug for DynLruCache<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Iterator over the entries of a `DynLruCache`, from most to least recently
+/// used.
+pub struct Iter<'a, K: ?Sized, V> {
+    cache_nodes: &'a [Option<Node<K, V>>],
+    next: usize,
+    remaining: usize,
+}
+
+impl<'a, K: ?Sized, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.cache_nodes.get(self.next)?.as_ref()?;
+        self.next = node.older;
+        self.remaining -= 1;
+        Some((&*node.key, &node.value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: ?Sized, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::Key;
+
+    fn keys(cache: &DynLruCache<dyn Key, u32>) -> Vec<u32> {
+        cache.iter().map(|(_, v)| *v).collect()
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = DynLruCache::<dyn Key, u32>::new(2);
+        assert_eq!(cache.put(Box::new("a"), 1), None);
+        assert_eq!(cache.put(Box::new('b'), 2), None);
+        assert_eq!(keys(&cache), [2, 1]);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(keys(&cache), [1, 2]);
+        let evicted = cache.push(Box::new(3u8), 3).unwrap();
+        assert!(evicted.0.unwrap().as_any().is::<char>());
+        assert_eq!(keys(&cache), [3, 1]);
+        assert!(!cache.contains(&'b'));
+        assert_eq!(cache.put(Box::new("a"), 10), Some(1));
+        assert_eq!(keys(&cache), [10, 3]);
+        assert_eq!(cache.peek(&3u8), Some(&3));
+        assert_eq!(keys(&cache), [10, 3]);
+    }
+
+    #[test]
+    fn pop_and_resize() {
+        let mut cache = DynLruCache::<dyn Key, u32>::new(3);
+        for i in 0..3u32 {
+            cache.put(Box::new(i), i);
+        }
+        assert_eq!(cache.pop(&1u32), Some(1));
+        assert_eq!(cache.len(), 2);
+        cache.put(Box::new(5u32), 5);
+        assert_eq!(keys(&cache), [5, 2, 0]);
+        cache.resize(1);
+        assert_eq!(keys(&cache), [5]);
+        assert_eq!(cache.pop_lru().map(|(_, v)| v), Some(5));
+        assert!(cache.is_empty());
+        assert_eq!(cache.pop_lru().map(|(_, v)| v), None);
+    }
+
+    #[test]
+    fn concrete_lookups() {
+        let mut cache = DynLruCache::<dyn Key, u32>::new(2);
+        cache.put(Box::new(String::from("a")), 1);
+        cache.put(Box::new(2u8), 2);
+        assert_eq!(cache.get_by(&String::from("a")), Some(&1));
+        assert_eq!(keys(&cache), [1, 2]);
+        assert_eq!(cache.peek_by(&2u8), Some(&2));
+        assert!(!cache.contains_by(&2u16));
+    }
+}
@@ -0,0 +1,82 @@
+//! Collections of boxed trait objects, which compare and hash their keys
+//! through the object-safe traits of this crate.
+//!
+//! Unlike a `HashMap<Obj<Box<dyn MyKey>>, V>`, which can only be queried with
+//! another `Obj<Box<dyn MyKey>>`, these collections are queried with a
+//! borrowed `&dyn MyKey`, or a reference to any concrete key type, so lookups
+//! never allocate a boxed probe key.
+
+/// Defines an iterator that wraps an iterator over the inner collection and
+/// maps its items.
+macro_rules! iterator {
+    (
+        $(#[$attr:meta])*
+        $Name:ident<$($lt:lifetime,)? K $(, $V:ident)?>($Inner:ty) -> $Item:ty,
+        |$x:pat_param| $map:expr
+    ) => {
+        $(#[$attr])*
+        pub struct $Name<$($lt,)? K: ?Sized $(, $V)?>($Inner);
+
+        impl<$($lt,)? K: ?Sized $(, $V)?> Iterator for $Name<$($lt,)? K $(, $V)?> {
+            type Item = $Item;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.0.next().map(|$x| $map)
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                self.0.size_hint()
+            }
+        }
+    };
+}
+
+/// Implements `ExactSizeIterator` for iterators defined with `iterator!`.
+macro_rules! exact_size {
+    ($($Name:ident<$($lt:lifetime,)? K $(, $V:ident)?>),*) => {$(
+        impl<$($lt,)? K: ?Sized $(, $V)?> ExactSizeIterator for $Name<$($lt,)? K $(, $V)?> {}
+    )*};
+}
+
+/// Implements `DoubleEndedIterator` for iterators defined with `iterator!`.
+macro_rules! double_ended {
+    ($Name:ident<$($lt:lifetime,)? K $(, $V:ident)?>, |$x:pat_param| $map:expr) => {
+        impl<$($lt,)? K: ?Sized $(, $V)?> DoubleEndedIterator for $Name<$($lt,)? K $(, $V)?> {
+            fn next_back(&mut self) -> Option<Self::Item> {
+                self.0.next_back().map(|$x| $map)
+            }
+        }
+    };
+}
+
+pub mod adaptive_set;
+pub mod any_map;
+pub mod btree_map;
+pub mod btree_set;
+pub mod counter;
+pub mod hash_map;
+pub mod hash_set;
+pub mod index_set;
+mod key;
+#[cfg(feature = "lru")]
+pub mod lru;
+pub mod priority_queue;
+pub mod store;
+pub mod vec_map;
+pub mod vec_set;
+
+pub use adaptive_set::DynAdaptiveSet;
+pub use any_map::ObjAnyMap;
+pub use btree_map::DynBTreeMap;
+pub use btree_set::DynBTreeSet;
+pub use counter::DynCounter;
+pub use hash_map::DynHashMap;
+pub use hash_set::DynHashSet;
+pub use index_set::DynIndexSet;
+pub use key::EqStats;
+#[cfg(feature = "lru")]
+pub use lru::DynLruCache;
+pub use priority_queue::DynPriorityQueue;
+pub use store::DynStore;
+pub use vec_map::DynVecMap;
+pub use vec_set::DynVecSet;
@@ -0,0 +1,334 @@
+//! A priority queue of boxed trait objects, whose elements can be updated
+//! after they are pushed.
+
+use core::{cmp::Ordering, fmt};
+use std::collections::HashMap;
+
+use crate::OrdObj;
+
+type Compare<K> = Box<dyn Fn(&K, &K) -> Ordering>;
+
+/// Identifies an element of a `DynPriorityQueue`, so that its priority can be
+/// changed or it can be removed while it is still in the queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Handle(u64);
+
+/// Priority queue of boxed trait objects, such as
+/// `DynPriorityQueue<dyn Task>`, which pops the greatest element first.
+///
+/// Elements are ordered either by their own `cmp_object`, when `K` extends
+/// `OrdObj`, or by a comparator provided with `with_comparator`, so `K` does
+/// not need to be ordered at all. Every push returns a `Handle`, which can be
+/// used to update or remove that element in logarithmic time.
+///
+/// ```rust ignore
+/// let mut queue = DynPriorityQueue::<dyn Task>::with_comparator(|a, b| {
+///     a.deadline().cmp(&b.deadline()).reverse()
+/// });
+/// let render = queue.push(Box::new(Render::new(frame)));
+/// queue.push(Box::new(Upload::new(buffer)));
+/// queue.update(render, |task| task.postpone(2));
+/// ```
+pub struct DynPriorityQueue<K: ?Sized> {
+    heap: Vec<(Handle, Box<K>)>,
+    /// Position of each element in `heap`.
+    positions: HashMap<Handle, usize>,
+    next_handle: u64,
+    compare: Compare<K>,
+}
+
+impl<K: OrdObj + ?Sized> DynPriorityQueue<K> {
+    /// Creates an empty queue, ordered by `cmp_object`.
+    pub fn new() -> Self {
+        Self::with_comparator(|a: &K, b: &K| a.cmp_object(b.as_ord_object()))
+    }
+}
+
+impl<K: ?Sized> DynPriorityQueue<K> {
+    /// Creates an empty queue, which pops the element that `compare` orders
+    /// last first.
+    pub fn with_comparator(compare: impl Fn(&K, &K) -> Ordering + 'static) -> Self {
+        Self {
+            heap: Vec::new(),
+            positions: HashMap::new(),
+            next_handle: 0,
+            compare: Box::new(compare),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Adds an element to the queue, returning its handle.
+    pub fn push(&mut self, element: Box<K>) -> Handle {
+        let handle = Handle(self.next_handle);
+        self.next_handle += 1;
+        let i = self.heap.len();
+        self.heap.push((handle, element));
+        self.positions.insert(handle, i);
+        self.sift_up(i);
+        handle
+    }
+
+    /// Returns the greatest element.
+    pub fn peek(&self) -> Option<&K> {
+        self.heap.first().map(|(_, element)| &**element)
+    }
+
+    /// Removes and returns the greatest element.
+    pub fn pop(&mut self) -> Option<Box<K>> {
+        self.pop_with_handle().map(|(_, element)| element)
+    }
+
+    /// Removes and returns the greatest element, along with its handle.
+    pub fn pop_with_handle(&mut self) -> Option<(Handle, Box<K>)> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        Some(self.remove_at(0))
+    }
+
+    /// Returns true if the element identified by `handle` is still queued.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.positions.contains_key(&handle)
+    }
+
+    /// Returns the element identified by `handle`, if it is still queued.
+    pub fn get(&self, handle: Handle) -> Option<&K> {
+        let &i = self.positions.get(&handle)?;
+        Some(&self.heap[i].1)
+    }
+
+    /// Replaces the element identified by `handle` and moves it to the
+    /// position matching its new priority. Returns the previous element, or
+    /// gives `element` back as an error if the handle is no longer queued.
+    pub fn change_priority(&mut self, handle: Handle, element: Box<K>) -> Result<Box<K>, Box<K>> {
+        let Some(&i) = self.positions.get(&handle) else {
+            return Err(element);
+        };
+        let previous = core::mem::replace(&mut self.heap[i].1, element);
+        self.restore(i);
+        Ok(previous)
+    }
+
+    /// Modifies the element identified by `handle` in place, then moves it to
+    /// the position matching its new priority. Returns false if the handle is
+    /// no longer queued.
+    pub fn update(&mut self, handle: Handle, f: impl FnOnce(&mut K)) -> bool {
+        let Some(&i) = self.positions.get(&handle) else {
+            return false;
+        };
+        f(&mut self.heap[i].1);
+        self.restore(i);
+        true
+    }
+
+    /// Removes and returns the element identified by `handle`.
+    pub fn remove(&mut self, handle: Handle) -> Option<Box<K>> {
+        let &i = self.positions.get(&handle)?;
+        Some(self.remove_at(i).1)
+    }
+
+    /// Iterates over the elements in arbitrary order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (Handle, &K)> {
+        self.heap
+            .iter()
+            .map(|(handle, element)| (*handle, &**element))
+    }
+
+    /// Removes every element, returning them from greatest to least.
+    pub fn drain_sorted(&mut self) -> Vec<Box<K>> {
+        let mut sorted = Vec::with_capacity(self.len());
+        while let Some(element) = self.pop() {
+            sorted.push(element);
+        }
+        sorted
+    }
+
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.positions.clear();
+    }
+
+    fn less(&self, i: usize, j: usize) -> bool {
+        (self.compare)(&self.heap[i].1, &self.heap[j].1) == Ordering::Less
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.positions.insert(self.heap[i].0, i);
+        self.positions.insert(self.heap[j].0, j);
+    }
+
+    fn remove_at(&mut self, i: usize) -> (Handle, Box<K>) {
+        let last = self.heap.len() - 1;
+        self.swap(i, last);
+        let removed = self.heap.pop().expect("the heap is not empty");
+        self.positions.remove(&removed.0);
+        if i < self.heap.len() {
+            self.restore(i);
+        }
+        removed
+    }
+
+    /// Moves the element at `i` up or down until the heap is ordered again.
+    fn restore(&mut self, i: usize) {
+        let i = self.sift_up(i);
+        self.sift_down(i);
+    }
+
+    fn sift_up(&mut self, mut i: usize) -> usize {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if !self.less(parent, i) {
+                break;
+            }
+            self.swap(parent, i);
+            i = parent;
+        }
+        i
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let (left, right) = (2 * i + 1, 2 * i + 2);
+            let mut greatest = i;
+            if left < self.heap.len() && self.less(greatest, left) {
+                greatest = left;
+            }
+            if right < self.heap.len() && self.less(greatest, right) {
+                greatest = right;
+            }
+            if greatest == i {
+                return;
+            }
+            self.swap(i, greatest);
+            i = greatest;
+        }
+    }
+}
+
+impl<K: OrdObj + ?Sized> Default for DynPriorityQueue<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: fmt::Debug + ?Sized> fmt::Debug for DynPriorityQueue<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.heap.iter().map(|(_, element)| element))
+            .finish()
+    }
+}
+
+impl<K: OrdObj + ?Sized> FromIterator<Box<K>> for DynPriorityQueue<K> {
+    fn from_iter<I: IntoIterator<Item = Box<K>>>(iter: I) -> Self {
+        let mut queue = Self::new();
+        queue.extend(iter);
+        queue
+    }
+}
+
+impl<K: ?Sized> Extend<Box<K>> for DynPriorityQueue<K> {
+    fn extend<I: IntoIterator<Item = Box<K>>>(&mut self, iter: I) {
+        for element in iter {
+            self.push(element);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::AsAny;
+
+    trait Task: AsAny + fmt::Debug {
+        fn priority(&self) -> u32;
+        fn set_priority(&mut self, priority: u32);
+    }
+
+    #[derive(Debug)]
+    struct Render(u32);
+
+    #[derive(Debug)]
+    struct Upload(u32);
+
+    impl Task for Render {
+        fn priority(&self) -> u32 {
+            self.0
+        }
+
+        fn set_priority(&mut self, priority: u32) {
+            self.0 = priority;
+        }
+    }
+
+    impl Task for Upload {
+        fn priority(&self) -> u32 {
+            self.0
+        }
+
+        fn set_priority(&mut self, priority: u32) {
+            self.0 = priority;
+        }
+    }
+
+    fn scheduler() -> DynPriorityQueue<dyn Task> {
+        DynPriorityQueue::with_comparator(|a: &dyn Task, b: &dyn Task| {
+            a.priority().cmp(&b.priority())
+        })
+    }
+
+    fn priorities(queue: &mut DynPriorityQueue<dyn Task>) -> Vec<u32> {
+        queue.drain_sorted().iter().map(|t| t.priority()).collect()
+    }
+
+    #[test]
+    fn pops_greatest_first() {
+        let mut queue = scheduler();
+        for (i, priority) in [3, 9, 1, 7, 5].into_iter().enumerate() {
+            if i % 2 == 0 {
+                queue.push(Box::new(Render(priority)));
+            } else {
+                queue.push(Box::new(Upload(priority)));
+            }
+        }
+        assert_eq!(queue.peek().map(|t| t.priority()), Some(9));
+        assert!(queue.peek().unwrap().as_any().is::<Upload>());
+        assert_eq!(priorities(&mut queue), [9, 7, 5, 3, 1]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn change_priority_and_remove() {
+        let mut queue = scheduler();
+        let handles: Vec<Handle> = (0..6).map(|p| queue.push(Box::new(Render(p)))).collect();
+        assert!(queue.update(handles[0], |task| task.set_priority(10)));
+        let previous = queue.change_priority(handles[5], Box::new(Upload(2)));
+        assert_eq!(previous.map(|t| t.priority()).ok(), Some(5));
+        assert_eq!(queue.remove(handles[3]).map(|t| t.priority()), Some(3));
+        assert!(!queue.contains(handles[3]));
+        assert_eq!(queue.get(handles[5]).map(|t| t.priority()), Some(2));
+        assert_eq!(priorities(&mut queue), [10, 4, 2, 2, 1]);
+        assert!(!queue.update(handles[0], |task| task.set_priority(0)));
+        assert!(queue
+            .change_priority(handles[0], Box::new(Render(0)))
+            .is_err());
+    }
+
+    #[test]
+    fn ord_obj_order() {
+        let mut queue: DynPriorityQueue<dyn OrdObj> = [3u8, 1, 2]
+            .map(|x| Box::new(x) as Box<dyn OrdObj>)
+            .into_iter()
+            .collect();
+        let top = queue.pop().unwrap();
+        assert_eq!((*top).as_any().downcast_ref(), Some(&3u8));
+    }
+}
@@ -0,0 +1,177 @@
+//! A store mapping boxed trait objects to values of any type.
+
+use core::{any::Any, fmt, hash::BuildHasher};
+use std::collections::hash_map::RandomState;
+
+use super::{hash_map::Keys, DynHashMap};
+use crate::{EqObj, HashObj};
+
+/// Store keyed by boxed trait objects, like `DynHashMap<K, _>`, where every
+/// key can hold a value of a different type, which is retrieved by naming
+/// that type.
+///
+/// ```rust ignore
+/// let mut config = DynStore::<dyn MyKey>::new();
+/// config.insert(Box::new(Setting::Timeout), Duration::from_secs(5));
+/// config.insert(Box::new("retries"), 3u32);
+/// assert_eq!(config.get::<u32>(&"retries"), Some(&3));
+/// assert_eq!(config.get::<Duration>(&"retries"), None);
+/// ```
+///
+/// Values are stored as `Box<dyn Any>`, so a typed accessor returns None both
+/// when the key is missing and when its value has another type. Use
+/// `get_dyn` or `contains_key` to tell the two apart.
+pub struct DynStore<K: ?Sized, S = RandomState> {
+    inner: DynHashMap<K, Box<dyn Any>, S>,
+}
+
+impl<K: HashObj + EqObj + ?Sized> DynStore<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K: ?Sized, S> DynStore<K, S> {
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            inner: DynHashMap::with_hasher(hasher),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, Box<dyn Any>> {
+        self.inner.keys()
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+}
+
+impl<K, S> DynStore<K, S>
+where
+    K: HashObj + EqObj + ?Sized,
+    S: BuildHasher,
+{
+    /// Stores a value for `key`, returning the value previously stored for
+    /// an equal key, whatever its type. In that case the key is not updated.
+    pub fn insert<V: Any>(&mut self, key: Box<K>, value: V) -> Option<Box<dyn Any>> {
+        self.inner.insert(key, Box::new(value))
+    }
+
+    /// Returns the value for `key` if it has type `V`.
+    pub fn get<V: Any>(&self, key: &K) -> Option<&V> {
+        self.get_dyn(key)?.downcast_ref()
+    }
+
+    /// Returns the value for `key` if it has type `V`.
+    pub fn get_mut<V: Any>(&mut self, key: &K) -> Option<&mut V> {
+        self.inner.get_mut(key)?.downcast_mut()
+    }
+
+    /// Returns the value for `key`, whatever its type.
+    pub fn get_dyn(&self, key: &K) -> Option<&dyn Any> {
+        self.inner.get(key).map(|value| &**value)
+    }
+
+    /// Returns the value for `key` if it has type `V`, inserting `default()`
+    /// first if the key is not present. Like
+    /// [`DynHashMap::get_or_insert_with`], `to_owned` is only called to box
+    /// the key if it is inserted.
+    ///
+    /// Returns None, and leaves the store unchanged, if the key is present
+    /// with a value of another type.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `to_owned` returns a key that is not equal to `key`.
+    pub fn get_or_insert_with<V: Any>(
+        &mut self,
+        key: &K,
+        to_owned: impl FnOnce(&K) -> Box<K>,
+        default: impl FnOnce() -> V,
+    ) -> Option<&mut V> {
+        self.inner
+            .get_or_insert_with(key, to_owned, || Box::new(default()))
+            .downcast_mut()
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Returns true if `key` is present with a value of type `V`.
+    pub fn contains<V: Any>(&self, key: &K) -> bool {
+        self.get_dyn(key).is_some_and(|value| value.is::<V>())
+    }
+
+    /// Removes the value for `key` if it has type `V`. A value of another
+    /// type is left in the store.
+    pub fn remove<V: Any>(&mut self, key: &K) -> Option<V> {
+        if !self.contains::<V>(key) {
+            return None;
+        }
+        let value = self.inner.remove(key)?;
+        Some(*value.downcast().expect("the type was just checked"))
+    }
+
+    /// Removes the value for `key`, whatever its type.
+    pub fn remove_dyn(&mut self, key: &K) -> Option<Box<dyn Any>> {
+        self.inner.remove(key)
+    }
+}
+
+impl<K: ?Sized, S: Default> Default for DynStore<K, S> {
+    fn default() -> Self {
+        Self::with_hasher(S::default())
+    }
+}
+
+/// Lists the keys, since the values can be of any type.
+impl<K: fmt::Debug + ?Sized, S> fmt::Debug for DynStore<K, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::KeyObj;
+
+    #[derive(Hash, PartialEq, Eq)]
+    enum Setting {
+        Timeout,
+    }
+
+    #[test]
+    fn typed_values() {
+        let mut store = DynStore::<dyn KeyObj>::new();
+        store.insert(Box::new(Setting::Timeout), 5.0f64);
+        assert!(store.insert(Box::new("retries"), 3u32).is_none());
+        assert_eq!(store.get::<f64>(&Setting::Timeout), Some(&5.0));
+        assert_eq!(store.get::<u32>(&Setting::Timeout), None);
+        *store.get_mut::<u32>(&"retries").unwrap() += 1;
+        assert_eq!(store.get::<u32>(&"retries"), Some(&4));
+        assert!(store.contains_key(&"retries"));
+        assert!(!store.contains::<f64>(&"retries"));
+
+        assert_eq!(store.remove::<f64>(&"retries"), None);
+        assert_eq!(store.remove::<u32>(&"retries"), Some(4));
+        assert_eq!(store.len(), 1);
+
+        let name = store.get_or_insert_with(&"name", |_| Box::new("name"), String::new);
+        name.unwrap().push_str("store");
+        assert_eq!(store.get::<String>(&"name").unwrap(), "store");
+        assert!(store
+            .get_or_insert_with(&"name", |_| Box::new("name"), || 0u8)
+            .is_none());
+    }
+}
@@ -0,0 +1,302 @@
+//! A linear-scan map keyed by boxed trait objects, and its iterators.
+
+use core::{fmt, ops::Index};
+
+use crate::EqObj;
+
+/// Map keyed by boxed trait objects and backed by a `Vec`, such as
+/// `DynVecMap<dyn MyKey, V>` where `MyKey` extends `EqObj`.
+///
+/// Like `DynVecSet`, this compares the probe with each key in turn, so it is
+/// only meant for a handful of keys, and the keys do not need to implement
+/// `HashObj`. The API mirrors `DynHashMap`, and the iteration order is
+/// unspecified.
+pub struct DynVecMap<K: ?Sized, V> {
+    inner: Vec<(Box<K>, V)>,
+}
+
+impl<K: ?Sized, V> DynVecMap<K, V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter(self.inner.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut(self.inner.iter_mut())
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys(self.inner.iter())
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values(self.inner.iter())
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut(self.inner.iter_mut())
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional)
+    }
+
+    /// Keeps only the entries for which `f` returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        self.inner.retain_mut(|(key, value)| f(key, value))
+    }
+}
+
+impl<K: EqObj + ?Sized, V> DynVecMap<K, V> {
+    /// Inserts a value, returning the value previously stored for an equal
+    /// key, if any. In that case the key is not updated.
+    pub fn insert(&mut self, key: Box<K>, value: V) -> Option<V> {
+        match self.position(&key) {
+            Some(i) => Some(core::mem::replace(&mut self.inner[i].1, value)),
+            None => {
+                self.inner.push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.position(key).map(|i| &self.inner[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.position(key).map(|i| &mut self.inner[i].1)
+    }
+
+    /// Returns the stored key that is equal to `key`, and its value.
+    pub fn get_key_value(&self, key: &K) -> Option<(&K, &V)> {
+        self.position(key).map(|i| {
+            let (key, value) = &self.inner[i];
+            (&**key, value)
+        })
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.position(key).is_some()
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    pub fn remove_entry(&mut self, key: &K) -> Option<(Box<K>, V)> {
+        let i = self.position(key)?;
+        Some(self.inner.swap_remove(i))
+    }
+
+    /// Returns true if the map contains a key of type `T` equal to `value`.
+    /// Comparators in the [`registry`](crate::registry) are not used: only
+    /// keys of type `T` can match.
+    pub fn contains_by<T: PartialEq + 'static>(&self, value: &T) -> bool {
+        self.position_by(value).is_some()
+    }
+
+    /// Returns the value for the key of type `T` equal to `value`. See
+    /// `contains_by`.
+    pub fn get_by<T: PartialEq + 'static>(&self, value: &T) -> Option<&V> {
+        self.position_by(value).map(|i| &self.inner[i].1)
+    }
+
+    /// Returns the value for the key of type `T` equal to `value`. See
+    /// `contains_by`.
+    pub fn get_mut_by<T: PartialEq + 'static>(&mut self, value: &T) -> Option<&mut V> {
+        self.position_by(value).map(|i| &mut self.inner[i].1)
+    }
+
+    /// Removes the key of type `T` equal to `value`, returning its value. See
+    /// `contains_by`.
+    pub fn remove_by<T: PartialEq + 'static>(&mut self, value: &T) -> Option<V> {
+        let i = self.position_by(value)?;
+        Some(self.inner.swap_remove(i).1)
+    }
+
+    /// Keeps every entry whose key's concrete type is not `T`, and the entries
+    /// with a key of type `T` for which `f` returns true.
+    pub fn retain_by<T: 'static>(&mut self, mut f: impl FnMut(&T, &mut V) -> bool) {
+        self.retain(|key, value| key.as_any().downcast_ref().is_none_or(|key| f(key, value)))
+    }
+
+    fn position(&self, key: &K) -> Option<usize> {
+        self.inner
+            .iter()
+            .position(|(k, _)| k.eq_object(key.as_partial_eq_object()))
+    }
+
+    fn position_by<T: PartialEq + 'static>(&self, value: &T) -> Option<usize> {
+        self.inner
+            .iter()
+            .position(|(k, _)| (**k).as_any().downcast_ref::<T>() == Some(value))
+    }
+}
+
+impl<K: ?Sized, V> Default for DynVecMap<K, V> {
+    fn default() -> Self {
+        Self { inner: Vec::new() }
+    }
+}
+
+impl<K: fmt::Debug + ?Sized, V: fmt::Debug> fmt::Debug for DynVecMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Maps are equal if they contain the same entries, regardless of their order.
+impl<K: EqObj + ?Sized, V: PartialEq> PartialEq for DynVecMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<K: EqObj + ?Sized, V: Eq> Eq for DynVecMap<K, V> {}
+
+impl<K: EqObj + ?Sized, V> Index<&K> for DynVecMap<K, V> {
+    type Output = V;
+
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the map.
+    fn index(&self, key: &K) -> &V {
+        self.get(key).expect("key not found in DynVecMap")
+    }
+}
+
+impl<K: EqObj + ?Sized, V> FromIterator<(Box<K>, V)> for DynVecMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (Box<K>, V)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: EqObj + ?Sized, V> Extend<(Box<K>, V)> for DynVecMap<K, V> {
+    fn extend<I: IntoIterator<Item = (Box<K>, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<'a, K: ?Sized, V> IntoIterator for &'a DynVecMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: ?Sized, V> IntoIterator for &'a mut DynVecMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K: ?Sized, V> IntoIterator for DynVecMap<K, V> {
+    type Item = (Box<K>, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.inner.into_iter())
+    }
+}
+
+iterator! {
+    /// Iterator over the entries of a `DynVecMap`.
+    Iter<'a, K, V>(core::slice::Iter<'a, (Box<K>, V)>) -> (&'a K, &'a V),
+    |(key, value)| (&**key, value)
+}
+
+iterator! {
+    /// Mutable iterator over the entries of a `DynVecMap`.
+    IterMut<'a, K, V>(core::slice::IterMut<'a, (Box<K>, V)>) -> (&'a K, &'a mut V),
+    |(key, value)| (&**key, value)
+}
+
+iterator! {
+    /// Iterator over the keys of a `DynVecMap`.
+    Keys<'a, K, V>(core::slice::Iter<'a, (Box<K>, V)>) -> &'a K,
+    |(key, _)| &**key
+}
+
+iterator! {
+    /// Iterator over the values of a `DynVecMap`.
+    Values<'a, K, V>(core::slice::Iter<'a, (Box<K>, V)>) -> &'a V,
+    |(_, value)| value
+}
+
+iterator! {
+    /// Mutable iterator over the values of a `DynVecMap`.
+    ValuesMut<'a, K, V>(core::slice::IterMut<'a, (Box<K>, V)>) -> &'a mut V,
+    |(_, value)| value
+}
+
+iterator! {
+    /// Owning iterator over the entries of a `DynVecMap`.
+    IntoIter<K, V>(std::vec::IntoIter<(Box<K>, V)>) -> (Box<K>, V),
+    |entry| entry
+}
+
+exact_size!(
+    Iter<'a, K, V>,
+    IterMut<'a, K, V>,
+    Keys<'a, K, V>,
+    Values<'a, K, V>,
+    ValuesMut<'a, K, V>,
+    IntoIter<K, V>
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::Key;
+
+    #[test]
+    fn map_operations() {
+        let mut map = DynVecMap::<dyn Key, &str>::new();
+        assert_eq!(map.insert(Box::new(1), "int"), None);
+        assert_eq!(map.insert(Box::new(1u8), "byte"), None);
+        assert_eq!(map.insert(Box::new(1), "i32"), Some("int"));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&1u8], "byte");
+        assert_eq!(map.get_by(&1), Some(&"i32"));
+        assert!(!map.contains_key(&1u16));
+        *map.get_mut(&1u8).unwrap() = "u8";
+        assert_eq!(map.remove(&1u8), Some("u8"));
+        assert_eq!(map.remove_by(&1u8), None);
+        map.retain(|_, _| false);
+        assert!(map.is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+//! A linear-scan set of boxed trait objects, and its iterators.
+
+use core::fmt;
+
+use crate::EqObj;
+
+/// Set of boxed trait objects backed by a `Vec`, such as
+/// `DynVecSet<dyn MyKey>` where `MyKey` extends `EqObj`.
+///
+/// Every lookup compares the probe with each key in turn, so this is only
+/// meant for a handful of keys, where comparing is cheaper than hashing.
+/// Since keys are never hashed, they do not need to implement `HashObj`. The
+/// API mirrors `DynHashSet`, and the iteration order is unspecified.
+pub struct DynVecSet<K: ?Sized> {
+    inner: Vec<Box<K>>,
+}
+
+impl<K: ?Sized> DynVecSet<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter(self.inner.iter())
+    }
+
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        self.inner.reserve(additional)
+    }
+
+    /// Keeps only the keys for which `f` returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(&K) -> bool) {
+        self.inner.retain(|key| f(key))
+    }
+}
+
+impl<K: EqObj + ?Sized> DynVecSet<K> {
+    /// Adds a key to the set, returning false if an equal key was already
+    /// present, in which case the set is not modified.
+    pub fn insert(&mut self, key: Box<K>) -> bool {
+        if self.contains(&key) {
+            return false;
+        }
+        self.inner.push(key);
+        true
+    }
+
+    /// Adds a key to the set, replacing and returning the equal key that was
+    /// already present, if any.
+    pub fn replace(&mut self, key: Box<K>) -> Option<Box<K>> {
+        match self.position(&key) {
+            Some(i) => Some(core::mem::replace(&mut self.inner[i], key)),
+            None => {
+                self.inner.push(key);
+                None
+            }
+        }
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.position(key).is_some()
+    }
+
+    /// Returns the key in the set that is equal to `key`.
+    pub fn get(&self, key: &K) -> Option<&K> {
+        self.position(key).map(|i| &*self.inner[i])
+    }
+
+    /// Removes the key that is equal to `key`, returning whether it was
+    /// present.
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.take(key).is_some()
+    }
+
+    /// Removes and returns the key that is equal to `key`.
+    pub fn take(&mut self, key: &K) -> Option<Box<K>> {
+        let i = self.position(key)?;
+        Some(self.inner.swap_remove(i))
+    }
+
+    /// Returns true if the set contains a key of type `T` equal to `value`.
+    /// Comparators in the [`registry`](crate::registry) are not used: only
+    /// keys of type `T` can match.
+    pub fn contains_by<T: PartialEq + 'static>(&self, value: &T) -> bool {
+        self.position_by(value).is_some()
+    }
+
+    /// Returns the key of type `T` equal to `value`. See `contains_by`.
+    pub fn get_by<T: PartialEq + 'static>(&self, value: &T) -> Option<&K> {
+        self.position_by(value).map(|i| &*self.inner[i])
+    }
+
+    /// Removes the key of type `T` equal to `value`, returning whether it was
+    /// present. See `contains_by`.
+    pub fn remove_by<T: PartialEq + 'static>(&mut self, value: &T) -> bool {
+        match self.position_by(value) {
+            Some(i) => {
+                self.inner.swap_remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Keeps every key whose concrete type is not `T`, and the keys of type
+    /// `T` for which `f` returns true.
+    pub fn retain_by<T: 'static>(&mut self, mut f: impl FnMut(&T) -> bool) {
+        self.retain(|key| key.as_any().downcast_ref().is_none_or(&mut f))
+    }
+
+    fn position(&self, key: &K) -> Option<usize> {
+        self.inner
+            .iter()
+            .position(|k| k.eq_object(key.as_partial_eq_object()))
+    }
+
+    fn position_by<T: PartialEq + 'static>(&self, value: &T) -> Option<usize> {
+        self.inner
+            .iter()
+            .position(|k| (**k).as_any().downcast_ref::<T>() == Some(value))
+    }
+}
+
+impl<K: ?Sized> Default for DynVecSet<K> {
+    fn default() -> Self {
+        Self { inner: Vec::new() }
+    }
+}
+
+impl<K: fmt::Debug + ?Sized> fmt::Debug for DynVecSet<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Sets are equal if they contain the same keys, regardless of their order.
+impl<K: EqObj + ?Sized> PartialEq for DynVecSet<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|key| other.contains(key))
+    }
+}
+
+impl<K: EqObj + ?Sized> Eq for DynVecSet<K> {}
+
+impl<K: EqObj + ?Sized> FromIterator<Box<K>> for DynVecSet<K> {
+    fn from_iter<I: IntoIterator<Item = Box<K>>>(iter: I) -> Self {
+        let mut set = Self::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<K: EqObj + ?Sized> Extend<Box<K>> for DynVecSet<K> {
+    fn extend<I: IntoIterator<Item = Box<K>>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+impl<'a, K: ?Sized> IntoIterator for &'a DynVecSet<K> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K: ?Sized> IntoIterator for DynVecSet<K> {
+    type Item = Box<K>;
+    type IntoIter = IntoIter<K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.inner.into_iter())
+    }
+}
+
+iterator! {
+    /// Iterator over the keys of a `DynVecSet`.
+    Iter<'a, K>(core::slice::Iter<'a, Box<K>>) -> &'a K,
+    |key| &**key
+}
+
+iterator! {
+    /// Owning iterator over the keys of a `DynVecSet`.
+    IntoIter<K>(std::vec::IntoIter<Box<K>>) -> Box<K>,
+    |key| key
+}
+
+exact_size!(Iter<'a, K>, IntoIter<K>);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::Key;
+
+    #[test]
+    fn set_operations() {
+        let mut set = DynVecSet::<dyn Key>::new();
+        assert!(set.insert(Box::new(1)));
+        assert!(set.insert(Box::new("one")));
+        assert!(set.insert(Box::new(1u8)));
+        assert!(!set.insert(Box::new(1)));
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&1));
+        assert!(!set.contains(&1u16));
+        assert!(set.contains_by(&"one"));
+        assert!(set.get_by(&1u8).unwrap().as_any().is::<u8>());
+        assert!(set.replace(Box::new(1u8)).is_some());
+        assert!(set.remove(&1u8));
+        assert!(!set.remove_by(&1u8));
+        assert!(set.take(&"one").is_some());
+        assert_eq!(set.iter().count(), 1);
+        set.retain_by(|_: &i32| false);
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn equality_ignores_order() {
+        let a: DynVecSet<dyn Key> = [Box::new(1) as Box<dyn Key>, Box::new('a')]
+            .into_iter()
+            .collect();
+        let b: DynVecSet<dyn Key> = [Box::new('a') as Box<dyn Key>, Box::new(1)]
+            .into_iter()
+            .collect();
+        assert_eq!(a, b);
+    }
+}
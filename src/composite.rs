@@ -0,0 +1,128 @@
+use alloc::{boxed::Box, vec::Vec};
+use core::{any::Any, fmt};
+
+use crate::FullObj;
+
+/// One component of a `CompositeKey`.
+type Component = dyn FullObj + Send + Sync;
+
+/// Key made of several objects of any types, such as a tenant, a resource and
+/// a variant, for keys whose parts vary too much to be a struct of their own.
+///
+/// Keys are compared and hashed component by component, in the order they
+/// were pushed, like a tuple of the components, so keys with different
+/// numbers of components are never equal, and a key sorts after its own
+/// prefixes. Components of different types at the same position are ordered
+/// by type, like any objects.
+///
+/// ```rust ignore
+/// let key = CompositeKey::new().push(TenantId(7)).push("invoice").push(Region::Eu);
+/// cache.insert(key, document);
+/// ```
+#[derive(Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompositeKey(Vec<Box<Component>>);
+
+impl CompositeKey {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Appends a component.
+    pub fn push<T: FullObj + Send + Sync>(self, component: T) -> Self {
+        self.push_boxed(Box::new(component))
+    }
+
+    /// Appends a component that is already boxed.
+    pub fn push_boxed(mut self, component: Box<Component>) -> Self {
+        self.0.push(component);
+        self
+    }
+
+    /// Number of components.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the component at `index`.
+    pub fn get(&self, index: usize) -> Option<&Component> {
+        self.0.get(index).map(|component| &**component)
+    }
+
+    /// Returns the component at `index` if it has type `T`.
+    pub fn downcast_ref<T: Any>(&self, index: usize) -> Option<&T> {
+        self.get(index)?.as_any().downcast_ref()
+    }
+
+    /// The components, in order.
+    pub fn components(&self) -> impl ExactSizeIterator<Item = &Component> {
+        self.0.iter().map(|component| &**component)
+    }
+
+    pub fn into_inner(self) -> Vec<Box<Component>> {
+        self.0
+    }
+}
+
+impl From<Vec<Box<Component>>> for CompositeKey {
+    fn from(components: Vec<Box<Component>>) -> Self {
+        Self(components)
+    }
+}
+
+impl FromIterator<Box<Component>> for CompositeKey {
+    fn from_iter<I: IntoIterator<Item = Box<Component>>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+/// Shows the concrete types of the components, since they may not implement
+/// `Debug`.
+impl fmt::Debug for CompositeKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CompositeKey")
+            .field(&DebugTypes(self))
+            .finish()
+    }
+}
+
+struct DebugTypes<'a>(&'a CompositeKey);
+
+impl fmt::Debug for DebugTypes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.components().map(|component| component.type_name()))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{BTreeSet, HashMap};
+
+    use super::*;
+
+    #[test]
+    fn compare_components_in_order() {
+        let key = || CompositeKey::new().push(7u32).push("invoice");
+        let mut map = HashMap::new();
+        map.insert(key(), 1);
+        map.insert(key().push(()), 2);
+        map.insert(CompositeKey::new().push("invoice").push(7u32), 3);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map[&key()], 1);
+        assert!(!map.contains_key(&CompositeKey::new().push(7u64).push("invoice")));
+
+        let sorted: BTreeSet<_> = [key().push(()), key(), CompositeKey::new()].into();
+        let lengths: Vec<_> = sorted.iter().map(CompositeKey::len).collect();
+        assert_eq!(lengths, [0, 2, 3]);
+
+        let key = key();
+        assert_eq!(key.downcast_ref::<&str>(1), Some(&"invoice"));
+        assert_eq!(key.downcast_ref::<u64>(0), None);
+        assert_eq!(format!("{key:?}"), "CompositeKey([\"u32\", \"&str\"])");
+    }
+}
@@ -0,0 +1,101 @@
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    hint::black_box,
+};
+
+/// Wrapper for secrets such as tokens or keys, whose equality takes the same
+/// time for any two values of the same length, so comparing a guess with a
+/// secret does not reveal how much of the guess was right. `T` is anything
+/// that can be viewed as bytes, such as a `Vec<u8>`, a `String` or an array.
+///
+/// It implements `PartialEq`, `Eq` and `Hash`, and so the object-safe
+/// traits, so secrets can be keys behind `dyn MyKey` like any other value.
+/// Only this wrapper's own comparison is constant-time: the lengths of the
+/// values are not hidden, a hashed collection still compares hashes first,
+/// and `Debug` does not print the secret.
+///
+/// ```rust ignore
+/// let token: Box<dyn MyKey> = Box::new(ConstantTimeEqObj(request.token.into_bytes()));
+/// if sessions.contains_key(&*token) { ... }
+/// ```
+#[derive(Clone, Copy, Default)]
+pub struct ConstantTimeEqObj<T>(pub T);
+
+impl<T> ConstantTimeEqObj<T> {
+    pub fn new(secret: T) -> Self {
+        Self(secret)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for ConstantTimeEqObj<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ConstantTimeEqObj(..)")
+    }
+}
+
+/// Compares two byte strings without branching on their contents.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let difference = a
+        .iter()
+        .zip(b)
+        .fold(0u8, |difference, (x, y)| black_box(difference | (x ^ y)));
+    black_box(difference) == 0
+}
+
+impl<T: AsRef<[u8]>> PartialEq for ConstantTimeEqObj<T> {
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(self.0.as_ref(), other.0.as_ref())
+    }
+}
+
+impl<T: AsRef<[u8]>> Eq for ConstantTimeEqObj<T> {}
+
+impl<T: AsRef<[u8]>> Hash for ConstantTimeEqObj<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_ref().hash(state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "alloc")]
+    use std::collections::HashSet;
+
+    use super::*;
+    #[cfg(feature = "alloc")]
+    use crate::KeyObj;
+
+    #[test]
+    fn compares_bytes() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secrets"));
+        assert!(constant_time_eq(b"", b""));
+        assert_eq!(
+            format!("{:?}", ConstantTimeEqObj("a")),
+            "ConstantTimeEqObj(..)"
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn keys() {
+        let tokens: HashSet<Box<dyn KeyObj>> = [
+            Box::new(ConstantTimeEqObj("a".to_string())) as Box<dyn KeyObj>,
+            Box::new(ConstantTimeEqObj([1u8, 2])),
+        ]
+        .into();
+        assert!(tokens.contains(&(Box::new(ConstantTimeEqObj("a".to_string())) as Box<dyn KeyObj>)));
+        assert!(
+            !tokens.contains(&(Box::new(ConstantTimeEqObj("b".to_string())) as Box<dyn KeyObj>))
+        );
+    }
+}
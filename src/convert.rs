@@ -0,0 +1,180 @@
+//! Conversions from boxed values to boxed trait objects.
+//!
+//! `Box::new(x).to_partial_eq_object()` erases the `Box<T>` itself, so the
+//! result is a second allocation that points to the first. Converting with
+//! `Box<dyn PartialEqObj>::from` or `.into()` instead reuses the existing box
+//! as the trait object, which only changes the pointer's metadata.
+//!
+//! ```rust ignore
+//! let boxed = Box::new(5);
+//! let obj: Box<dyn PartialEqObj> = boxed.into();
+//! assert_eq!(obj.as_any().downcast_ref(), Some(&5));
+//! ```
+//!
+//! The boxed trait objects returned by the `to_*_object` methods are never
+//! `Send` or `Sync`, even if the value is. `ToMarkedObject` and the `From`
+//! impls for `Box<dyn Trait + Send>` and so on keep the auto traits instead.
+//!
+//! The same conversions are implemented from an `Obj<Box<T>>`, and any value
+//! converts into an `Obj` around it, so values can cross between the wrapper
+//! and the erased boxes at API boundaries. An `Obj` around a box that already
+//! holds a trait object is unwrapped with `into_inner`.
+//!
+//! ```rust ignore
+//! let key: Box<dyn HashObj + Send> = Obj(Box::new(5)).into();
+//! let obj: Obj<Box<dyn HashObj + Send>> = key.into();
+//! let key = obj.into_inner();
+//! ```
+
+use alloc::boxed::Box;
+
+use crate::{EqObj, HashObj, Obj, OrdObj, PartialEqObj, PartialOrdObj};
+
+macro_rules! impl_from_box {
+    ($($Trait:ident),*) => {$(
+        impl_from_box!(@markers $Trait: [], [Send], [Sync], [Send, Sync]);
+    )*};
+    (@markers $Trait:ident: $([$($Marker:ident),*]),*) => {$(
+        impl<T: $Trait $(+ $Marker)* + 'static> From<Box<T>> for Box<dyn $Trait $(+ $Marker)*> {
+            fn from(value: Box<T>) -> Self {
+                value
+            }
+        }
+
+        impl<T: $Trait $(+ $Marker)* + 'static> From<Obj<Box<T>>> for Box<dyn $Trait $(+ $Marker)*> {
+            fn from(value: Obj<Box<T>>) -> Self {
+                value.0
+            }
+        }
+    )*};
+}
+
+impl_from_box!(HashObj, PartialEqObj, EqObj, PartialOrdObj, OrdObj);
+
+macro_rules! to_marked_object {
+    ($($Trait:ident {$($method:ident: [$($Marker:ident),*];)*})*) => {
+        /// Variants of the `to_*_object` methods that keep `Send` and `Sync`.
+        /// This is implemented for every `'static` type, and each method is
+        /// available if the type implements the trait and the auto traits.
+        ///
+        /// ```rust ignore
+        /// let key: Box<dyn HashObj + Send + Sync> = 5.to_hash_object_send_sync();
+        /// std::thread::spawn(move || key.hash_object_u64());
+        /// ```
+        ///
+        /// `to_object` names the trait object as a type parameter instead, so
+        /// it works for any combination of traits and auto traits with a
+        /// `From<Box<T>>` impl, including the ones of this module.
+        ///
+        /// ```rust ignore
+        /// let key = 5.to_object::<dyn OrdObj + Send>();
+        /// ```
+        pub trait ToMarkedObject: Sized + 'static {
+            fn to_object<D: ?Sized>(self) -> Box<D>
+            where
+                Box<Self>: Into<Box<D>>,
+            {
+                Box::new(self).into()
+            }
+$($(
+            fn $method(self) -> Box<dyn $Trait $(+ $Marker)*>
+            where
+                Self: $Trait $(+ $Marker)*,
+            {
+                Box::new(self)
+            }
+        )*)*}
+    };
+}
+
+to_marked_object! {
+    HashObj {
+        to_hash_object_send: [Send];
+        to_hash_object_sync: [Sync];
+        to_hash_object_send_sync: [Send, Sync];
+    }
+    PartialEqObj {
+        to_partial_eq_object_send: [Send];
+        to_partial_eq_object_sync: [Sync];
+        to_partial_eq_object_send_sync: [Send, Sync];
+    }
+    EqObj {
+        to_eq_object_send: [Send];
+        to_eq_object_sync: [Sync];
+        to_eq_object_send_sync: [Send, Sync];
+    }
+    PartialOrdObj {
+        to_partial_ord_object_send: [Send];
+        to_partial_ord_object_sync: [Sync];
+        to_partial_ord_object_send_sync: [Send, Sync];
+    }
+    OrdObj {
+        to_ord_object_send: [Send];
+        to_ord_object_sync: [Sync];
+        to_ord_object_send_sync: [Send, Sync];
+    }
+}
+
+impl<T: 'static> ToMarkedObject for T {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reuses_allocation() {
+        let boxed = Box::new(5u32);
+        let address = &*boxed as *const u32;
+        let obj: Box<dyn OrdObj> = boxed.into();
+        assert!(core::ptr::addr_eq(&*obj, address));
+        assert_eq!((*obj).as_any().downcast_ref(), Some(&5u32));
+        assert!(*obj == *Box::<dyn OrdObj>::from(Box::new(5u32)));
+
+        let double = Box::new(5u32).to_partial_eq_object();
+        assert!((*double).as_any().is::<Box<u32>>());
+        let single = Box::<dyn PartialEqObj>::from(Box::new(5u32));
+        assert!((*single).as_any().is::<u32>());
+        let shared: Box<dyn EqObj + Send + Sync> = Box::new(5u32).into();
+        assert!((*shared).as_any().is::<u32>());
+    }
+
+    #[test]
+    fn from_obj() {
+        let boxed = Box::new(5u32);
+        let address = &*boxed as *const u32;
+        let key: Box<dyn HashObj + Send> = Obj(boxed).into();
+        assert!(core::ptr::addr_eq(&*key, address));
+        let obj: Obj<Box<dyn HashObj + Send>> = key.into();
+        assert!(core::ptr::addr_eq(&*obj.into_inner(), address));
+    }
+
+    #[test]
+    fn keeps_markers() {
+        fn send<T: Send + ?Sized>(_: &T) {}
+        fn sync<T: Sync + ?Sized>(_: &T) {}
+        let hash = 5u32.to_hash_object_send();
+        send(&hash);
+        let eq = 5u32.to_eq_object_sync();
+        sync(&eq);
+        let ord = 5u32.to_ord_object_send_sync();
+        send(&ord);
+        sync(&ord);
+        assert_eq!(
+            (*ord).cmp_object(5u32.as_ord_object()),
+            core::cmp::Ordering::Equal
+        );
+        assert!((*eq).eq_object(&5u32));
+        #[cfg(feature = "std")]
+        assert_eq!((*hash).hash_object_u64(), 5u32.hash_object_u64());
+    }
+
+    #[test]
+    fn to_object_names_markers() {
+        fn send_sync<T: Send + Sync + ?Sized>(_: &T) {}
+        let ord = 5u32.to_object::<dyn OrdObj + Send + Sync>();
+        send_sync(&ord);
+        assert!((*ord).as_any().is::<u32>());
+        let eq = 5u32.to_object::<dyn PartialEqObj + Sync>();
+        assert!((*eq).eq_object(&5u32));
+    }
+}
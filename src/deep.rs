@@ -0,0 +1,227 @@
+//! Iterative comparison and hashing for trees of trait objects.
+//!
+//! Trait objects can contain other trait objects, for example a node type
+//! holding `Vec<Obj<Box<dyn Node>>>` children. Deriving `PartialEq` and `Hash`
+//! for such a node works to any depth, and always compares and hashes the
+//! values of the children, never their addresses. However, like any derived
+//! implementation, it recurses once per level of the tree, so very deep trees
+//! can overflow the stack. `deep_eq` and `deep_hash` walk the tree with an
+//! explicit stack instead.
+
+use alloc::vec;
+use core::hash::Hasher;
+
+/// Compares two trees without recursion.
+///
+/// `shallow_eq` compares two nodes while ignoring their children, and
+/// `children` returns the children of a node. The trees are equal if every
+/// pair of corresponding nodes is `shallow_eq`, and corresponding nodes have
+/// the same number of children.
+///
+/// ```rust ignore
+/// let equal = deep_eq(
+///     &*left,
+///     &*right,
+///     |a: &dyn Node, b| a.label_object().eq_object(b.label_object()),
+///     |node| node.children().iter().map(|child| &***child),
+/// );
+/// ```
+pub fn deep_eq<'a, X, I>(
+    left: &'a X,
+    right: &'a X,
+    mut shallow_eq: impl FnMut(&'a X, &'a X) -> bool,
+    mut children: impl FnMut(&'a X) -> I,
+) -> bool
+where
+    X: ?Sized,
+    I: IntoIterator<Item = &'a X>,
+{
+    if !shallow_eq(left, right) {
+        return false;
+    }
+    let mut stack = vec![(children(left).into_iter(), children(right).into_iter())];
+    while let Some((lefts, rights)) = stack.last_mut() {
+        match (lefts.next(), rights.next()) {
+            (None, None) => {
+                stack.pop();
+            }
+            (Some(left), Some(right)) => {
+                if !shallow_eq(left, right) {
+                    return false;
+                }
+                stack.push((children(left).into_iter(), children(right).into_iter()));
+            }
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Hashes a tree without recursion, consistently with `deep_eq`: trees that
+/// are `deep_eq` hash the same as long as nodes that are `shallow_eq` do.
+///
+/// `shallow_hash` hashes a node while ignoring its children, and `children`
+/// returns the children of a node. The result is not the same as hashing the
+/// tree with a derived `Hash` implementation.
+pub fn deep_hash<'a, X, I>(
+    root: &'a X,
+    state: &mut dyn Hasher,
+    mut shallow_hash: impl FnMut(&'a X, &mut dyn Hasher),
+    mut children: impl FnMut(&'a X) -> I,
+) where
+    X: ?Sized,
+    I: IntoIterator<Item = &'a X>,
+{
+    // Every node is written as a start marker, its shallow hash, its
+    // children, and an end marker, so different shapes hash differently.
+    state.write_u8(1);
+    shallow_hash(root, state);
+    let mut stack = vec![children(root).into_iter()];
+    while let Some(nodes) = stack.last_mut() {
+        match nodes.next() {
+            Some(node) => {
+                state.write_u8(1);
+                shallow_hash(node, state);
+                stack.push(children(node).into_iter());
+            }
+            None => {
+                state.write_u8(0);
+                stack.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::{
+        fmt::Debug,
+        hash::{Hash, Hasher},
+        ops::Deref,
+    };
+    use std::collections::{hash_map::DefaultHasher, HashSet};
+
+    use crate::*;
+
+    trait Node: HashObj + EqObj + Debug + Send {
+        fn label(&self) -> u32;
+        fn children(&self) -> &[Obj<Box<dyn Node>>];
+    }
+    impl_hash!(dyn Node);
+    impl_eq!(dyn Node);
+    impl_partial_eq!(dyn Node);
+
+    #[derive(Debug, Hash, PartialEq, Eq)]
+    struct Leaf(u32);
+
+    impl Node for Leaf {
+        fn label(&self) -> u32 {
+            self.0
+        }
+
+        fn children(&self) -> &[Obj<Box<dyn Node>>] {
+            &[]
+        }
+    }
+
+    #[derive(Debug, Hash, PartialEq, Eq)]
+    struct Branch(u32, Vec<Obj<Box<dyn Node>>>);
+
+    impl Node for Branch {
+        fn label(&self) -> u32 {
+            self.0
+        }
+
+        fn children(&self) -> &[Obj<Box<dyn Node>>] {
+            &self.1
+        }
+    }
+
+    fn chain(depth: u32, leaf: u32) -> Box<dyn Node> {
+        (0..depth).fold(Box::new(Leaf(leaf)), |child, label| {
+            Box::new(Branch(label, vec![Obj(child)]))
+        })
+    }
+
+    fn tree(leaf: u32) -> Obj<Box<dyn Node>> {
+        Obj(Box::new(Branch(
+            0,
+            vec![
+                Obj(Box::new(Leaf(1))),
+                Obj(Box::new(Branch(2, vec![Obj(chain(10, leaf))]))),
+            ],
+        )))
+    }
+
+    fn hash<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn eq(a: &dyn Node, b: &dyn Node) -> bool {
+        deep_eq(
+            a,
+            b,
+            |a, b| a.as_any().type_id() == b.as_any().type_id() && a.label() == b.label(),
+            |node| node.children().iter().map(|child| &***child),
+        )
+    }
+
+    fn deep_hash_u64(node: &dyn Node) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        deep_hash(
+            node,
+            &mut hasher,
+            |node, state| {
+                node.as_any().type_id().hash_object(state);
+                state.write_u32(node.label());
+            },
+            |node| node.children().iter().map(|child| &***child),
+        );
+        hasher.finish()
+    }
+
+    #[test]
+    fn nested_objects_compare_by_value() {
+        assert!(tree(7) == tree(7));
+        assert!(tree(7) != tree(8));
+        assert_eq!(hash(&tree(7)), hash(&tree(7)));
+        let set: HashSet<_> = [tree(7), tree(7), tree(8)].into_iter().collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&tree(8)));
+    }
+
+    #[test]
+    fn deep_eq_matches_derived_eq() {
+        let trees = [tree(7), tree(8), Obj(chain(3, 7)), Obj(Box::new(Leaf(0)))];
+        for a in &trees {
+            for b in &trees {
+                assert_eq!(eq(&***a, &***b), a == b);
+                if a == b {
+                    assert_eq!(deep_hash_u64(&***a), deep_hash_u64(&***b));
+                }
+            }
+        }
+        let wide = Branch(0, vec![Obj(Box::new(Leaf(1))), Obj(Box::new(Leaf(1)))]);
+        let narrow = Branch(0, vec![Obj(Box::new(Leaf(1)))]);
+        assert!(!eq(&wide, &narrow));
+        assert_ne!(deep_hash_u64(&wide), deep_hash_u64(&narrow));
+    }
+
+    #[test]
+    fn very_deep_trees() {
+        let (a, b, c) = (chain(200_000, 1), chain(200_000, 1), chain(200_000, 2));
+        assert!(eq(a.deref(), b.deref()));
+        assert!(!eq(a.deref(), c.deref()));
+        assert_eq!(deep_hash_u64(a.deref()), deep_hash_u64(b.deref()));
+        assert_ne!(deep_hash_u64(a.deref()), deep_hash_u64(c.deref()));
+        // Dropping the trees recurses once per level, like any boxed tree.
+        std::thread::Builder::new()
+            .stack_size(1 << 30)
+            .spawn(move || drop((a, b, c)))
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+}
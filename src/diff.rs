@@ -0,0 +1,131 @@
+//! Structured comparison reports for trait objects.
+//!
+//! ```rust ignore
+//! let old: Box<dyn Setting> = Box::new(8080u16);
+//! let new: Box<dyn Setting> = Box::new(8081u16);
+//! match diff(&*old, &*new) {
+//!     Diff::Equal => {}
+//!     Diff::ValueMismatch { ty, left, right } => println!("{ty}: {left} -> {right}"),
+//!     Diff::TypeMismatch { left, right } => println!("{} -> {}", left.ty, right.ty),
+//! }
+//! ```
+
+use alloc::{format, string::String};
+use core::fmt::{self, Debug};
+
+use crate::{ExplainEqObj, Inequality, PartialEqObj};
+
+/// The result of comparing two objects with `diff`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Diff {
+    /// The objects are equal.
+    Equal,
+    /// The objects have different concrete types.
+    TypeMismatch { left: Rendered, right: Rendered },
+    /// The objects have the same concrete type, `ty`, but different values.
+    ValueMismatch {
+        ty: &'static str,
+        left: String,
+        right: String,
+    },
+}
+
+/// The concrete type name and `Debug` output of an object.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Rendered {
+    pub ty: &'static str,
+    pub value: String,
+}
+
+impl Rendered {
+    fn new<X: PartialEqObj + Debug + ?Sized>(object: &X) -> Self {
+        Rendered {
+            ty: object.type_name(),
+            value: format!("{object:?}"),
+        }
+    }
+}
+
+impl Diff {
+    pub fn is_equal(&self) -> bool {
+        matches!(self, Diff::Equal)
+    }
+}
+
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diff::Equal => write!(f, "equal"),
+            Diff::TypeMismatch { left, right } => write!(
+                f,
+                "type changed from {} to {}: {} -> {}",
+                left.ty, right.ty, left.value, right.value
+            ),
+            Diff::ValueMismatch { ty, left, right } => {
+                write!(f, "{ty} changed: {left} -> {right}")
+            }
+        }
+    }
+}
+
+/// Compares two objects with `PartialEqObj`, and renders them with `Debug` if
+/// they are not equal.
+///
+/// Objects of different concrete types are reported as a `TypeMismatch`.
+pub fn diff<X: PartialEqObj + Debug + ?Sized>(left: &X, right: &X) -> Diff {
+    match left.eq_object_explain(right.as_partial_eq_object()) {
+        Ok(()) => Diff::Equal,
+        Err(Inequality::DifferentTypes { .. }) => Diff::TypeMismatch {
+            left: Rendered::new(left),
+            right: Rendered::new(right),
+        },
+        Err(Inequality::DifferentValues) => Diff::ValueMismatch {
+            ty: left.type_name(),
+            left: format!("{left:?}"),
+            right: format!("{right:?}"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::fmt::Debug;
+
+    use super::*;
+
+    trait Setting: PartialEqObj + Debug {}
+    impl<T: PartialEq + Debug + 'static> Setting for T {}
+
+    #[test]
+    fn reports() {
+        let port: &dyn Setting = &8080u16;
+        assert_eq!(diff(port, &8080u16), Diff::Equal);
+        assert_eq!(
+            diff(port, &8081u16),
+            Diff::ValueMismatch {
+                ty: "u16",
+                left: "8080".into(),
+                right: "8081".into(),
+            }
+        );
+        let name: &dyn Setting = &"8080";
+        let report = diff(port, name);
+        assert_eq!(
+            report,
+            Diff::TypeMismatch {
+                left: Rendered {
+                    ty: "u16",
+                    value: "8080".into()
+                },
+                right: Rendered {
+                    ty: "&str",
+                    value: "\"8080\"".into()
+                },
+            }
+        );
+        assert_eq!(
+            report.to_string(),
+            "type changed from u16 to &str: 8080 -> \"8080\""
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use alloc::{format, string::String};
+use core::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
+
+macro_rules! rendered_obj {
+    ($(
+        $(#[$attr:meta])*
+        $Name:ident($Fmt:ident, $format:literal);
+    )*) => {$(
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $Name<T>(pub T);
+
+        impl<T: fmt::$Fmt> $Name<T> {
+            pub fn new(inner: T) -> Self {
+                Self(inner)
+            }
+
+            /// The rendering that this value is compared and hashed by.
+            pub fn rendered(&self) -> String {
+                format!($format, self.0)
+            }
+        }
+
+        impl<T> $Name<T> {
+            pub fn into_inner(self) -> T {
+                self.0
+            }
+        }
+
+        impl<T> Deref for $Name<T> {
+            type Target = T;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl<T> DerefMut for $Name<T> {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.0
+            }
+        }
+
+        impl<T: fmt::$Fmt> PartialEq for $Name<T> {
+            fn eq(&self, other: &Self) -> bool {
+                self.rendered() == other.rendered()
+            }
+        }
+
+        impl<T: fmt::$Fmt> Eq for $Name<T> {}
+
+        impl<T: fmt::$Fmt> Hash for $Name<T> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.rendered().hash(state)
+            }
+        }
+    )*};
+}
+
+rendered_obj! {
+    /// Wrapper that compares and hashes a value by its `Display` rendering,
+    /// for prototypes and test fixtures whose types do not implement `Eq` or
+    /// `Hash` yet. It implements `PartialEq`, `Eq` and `Hash`, and so the
+    /// object-safe traits, so it can be used as any key object.
+    ///
+    /// ```rust ignore
+    /// let key: Box<dyn MyKey> = Box::new(DisplayEqObj(Url::parse(text)?));
+    /// ```
+    ///
+    /// This is a best-effort comparison: values that render the same are
+    /// equal, even if their types differ when `T` is itself a trait object.
+    /// Every comparison allocates both renderings.
+    DisplayEqObj(Display, "{}");
+
+    /// Wrapper that compares and hashes a value by its `Debug` rendering,
+    /// like `DisplayEqObj`.
+    DebugEqObj(Debug, "{:?}");
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::*;
+
+    struct Point(f32, f32);
+
+    impl core::fmt::Display for Point {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            write!(f, "({}, {})", self.0, self.1)
+        }
+    }
+
+    #[test]
+    fn compares_renderings() {
+        assert!(DisplayEqObj(Point(1.0, 2.0)) == DisplayEqObj(Point(1.0, 2.0)));
+        assert!(DisplayEqObj(Point(1.0, 2.0)) != DisplayEqObj(Point(2.0, 1.0)));
+        let set: HashSet<Box<dyn KeyObj>> = [
+            Box::new(DisplayEqObj(Point(1.0, 2.0))) as Box<dyn KeyObj>,
+            Box::new(DisplayEqObj(Point(1.0, 2.0))),
+            Box::new(DebugEqObj(Some(1.5))),
+        ]
+        .into();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&(Box::new(DebugEqObj(Some(1.5))) as Box<dyn KeyObj>)));
+        assert_eq!(DebugEqObj("a").rendered(), r#""a""#);
+    }
+}
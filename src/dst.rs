@@ -0,0 +1,140 @@
+//! Support for comparing erased objects against dynamically sized types like
+//! `str`, `[u8]` and `Path`.
+//!
+//! Trait objects can only hold sized values, so string-like objects are
+//! erased as `String`, `Box<str>`, `Arc<str>`, and so on, which are all
+//! different concrete types. All of these hash exactly like the `str` they
+//! point to, so this module lets them be compared with `str`, and with each
+//! other by `obj_eq` once registered with
+//! [`register_borrowed_eq`](crate::registry::register_borrowed_eq), without
+//! caring which owned or shared form was used.
+
+use core::any::Any;
+use std::{
+    borrow::Cow,
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::{registry::register_eq, EqObj, PartialEqObj};
+
+/// An unsized type that values behind a trait object may borrow as.
+pub trait BorrowedForm: 'static {
+    /// Returns the borrowed form of the value, if it is one of the sized
+    /// types that borrow as `Self`: the owned type, `Box<Self>`,
+    /// `&'static Self`, `Rc<Self>`, `Arc<Self>`, or `Cow<'static, Self>`.
+    fn downcast_borrowed(any: &dyn Any) -> Option<&Self>;
+
+    /// Registers comparators that make all the sized types that borrow as
+    /// `Self` compare equal to each other when their borrowed forms are equal.
+    fn register_eq()
+    where
+        Self: PartialEq;
+}
+
+macro_rules! impl_borrowed_form {
+    ($($Unsized:ty: $Owned:ty),*) => {$(
+        impl BorrowedForm for $Unsized {
+            fn downcast_borrowed(any: &dyn Any) -> Option<&Self> {
+                impl_borrowed_form!(@downcast any, $Owned, Box<$Unsized>, &'static $Unsized, Rc<$Unsized>, Arc<$Unsized>, Cow<'static, $Unsized>)
+            }
+
+            fn register_eq() {
+                impl_borrowed_form!(@register $Unsized; $Owned, Box<$Unsized>, &'static $Unsized, Rc<$Unsized>, Arc<$Unsized>, Cow<'static, $Unsized>);
+            }
+        }
+    )*};
+    (@downcast $any:ident, $($Sized:ty),*) => {
+        None$(.or_else(|| $any.downcast_ref::<$Sized>().map(|x| &**x)))*
+    };
+    (@register $Unsized:ty; $First:ty $(, $Rest:ty)*) => {
+        $(register_eq::<$First, $Rest>(|a, b| <$Unsized as PartialEq>::eq(a.as_ref(), b.as_ref()));)*
+        impl_borrowed_form!(@register $Unsized; $($Rest),*);
+    };
+    (@register $Unsized:ty;) => {};
+}
+
+impl_borrowed_form!(str: String, [u8]: Vec<u8>, Path: PathBuf, OsStr: OsString);
+
+/// Returns the borrowed form of an erased value, if it has one of the sized
+/// types that borrow as `U`. See [`BorrowedForm::downcast_borrowed`].
+pub fn downcast_borrowed<U: BorrowedForm + ?Sized>(obj: &dyn Any) -> Option<&U> {
+    U::downcast_borrowed(obj)
+}
+
+/// Compares an erased value with a borrowed unsized value, such as a
+/// `Box<dyn PartialEqObj>` holding a `String` with a `&str`.
+pub fn eq_borrowed<U>(obj: &dyn PartialEqObj, borrowed: &U) -> bool
+where
+    U: BorrowedForm + PartialEq + ?Sized,
+{
+    downcast_borrowed::<U>(obj.as_any()).is_some_and(|x| x == borrowed)
+}
+
+macro_rules! impl_partial_eq_borrowed {
+    ($($Dyn:ty: $($Unsized:ty),*;)*) => {$($(
+        impl PartialEq<$Unsized> for $Dyn {
+            fn eq(&self, other: &$Unsized) -> bool {
+                eq_borrowed(self.as_partial_eq_object(), other)
+            }
+        }
+
+        impl PartialEq<$Dyn> for $Unsized {
+            fn eq(&self, other: &$Dyn) -> bool {
+                eq_borrowed(other.as_partial_eq_object(), self)
+            }
+        }
+    )*)*};
+}
+
+impl_partial_eq_borrowed! {
+    dyn PartialEqObj: str, [u8], Path, OsStr;
+    dyn EqObj: str, [u8], Path, OsStr;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{hasher::hash_one, obj_eq, registry, HashObj};
+
+    #[test]
+    fn eq_with_borrowed_form() {
+        let objects: Vec<Box<dyn PartialEqObj>> = vec![
+            Box::new("abc".to_string()),
+            Box::new(Box::<str>::from("abc")),
+            Box::new("abc"),
+            Box::new(Rc::<str>::from("abc")),
+            Box::new(Arc::<str>::from("abc")),
+            Box::new(Cow::<str>::Borrowed("abc")),
+        ];
+        for obj in &objects {
+            assert!(**obj == *"abc");
+            assert!(*"abc" == **obj);
+            assert!(**obj != *"abd");
+            assert!(**obj != *b"abc".as_slice());
+        }
+        let bytes: Box<dyn EqObj> = Box::new(vec![1u8, 2]);
+        assert!(*bytes == [1u8, 2][..]);
+        let path: Box<dyn EqObj> = Box::new(PathBuf::from("/tmp"));
+        assert!(*path == *Path::new("/tmp"));
+    }
+
+    #[test]
+    fn registered_borrowed_eq_is_consistent_with_hash() {
+        #[derive(PartialEq)]
+        struct NotAString;
+        registry::register_borrowed_eq::<Path>();
+        let a: Box<dyn PartialEqObj> = Box::new(PathBuf::from("/a"));
+        let b: Box<dyn PartialEqObj> = Box::new(Arc::<Path>::from(Path::new("/a")));
+        assert!(obj_eq(&*a, &*b));
+        assert!(*a != *b);
+        assert!(!obj_eq(&*a, &NotAString));
+
+        let state = std::collections::hash_map::RandomState::new();
+        let a: &dyn HashObj = &PathBuf::from("/a");
+        let b: &dyn HashObj = &Arc::<Path>::from(Path::new("/a"));
+        assert_eq!(hash_one(&state, a), hash_one(&state, b));
+    }
+}
@@ -0,0 +1,235 @@
+use alloc::{boxed::Box, sync::Arc};
+use core::{
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use crate::{EqObj, HashObj};
+
+/// A value that can be used as a key in a `HashMap` or `HashSet` from any
+/// thread. This is automatically implemented for every type that implements
+/// `Hash`, `Eq`, `Send` and `Sync`.
+pub trait KeyObj: HashObj + EqObj + Send + Sync {
+    fn as_key_object(&self) -> &dyn KeyObj;
+}
+
+impl<T: Hash + Eq + Send + Sync + 'static> KeyObj for T {
+    fn as_key_object(&self) -> &dyn KeyObj {
+        self
+    }
+}
+
+crate::impl_partial_eq!(dyn KeyObj);
+crate::impl_eq!(dyn KeyObj);
+crate::impl_hash!(dyn KeyObj);
+
+/// Implements accessors for the concrete type of the value of a key type that
+/// derefs to `dyn KeyObj`, and a `Debug` impl that shows the type, since the
+/// value may not implement `Debug`.
+macro_rules! impl_key_accessors {
+    ($Key:ident $(<const $N:ident: usize>)?) => {
+        impl$(<const $N: usize>)? $Key$(<$N>)? {
+            /// Returns the value if it has type `T`.
+            pub fn downcast_ref<T: ::core::any::Any>(&self) -> Option<&T> {
+                crate::AsAny::as_any(&**self).downcast_ref()
+            }
+
+            /// Returns true if the value has type `T`.
+            pub fn is<T: ::core::any::Any>(&self) -> bool {
+                crate::AsAny::as_any(&**self).is::<T>()
+            }
+
+            /// Name of the concrete type of the value.
+            pub fn type_name(&self) -> &'static str {
+                crate::AsAny::type_name(&**self)
+            }
+        }
+
+        impl$(<const $N: usize>)? ::core::fmt::Debug for $Key$(<$N>)? {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                f.debug_tuple(stringify!($Key)).field(&self.type_name()).finish()
+            }
+        }
+    };
+}
+pub(crate) use impl_key_accessors;
+
+/// Ready-to-use key type that can hold a value of any type implementing
+/// `Hash`, `Eq`, `Send` and `Sync`.
+///
+/// Keys are equal if they hold values of the same concrete type that are
+/// equal, and hash exactly like the value they hold.
+///
+/// ```rust ignore
+/// let mut map = HashMap::new();
+/// map.insert(DynKey::new(1u32), "int");
+/// map.insert(DynKey::new("one"), "str");
+/// assert_eq!(map[&DynKey::new("one")], "str");
+/// ```
+pub struct DynKey(Box<dyn KeyObj>);
+
+impl DynKey {
+    pub fn new<T: Hash + Eq + Send + Sync + 'static>(value: T) -> Self {
+        DynKey(Box::new(value))
+    }
+
+    pub fn into_inner(self) -> Box<dyn KeyObj> {
+        self.0
+    }
+}
+
+impl From<Box<dyn KeyObj>> for DynKey {
+    fn from(value: Box<dyn KeyObj>) -> Self {
+        DynKey(value)
+    }
+}
+
+impl Deref for DynKey {
+    type Target = dyn KeyObj;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl PartialEq for DynKey {
+    fn eq(&self, other: &Self) -> bool {
+        crate::eq_or_same(&*self.0, &*other.0)
+    }
+}
+
+impl Eq for DynKey {}
+
+impl Hash for DynKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl_key_accessors!(DynKey);
+
+/// Like `DynKey`, but holds the value in an `Arc`, so the key is cheap to
+/// clone and can be shared by several collections and threads at once, such
+/// as the component identities of an ECS.
+///
+/// ```rust ignore
+/// let position = SharedKey::new(ComponentId("position"));
+/// let index = HashMap::from([(position.clone(), 0)]);
+/// std::thread::spawn(move || index[&position]);
+/// ```
+#[derive(Clone)]
+pub struct SharedKey(Arc<dyn KeyObj>);
+
+impl SharedKey {
+    pub fn new<T: Hash + Eq + Send + Sync + 'static>(value: T) -> Self {
+        SharedKey(Arc::new(value))
+    }
+
+    /// Returns true if both keys are clones of the same key.
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub fn into_inner(self) -> Arc<dyn KeyObj> {
+        self.0
+    }
+}
+
+impl From<Arc<dyn KeyObj>> for SharedKey {
+    fn from(value: Arc<dyn KeyObj>) -> Self {
+        SharedKey(value)
+    }
+}
+
+impl From<DynKey> for SharedKey {
+    fn from(key: DynKey) -> Self {
+        SharedKey(key.0.into())
+    }
+}
+
+impl Deref for SharedKey {
+    type Target = dyn KeyObj;
+
+    fn deref(&self) -> &Self::Target {
+        &*self.0
+    }
+}
+
+impl PartialEq for SharedKey {
+    fn eq(&self, other: &Self) -> bool {
+        crate::eq_or_same(&*self.0, &*other.0)
+    }
+}
+
+impl Eq for SharedKey {}
+
+impl Hash for SharedKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl_key_accessors!(SharedKey);
+
+#[cfg(test)]
+mod test {
+    use std::collections::{hash_map::DefaultHasher, HashMap};
+
+    use super::*;
+
+    fn hash(value: &impl Hash) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn map_keys() {
+        let mut map = HashMap::new();
+        map.insert(DynKey::new(1u32), "int");
+        map.insert(DynKey::new("one"), "str");
+        map.insert(DynKey::new(1u32), "u32");
+        assert_eq!(map.len(), 2);
+        assert_eq!(map[&DynKey::new("one")], "str");
+        assert_eq!(map[&DynKey::new(1u32)], "u32");
+        assert!(!map.contains_key(&DynKey::new(1u64)));
+        assert_eq!(hash(&DynKey::new(5u8)), hash(&5u8));
+    }
+
+    #[test]
+    fn same_key_skips_comparison() {
+        #[allow(clippy::derived_hash_with_manual_eq)]
+        #[derive(Hash, Eq)]
+        struct NeverCompared;
+        impl PartialEq for NeverCompared {
+            fn eq(&self, _: &Self) -> bool {
+                panic!("should not be compared")
+            }
+        }
+        let key = DynKey::new(NeverCompared);
+        assert!(key == key);
+    }
+
+    #[test]
+    fn inspect() {
+        let key = DynKey::new(String::from("a"));
+        assert!(key.is::<String>());
+        assert_eq!(key.downcast_ref::<String>().unwrap(), "a");
+        assert_eq!(format!("{key:?}"), "DynKey(\"alloc::string::String\")");
+        std::thread::spawn(move || drop(key)).join().unwrap();
+    }
+
+    #[test]
+    fn shared_keys() {
+        let key = SharedKey::new("position");
+        let map = HashMap::from([(key.clone(), 0)]);
+        let clone = key.clone();
+        assert!(clone.ptr_eq(&key));
+        assert!(!SharedKey::new("position").ptr_eq(&key));
+        let found = std::thread::spawn(move || map[&clone]).join().unwrap();
+        assert_eq!(found, 0);
+        assert_eq!(key, SharedKey::from(DynKey::new("position")));
+        assert_eq!(hash(&key), hash(&DynKey::new("position")));
+        assert!(key.is::<&str>());
+    }
+}
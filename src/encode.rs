@@ -0,0 +1,280 @@
+//! Canonical binary encoding of objects, for content addressing: two values
+//! have the same address if and only if they have the same encoding, on any
+//! platform and with any version of this crate with the same
+//! [`ENCODING_VERSION`].
+//!
+//! Unlike `Hash`, whose output is up to each implementation and may change
+//! between Rust versions, the encoding of the implemented types is fixed:
+//! - integers are little-endian, with `usize` and `isize` widened to 64 bits.
+//! - `bool` is one byte, 0 or 1, and `char` is encoded as a `u32`.
+//! - floats are encoded by their bits, except that every NaN is encoded as
+//!   the canonical quiet NaN.
+//! - strings, slices and vectors are their length as a `u64`, followed by
+//!   their elements, while arrays and tuples are just their elements.
+//! - `Option` is a 0 byte for `None`, and a 1 byte followed by the value for
+//!   `Some`.
+//!
+//! The encoding does not include the type of the value, so values of
+//! different types may have the same encoding. Store objects of one type per
+//! address space, or encode a type fingerprint from the registry first.
+//!
+//! ```rust ignore
+//! let address = content_address(&*document as &dyn EncodeObj)?;
+//! store.put(address, document);
+//! ```
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use std::io::{self, Write};
+
+/// Version of the encoding of the types implemented by this crate, which will
+/// be incremented if it ever changes.
+pub const ENCODING_VERSION: u32 = 1;
+
+/// Object-safe canonical encoding. See the [module documentation](self) for
+/// the encoding of the implemented types. Implement it for a struct with
+/// `impl_encode!`, which encodes its fields in order, or by hand for other
+/// types, such as an enum, which typically encodes its variant as a `u32`
+/// before its fields.
+pub trait EncodeObj {
+    /// Writes the canonical encoding of the value to `out`.
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Returns the canonical encoding of the object.
+pub fn encode_to_vec(obj: &dyn EncodeObj) -> Vec<u8> {
+    let mut out = Vec::new();
+    obj.encode_canonical(&mut out)
+        .expect("writing to a Vec cannot fail");
+    out
+}
+
+/// Hashes the canonical encoding of the object with
+/// `stable::StableHasher128`, without buffering it, for use as its content
+/// address.
+#[cfg(feature = "stable-hash")]
+pub fn content_address(obj: &dyn EncodeObj) -> io::Result<u128> {
+    use core::hash::Hasher;
+
+    use crate::{hasher::Hasher128, stable::StableHasher128};
+
+    struct HashWriter(StableHasher128);
+
+    impl Write for HashWriter {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.write(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut out = HashWriter(StableHasher128::new());
+    obj.encode_canonical(&mut out)?;
+    Ok(out.0.finish128())
+}
+
+/// Implements `EncodeObj` for a struct by encoding the listed fields in
+/// order, which should usually be all of them. Tuple structs list the field
+/// indices.
+///
+/// ```rust ignore
+/// impl_encode!(Document { title, body, revision });
+/// impl_encode!(Meters(0));
+/// ```
+///
+/// Generic structs are not supported, so implement the trait by hand for
+/// them.
+#[macro_export]
+macro_rules! impl_encode {
+    ($($Type:ident $({ $($field:tt),* $(,)? })? $(( $($index:tt),* $(,)? ))?);+ $(;)?) => {$(
+        impl $crate::EncodeObj for $Type {
+            fn encode_canonical(
+                &self,
+                out: &mut dyn ::std::io::Write,
+            ) -> ::std::io::Result<()> {
+                $($($crate::EncodeObj::encode_canonical(&self.$field, out)?;)*)?
+                $($($crate::EncodeObj::encode_canonical(&self.$index, out)?;)*)?
+                let _ = out;
+                Ok(())
+            }
+        }
+    )+};
+}
+
+macro_rules! encode_le {
+    ($($Type:ty),+) => {$(
+        impl EncodeObj for $Type {
+            fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+                out.write_all(&self.to_le_bytes())
+            }
+        }
+    )+};
+}
+
+encode_le!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl EncodeObj for usize {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        (*self as u64).encode_canonical(out)
+    }
+}
+
+impl EncodeObj for isize {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        (*self as i64).encode_canonical(out)
+    }
+}
+
+impl EncodeObj for bool {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        (*self as u8).encode_canonical(out)
+    }
+}
+
+impl EncodeObj for char {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        (*self as u32).encode_canonical(out)
+    }
+}
+
+impl EncodeObj for f32 {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        let value = if self.is_nan() { f32::NAN } else { *self };
+        value.to_bits().encode_canonical(out)
+    }
+}
+
+impl EncodeObj for f64 {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        let value = if self.is_nan() { f64::NAN } else { *self };
+        value.to_bits().encode_canonical(out)
+    }
+}
+
+impl EncodeObj for str {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.as_bytes().encode_canonical(out)
+    }
+}
+
+impl EncodeObj for String {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.as_str().encode_canonical(out)
+    }
+}
+
+impl<T: EncodeObj> EncodeObj for [T] {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.len().encode_canonical(out)?;
+        self.iter().try_for_each(|item| item.encode_canonical(out))
+    }
+}
+
+impl<T: EncodeObj> EncodeObj for Vec<T> {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.as_slice().encode_canonical(out)
+    }
+}
+
+impl<T: EncodeObj, const N: usize> EncodeObj for [T; N] {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.iter().try_for_each(|item| item.encode_canonical(out))
+    }
+}
+
+impl<T: EncodeObj> EncodeObj for Option<T> {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        match self {
+            None => false.encode_canonical(out),
+            Some(value) => {
+                true.encode_canonical(out)?;
+                value.encode_canonical(out)
+            }
+        }
+    }
+}
+
+impl<T: EncodeObj + ?Sized> EncodeObj for &T {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        (**self).encode_canonical(out)
+    }
+}
+
+impl<T: EncodeObj + ?Sized> EncodeObj for Box<T> {
+    fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+        (**self).encode_canonical(out)
+    }
+}
+
+macro_rules! encode_tuple {
+    ($(($($T:ident $i:tt),*))+) => {$(
+        impl<$($T: EncodeObj),*> EncodeObj for ($($T,)*) {
+            fn encode_canonical(&self, out: &mut dyn Write) -> io::Result<()> {
+                $(self.$i.encode_canonical(out)?;)*
+                let _ = out;
+                Ok(())
+            }
+        }
+    )+};
+}
+
+encode_tuple! {
+    ()
+    (A 0)
+    (A 0, B 1)
+    (A 0, B 1, C 2)
+    (A 0, B 1, C 2, D 3)
+    (A 0, B 1, C 2, D 3, E 4)
+    (A 0, B 1, C 2, D 3, E 4, F 5)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Document {
+        title: String,
+        revision: u32,
+    }
+
+    struct Meters(f64);
+
+    impl_encode!(Document { title, revision }; Meters(0));
+
+    #[test]
+    fn canonical_bytes() {
+        assert_eq!(encode_to_vec(&1u16), [1, 0]);
+        assert_eq!(encode_to_vec(&1usize), encode_to_vec(&1u64));
+        assert_eq!(encode_to_vec(&"ab"), [2, 0, 0, 0, 0, 0, 0, 0, b'a', b'b']);
+        assert_eq!(encode_to_vec(&[1u8, 2]), [1, 2]);
+        assert_eq!(encode_to_vec(&(Some(true), None::<u8>, ())), [1, 1, 0]);
+        assert_eq!(
+            encode_to_vec(&f64::NAN),
+            encode_to_vec(&-f64::from_bits(f64::NAN.to_bits() | 1))
+        );
+
+        let document = Document {
+            title: "a".into(),
+            revision: 2,
+        };
+        let objs: [&dyn EncodeObj; 2] = [&document, &Meters(1.0)];
+        assert_eq!(
+            encode_to_vec(objs[0]),
+            [1, 0, 0, 0, 0, 0, 0, 0, b'a', 2, 0, 0, 0]
+        );
+        assert_eq!(encode_to_vec(objs[1]), 1.0f64.to_bits().to_le_bytes());
+    }
+
+    #[test]
+    #[cfg(feature = "stable-hash")]
+    fn content_addresses() {
+        let address = content_address(&("doc", 1u8)).unwrap();
+        assert_eq!(
+            address,
+            content_address(&(String::from("doc"), 1u8)).unwrap()
+        );
+        assert_ne!(address, content_address(&("doc", 2u8)).unwrap());
+    }
+}
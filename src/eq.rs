@@ -0,0 +1,294 @@
+//! `PartialEqObj` and `EqObj`, the object-safe versions of `PartialEq` and
+//! `Eq`, and the macros implementing the std traits with them.
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{any::Any, ops::Deref};
+
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use crate::{
+    impl_with_auto_traits, report_mismatch, upcast_any, AsAny, Obj, OrdObj, PartialOrdObj,
+};
+
+/// Object-safe version of Eq
+pub trait EqObj: PartialEqObj {
+    fn as_eq_object(&self) -> &dyn EqObj;
+    #[cfg(feature = "alloc")]
+    fn to_eq_object(self) -> Box<dyn EqObj>;
+}
+
+impl<T> EqObj for T
+where
+    T: Eq + PartialEqObj,
+{
+    fn as_eq_object(&self) -> &dyn EqObj {
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    fn to_eq_object(self) -> Box<dyn EqObj> {
+        Box::new(self)
+    }
+}
+
+// The impls for `Obj` are written out rather than generated, since the macros
+// require the whole type to be `'static`, while only the target needs to be.
+// This lets `Obj` wrap borrowed views such as `Ref`, `RefMut`, lock guards and
+// `Cow`, which are then compared through the object they point to.
+impl<T: Deref<Target = X>, X: EqObj + ?Sized> Eq for Obj<T> {}
+impl_with_auto_traits!(crate::impl_eq: dyn EqObj, dyn OrdObj);
+
+#[macro_export]
+macro_rules! impl_eq {
+    (@impl [$($G:tt)*] [$Type:ty] [$($W:tt)*]) => {
+        impl<$($G)*> ::core::cmp::Eq for $Type where $($W)* {}
+    };
+    (impl $($input:tt)*) => {
+        $crate::__impl_header! { $crate::impl_eq; impl $($input)* }
+    };
+    ($($input:tt)*) => {
+        $crate::__legacy_header! { $crate::impl_eq; _; $($input)* }
+    };
+}
+
+/// Object-safe version of PartialEq
+pub trait PartialEqObj: AsAny {
+    fn eq_object(&self, other: &dyn PartialEqObj) -> bool;
+    fn ne_object(&self, other: &dyn PartialEqObj) -> bool {
+        !self.eq_object(other)
+    }
+    fn as_partial_eq_object(&self) -> &dyn PartialEqObj;
+    /// Boxes the value. A value that is already boxed is boxed again, so
+    /// convert a `Box<T>` with `Box::<dyn PartialEqObj>::from` instead, which
+    /// reuses its allocation. The same conversion exists for the other traits.
+    #[cfg(feature = "alloc")]
+    fn to_partial_eq_object(self) -> Box<dyn PartialEqObj>;
+}
+
+impl<T> PartialEqObj for T
+where
+    T: PartialEq + AsAny,
+{
+    fn eq_object(&self, other: &dyn PartialEqObj) -> bool {
+        match upcast_any(other).downcast_ref::<Self>() {
+            Some(other) => self == other,
+            None => cross_eq(self, other),
+        }
+    }
+
+    fn ne_object(&self, other: &dyn PartialEqObj) -> bool {
+        match upcast_any(other).downcast_ref::<Self>() {
+            Some(other) => self != other,
+            None => !cross_eq(self, other),
+        }
+    }
+
+    fn as_partial_eq_object(&self) -> &dyn PartialEqObj {
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    fn to_partial_eq_object(self) -> Box<dyn PartialEqObj> {
+        Box::new(self)
+    }
+}
+
+/// Compares objects of different concrete types, which are never equal, and
+/// reports the mismatch. The registry is not consulted, since `==` must agree
+/// with the order and the hash of the objects: `obj_eq` and `dyn_eq` compare
+/// objects with the registered comparators instead.
+///
+/// This and the other `cross_*` functions are kept out of line, so the
+/// blanket impls only inline the comparison of values of the same type.
+#[cold]
+#[inline(never)]
+#[cfg_attr(not(feature = "mismatch-hook"), allow(unused_variables))]
+fn cross_eq(left: &dyn PartialEqObj, right: &dyn PartialEqObj) -> bool {
+    #[cfg(feature = "metrics")]
+    metrics::count(&metrics::EQ_MISMATCHES);
+    report_mismatch!(Eq, left, right);
+    false
+}
+
+impl<T: Deref<Target = X>, X: PartialEqObj + ?Sized> PartialEq for Obj<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.deref().eq_object(other.deref().as_partial_eq_object())
+    }
+
+    #[allow(clippy::partialeq_ne_impl)]
+    fn ne(&self, other: &Self) -> bool {
+        self.deref().ne_object(other.deref().as_partial_eq_object())
+    }
+}
+crate::__impl_partial_eq_boxed! {
+    Obj<T> where <T: Deref<Target=X>, X: PartialEqObj + ?Sized>,
+}
+impl_with_auto_traits!(crate::impl_partial_eq: dyn PartialEqObj, dyn PartialOrdObj);
+
+// `Eq` guarantees that every value is equal to itself, so the trait objects
+// that require it can skip the comparison when both sides are the same object.
+// This is not done for `PartialEqObj`, since values like `f64::NAN` are not.
+macro_rules! impl_reflexive_partial_eq {
+    ($($Type:ty),*$(,)?) => {$(
+        impl PartialEq for $Type {
+            fn eq(&self, other: &Self) -> bool {
+                is_same_object(self, other) || self.eq_object(other.as_partial_eq_object())
+            }
+
+            #[allow(clippy::partialeq_ne_impl)]
+            fn ne(&self, other: &Self) -> bool {
+                !is_same_object(self, other) && self.ne_object(other.as_partial_eq_object())
+            }
+        }
+    )*};
+}
+
+impl_with_auto_traits!(impl_reflexive_partial_eq: dyn EqObj, dyn OrdObj);
+
+/// Whether both references point to the same value of the same concrete type.
+fn is_same_object(a: &dyn Any, b: &dyn Any) -> bool {
+    core::ptr::addr_eq(a, b) && a.type_id() == b.type_id()
+}
+
+/// Compares objects whose type implements `Eq`, skipping `eq_object` when
+/// both references are identical, including their vtables if they are trait
+/// objects.
+#[cfg(feature = "alloc")]
+pub(crate) fn eq_or_same<X: EqObj + ?Sized>(a: &X, b: &X) -> bool {
+    core::ptr::eq(a, b) || a.eq_object(b.as_partial_eq_object())
+}
+
+#[macro_export]
+macro_rules! impl_partial_eq {
+    ($($input:tt)*) => {
+        $crate::__impl_partial_eq! { $($input)* }
+        $crate::__impl_partial_eq_boxed! { $($input)* }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_partial_eq {
+    (@impl [$($G:tt)*] [$Type:ty] [$($W:tt)*]) => {
+        impl<$($G)*> ::core::cmp::PartialEq for $Type where $($W)* {
+            fn eq(&self, other: &Self) -> bool {
+                use ::core::ops::Deref as _;
+                self.deref().eq_object(other.deref().as_partial_eq_object())
+            }
+
+            #[allow(clippy::partialeq_ne_impl)]
+            fn ne(&self, other: &Self) -> bool {
+                use ::core::ops::Deref as _;
+                self.deref().ne_object(other.deref().as_partial_eq_object())
+            }
+        }
+    };
+    (impl $($input:tt)*) => {
+        $crate::__impl_header! { $crate::__impl_partial_eq; impl $($input)* }
+    };
+    ($($input:tt)*) => {
+        $crate::__legacy_header! { $crate::__impl_partial_eq; 'static; $($input)* }
+    };
+}
+
+/// The impls of `impl_partial_eq!` that compare boxed objects with references,
+/// which are only generated if the `alloc` feature is enabled.
+#[doc(hidden)]
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! __impl_partial_eq_boxed {
+    (@impl [$($G:tt)*] [$Type:ty] [$($W:tt)*]) => {
+        impl<$($G)*> ::core::cmp::PartialEq<&$Type> for $crate::__private::Box<$Type>
+        where
+            $($W)*
+        {
+            fn eq(&self, other: &&$Type) -> bool {
+                <$Type as ::core::cmp::PartialEq>::eq(&**self, *other)
+            }
+
+            #[allow(clippy::partialeq_ne_impl)]
+            fn ne(&self, other: &&$Type) -> bool {
+                <$Type as ::core::cmp::PartialEq>::ne(&**self, *other)
+            }
+        }
+
+        impl<$($G)*> ::core::cmp::PartialEq<$crate::__private::Box<$Type>> for &$Type
+        where
+            $($W)*
+        {
+            fn eq(&self, other: &$crate::__private::Box<$Type>) -> bool {
+                <$Type as ::core::cmp::PartialEq>::eq(*self, &**other)
+            }
+
+            #[allow(clippy::partialeq_ne_impl)]
+            fn ne(&self, other: &$crate::__private::Box<$Type>) -> bool {
+                <$Type as ::core::cmp::PartialEq>::ne(*self, &**other)
+            }
+        }
+    };
+    (impl $($input:tt)*) => {
+        $crate::__impl_header! { $crate::__impl_partial_eq_boxed; impl $($input)* }
+    };
+    ($($input:tt)*) => {
+        $crate::__legacy_header! { $crate::__impl_partial_eq_boxed; 'static; $($input)* }
+    };
+}
+
+#[doc(hidden)]
+#[cfg(not(feature = "alloc"))]
+#[macro_export]
+macro_rules! __impl_partial_eq_boxed {
+    ($($input:tt)*) => {};
+}
+
+/// Implements `PartialEq` in both directions between a trait object and
+/// concrete types, so a concrete value can be compared directly against an
+/// erased one.
+///
+/// ```rust ignore
+/// impl_partial_eq_concrete! {
+///     dyn MyTrait => i32, String, MyStruct;
+///     dyn AnotherTrait => MyStruct;
+/// }
+///
+/// assert!(*boxed_dyn == 5);
+/// assert!(5 == *boxed_dyn);
+/// ```
+///
+/// The right side may also be another trait object, whose trait extends
+/// `PartialEqObj`, to compare objects erased behind different traits. They
+/// are equal if they hold equal values of the same type. List each pair once,
+/// since both directions are implemented.
+///
+/// ```rust ignore
+/// impl_partial_eq_concrete!(dyn MyTrait => dyn AnotherTrait);
+///
+/// assert!(&*boxed_dyn as &dyn MyTrait == &*other_boxed as &dyn AnotherTrait);
+/// ```
+#[macro_export]
+macro_rules! impl_partial_eq_concrete {
+    ($($Type:ty => $($Concrete:ty),+);*$(;)?) => {$($(
+        impl PartialEq<$Concrete> for $Type {
+            fn eq(&self, other: &$Concrete) -> bool {
+                self.eq_object(other.as_partial_eq_object())
+            }
+
+            #[allow(clippy::partialeq_ne_impl)]
+            fn ne(&self, other: &$Concrete) -> bool {
+                self.ne_object(other.as_partial_eq_object())
+            }
+        }
+
+        impl PartialEq<$Type> for $Concrete {
+            fn eq(&self, other: &$Type) -> bool {
+                self.eq_object(other.as_partial_eq_object())
+            }
+
+            #[allow(clippy::partialeq_ne_impl)]
+            fn ne(&self, other: &$Type) -> bool {
+                self.ne_object(other.as_partial_eq_object())
+            }
+        }
+    )+)*};
+}
@@ -0,0 +1,215 @@
+use core::{any::Any, fmt};
+
+use crate::{AsAny, HashError, TypeMismatch};
+
+/// Errors returned by the fallible APIs of this crate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Error {
+    /// Two objects with different concrete types were compared strictly.
+    TypeMismatch(TypeMismatch),
+    /// An object could not be hashed.
+    Unhashable(HashError),
+    /// No comparator is registered in the [`registry`](crate::registry) for
+    /// this pair of types.
+    Unregistered {
+        left: &'static str,
+        right: &'static str,
+    },
+    /// The [`registry`](crate::registry) has no room left for another
+    /// comparator, which only happens without the `std` feature.
+    RegistryFull,
+    /// The [`registry`](crate::registry) was frozen, so no more comparators
+    /// can be registered.
+    RegistryFrozen,
+    /// A comparator is already registered for this pair of types.
+    AlreadyRegistered {
+        left: &'static str,
+        right: &'static str,
+    },
+    /// The lower bound of a range or interval is greater than its upper
+    /// bound.
+    InvalidRange,
+    /// Two types were registered with the same stable fingerprint.
+    FingerprintCollision {
+        fingerprint: u64,
+        first: &'static str,
+        second: &'static str,
+    },
+    /// An object could not be downcast to the requested concrete type.
+    Downcast {
+        expected: &'static str,
+        found: &'static str,
+    },
+}
+
+/// `Result` with `Error` as the default error type.
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+impl From<TypeMismatch> for Error {
+    fn from(mismatch: TypeMismatch) -> Self {
+        Error::TypeMismatch(mismatch)
+    }
+}
+
+impl From<HashError> for Error {
+    fn from(error: HashError) -> Self {
+        Error::Unhashable(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TypeMismatch(mismatch) => mismatch.fmt(f),
+            Error::Unhashable(error) => error.fmt(f),
+            Error::Unregistered { left, right } => {
+                write!(f, "no comparator is registered for {left} and {right}")
+            }
+            Error::RegistryFull => f.write_str("the registry has no room for another comparator"),
+            Error::RegistryFrozen => f.write_str("the registry is frozen"),
+            Error::AlreadyRegistered { left, right } => {
+                write!(
+                    f,
+                    "a comparator is already registered for {left} and {right}"
+                )
+            }
+            Error::InvalidRange => f.write_str("the lower bound is greater than the upper bound"),
+            Error::FingerprintCollision {
+                fingerprint,
+                first,
+                second,
+            } => write!(
+                f,
+                "{first} and {second} have the same fingerprint {fingerprint:#018x}"
+            ),
+            Error::Downcast { expected, found } => {
+                write!(f, "cannot downcast {found} to {expected}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Error::TypeMismatch(mismatch) => Some(mismatch),
+            Error::Unhashable(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Downcasts an object to its concrete type `T`, such as a `&dyn MyTrait`
+/// where `MyTrait` extends `AsAny`.
+///
+/// Pass the trait object itself, not a pointer to it: the concrete type of a
+/// `Box<dyn MyTrait>` is `Box<dyn MyTrait>`.
+pub fn downcast_ref<T: Any, X: AsAny + ?Sized>(object: &X) -> Result<&T> {
+    object.as_any().downcast_ref().ok_or(Error::Downcast {
+        expected: core::any::type_name::<T>(),
+        found: object.type_name(),
+    })
+}
+
+/// Like [`downcast_ref`], except that when debug assertions are enabled, a
+/// failed downcast panics with both concrete types and the trait object they
+/// were found behind, such as "expected Foo, found Bar behind dyn MyTrait".
+///
+/// Use this where a failed downcast is a bug, such as when an object was
+/// routed to the handler for another type.
+#[track_caller]
+pub fn debug_downcast_ref<T: Any, X: AsAny + ?Sized>(object: &X) -> Result<&T> {
+    let result = downcast_ref(object);
+    if let Err(Error::Downcast { expected, found }) = result {
+        debug_assert!(
+            false,
+            "expected {expected}, found {found} behind {}",
+            core::any::type_name::<X>()
+        );
+    }
+    result
+}
+
+/// Mutable version of [`debug_downcast_ref`].
+#[track_caller]
+pub fn debug_downcast_mut<T: Any, X: AsAny + ?Sized>(object: &mut X) -> Result<&mut T> {
+    let found = (*object).type_name();
+    match object.as_any_mut().downcast_mut() {
+        Some(target) => Ok(target),
+        None => {
+            let expected = core::any::type_name::<T>();
+            debug_assert!(
+                false,
+                "expected {expected}, found {found} behind {}",
+                core::any::type_name::<X>()
+            );
+            Err(Error::Downcast { expected, found })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    fn strictly_equal(a: &dyn PartialEqObj, b: &dyn PartialEqObj) -> Result<bool> {
+        Ok(a.try_eq_object(b)?)
+    }
+
+    #[test]
+    fn errors_compose() {
+        assert_eq!(strictly_equal(&1, &1), Ok(true));
+        let err = strictly_equal(&1, &"1").unwrap_err();
+        assert_eq!(
+            err,
+            Error::TypeMismatch(TypeMismatch {
+                left: "i32",
+                right: "&str"
+            })
+        );
+        assert!(core::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn downcast() {
+        let one: &dyn PartialEqObj = &1u8;
+        assert_eq!(downcast_ref::<u8, _>(one), Ok(&1));
+        let err = downcast_ref::<u16, _>(one).unwrap_err();
+        assert_eq!(
+            err,
+            Error::Downcast {
+                expected: "u16",
+                found: "u8"
+            }
+        );
+        assert_eq!(err.to_string(), "cannot downcast u8 to u16");
+    }
+
+    #[test]
+    fn debug_downcast() {
+        let mut one = Box::new(1u8) as Box<dyn PartialEqObj>;
+        assert_eq!(debug_downcast_ref::<u8, _>(&*one), Ok(&1));
+        *debug_downcast_mut::<u8, _>(&mut *one).unwrap() = 2;
+        assert_eq!(downcast_ref::<u8, _>(&*one), Ok(&2));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            debug_downcast_ref::<u16, _>(&*one).is_err()
+        }));
+        if cfg!(debug_assertions) {
+            let panic = result.unwrap_err();
+            assert_eq!(
+                panic.downcast_ref::<String>().unwrap(),
+                "expected u16, found u8 behind dyn object_safe::eq::PartialEqObj"
+            );
+        } else {
+            assert!(result.unwrap());
+        }
+    }
+
+    #[test]
+    fn unregistered() {
+        struct Unknown;
+        let err = registry::try_cross_eq(&Unknown, &1).unwrap_err();
+        assert!(matches!(err, Error::Unregistered { right: "i32", .. }));
+    }
+}
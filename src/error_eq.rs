@@ -0,0 +1,150 @@
+//! Equality for error trait objects, which rarely implement `PartialEq`.
+//!
+//! Store errors as `Box<dyn ErrorObj>` instead of `Box<dyn Error>`, so that
+//! their concrete type is still known after erasure. `Box<dyn ErrorObj>` can
+//! be upcast to `Box<dyn Error>` whenever needed.
+//!
+//! ```rust ignore
+//! let a: Box<dyn ErrorObj> = Box::new(ParseError::new("line 1"));
+//! let b: Box<dyn ErrorObj> = Box::new(ParseError::new("line 1"));
+//! assert_eq!(ErrorEq(a), ErrorEq(b));
+//! ```
+
+use core::{fmt, ops::Deref};
+use std::error::Error as StdError;
+
+use crate::{registry, AsAny, Error, Result};
+
+/// An error whose concrete type can be inspected. This is automatically
+/// implemented for every `'static` error type.
+pub trait ErrorObj: StdError + AsAny {
+    fn as_error_object(&self) -> &dyn ErrorObj;
+}
+
+impl<E: StdError + 'static> ErrorObj for E {
+    fn as_error_object(&self) -> &dyn ErrorObj {
+        self
+    }
+}
+
+/// Compares two errors. They are equal if they have the same concrete type
+/// and the same `Display` output, or, for types whose `PartialEq` has been
+/// registered with `register_error_eq`, if they are equal according to
+/// `PartialEq`.
+pub fn error_eq(a: &dyn ErrorObj, b: &dyn ErrorObj) -> bool {
+    a.as_any().type_id() == b.as_any().type_id()
+        && registry::cross_eq(a.as_any(), b.as_any())
+            .unwrap_or_else(|| a.to_string() == b.to_string())
+}
+
+/// Compares two errors with their `PartialEq` implementation, returning an
+/// error if their types differ or no `PartialEq` has been registered for
+/// their type with `register_error_eq`.
+pub fn error_eq_strict(a: &dyn ErrorObj, b: &dyn ErrorObj) -> Result<bool> {
+    if a.as_any().type_id() != b.as_any().type_id() {
+        return Err(Error::TypeMismatch(crate::TypeMismatch {
+            left: a.type_name(),
+            right: b.type_name(),
+        }));
+    }
+    registry::try_cross_eq(a, b)
+}
+
+/// Registers the `PartialEq` implementation of `E`, so that it is used instead
+/// of the `Display` output to compare errors of type `E`.
+pub fn register_error_eq<E: ErrorObj + PartialEq>() {
+    registry::register_eq::<E, E>(E::eq);
+}
+
+/// Wrapper that implements `PartialEq` for pointers to errors, such as
+/// `Box<dyn ErrorObj>`, using `error_eq`.
+#[derive(Clone, Copy)]
+pub struct ErrorEq<P>(pub P);
+
+impl<P> ErrorEq<P> {
+    pub fn into_inner(self) -> P {
+        self.0
+    }
+}
+
+impl<P> Deref for ErrorEq<P> {
+    type Target = P;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<P: fmt::Debug> fmt::Debug for ErrorEq<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<P, X> PartialEq for ErrorEq<P>
+where
+    P: Deref<Target = X>,
+    X: ErrorObj + ?Sized,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b): (&X, &X) = (&self.0, &other.0);
+        error_eq(a.as_error_object(), b.as_error_object())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fmt, io};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Parse {
+        line: u32,
+        context: &'static str,
+    }
+
+    impl fmt::Display for Parse {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "parse error on line {}", self.line)
+        }
+    }
+
+    impl StdError for Parse {}
+
+    fn boxed(e: impl ErrorObj) -> ErrorEq<Box<dyn ErrorObj>> {
+        ErrorEq(Box::new(e))
+    }
+
+    #[test]
+    fn display_and_type() {
+        let other = || io::Error::other("parse error on line 1");
+        assert_eq!(boxed(other()), boxed(other()));
+        assert_ne!(boxed(other()), boxed(io::Error::other("oops")));
+        let parse = Parse {
+            line: 1,
+            context: "",
+        };
+        assert!(!error_eq(&parse, &other()));
+        let upcast: Box<dyn StdError> = boxed(other()).into_inner();
+        assert_eq!(upcast.to_string(), "parse error on line 1");
+    }
+
+    #[test]
+    fn registered_partial_eq() {
+        let a = Parse {
+            line: 1,
+            context: "a",
+        };
+        let b = Parse {
+            line: 1,
+            context: "b",
+        };
+        assert!(error_eq(&a, &b));
+        assert!(error_eq_strict(&a, &b).is_err());
+        register_error_eq::<Parse>();
+        assert!(!error_eq(&a, &b));
+        assert_eq!(error_eq_strict(&a, &a), Ok(true));
+        assert_ne!(boxed(a), boxed(b));
+    }
+}
@@ -0,0 +1,71 @@
+use core::fmt;
+
+use crate::PartialEqObj;
+
+/// Equality for trait objects that explains why two objects are not equal.
+///
+/// This is automatically implemented for every `PartialEqObj`, including trait
+/// objects like `dyn MyTrait` where `MyTrait` extends `PartialEqObj`.
+pub trait ExplainEqObj: PartialEqObj {
+    /// Compares the objects like `eq_object`, returning the reason they are
+    /// not equal instead of false.
+    fn eq_object_explain(&self, other: &dyn PartialEqObj) -> Result<(), Inequality>;
+}
+
+impl<T: PartialEqObj + ?Sized> ExplainEqObj for T {
+    fn eq_object_explain(&self, other: &dyn PartialEqObj) -> Result<(), Inequality> {
+        if self.eq_object(other) {
+            Ok(())
+        } else if self.as_any().type_id() != other.as_any().type_id() {
+            Err(Inequality::DifferentTypes {
+                left: self.type_name(),
+                right: other.type_name(),
+            })
+        } else {
+            Err(Inequality::DifferentValues)
+        }
+    }
+}
+
+/// The reason two objects are not equal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Inequality {
+    /// The objects have different concrete types.
+    DifferentTypes {
+        left: &'static str,
+        right: &'static str,
+    },
+    /// The objects have the same concrete type, but their values are not
+    /// equal.
+    DifferentValues,
+}
+
+impl fmt::Display for Inequality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inequality::DifferentTypes { left, right } => {
+                write!(f, "objects have different types: {left} and {right}")
+            }
+            Inequality::DifferentValues => write!(f, "objects have different values"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn explain() {
+        let one: &dyn PartialEqObj = &1;
+        assert_eq!(one.eq_object_explain(&1), Ok(()));
+        assert_eq!(one.eq_object_explain(&2), Err(Inequality::DifferentValues));
+        assert_eq!(
+            one.eq_object_explain(&"1"),
+            Err(Inequality::DifferentTypes {
+                left: "i32",
+                right: "&str"
+            })
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! C interface for hosts, such as a C or C++ program embedding Rust plugins,
+//! that hold Rust objects only as opaque pointers and need to compare and
+//! hash them.
+//!
+//! The Rust side creates a handle with [`ObjHandle::into_raw`] and passes the
+//! pointer to the host, which uses these functions and gives it back to
+//! [`object_safe_free`] when done:
+//!
+//! ```c
+//! typedef struct ObjHandle ObjHandle;
+//!
+//! bool object_safe_eq(const ObjHandle *a, const ObjHandle *b);
+//! uint64_t object_safe_hash(const ObjHandle *handle);
+//! void object_safe_free(ObjHandle *handle);
+//! ```
+//!
+//! Handles are equal if they hold equal values of the same concrete type, and
+//! hash like `HashObj::hash_object_u64` of the value they hold, so hashes are
+//! only consistent within one process. The functions may be called from any
+//! thread, since the values are `Send` and `Sync`.
+
+use alloc::boxed::Box;
+use core::hash::Hash;
+
+use crate::{HashObj, KeyObj};
+
+/// Opaque handle owning a value that can be compared and hashed from C.
+pub struct ObjHandle(Box<dyn KeyObj>);
+
+impl ObjHandle {
+    pub fn new<T: Hash + Eq + Send + Sync + 'static>(value: T) -> Self {
+        Self(Box::new(value))
+    }
+
+    /// Moves the handle to the heap and returns the pointer for the host,
+    /// which must eventually free it with [`object_safe_free`].
+    pub fn into_raw(self) -> *mut ObjHandle {
+        Box::into_raw(Box::new(self))
+    }
+
+    /// Takes back ownership of a handle, like [`object_safe_free`] without
+    /// dropping it.
+    ///
+    /// # Safety
+    ///
+    /// `handle` must come from `into_raw`, and not be used afterwards.
+    pub unsafe fn from_raw(handle: *mut ObjHandle) -> Self {
+        // SAFETY: The caller guarantees that the handle is owned.
+        *unsafe { Box::from_raw(handle) }
+    }
+
+    pub fn get(&self) -> &dyn KeyObj {
+        &*self.0
+    }
+
+    pub fn into_inner(self) -> Box<dyn KeyObj> {
+        self.0
+    }
+}
+
+/// Returns true if both handles hold equal values of the same type, and
+/// false if either is null.
+///
+/// # Safety
+///
+/// Each handle must be null or a live pointer from `ObjHandle::into_raw`.
+#[no_mangle]
+pub unsafe extern "C" fn object_safe_eq(a: *const ObjHandle, b: *const ObjHandle) -> bool {
+    // SAFETY: The caller guarantees that non-null handles are live.
+    match unsafe { (a.as_ref(), b.as_ref()) } {
+        (Some(a), Some(b)) => *a.0 == *b.0,
+        _ => false,
+    }
+}
+
+/// Returns the hash of the value held by the handle, or 0 if it is null.
+///
+/// # Safety
+///
+/// The handle must be null or a live pointer from `ObjHandle::into_raw`.
+#[no_mangle]
+pub unsafe extern "C" fn object_safe_hash(handle: *const ObjHandle) -> u64 {
+    // SAFETY: The caller guarantees that a non-null handle is live.
+    unsafe { handle.as_ref() }.map_or(0, |handle| handle.0.hash_object_u64())
+}
+
+/// Drops the handle and the value it holds. Does nothing if it is null.
+///
+/// # Safety
+///
+/// The handle must be null or a live pointer from `ObjHandle::into_raw`, and
+/// is dangling afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn object_safe_free(handle: *mut ObjHandle) {
+    if !handle.is_null() {
+        // SAFETY: The caller guarantees that the handle is live and owned.
+        drop(unsafe { ObjHandle::from_raw(handle) });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::ptr;
+
+    use super::*;
+
+    #[test]
+    fn compare_handles() {
+        let a = ObjHandle::new("plugin").into_raw();
+        let b = ObjHandle::new("plugin").into_raw();
+        let c = ObjHandle::new(7u8).into_raw();
+        unsafe {
+            assert!(object_safe_eq(a, b));
+            assert!(!object_safe_eq(a, c));
+            assert!(!object_safe_eq(a, ptr::null()));
+            assert_eq!(object_safe_hash(a), object_safe_hash(b));
+            assert_eq!(object_safe_hash(a), "plugin".hash_object_u64());
+            assert_eq!(object_safe_hash(ptr::null()), 0);
+            let c = ObjHandle::from_raw(c);
+            assert_eq!(c.get().as_any().downcast_ref(), Some(&7u8));
+            for handle in [a, b, ptr::null_mut()] {
+                object_safe_free(handle);
+            }
+        }
+    }
+}
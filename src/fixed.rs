@@ -0,0 +1,311 @@
+//! Fixed-capacity collections of borrowed trait objects, which need neither
+//! `std` nor `alloc`.
+//!
+//! `DynFixedSet<'a, dyn MyKey, N>` and `DynFixedMap<'a, dyn MyKey, V, N>`
+//! hold up to `N` keys inline, without ever allocating. The keys are borrowed
+//! for `'a`, so they live wherever the caller puts them: in statics, on the
+//! stack, or in any storage the caller manages. This suits firmware that
+//! needs a small registry of heterogeneous values.
+//!
+//! ```rust ignore
+//! static KEYS: (u8, &str) = (7, "uart");
+//! let mut set = DynFixedSet::<dyn MyKey, 4>::new();
+//! set.insert(&KEYS.0).unwrap();
+//! set.insert(&KEYS.1).unwrap();
+//! assert!(set.contains(&7u8));
+//! ```
+//!
+//! Each key is hashed once when it is inserted, with FNV-1a, and lookups only
+//! compare the keys whose hash matches the probe's.
+
+use core::{fmt, hash::Hasher};
+
+use crate::{EqObj, HashObj};
+
+/// The 64-bit FNV-1a hasher, which is deterministic and needs no state
+/// besides the running hash.
+struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+fn fnv_hash<K: HashObj + ?Sized>(key: &K) -> u64 {
+    let mut hasher = FnvHasher::default();
+    key.hash_object(&mut hasher);
+    hasher.finish()
+}
+
+/// Set of up to `N` borrowed trait objects, such as
+/// `DynFixedSet<'a, dyn MyKey, 8>` where `MyKey` extends `HashObj` and
+/// `EqObj`. The iteration order is unspecified.
+pub struct DynFixedSet<'a, K: ?Sized, const N: usize> {
+    map: DynFixedMap<'a, K, (), N>,
+}
+
+impl<'a, K: ?Sized, const N: usize> DynFixedSet<'a, K, N> {
+    pub const fn new() -> Self {
+        Self {
+            map: DynFixedMap::new(),
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.map.is_full()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a K> + '_ {
+        self.map.keys()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+}
+
+impl<'a, K: HashObj + EqObj + ?Sized, const N: usize> DynFixedSet<'a, K, N> {
+    /// Adds a key to the set, returning false if an equal key was already
+    /// present, in which case the set is not modified. If the set is full,
+    /// the key is handed back as an error.
+    pub fn insert(&mut self, key: &'a K) -> Result<bool, &'a K> {
+        match self.map.insert(key, ()) {
+            Ok(old) => Ok(old.is_none()),
+            Err((key, ())) => Err(key),
+        }
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Returns the key in the set that is equal to `key`.
+    pub fn get(&self, key: &K) -> Option<&'a K> {
+        self.map.get_key_value(key).map(|(key, ())| key)
+    }
+
+    /// Removes the key that is equal to `key`, returning it if it was
+    /// present.
+    pub fn remove(&mut self, key: &K) -> Option<&'a K> {
+        self.map.remove_entry(key).map(|(key, ())| key)
+    }
+}
+
+impl<K: ?Sized, const N: usize> Default for DynFixedSet<'_, K, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: fmt::Debug + ?Sized, const N: usize> fmt::Debug for DynFixedSet<'_, K, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+struct Entry<'a, K: ?Sized, V> {
+    hash: u64,
+    key: &'a K,
+    value: V,
+}
+
+/// Map of up to `N` borrowed trait objects to values, such as
+/// `DynFixedMap<'a, dyn MyKey, V, 8>` where `MyKey` extends `HashObj` and
+/// `EqObj`. The iteration order is unspecified.
+pub struct DynFixedMap<'a, K: ?Sized, V, const N: usize> {
+    /// The first `len` entries are occupied.
+    entries: [Option<Entry<'a, K, V>>; N],
+    len: usize,
+}
+
+impl<'a, K: ?Sized, V, const N: usize> DynFixedMap<'a, K, V, N> {
+    pub const fn new() -> Self {
+        Self {
+            entries: [const { None }; N],
+            len: 0,
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'a K, &V)> + '_ {
+        self.occupied().map(|entry| (entry.key, &entry.value))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&'a K, &mut V)> + '_ {
+        self.entries[..self.len]
+            .iter_mut()
+            .flatten()
+            .map(|entry| (entry.key, &mut entry.value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &'a K> + '_ {
+        self.occupied().map(|entry| entry.key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> + '_ {
+        self.occupied().map(|entry| &entry.value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries[..self.len].fill_with(|| None);
+        self.len = 0;
+    }
+
+    fn occupied(&self) -> impl Iterator<Item = &Entry<'a, K, V>> + '_ {
+        self.entries[..self.len].iter().flatten()
+    }
+}
+
+impl<'a, K: HashObj + EqObj + ?Sized, V, const N: usize> DynFixedMap<'a, K, V, N> {
+    /// Inserts a value for the key, returning the previous value of an equal
+    /// key, which keeps its original key. If the map is full and has no equal
+    /// key, the key and value are handed back as an error.
+    pub fn insert(&mut self, key: &'a K, value: V) -> Result<Option<V>, (&'a K, V)> {
+        let hash = fnv_hash(key);
+        if let Some(i) = self.position(hash, key) {
+            let entry = self.entries[i].as_mut().expect("occupied");
+            return Ok(Some(core::mem::replace(&mut entry.value, value)));
+        }
+        if self.is_full() {
+            return Err((key, value));
+        }
+        self.entries[self.len] = Some(Entry { hash, key, value });
+        self.len += 1;
+        Ok(None)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.position(fnv_hash(key), key).is_some()
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    pub fn get_key_value(&self, key: &K) -> Option<(&'a K, &V)> {
+        let i = self.position(fnv_hash(key), key)?;
+        let entry = self.entries[i].as_ref()?;
+        Some((entry.key, &entry.value))
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        let i = self.position(fnv_hash(key), key)?;
+        Some(&mut self.entries[i].as_mut()?.value)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.remove_entry(key).map(|(_, value)| value)
+    }
+
+    /// Removes the entry whose key is equal to `key`, moving the last entry
+    /// into its place.
+    pub fn remove_entry(&mut self, key: &K) -> Option<(&'a K, V)> {
+        let i = self.position(fnv_hash(key), key)?;
+        self.len -= 1;
+        self.entries.swap(i, self.len);
+        let entry = self.entries[self.len].take()?;
+        Some((entry.key, entry.value))
+    }
+
+    fn position(&self, hash: u64, key: &K) -> Option<usize> {
+        self.occupied()
+            .position(|entry| entry.hash == hash && entry.key.eq_object(key.as_partial_eq_object()))
+    }
+}
+
+impl<K: ?Sized, V, const N: usize> Default for DynFixedMap<'_, K, V, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: fmt::Debug + ?Sized, V: fmt::Debug, const N: usize> fmt::Debug
+    for DynFixedMap<'_, K, V, N>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixture::Key;
+
+    #[test]
+    fn set_operations() {
+        static PIN: u8 = 7;
+        let name = "uart";
+        let mut set = DynFixedSet::<dyn Key, 2>::new();
+        assert_eq!(set.insert(&PIN).ok(), Some(true));
+        assert_eq!(set.insert(&name).ok(), Some(true));
+        assert_eq!(set.insert(&7u8).ok(), Some(false));
+        assert!(set.is_full());
+        assert!(set.insert(&7u16).is_err());
+        assert!(set.contains(&7u8));
+        assert!(!set.contains(&7u16));
+        assert!(set.get(&"uart").unwrap().as_any().is::<&str>());
+        assert!(set.remove(&7u8).is_some());
+        assert!(set.remove(&7u8).is_none());
+        assert_eq!(set.len(), 1);
+        assert!(set.insert(&7u16).is_ok());
+    }
+
+    #[test]
+    fn map_operations() {
+        let keys = (1u32, 'a', 2u32);
+        let mut map = DynFixedMap::<dyn Key, &str, 3>::new();
+        assert_eq!(map.insert(&keys.0, "one").ok(), Some(None));
+        assert_eq!(map.insert(&keys.1, "a").ok(), Some(None));
+        assert_eq!(map.insert(&1u32, "uno").ok(), Some(Some("one")));
+        assert_eq!(map.get(&1u32), Some(&"uno"));
+        *map.get_mut(&'a').unwrap() = "b";
+        assert_eq!(map.remove(&'a'), Some("b"));
+        assert_eq!(map.insert(&keys.2, "two").ok(), Some(None));
+        assert_eq!(map.keys().count(), 2);
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.get(&2u32), None);
+    }
+}
@@ -0,0 +1,9 @@
+//! Key trait for the tests of the collections and of `fixed`, which all need
+//! a trait object that can be hashed, compared and printed by `assert_eq!`.
+
+use core::{fmt, hash::Hash};
+
+use crate::{EqObj, HashObj};
+
+pub trait Key: HashObj + EqObj + fmt::Debug {}
+impl<T: Hash + Eq + fmt::Debug + 'static> Key for T {}
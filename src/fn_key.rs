@@ -0,0 +1,165 @@
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+/// Function pointers, which `FnKeyObj::fn_ptr` compares by address. This is
+/// implemented for `fn` pointers with up to six arguments.
+pub trait FnPtr: Copy + 'static {
+    fn addr(self) -> usize;
+}
+
+macro_rules! impl_fn_ptr {
+    ($(($($A:ident),*)),*) => {$(
+        impl<R: 'static, $($A: 'static),*> FnPtr for fn($($A),*) -> R {
+            fn addr(self) -> usize {
+                self as usize
+            }
+        }
+    )*};
+}
+
+impl_fn_ptr!(
+    (),
+    (A),
+    (A, B),
+    (A, B, C),
+    (A, B, C, D),
+    (A, B, C, D, E),
+    (A, B, C, D, E, F)
+);
+
+/// Wrapper that makes a function, such as a callback, usable as a key, by
+/// comparing and hashing a key that identifies it instead of the function
+/// itself, which cannot be compared. It implements `PartialEq`, `Eq`, `Ord`
+/// and `Hash`, and so the object-safe traits.
+///
+/// The key is either supplied with `new`, or the address of the function:
+/// `fn_ptr` takes a function pointer, and `by_address` takes a pointer to a
+/// closure, such as a `Box<dyn Fn()>` or an `Arc<dyn Fn()>`, like
+/// `ByAddressObj`.
+///
+/// ```rust ignore
+/// let mut callbacks = DynHashSet::<dyn MyKey>::new();
+/// callbacks.insert(Box::new(FnKeyObj::fn_ptr(on_exit as fn())));
+/// callbacks.insert(Box::new(FnKeyObj::new(Box::new(move || log(id)) as Box<dyn Fn()>, id)));
+/// ```
+///
+/// Like for any address, the same function may have different addresses in
+/// different codegen units, different functions may be merged into one, and
+/// closures that capture nothing occupy no memory, so they may all share the
+/// same address. Supply a key when that matters.
+#[derive(Clone, Copy)]
+pub struct FnKeyObj<F, K = usize> {
+    function: F,
+    key: K,
+}
+
+impl<F, K> FnKeyObj<F, K> {
+    /// Identifies the function by `key`.
+    pub fn new(function: F, key: K) -> Self {
+        Self { function, key }
+    }
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_inner(self) -> F {
+        self.function
+    }
+}
+
+impl<F: FnPtr> FnKeyObj<F> {
+    /// Identifies a function pointer, such as `on_exit as fn()`, by its
+    /// address.
+    pub fn fn_ptr(function: F) -> Self {
+        Self::new(function, function.addr())
+    }
+}
+
+impl<F: Deref> FnKeyObj<F> {
+    /// Identifies the closure that `function` points to by its address.
+    pub fn by_address(function: F) -> Self {
+        let target: &F::Target = &function;
+        let addr = (target as *const F::Target).cast::<()>() as usize;
+        Self::new(function, addr)
+    }
+}
+
+impl<F, K> Deref for FnKeyObj<F, K> {
+    type Target = F;
+
+    fn deref(&self) -> &Self::Target {
+        &self.function
+    }
+}
+
+impl<F, K: fmt::Debug> fmt::Debug for FnKeyObj<F, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FnKeyObj").field(&self.key).finish()
+    }
+}
+
+impl<F, K: PartialEq> PartialEq for FnKeyObj<F, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<F, K: Eq> Eq for FnKeyObj<F, K> {}
+
+impl<F, K: PartialOrd> PartialOrd for FnKeyObj<F, K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+
+impl<F, K: Ord> Ord for FnKeyObj<F, K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl<F, K: Hash> Hash for FnKeyObj<F, K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashSet, rc::Rc};
+
+    use crate::*;
+
+    fn one() -> u8 {
+        1
+    }
+
+    fn two() -> u8 {
+        2
+    }
+
+    #[test]
+    fn function_keys() {
+        let set: HashSet<Box<dyn ObjectSafeKey>> = [one as fn() -> u8, two, one]
+            .map(|f| Box::new(FnKeyObj::fn_ptr(f)) as Box<dyn ObjectSafeKey>)
+            .into();
+        assert_eq!(set.len(), 2);
+
+        let offset = 2;
+        let add: Rc<dyn Fn(u8) -> u8> = Rc::new(move |x| x + offset);
+        let key = FnKeyObj::by_address(add.clone());
+        assert!(key == FnKeyObj::by_address(add));
+        let other: Rc<dyn Fn(u8) -> u8> = Rc::new(move |x| x + offset);
+        assert!(key != FnKeyObj::by_address(other));
+        assert_eq!(key(1), 3);
+
+        let named = FnKeyObj::new(Box::new(two) as Box<dyn Fn() -> u8>, "two");
+        assert!(named == FnKeyObj::new(Box::new(one), "two"));
+        assert_eq!(named.into_inner()(), 2);
+    }
+}
@@ -0,0 +1,88 @@
+//! Grouping of trait objects by equality or by concrete type.
+
+use core::{any::TypeId, ops::Deref};
+use std::collections::HashMap;
+
+use crate::{AsAny, PartialEqObj};
+
+/// Groups the objects into runs of equal values, preserving the order in which
+/// each group first appears, as well as the order within each group.
+///
+/// This works with any pointer to a `PartialEqObj`, such as
+/// `Box<dyn MyTrait>` where `MyTrait` extends `PartialEqObj`. Each object is
+/// compared with the first member of every existing group, so this takes
+/// quadratic time in the worst case.
+pub fn group_by_eq<I, P, T>(objects: I) -> Vec<Vec<P>>
+where
+    I: IntoIterator<Item = P>,
+    P: Deref<Target = T>,
+    T: PartialEqObj + ?Sized,
+{
+    let mut groups: Vec<Vec<P>> = Vec::new();
+    for object in objects {
+        let group = groups
+            .iter_mut()
+            .find(|group| group[0].eq_object(object.as_partial_eq_object()));
+        match group {
+            Some(group) => group.push(object),
+            None => groups.push(vec![object]),
+        }
+    }
+    groups
+}
+
+/// Buckets the objects by their concrete type, preserving their relative order
+/// within each bucket.
+pub fn partition_by_type<I, P, T>(objects: I) -> HashMap<TypeId, Vec<P>>
+where
+    I: IntoIterator<Item = P>,
+    P: Deref<Target = T>,
+    T: AsAny + ?Sized,
+{
+    let mut buckets: HashMap<TypeId, Vec<P>> = HashMap::new();
+    for object in objects {
+        let type_id = (*object).as_any().type_id();
+        buckets.entry(type_id).or_default().push(object);
+    }
+    buckets
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    trait Shape: PartialEqObj {}
+    impl<T: PartialEq + 'static> Shape for T {}
+
+    fn shapes() -> Vec<Box<dyn Shape>> {
+        vec![
+            Box::new(1u8),
+            Box::new("a"),
+            Box::new(1u8),
+            Box::new(2u8),
+            Box::new("a"),
+        ]
+    }
+
+    #[test]
+    fn groups_equal_objects_in_order() {
+        let groups = group_by_eq(shapes());
+        let sizes: Vec<usize> = groups.iter().map(Vec::len).collect();
+        assert_eq!(sizes, [2, 2, 1]);
+        assert_eq!((*groups[0][0]).as_any().downcast_ref(), Some(&1u8));
+        assert_eq!((*groups[1][1]).as_any().downcast_ref(), Some(&"a"));
+        assert_eq!((*groups[2][0]).as_any().downcast_ref(), Some(&2u8));
+    }
+
+    #[test]
+    fn partitions_by_concrete_type() {
+        let buckets = partition_by_type(shapes());
+        assert_eq!(buckets.len(), 2);
+        let bytes: Vec<u8> = buckets[&TypeId::of::<u8>()]
+            .iter()
+            .map(|x| *(**x).as_any().downcast_ref::<u8>().unwrap())
+            .collect();
+        assert_eq!(bytes, [1, 1, 2]);
+        assert_eq!(buckets[&TypeId::of::<&str>()].len(), 2);
+    }
+}
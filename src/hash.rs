@@ -0,0 +1,89 @@
+//! `HashObj`, the object-safe version of `Hash`, and `impl_hash!`.
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use crate::{impl_with_auto_traits, Obj};
+
+/// Object-safe version of `std::hash::Hash`
+///
+/// Hashing a value through `dyn HashObj`, `Obj`, or any stack of those
+/// wrappers feeds the hasher exactly the same data as hashing the value
+/// directly, so equal values always hash the same regardless of how they are
+/// wrapped.
+///
+/// Unlike the comparison traits, `HashObj` does not require `AsAny`, since
+/// hashing never needs the concrete type. Types that only need to be hashed
+/// don't need to be `'static`, and `dyn HashObj` has no `Any` methods in its
+/// vtable.
+pub trait HashObj {
+    fn hash_object(&self, state: &mut dyn Hasher);
+    /// Hashes the object with `DefaultHasher` and returns the result, like
+    /// `BuildHasher::hash_one`. The result is the same for every call within
+    /// a process, but may change between Rust versions.
+    #[cfg(feature = "std")]
+    fn hash_object_u64(&self) -> u64 {
+        crate::hasher::FinishableHasher::new(std::collections::hash_map::DefaultHasher::new())
+            .write_object(self)
+            .finish()
+    }
+    fn as_hash_object(&self) -> &dyn HashObj;
+    #[cfg(feature = "alloc")]
+    fn to_hash_object(self) -> Box<dyn HashObj>
+    where
+        Self: 'static;
+}
+
+impl<T: Hash> HashObj for T {
+    fn hash_object(&self, mut state: &mut dyn Hasher) {
+        self.hash(&mut state);
+    }
+
+    fn as_hash_object(&self) -> &dyn HashObj {
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    fn to_hash_object(self) -> Box<dyn HashObj>
+    where
+        Self: 'static,
+    {
+        Box::new(self)
+    }
+}
+
+/// Non-generic core of the `Hash` impls generated by `impl_hash!`, so each
+/// hasher type only monomorphizes a thin wrapper around it.
+#[doc(hidden)]
+#[inline(never)]
+pub fn hash_object(object: &dyn HashObj, state: &mut dyn Hasher) {
+    object.hash_object(state);
+}
+
+crate::impl_hash! {
+    Obj<T> where <T: Deref<Target=X>, X: HashObj + ?Sized>,
+}
+impl_with_auto_traits!(crate::impl_hash: dyn HashObj + '_);
+
+#[macro_export]
+macro_rules! impl_hash {
+    (@impl [$($G:tt)*] [$Type:ty] [$($W:tt)*]) => {
+        impl<$($G)*> ::core::hash::Hash for $Type where $($W)* {
+            #[inline]
+            fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+                use ::core::ops::Deref as _;
+                $crate::__private::hash_object(self.deref().as_hash_object(), state);
+            }
+        }
+    };
+    (impl $($input:tt)*) => {
+        $crate::__impl_header! { $crate::impl_hash; impl $($input)* }
+    };
+    ($($input:tt)*) => {
+        $crate::__legacy_header! { $crate::impl_hash; _; $($input)* }
+    };
+}
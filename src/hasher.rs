@@ -0,0 +1,665 @@
+//! Helpers for hashing trait objects with a hashing algorithm of your choice.
+
+use core::hash::{BuildHasher, BuildHasherDefault, Hasher};
+use std::collections::hash_map::{DefaultHasher, RandomState};
+
+use crate::HashObj;
+
+/// Hashes an object with a hasher built by `build`, like
+/// `BuildHasher::hash_one`.
+pub fn hash_one<S: BuildHasher>(build: &S, obj: &dyn HashObj) -> u64 {
+    FinishableHasher::from_build(build)
+        .write_object(obj)
+        .finish()
+}
+
+/// Hasher that can produce a 128-bit hash, such as `SipHasher128`.
+pub trait Hasher128: Hasher {
+    /// Returns the 128-bit hash of everything written so far.
+    fn finish128(&self) -> u128;
+}
+
+/// Hashes an object to 128 bits with `SipHasher128` and zero keys, like
+/// `HashObj::hash_object_u64`, so that even billions of objects are unlikely
+/// to have the same hash by accident. The keys are known, so use
+/// `hash_one128` with a `SeededState` if the objects may be chosen by an
+/// adversary, and `stable::stable_hash_object128` if the hashes are
+/// persisted.
+pub fn hash_object128(obj: &dyn HashObj) -> u128 {
+    FinishableHasher::new(SipHasher128::default())
+        .write_object(obj)
+        .finish128()
+}
+
+/// Hashes an object to 128 bits with a hasher built by `build`, like
+/// `hash_one`.
+pub fn hash_one128<S>(build: &S, obj: &dyn HashObj) -> u128
+where
+    S: BuildHasher,
+    S::Hasher: Hasher128,
+{
+    FinishableHasher::from_build(build)
+        .write_object(obj)
+        .finish128()
+}
+
+/// Hasher that objects can be fed to, either directly or by lending it out as
+/// a `&mut dyn Hasher`, before finishing it to get their combined hash.
+///
+/// ```rust ignore
+/// let mut hasher = FinishableHasher::from_build(&RandomState::new());
+/// hasher.write_object(&header).write_object(&*body);
+/// hash_objects(&parts, hasher.as_dyn());
+/// let hash = hasher.finish();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct FinishableHasher<H = DefaultHasher>(H);
+
+/// `FinishableHasher` for a hashing algorithm chosen at runtime, which can be
+/// built from a `&dyn BuildHasherObj`.
+pub type HasherBox = FinishableHasher<Box<dyn Hasher>>;
+
+impl<H: Hasher> FinishableHasher<H> {
+    pub fn new(hasher: H) -> Self {
+        Self(hasher)
+    }
+
+    /// Starts hashing with a hasher built by `build`.
+    pub fn from_build<S: BuildHasher<Hasher = H>>(build: &S) -> Self {
+        Self(build.build_hasher())
+    }
+
+    /// Feeds the object to the hasher.
+    pub fn write_object<T: HashObj + ?Sized>(&mut self, obj: &T) -> &mut Self {
+        obj.hash_object(&mut self.0);
+        self
+    }
+
+    /// Lends out the hasher, to pass it to `hash_object` or any other code
+    /// that takes a `&mut dyn Hasher`.
+    pub fn as_dyn(&mut self) -> &mut dyn Hasher {
+        &mut self.0
+    }
+
+    /// Returns the hash of everything written so far.
+    pub fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    pub fn into_inner(self) -> H {
+        self.0
+    }
+}
+
+impl<H: Hasher128> FinishableHasher<H> {
+    /// Returns the 128-bit hash of everything written so far.
+    pub fn finish128(&self) -> u128 {
+        self.0.finish128()
+    }
+}
+
+impl HasherBox {
+    /// Starts hashing with a hasher built by `build`, boxing it.
+    pub fn from_build_object(build: &dyn BuildHasherObj) -> Self {
+        Self(build.build_hasher_object())
+    }
+}
+
+impl<H: Hasher> Hasher for FinishableHasher<H> {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.0.write_u8(i);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.0.write_u16(i);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.0.write_u32(i);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0.write_u64(i);
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.0.write_u128(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.0.write_usize(i);
+    }
+
+    fn write_i8(&mut self, i: i8) {
+        self.0.write_i8(i);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.0.write_i16(i);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.0.write_i32(i);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.0.write_i64(i);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.0.write_i128(i);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.0.write_isize(i);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+}
+
+/// Hasher that forwards every write to several hashers, so an object can be
+/// hashed with all of them in a single pass, such as a fast hash for a
+/// `HashMap` along with a stable content hash.
+///
+/// ```rust ignore
+/// let (mut fast, mut stable) = (FxHasher::default(), SipHasher13::new_with_keys(0, 0));
+/// object.hash_object(&mut HasherTee::new([&mut fast as &mut dyn Hasher, &mut stable]));
+/// let (fast, stable) = (fast.finish(), stable.finish());
+/// ```
+///
+/// `finish` returns the hash of the first hasher, or 0 if there is none. Read
+/// the others from the hashers themselves once the tee is dropped.
+pub struct HasherTee<'a, const N: usize>([&'a mut dyn Hasher; N]);
+
+impl<'a, const N: usize> HasherTee<'a, N> {
+    pub fn new(hashers: [&'a mut dyn Hasher; N]) -> Self {
+        Self(hashers)
+    }
+}
+
+macro_rules! tee {
+    ($($method:ident($T:ty)),*) => {$(
+        fn $method(&mut self, i: $T) {
+            for hasher in &mut self.0 {
+                hasher.$method(i);
+            }
+        }
+    )*};
+}
+
+impl<const N: usize> Hasher for HasherTee<'_, N> {
+    fn write(&mut self, bytes: &[u8]) {
+        for hasher in &mut self.0 {
+            hasher.write(bytes);
+        }
+    }
+
+    tee! {
+        write_u8(u8),
+        write_u16(u16),
+        write_u32(u32),
+        write_u64(u64),
+        write_u128(u128),
+        write_usize(usize),
+        write_i8(i8),
+        write_i16(i16),
+        write_i32(i32),
+        write_i64(i64),
+        write_i128(i128),
+        write_isize(isize)
+    }
+
+    fn finish(&self) -> u64 {
+        self.0.first().map_or(0, |hasher| hasher.finish())
+    }
+}
+
+/// Feeds every object to the same hasher, in order. Unlike hashing a slice,
+/// the number of objects is not hashed, so this is equivalent to calling
+/// `hash_object` on each of them in turn.
+pub fn hash_objects(objs: &[&dyn HashObj], state: &mut dyn Hasher) {
+    for &obj in objs {
+        obj.hash_object(state);
+    }
+}
+
+/// Hashes each object separately with a hasher built by `build`, returning
+/// their hashes in the same order. The hashes are the same as `hash_one`
+/// returns for each object.
+pub fn hash_objects_to_u64s<S: BuildHasher>(build: &S, objs: &[&dyn HashObj]) -> Vec<u64> {
+    objs.iter().map(|&obj| hash_one(build, obj)).collect()
+}
+
+/// Object-safe version of `std::hash::BuildHasher`, so the hashing algorithm
+/// can be chosen at runtime.
+pub trait BuildHasherObj {
+    fn build_hasher_object(&self) -> Box<dyn Hasher>;
+    /// Hashes an object with a hasher built by `self`. This avoids boxing the
+    /// hasher, unlike `build_hasher_object`.
+    fn hash_one_object(&self, obj: &dyn HashObj) -> u64;
+}
+
+impl<S> BuildHasherObj for S
+where
+    S: BuildHasher,
+    S::Hasher: 'static,
+{
+    fn build_hasher_object(&self) -> Box<dyn Hasher> {
+        Box::new(self.build_hasher())
+    }
+
+    fn hash_one_object(&self, obj: &dyn HashObj) -> u64 {
+        hash_one(self, obj)
+    }
+}
+
+/// `BuildHasher` that seeds every hasher it builds with the same secret keys,
+/// like `RandomState`, so hash collisions cannot be predicted by anyone who
+/// doesn't know the keys. Give each collection its own state to protect it
+/// against keys crafted to collide.
+///
+/// Unlike `RandomState`, the keys can be chosen explicitly, so hashes can be
+/// reproduced, for example to rebuild a collection that was persisted along
+/// with its seed.
+#[derive(Clone, Debug)]
+pub struct SeededState {
+    k0: u64,
+    k1: u64,
+}
+
+impl SeededState {
+    /// Creates a state with random keys.
+    pub fn new() -> Self {
+        let random = RandomState::new();
+        Self::with_keys(random.hash_one(0u8), random.hash_one(1u8))
+    }
+
+    /// Creates a state with keys derived from a 64-bit seed.
+    pub fn with_seed(seed: u64) -> Self {
+        // splitmix64, so that similar seeds produce unrelated keys
+        let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        Self::with_keys(seed, z ^ (z >> 31))
+    }
+
+    pub fn with_keys(k0: u64, k1: u64) -> Self {
+        Self { k0, k1 }
+    }
+
+    pub fn keys(&self) -> (u64, u64) {
+        (self.k0, self.k1)
+    }
+
+    /// Builds a 128-bit hasher seeded with the same keys, for `hash_one128`
+    /// through `SeededState128`.
+    pub fn build_hasher128(&self) -> SipHasher128 {
+        SipHasher128::new_with_keys(self.k0, self.k1)
+    }
+}
+
+/// `SeededState` whose hashers are `SipHasher128`, for `hash_one128`.
+#[derive(Clone, Debug, Default)]
+pub struct SeededState128(pub SeededState);
+
+impl BuildHasher for SeededState128 {
+    type Hasher = SipHasher128;
+
+    fn build_hasher(&self) -> SipHasher128 {
+        self.0.build_hasher128()
+    }
+}
+
+impl Default for SeededState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BuildHasher for SeededState {
+    type Hasher = SipHasher13;
+
+    fn build_hasher(&self) -> SipHasher13 {
+        SipHasher13::new_with_keys(self.k0, self.k1)
+    }
+}
+
+/// Keyed SipHash-1-3 hasher, the algorithm used by `DefaultHasher` at the
+/// time of writing.
+#[derive(Clone, Debug)]
+pub struct SipHasher13 {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    tail: u64,
+    length: usize,
+}
+
+impl SipHasher13 {
+    pub fn new_with_keys(k0: u64, k1: u64) -> Self {
+        Self {
+            v0: k0 ^ 0x736f6d6570736575,
+            v1: k1 ^ 0x646f72616e646f6d,
+            v2: k0 ^ 0x6c7967656e657261,
+            v3: k1 ^ 0x7465646279746573,
+            tail: 0,
+            length: 0,
+        }
+    }
+
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    fn compress(&mut self, m: u64) {
+        self.v3 ^= m;
+        self.round();
+        self.v0 ^= m;
+    }
+
+    /// The state after compressing the last block and the finalization
+    /// rounds, with `marker` mixed in to tell the output lengths apart.
+    fn finalized(&self, marker: u64) -> Self {
+        let mut state = self.clone();
+        let b = ((self.length as u64 & 0xff) << 56) | self.tail;
+        state.compress(b);
+        state.v2 ^= marker;
+        for _ in 0..3 {
+            state.round();
+        }
+        state
+    }
+
+    fn digest(&self) -> u64 {
+        self.v0 ^ self.v1 ^ self.v2 ^ self.v3
+    }
+}
+
+impl Default for SipHasher13 {
+    fn default() -> Self {
+        Self::new_with_keys(0, 0)
+    }
+}
+
+impl Hasher for SipHasher13 {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.tail |= (byte as u64) << (8 * (self.length % 8));
+            self.length += 1;
+            if self.length.is_multiple_of(8) {
+                let m = core::mem::take(&mut self.tail);
+                self.compress(m);
+            }
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.finalized(0xff).digest()
+    }
+}
+
+/// Keyed SipHash-1-3 hasher with a 128-bit output, for hashes that must
+/// practically never collide, such as content addresses of billions of
+/// objects. See `hash_object128`.
+#[derive(Clone, Debug)]
+pub struct SipHasher128(SipHasher13);
+
+impl SipHasher128 {
+    pub fn new_with_keys(k0: u64, k1: u64) -> Self {
+        let mut state = SipHasher13::new_with_keys(k0, k1);
+        state.v1 ^= 0xee;
+        Self(state)
+    }
+}
+
+impl Default for SipHasher128 {
+    fn default() -> Self {
+        Self::new_with_keys(0, 0)
+    }
+}
+
+impl Hasher for SipHasher128 {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    /// The lower 64 bits of `finish128`.
+    fn finish(&self) -> u64 {
+        self.finish128() as u64
+    }
+}
+
+impl Hasher128 for SipHasher128 {
+    fn finish128(&self) -> u128 {
+        let mut state = self.0.finalized(0xee);
+        let low = state.digest();
+        state.v1 ^= 0xdd;
+        for _ in 0..3 {
+            state.round();
+        }
+        (u128::from(state.digest()) << 64) | u128::from(low)
+    }
+}
+
+/// `BuildHasher` for `FxHasher`, to use as the `S` parameter of the dyn
+/// collections, as in `DynHashMap<dyn MyKey, V, FxBuildHasher>`.
+pub type FxBuildHasher = BuildHasherDefault<FxHasher>;
+
+/// Fast, unkeyed hasher, which mixes in one word at a time like `rustc-hash`.
+/// It is much faster than SipHash for small keys, but its collisions are easy
+/// to predict, so only use it for keys that are not chosen by an adversary.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FxHasher {
+    hash: u64,
+}
+
+const FX_SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+impl FxHasher {
+    fn add_to_hash(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(FX_SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            self.add_to_hash(u64::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        for &byte in chunks.remainder() {
+            self.add_to_hash(byte as u64);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add_to_hash(i);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add_to_hash(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        // The multiplications only mix bits upwards, so fold the high bits
+        // back down to where hash tables pick their buckets.
+        let hash = (self.hash ^ (self.hash >> 32)).wrapping_mul(FX_SEED);
+        hash ^ (hash >> 29)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasherDefault, Hash},
+    };
+
+    use super::*;
+
+    /// Simple hasher to contrast with the standard one.
+    #[derive(Default)]
+    struct FixedHasher(u64);
+
+    impl Hasher for FixedHasher {
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                self.0 = self.0.wrapping_mul(31).wrapping_add(byte as u64);
+            }
+        }
+
+        fn finish(&self) -> u64 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn finishable_hasher() {
+        let random = RandomState::new();
+        let mut hasher = FinishableHasher::from_build(&random);
+        hasher.write_object(&"a").write_object(&1u8);
+        hash_objects(&[&[2u16]], hasher.as_dyn());
+        assert_eq!(hasher.finish(), random.hash_one(("a", 1u8, [2u16])));
+
+        let mut boxed = HasherBox::from_build_object(&random);
+        boxed.write_object(&"a");
+        assert_eq!(boxed.finish(), random.hash_one("a"));
+        let default = FinishableHasher::<DefaultHasher>::default()
+            .write_object(&"a")
+            .finish();
+        assert_eq!(default, "a".hash_object_u64());
+    }
+
+    #[test]
+    fn tee() {
+        let object: &dyn HashObj = &("a", 1u8);
+        let (mut fixed, mut default) = (FixedHasher::default(), DefaultHasher::new());
+        let mut tee = HasherTee::new([&mut fixed as &mut dyn Hasher, &mut default]);
+        object.hash_object(&mut tee);
+        let first = tee.finish();
+        assert_eq!(first, fixed.finish());
+        assert_eq!(
+            fixed.finish(),
+            hash_one(&BuildHasherDefault::<FixedHasher>::default(), object)
+        );
+        assert_eq!(default.finish(), object.hash_object_u64());
+        assert_eq!(HasherTee::new([]).finish(), 0);
+    }
+
+    #[test]
+    fn hash_batches() {
+        let random = RandomState::new();
+        let objs: [&dyn HashObj; 3] = [&1u8, &"two", &[3u16]];
+        let mut batched = random.build_hasher();
+        hash_objects(&objs, &mut batched);
+        let mut direct = random.build_hasher();
+        1u8.hash(&mut direct);
+        "two".hash(&mut direct);
+        [3u16].hash(&mut direct);
+        assert_eq!(batched.finish(), direct.finish());
+
+        let hashes = hash_objects_to_u64s(&random, &objs);
+        assert_eq!(
+            hashes,
+            [
+                random.hash_one(1u8),
+                random.hash_one("two"),
+                random.hash_one([3u16])
+            ]
+        );
+    }
+
+    #[test]
+    fn hash_with_chosen_algorithm() {
+        let random = RandomState::new();
+        let fixed = BuildHasherDefault::<FixedHasher>::default();
+        let obj: &dyn HashObj = &"key";
+        assert_eq!(hash_one(&random, obj), random.hash_one("key"));
+        assert_eq!(hash_one(&fixed, obj), fixed.hash_one("key"));
+
+        let builders: [&dyn BuildHasherObj; 2] = [&random, &fixed];
+        for build in builders {
+            let mut hasher = build.build_hasher_object();
+            obj.hash_object(&mut hasher);
+            assert_eq!(hasher.finish(), build.hash_one_object(obj));
+        }
+        assert_ne!(random.hash_one_object(obj), fixed.hash_one_object(obj));
+    }
+
+    #[test]
+    fn seeded_state() {
+        let obj: &dyn HashObj = &"key";
+        let a = SeededState::with_seed(1).hash_one_object(obj);
+        assert_eq!(a, SeededState::with_seed(1).hash_one_object(obj));
+        assert_ne!(a, SeededState::with_seed(2).hash_one_object(obj));
+        let state = SeededState::new();
+        let mut hasher = state.build_hasher_object();
+        obj.hash_object(&mut hasher);
+        assert_eq!(hasher.finish(), state.hash_one("key"));
+        assert_ne!(state.keys(), SeededState::new().keys());
+    }
+
+    trait HashEqKey: HashObj + crate::EqObj {}
+    impl<T: core::hash::Hash + Eq + 'static> HashEqKey for T {}
+
+    #[test]
+    fn fx_hasher() {
+        let fx = FxBuildHasher::default();
+        let obj: &dyn HashObj = &"key";
+        assert_eq!(hash_one(&fx, obj), fx.hash_one("key"));
+        assert_eq!(fx.hash_one(1u32), FxBuildHasher::default().hash_one(1u32));
+        assert_ne!(fx.hash_one(1u32), fx.hash_one(2u32));
+        assert_ne!(fx.hash_one("abcdefghi"), fx.hash_one("abcdefghj"));
+
+        let mut set = crate::collections::DynHashSet::<dyn HashEqKey, _>::with_hasher(fx);
+        set.insert(Box::new(1u8));
+        assert!(set.contains(&1u8));
+    }
+
+    #[test]
+    fn hash128() {
+        let obj: &dyn HashObj = &"key";
+        let hash = hash_object128(obj);
+        assert_eq!(
+            hash,
+            hash_one128(&BuildHasherDefault::<SipHasher128>::default(), obj)
+        );
+        assert_ne!(hash >> 64, hash & u128::from(u64::MAX));
+        assert_ne!(hash as u64, obj.hash_object_u64());
+        let mut hasher = SipHasher128::default();
+        "key".hash(&mut hasher);
+        assert_eq!(hasher.finish(), hash as u64);
+
+        let seeded = SeededState128(SeededState::with_seed(1));
+        assert_eq!(hash_one128(&seeded, obj), hash_one128(&seeded.clone(), obj));
+        assert_ne!(hash_one128(&seeded, obj), hash);
+    }
+}
@@ -0,0 +1,116 @@
+//! Hook invoked whenever objects of different concrete types are compared.
+//!
+//! Unintended type mismatches silently compare unequal, which makes them hard
+//! to track down. With the `mismatch-hook` feature enabled, every comparison
+//! between objects of different concrete types is reported to the hook. There
+//! is no hook by default, so nothing is reported until one is set. Comparisons that order
+//! objects of different types by type are reported too, so a hook should be
+//! cheap and quiet, such as a debug-level log event.
+//!
+//! ```rust ignore
+//! object_safe::hook::set_mismatch_hook(|mismatch| {
+//!     log::debug!("{mismatch}");
+//! });
+//! ```
+
+use core::fmt;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// The kind of comparison that encountered a type mismatch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Comparison {
+    /// `eq_object` or `ne_object`
+    Eq,
+    /// `partial_cmp_object`
+    PartialCmp,
+    /// `cmp_object`
+    Cmp,
+}
+
+/// A comparison between objects of different concrete types.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Mismatch {
+    pub comparison: Comparison,
+    pub left: &'static str,
+    pub right: &'static str,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let method = match self.comparison {
+            Comparison::Eq => "eq_object",
+            Comparison::PartialCmp => "partial_cmp_object",
+            Comparison::Cmp => "cmp_object",
+        };
+        write!(
+            f,
+            "{method} compared objects of different types: {} and {}",
+            self.left, self.right
+        )
+    }
+}
+
+type Hook = Arc<dyn Fn(&Mismatch) + Send + Sync>;
+
+static HOOK: RwLock<Option<Hook>> = RwLock::new(None);
+
+/// Replaces the hook, which is invoked for every type mismatch from any
+/// thread.
+pub fn set_mismatch_hook(hook: impl Fn(&Mismatch) + Send + Sync + 'static) {
+    *HOOK.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(hook));
+}
+
+/// Removes the hook, so mismatches are no longer reported.
+pub fn reset_mismatch_hook() {
+    *HOOK.write().unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+#[cold]
+#[inline(never)]
+pub(crate) fn report(comparison: Comparison, left: &'static str, right: &'static str) {
+    let mismatch = Mismatch {
+        comparison,
+        left,
+        right,
+    };
+    let hook = HOOK.read().unwrap_or_else(PoisonError::into_inner).clone();
+    if let Some(hook) = hook {
+        hook(&mismatch);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::*;
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct Reported;
+
+    #[test]
+    fn mismatches_are_reported() {
+        static SEEN: Mutex<Vec<Mismatch>> = Mutex::new(Vec::new());
+        set_mismatch_hook(|mismatch| {
+            if mismatch.left.ends_with("Reported") {
+                SEEN.lock().unwrap().push(*mismatch);
+            }
+        });
+        let reported: &dyn OrdObj = &Reported;
+        let _ = *reported == *(&1 as &dyn OrdObj);
+        let _ = reported.partial_cmp_object(&1u8);
+        let _ = reported.cmp_object(&1u16);
+        let _ = *reported == *(&Reported as &dyn OrdObj);
+        let seen = SEEN.lock().unwrap();
+        let seen: Vec<_> = seen.iter().map(|m| (m.comparison, m.right)).collect();
+        assert_eq!(
+            seen,
+            [
+                (Comparison::Eq, "i32"),
+                (Comparison::PartialCmp, "u8"),
+                (Comparison::Cmp, "u16"),
+            ]
+        );
+    }
+}
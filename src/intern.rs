@@ -0,0 +1,136 @@
+use core::hash::BuildHasher;
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    sync::Arc,
+};
+
+use crate::{hasher::hash_one, EqObj, HashObj};
+
+/// Deduplicating store for trait objects. Interning a value returns a shared
+/// handle, and equal values always share the same allocation, so
+/// `Arc::ptr_eq` can be used as a cheap equality check between handles from
+/// the same interner.
+///
+/// ```rust ignore
+/// let mut interner = ObjInterner::<dyn MyTrait>::new();
+/// let a = interner.intern(Box::new(1));
+/// let b = interner.intern(Box::new(1));
+/// assert!(Arc::ptr_eq(&a, &b));
+/// ```
+///
+/// The interner keeps every value alive until `purge` is called, which drops
+/// the values that are no longer referenced by any handle.
+pub struct ObjInterner<X: ?Sized, S = RandomState> {
+    buckets: HashMap<u64, Vec<Arc<X>>>,
+    build_hasher: S,
+    len: usize,
+}
+
+impl<X: HashObj + EqObj + ?Sized> ObjInterner<X> {
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<X: HashObj + EqObj + ?Sized> Default for ObjInterner<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: HashObj + EqObj + ?Sized, S: BuildHasher> ObjInterner<X, S> {
+    pub fn with_hasher(build_hasher: S) -> Self {
+        ObjInterner {
+            buckets: HashMap::new(),
+            build_hasher,
+            len: 0,
+        }
+    }
+
+    /// Returns the handle for a value equal to `value`, storing `value` first
+    /// if no such value is present yet.
+    pub fn intern(&mut self, value: Box<X>) -> Arc<X> {
+        let hash = hash_one(&self.build_hasher, value.as_hash_object());
+        let bucket = self.buckets.entry(hash).or_default();
+        if let Some(existing) = find(bucket, &value) {
+            return existing.clone();
+        }
+        let interned: Arc<X> = Arc::from(value);
+        bucket.push(interned.clone());
+        self.len += 1;
+        interned
+    }
+
+    /// Returns the handle for a value equal to `value`, if one is present.
+    pub fn get(&self, value: &X) -> Option<Arc<X>> {
+        let hash = hash_one(&self.build_hasher, value.as_hash_object());
+        find(self.buckets.get(&hash)?, value).cloned()
+    }
+
+    /// Number of distinct values in the interner.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Drops every value that is only referenced by the interner itself.
+    pub fn purge(&mut self) {
+        self.buckets.retain(|_, bucket| {
+            bucket.retain(|value| Arc::strong_count(value) > 1);
+            !bucket.is_empty()
+        });
+        self.len = self.buckets.values().map(Vec::len).sum();
+    }
+}
+
+fn find<'a, X: EqObj + ?Sized>(bucket: &'a [Arc<X>], value: &X) -> Option<&'a Arc<X>> {
+    bucket
+        .iter()
+        .find(|existing| existing.eq_object(value.as_partial_eq_object()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::*;
+
+    #[test]
+    fn equal_values_share_allocation() {
+        let mut interner = ObjInterner::<dyn Node>::new();
+        let a = interner.intern(Box::new(Leaf(1)));
+        let b = interner.intern(Box::new(Leaf(1)));
+        let c = interner.intern(Box::new(Leaf(2)));
+        let d = interner.intern(Box::new(Name("x")));
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert!(!Arc::ptr_eq(&c, &d));
+        assert_eq!(interner.len(), 3);
+        assert!(Arc::ptr_eq(&interner.get(&Leaf(2)).unwrap(), &c));
+        assert!(interner.get(&Leaf(3)).is_none());
+    }
+
+    #[test]
+    fn purge_drops_unreferenced_values() {
+        let mut interner = ObjInterner::<dyn Node>::new();
+        let kept = interner.intern(Box::new(Leaf(1)));
+        interner.intern(Box::new(Leaf(2)));
+        interner.purge();
+        assert_eq!(interner.len(), 1);
+        assert!(interner.get(&Leaf(2)).is_none());
+        assert!(Arc::ptr_eq(&interner.get(&Leaf(1)).unwrap(), &kept));
+    }
+
+    trait Node: HashObj + EqObj {}
+
+    #[derive(Hash, PartialEq, Eq)]
+    struct Leaf(u32);
+    impl Node for Leaf {}
+
+    #[derive(Hash, PartialEq, Eq)]
+    struct Name(&'static str);
+    impl Node for Name {}
+}
@@ -0,0 +1,251 @@
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use crate::{EqObj, ObjRefMut, OrdObj, PartialEqObj, PartialOrdObj, Refresh};
+
+/// Cheap key that identifies an object, such as the id of a document, for
+/// `KeyedObj::extracted`. Implement it for your trait object, such as
+/// `dyn Document`, or for concrete types.
+pub trait KeyExtract {
+    type Key;
+
+    fn extract_key(&self) -> Self::Key;
+}
+
+/// Wrapper that extracts a cheap key from the object it points to when it is
+/// created, and compares by that key first: the objects themselves are only
+/// compared when their keys are equal. It hashes only the key.
+///
+/// This suits objects that are expensive to compare but carry a cheap key
+/// that tells most of them apart. Two wrappers are equal if both their keys
+/// and their objects are equal, so the key does not need to be unique, but
+/// equal objects must have equal keys.
+///
+/// ```rust ignore
+/// let doc = KeyedObj::new(Box::new(document) as Box<dyn Document>, |doc| doc.id());
+/// let doc = KeyedObj::extracted(Box::new(document) as Box<dyn Document>);
+/// ```
+///
+/// The key is not updated if the object changes, so the wrapper only gives
+/// shared access to the object, except when its key comes from `KeyExtract`:
+/// then `get_mut` gives mutable access, and extracts the key again when the
+/// guard is dropped.
+#[derive(Clone, Copy, Debug)]
+pub struct KeyedObj<T, K> {
+    inner: T,
+    key: K,
+}
+
+impl<T, K> KeyedObj<T, K> {
+    /// Wraps a pointer to an object, with the key that `key` extracts from
+    /// the object.
+    pub fn new<X: ?Sized>(inner: T, key: impl FnOnce(&X) -> K) -> Self
+    where
+        T: Deref<Target = X>,
+    {
+        let key = key(&inner);
+        Self { inner, key }
+    }
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Splits the wrapper into its pointer and key.
+    pub fn into_parts(self) -> (T, K) {
+        (self.inner, self.key)
+    }
+}
+
+impl<T, X> KeyedObj<T, X::Key>
+where
+    T: Deref<Target = X>,
+    X: KeyExtract + ?Sized,
+{
+    /// Wraps a pointer to an object, with the key of its `KeyExtract` impl.
+    pub fn extracted(inner: T) -> Self {
+        Self::new(inner, X::extract_key)
+    }
+
+    /// Gives mutable access to the pointer, and extracts the key again when
+    /// the guard is dropped.
+    pub fn get_mut(&mut self) -> ObjRefMut<'_, Self> {
+        ObjRefMut::new(self)
+    }
+}
+
+/// Extracts the key again.
+impl<T, X> Refresh for KeyedObj<T, X::Key>
+where
+    T: Deref<Target = X>,
+    X: KeyExtract + ?Sized,
+{
+    type Target = T;
+
+    fn target(&self) -> &T {
+        &self.inner
+    }
+
+    fn target_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    fn refresh(&mut self) {
+        self.key = self.inner.extract_key();
+    }
+}
+
+impl<T, K> Deref for KeyedObj<T, K> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T, K, X> PartialEq for KeyedObj<T, K>
+where
+    T: Deref<Target = X>,
+    X: PartialEqObj + ?Sized,
+    K: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b): (&X, &X) = (&self.inner, &other.inner);
+        self.key == other.key && a.eq_object(b.as_partial_eq_object())
+    }
+}
+
+impl<T, K, X> Eq for KeyedObj<T, K>
+where
+    T: Deref<Target = X>,
+    X: EqObj + ?Sized,
+    K: Eq,
+{
+}
+
+impl<T, K, X> PartialOrd for KeyedObj<T, K>
+where
+    T: Deref<Target = X>,
+    X: PartialOrdObj + ?Sized,
+    K: PartialOrd,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.key.partial_cmp(&other.key)? {
+            Ordering::Equal => {
+                let (a, b): (&X, &X) = (&self.inner, &other.inner);
+                a.partial_cmp_object(b.as_partial_ord_object())
+            }
+            ordering => Some(ordering),
+        }
+    }
+}
+
+impl<T, K, X> Ord for KeyedObj<T, K>
+where
+    T: Deref<Target = X>,
+    X: OrdObj + ?Sized,
+    K: Ord,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key).then_with(|| {
+            let (a, b): (&X, &X) = (&self.inner, &other.inner);
+            a.cmp_object(b.as_ord_object())
+        })
+    }
+}
+
+impl<T, K: Hash> Hash for KeyedObj<T, K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::Cell, collections::BTreeSet};
+
+    use crate::*;
+
+    thread_local! {
+        static COMPARISONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct Body(&'static str);
+
+    #[derive(Eq, PartialOrd, Ord)]
+    struct Document {
+        id: u32,
+        body: Body,
+    }
+
+    impl PartialEq for Document {
+        fn eq(&self, other: &Self) -> bool {
+            COMPARISONS.with(|c| c.set(c.get() + 1));
+            self.id == other.id && self.body == other.body
+        }
+    }
+
+    trait Doc: OrdObj {
+        fn id(&self) -> u32;
+        fn set_id(&mut self, id: u32);
+    }
+
+    impl Doc for Document {
+        fn id(&self) -> u32 {
+            self.id
+        }
+
+        fn set_id(&mut self, id: u32) {
+            self.id = id;
+        }
+    }
+
+    impl KeyExtract for dyn Doc {
+        type Key = u32;
+
+        fn extract_key(&self) -> u32 {
+            self.id()
+        }
+    }
+
+    fn doc(id: u32, body: &'static str) -> Box<dyn Doc> {
+        Box::new(Document {
+            id,
+            body: Body(body),
+        })
+    }
+
+    #[test]
+    fn compares_keys_first() {
+        let a = KeyedObj::extracted(doc(1, "a"));
+        let b = KeyedObj::new(doc(2, "a"), |doc| doc.id());
+        assert_eq!(*a.key(), 1);
+        assert!(a != b);
+        assert_eq!(COMPARISONS.with(Cell::get), 0);
+        assert!(a == KeyedObj::extracted(doc(1, "a")));
+        assert!(a != KeyedObj::extracted(doc(1, "b")));
+        assert_eq!(COMPARISONS.with(Cell::get), 2);
+
+        let set: BTreeSet<_> = [doc(3, "c"), doc(1, "b"), doc(1, "a")]
+            .map(KeyedObj::extracted)
+            .into();
+        let keys: Vec<_> = set.iter().map(|doc| *doc.key()).collect();
+        assert_eq!(keys, [1, 1, 3]);
+    }
+
+    #[test]
+    fn extracts_key_after_mutation() {
+        let mut a = KeyedObj::extracted(doc(1, "a"));
+        a.get_mut().set_id(2);
+        assert_eq!(*a.key(), 2);
+        assert!(a == KeyedObj::extracted(doc(2, "a")));
+    }
+}
@@ -8,6 +8,9 @@
 //! - Hash
 //! - PartialEq
 //! - Eq
+//! - PartialOrd
+//! - Ord
+//! - Clone, for boxed objects
 //!
 //! I plan to extend this support to other traits, and offer macros to simplify
 //! the process for custom traits.
@@ -44,9 +47,12 @@
 //!
 //! Here are all the characteristics that come with HashObj:
 //! - anything implementing `Hash` automatically implements `HashObj`
-//! - `dyn HashObj` implements `Hash`.
+//! - `dyn HashObj` implements `Hash`, and so do `&dyn HashObj` and `&mut dyn
+//!   HashObj` through the std impls for references, so borrowed objects can
+//!   be passed to generic code expecting `K: Hash`.
 //! - `Obj<T>` implements `Hash` for any `T` that derefs to something
-//!   implementing `HashObj`.
+//!   implementing `HashObj`, including borrowed views such as `Ref`,
+//!   `RefMut`, lock guards and `Cow`, which don't need to be `'static`.
 //! - `impl_hash` can implement `Hash` for any type that implements `HashObj`,
 //!   for example a trait object `dyn MyTrait` where `MyTrait` is a trait
 //!   extending `HashObj`.
@@ -70,183 +76,375 @@
 //!     Obj<T> where <T: Deref<Target=X>, X: HashObj + ?Sized>,
 //! }
 //! ```
+//!
+//! The special syntax only takes type parameters with simple bounds. For
+//! anything else, such as lifetimes, const generics, bounds with paths and
+//! `where` clauses, write the impl header as in Rust, ending each impl with
+//! `;`. Each invocation takes one syntax or the other. Unlike the special
+//! syntax, which requires the type to be `'static` for every trait but
+//! `Hash`, impl headers get exactly the bounds they list. They are parsed a
+//! token at a time, so many long headers in one invocation may need a higher
+//! `#![recursion_limit]`.
+//!
+//! ```rust ignore
+//! impl_partial_eq! {
+//!     impl<'a, T: ?Sized + my_crate::Key, const N: usize> MyArray<'a, T, N>;
+//!     impl<I> FirstItem<I> where I: Iterator<Item = Box<dyn MyTrait>>;
+//!     impl MyStruct;
+//! }
+//! ```
+//!
+//! ## `no_std`
+//!
+//! The `std` feature is enabled by default. Without it, the crate only
+//! depends on `core` and `alloc`, and leaves out everything that needs the
+//! standard library: the mismatch hook, the dyn collections, the `hasher`
+//! module and the helpers built on them, and `hash_object_u64`. The registry
+//! remains, but stores its comparators in slots provided by the caller.
+//!
+//! Disable the `alloc` feature too, which `std` enables, for targets without
+//! an allocator. This leaves out the `to_*_object` methods and everything
+//! else that boxes values, but keeps the rest of the traits, which work on
+//! references: `as_*_object`, `hash_object`, `eq_object` and so on, and `Obj`
+//! wrapping a reference such as `&dyn MyTrait`. The `heapless` feature adds
+//! the `fixed` module, whose collections hold a fixed number of borrowed
+//! trait objects and need neither `std` nor `alloc`.
+//!
+//! ## Panics
+//!
+//! Comparing and hashing objects never panics by itself: objects of
+//! different types are unequal, or ordered by type, unless you opt into the
+//! `Panic` or `DebugPanic` mismatch policies, and the lock wrappers ignore
+//! poisoning. The functions that do panic document it, and offer a fallible
+//! variant returning the crate's `Error`:
+//! - `registry::register_eq`, `register_name` and `register_versioned` when
+//!   the registry is frozen or full: `try_register_eq` and
+//!   `try_register_versioned`.
+//! - `clamp_obj` when `min > max`: `try_clamp_obj`.
+//! - The `range` methods of the ordered collections for a decreasing range:
+//!   `try_range`.
+//! - Indexing the maps with a missing key: `get`.
+//!
+//! A few others only panic when given invalid arguments, such as a
+//! `DynLruCache` with a capacity of zero, or when a `SharedObj` is compared
+//! while its `RefCell` is borrowed mutably. The `testing` module and the
+//! `assert_dyn_*` macros panic on purpose, like `assert!`.
 
-use core::{
-    any::Any,
-    hash::{Hash, Hasher},
-    ops::Deref,
-};
-
-mod obj;
-
-pub use obj::Obj;
-
-/// Helper trait to enable trait upcasting, since upcasting is not stable.
-pub trait AsAny: Any {
-    fn as_any(&self) -> &dyn Any;
-}
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-impl<T: Any> AsAny for T {
-    fn as_any(&self) -> &(dyn Any) {
-        self as &dyn Any
-    }
-}
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
-/// Object-safe version of Eq
-pub trait EqObj: PartialEqObj {
-    fn as_eq_object(&self) -> &dyn EqObj;
-    fn to_eq_object(self) -> Box<dyn EqObj>;
-}
+use core::any::Any;
 
-impl<T> EqObj for T
-where
-    T: Eq + PartialEqObj,
-{
-    fn as_eq_object(&self) -> &dyn EqObj {
-        self
-    }
+mod address;
+mod alias;
+#[cfg(feature = "alloc")]
+mod arena;
+#[cfg(feature = "alloc")]
+mod assert;
+mod by;
+#[cfg(feature = "std")]
+mod cached;
+#[cfg(feature = "alloc")]
+mod clone;
+mod cmp;
+#[cfg(feature = "std")]
+pub mod collections;
+#[cfg(feature = "alloc")]
+mod composite;
+mod constant_time;
+#[cfg(feature = "alloc")]
+mod convert;
+#[cfg(feature = "alloc")]
+mod deep;
+#[cfg(feature = "alloc")]
+pub mod diff;
+#[cfg(feature = "alloc")]
+mod display;
+#[cfg(feature = "std")]
+pub mod dst;
+#[cfg(feature = "alloc")]
+mod dyn_key;
+#[cfg(feature = "std")]
+pub mod encode;
+mod eq;
+mod error;
+#[cfg(feature = "std")]
+pub mod error_eq;
+mod explain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "heapless")]
+pub mod fixed;
+#[cfg(all(test, any(feature = "std", feature = "heapless")))]
+mod fixture;
+mod fn_key;
+#[cfg(feature = "std")]
+mod group;
+mod hash;
+#[cfg(feature = "std")]
+pub mod hasher;
+#[cfg(feature = "mismatch-hook")]
+pub mod hook;
+#[cfg(feature = "std")]
+mod intern;
+mod keyed;
+#[cfg(feature = "std")]
+mod lock;
+mod marked;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod named;
+mod obj;
+mod ord;
+pub mod policy;
+#[cfg(feature = "alloc")]
+mod pool;
+#[cfg(feature = "std")]
+mod prehashed;
+mod ref_mut;
+pub mod registry;
+mod reverse;
+mod shared;
+#[cfg(feature = "alloc")]
+mod small;
+#[cfg(feature = "test-support")]
+pub mod snapshot;
+#[cfg(feature = "stable-hash")]
+pub mod stable;
+mod tagged;
+#[cfg(feature = "test-support")]
+pub mod testing;
+#[cfg(feature = "thin-key")]
+mod thin;
+#[cfg(feature = "total-cmp")]
+mod total_cmp;
+mod try_eq;
+mod try_hash;
 
-    fn to_eq_object(self) -> Box<dyn EqObj> {
-        Box::new(self)
-    }
-}
+pub use address::ByAddressObj;
+pub use alias::{FullObj, ObjectSafeKey};
+#[cfg(feature = "alloc")]
+pub use arena::{ArenaObj, ObjArena};
+pub use by::{EqBy, HashBy, OrdBy};
+#[cfg(feature = "std")]
+pub use cached::CachedHashObj;
+#[cfg(feature = "alloc")]
+pub use clone::{clone_arc, clone_boxed, clone_rc, CloneObj};
+#[cfg(feature = "alloc")]
+pub use cmp::ObjVecExt;
+pub use cmp::{
+    by_obj_order, clamp_obj, cmp_iters, cmp_obj_iters, cmp_slices, dyn_cmp, dyn_eq, eq_iters,
+    eq_obj_iters, hash_object_into, matches_concrete, max_obj, min_obj, obj_cmp, obj_eq,
+    obj_partial_cmp, partial_cmp_obj_iters, sort_unstable_objs, try_clamp_obj, ObjSliceExt,
+};
+#[cfg(feature = "std")]
+pub use cmp::{dyn_hash, hash_concrete};
+#[cfg(feature = "alloc")]
+pub use cmp::{sort_objs, sort_objs_by_cached_key};
+#[cfg(feature = "alloc")]
+pub use composite::CompositeKey;
+pub use constant_time::ConstantTimeEqObj;
+#[cfg(feature = "alloc")]
+pub use convert::ToMarkedObject;
 
-impl_eq! {
-    Obj<T> where <T: Deref<Target=X>, X: EqObj + ?Sized>,
-    dyn EqObj,
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "alloc")]
+    pub use crate::assert::{assert_failed, Describe, DescribeDebug, DescribeFallback};
+    pub use crate::hash_object;
+    #[cfg(feature = "alloc")]
+    pub use alloc::{boxed::Box, format};
 }
+#[cfg(feature = "alloc")]
+pub use deep::{deep_eq, deep_hash};
+#[cfg(feature = "alloc")]
+pub use display::{DebugEqObj, DisplayEqObj};
+#[cfg(feature = "alloc")]
+pub use dyn_key::{DynKey, KeyObj, SharedKey};
+#[cfg(feature = "std")]
+pub use encode::EncodeObj;
+#[cfg(feature = "alloc")]
+pub(crate) use eq::eq_or_same;
+pub use eq::{EqObj, PartialEqObj};
+pub use error::{debug_downcast_mut, debug_downcast_ref, downcast_ref, Error, Result};
+pub use explain::{ExplainEqObj, Inequality};
+pub use fn_key::{FnKeyObj, FnPtr};
+#[cfg(feature = "std")]
+pub use group::{group_by_eq, partition_by_type};
+#[doc(hidden)]
+pub use hash::hash_object;
+pub use hash::HashObj;
+#[cfg(feature = "std")]
+pub use intern::ObjInterner;
+pub use keyed::{KeyExtract, KeyedObj};
+#[cfg(feature = "std")]
+pub use lock::{MutexObj, RwLockObj};
+pub use marked::{SendObj, SyncObj};
+pub use named::NamedObj;
+pub use obj::{Obj, ToObj};
+pub use ord::{OrdObj, PartialOrdObj};
+#[cfg(feature = "alloc")]
+pub use pool::{ObjPool, PoolBox};
+#[cfg(feature = "std")]
+pub use prehashed::PrehashedKey;
+pub use ref_mut::{ObjRefMut, Refresh};
+pub use reverse::ReverseObj;
+pub use shared::{SharedCell, SharedObj};
+#[cfg(feature = "alloc")]
+pub use small::SmallObj;
+#[cfg(feature = "std")]
+pub use tagged::FingerprintTagged;
+pub use tagged::TypeTagged;
+#[cfg(feature = "thin-key")]
+pub use thin::ThinKey;
+#[cfg(feature = "total-cmp")]
+pub use total_cmp::{TotalCmp, TotalOrdF32, TotalOrdF64};
+pub use try_eq::{TryEqObj, TypeMismatch};
+pub use try_hash::{HashError, TryHashObj};
 
-#[macro_export]
-macro_rules! impl_eq {
-    ($(
-        $Type:ty $(where <$(
-            $G:ident$(:
-                $($Gb:ident $(<$($GbIn:ident$(=$GbInEq:ty)?)+>)?)?
-                $(?$Gbq:ident)?
-                $(
-                    +
-                    $($Gb2:ident $(<$($GbIn2:ident$(=$GbInEq2:ty)?)+>)?)?
-                    $(?$Gbq2:ident)?
-                )*
-            )?
-        ),+>)?
-    ),*$(,)?) => {$(
-        impl$(<$(
-            $G$(:
-                $($Gb $(<$($GbIn$(=$GbInEq)?)+>)?)?
-                $(?$Gbq)?
-                $(
-                    +
-                    $($Gb2 $({$($GbIn2$(=$GbInEq2:ty)?)+})?)?
-                    $(?$Gbq2)?
-                )*
-            )?
-        ),+>)?
-        Eq for $Type where $Type: 'static {})*
+/// Reports a comparison between different concrete types to the mismatch
+/// hook, if the `mismatch-hook` feature is enabled.
+macro_rules! report_mismatch {
+    ($comparison:ident, $left:expr, $right:expr) => {
+        #[cfg(feature = "mismatch-hook")]
+        crate::hook::report(
+            crate::hook::Comparison::$comparison,
+            $left.type_name(),
+            $right.type_name(),
+        );
     };
 }
+pub(crate) use report_mismatch;
 
-/// Object-safe version of PartialEq
-pub trait PartialEqObj: AsAny {
-    fn eq_object(&self, other: &dyn PartialEqObj) -> bool;
-    fn as_partial_eq_object(&self) -> &dyn PartialEqObj;
-    fn to_partial_eq_object(self) -> Box<dyn PartialEqObj>;
+/// Gives access to an object as `dyn Any`, and to the name of its type.
+///
+/// A trait object can also be upcast to `dyn Any` directly, but `as_any`
+/// works on values of any `?Sized` type, such as the target of a generic
+/// pointer, which can't be coerced.
+pub trait AsAny: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Name of the concrete type, as returned by `std::any::type_name`.
+    fn type_name(&self) -> &'static str;
 }
 
-impl<T> PartialEqObj for T
-where
-    T: PartialEq + AsAny,
-{
-    fn eq_object(&self, other: &dyn PartialEqObj) -> bool {
-        match other.as_any().downcast_ref::<Self>() {
-            Some(other) => self == other,
-            None => false,
-        }
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self as &dyn Any
     }
 
-    fn as_partial_eq_object(&self) -> &dyn PartialEqObj {
-        self
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self as &mut dyn Any
     }
 
-    fn to_partial_eq_object(self) -> Box<dyn PartialEqObj> {
-        Box::new(self)
+    fn type_name(&self) -> &'static str {
+        core::any::type_name::<T>()
     }
 }
 
-impl_partial_eq! {
-    Obj<T> where <T: Deref<Target=X>, X: PartialEqObj + ?Sized>,
-    dyn PartialEqObj,
-    dyn EqObj,
-}
-
+/// Invokes a macro such as `impl_hash!` with each trait object, along with
+/// every combination of `Send` and `Sync` added to it, so the impls cover the
+/// same objects no matter which auto traits the user erased them with.
+///
+/// ```rust ignore
+/// impl_with_auto_traits!(impl_hash: dyn MyTrait, dyn AnotherTrait);
+///
+/// // expands to
+/// impl_hash! {
+///     dyn MyTrait,
+///     dyn MyTrait + Send,
+///     dyn MyTrait + Sync,
+///     dyn MyTrait + Send + Sync,
+///     dyn AnotherTrait,
+///     ...
+/// }
+/// ```
+///
+/// A lifetime bound such as `dyn MyTrait + '_` is kept after the markers.
+/// Generic traits are not supported, so list their objects by hand instead.
 #[macro_export]
-macro_rules! impl_partial_eq {
-    ($(
-        $Type:ty $(where <$(
-            $G:ident$(:
-                $($Gb:ident $(<$($GbIn:ident$(=$GbInEq:ty)?)+>)?)?
-                $(?$Gbq:ident)?
-                $(
-                    +
-                    $($Gb2:ident $(<$($GbIn2:ident$(=$GbInEq2:ty)?)+>)?)?
-                    $(?$Gbq2:ident)?
-                )*
-            )?
-        ),+>)?
-    ),*$(,)?) => {$(
-        impl$(<$(
-            $G$(:
-                $($Gb $(<$($GbIn$(=$GbInEq)?)+>)?)?
-                $(?$Gbq)?
-                $(
-                    +
-                    $($Gb2 $({$($GbIn2$(=$GbInEq2:ty)?)+})?)?
-                    $(?$Gbq2)?
-                )*
-            )?
-        ),+>)?
-        PartialEq for $Type where $Type: 'static {
-            fn eq(&self, other: &Self) -> bool {
-                self.deref().eq_object(other.deref().as_partial_eq_object())
-            }
-        })*
+macro_rules! impl_with_auto_traits {
+    ($mac:path: $(dyn $($Trait:ident)::+ $(+ $lt:lifetime)?),+ $(,)?) => {
+        $mac! {$(
+            dyn $($Trait)::+ $(+ $lt)?,
+            dyn $($Trait)::+ + Send $(+ $lt)?,
+            dyn $($Trait)::+ + Sync $(+ $lt)?,
+            dyn $($Trait)::+ + Send + Sync $(+ $lt)?,
+        )+}
     };
 }
 
-/// Object-safe version of `std::hash::Hash`
-pub trait HashObj {
-    fn hash_object(&self, state: &mut dyn Hasher);
-    fn as_hash_object(&self) -> &dyn HashObj;
-    fn to_hash_object(self) -> Box<dyn HashObj>
-    where
-        Self: 'static;
-}
-
-impl<T: Hash> HashObj for T {
-    fn hash_object(&self, mut state: &mut dyn Hasher) {
-        self.hash(&mut state);
-    }
+/// Parses impl headers written like Rust impls, `impl<...> Type where ...;`,
+/// for the `impl_*!` macros, and invokes `$mac! { @impl [generics] [Type]
+/// [predicates] }` for each of them. The generics are split by counting angle
+/// brackets, since `macro_rules` can't match them any other way.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_header {
+    ($mac:path;) => {};
+    ($mac:path; ; $($rest:tt)*) => {
+        $crate::__impl_header! { $mac; $($rest)* }
+    };
+    ($mac:path; impl < $($rest:tt)*) => {
+        $crate::__impl_header! { @generics $mac; [] [] $($rest)* }
+    };
+    ($mac:path; impl $($rest:tt)*) => {
+        $crate::__impl_header! { @type $mac; [] $($rest)* }
+    };
 
-    fn as_hash_object(&self) -> &dyn HashObj {
-        self
-    }
+    (@generics $mac:path; [$($G:tt)*] [] > $($rest:tt)*) => {
+        $crate::__impl_header! { @type $mac; [$($G)*] $($rest)* }
+    };
+    (@generics $mac:path; [$($G:tt)*] [$_d:tt] >> $($rest:tt)*) => {
+        $crate::__impl_header! { @type $mac; [$($G)* >] $($rest)* }
+    };
+    (@generics $mac:path; [$($G:tt)*] [$_d:tt $($depth:tt)*] > $($rest:tt)*) => {
+        $crate::__impl_header! { @generics $mac; [$($G)* >] [$($depth)*] $($rest)* }
+    };
+    (@generics $mac:path; [$($G:tt)*] [$_d:tt $_e:tt $($depth:tt)*] >> $($rest:tt)*) => {
+        $crate::__impl_header! { @generics $mac; [$($G)* >>] [$($depth)*] $($rest)* }
+    };
+    (@generics $mac:path; [$($G:tt)*] [$($depth:tt)*] < $($rest:tt)*) => {
+        $crate::__impl_header! { @generics $mac; [$($G)* <] [< $($depth)*] $($rest)* }
+    };
+    (@generics $mac:path; [$($G:tt)*] [$($depth:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__impl_header! { @generics $mac; [$($G)* $next] [$($depth)*] $($rest)* }
+    };
 
-    fn to_hash_object(self) -> Box<dyn HashObj>
-    where
-        Self: 'static,
-    {
-        Box::new(self)
-    }
-}
+    (@type $mac:path; [$($G:tt)*] $Type:ty where $($rest:tt)*) => {
+        $crate::__impl_header! { @where $mac; [$($G)*] [$Type] [] $($rest)* }
+    };
+    (@type $mac:path; [$($G:tt)*] $Type:ty $(; $($rest:tt)*)?) => {
+        $mac! { @impl [$($G)*] [$Type] [] }
+        $crate::__impl_header! { $mac; $($($rest)*)? }
+    };
 
-impl_hash! {
-    Obj<T> where <T: Deref<Target=X>, X: HashObj + ?Sized>,
-    dyn HashObj,
+    (@where $mac:path; [$($G:tt)*] [$Type:ty] [$($W:tt)*] $(; $($rest:tt)*)?) => {
+        $mac! { @impl [$($G)*] [$Type] [$($W)*] }
+        $crate::__impl_header! { $mac; $($($rest)*)? }
+    };
+    (@where $mac:path; [$($G:tt)*] [$Type:ty] [$($W:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::__impl_header! { @where $mac; [$($G)*] [$Type] [$($W)* $next] $($rest)* }
+    };
 }
 
+/// Parses the original syntax of the `impl_*!` macros, a list of types that
+/// each take an optional `where <T: Bound, ...>`, and invokes `$mac! { @impl
+/// [generics] [Type] [predicates] }` for each of them, like `__impl_header!`.
+/// The predicates require the type to outlive `$bound`, or are empty if it is
+/// `_`.
+#[doc(hidden)]
 #[macro_export]
-macro_rules! impl_hash {
-    ($(
+macro_rules! __legacy_header {
+    (@bound $mac:path; _; [$($G:tt)*] [$Type:ty]) => {
+        $mac! { @impl [$($G)*] [$Type] [] }
+    };
+    (@bound $mac:path; $bound:lifetime; [$($G:tt)*] [$Type:ty]) => {
+        $mac! { @impl [$($G)*] [$Type] [$Type: $bound] }
+    };
+    ($mac:path; $bound:tt; $(
         $Type:ty $(where <$(
             $G:ident$(:
                 $($Gb:ident $(<$($GbIn:ident$(=$GbInEq:ty)?)+>)?)?
@@ -259,28 +457,36 @@ macro_rules! impl_hash {
             )?
         ),+>)?
     ),*$(,)?) => {$(
-        impl$(<$(
+        $crate::__legacy_header! { @bound $mac; $bound; [$($(
             $G$(:
                 $($Gb $(<$($GbIn$(=$GbInEq)?)+>)?)?
                 $(?$Gbq)?
                 $(
                     +
-                    $($Gb2 $({$($GbIn2$(=$GbInEq2:ty)?)+})?)?
+                    $($Gb2 $(<$($GbIn2$(=$GbInEq2)?)+>)?)?
                     $(?$Gbq2)?
                 )*
             )?
-        ),+>)?
-        std::hash::Hash for $Type {
-            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-                self.deref().hash_object(state);
-            }
-        }
+        ),+)?] [$Type] }
     )*};
 }
 
+/// Views an object as `dyn Any` through its vtable, which unlike `as_any`
+/// doesn't take a virtual call. The comparisons of the blanket impls only
+/// dispatch dynamically once per object for each trait they go through: to
+/// compare, to convert the other object, and to get its `TypeId`.
+#[inline]
+pub(crate) fn upcast_any(x: &dyn Any) -> &dyn Any {
+    x
+}
+
 #[cfg(test)]
 mod test {
-    use std::collections::hash_map::DefaultHasher;
+    use std::{
+        cmp::Ordering,
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+    };
 
     use super::*;
 
@@ -304,6 +510,17 @@ mod test {
         let z: &dyn HashObj = "banana".as_hash_object();
         assert_eq!(hash(x), hash(y));
         assert_ne!(hash(y), hash(z));
+        #[cfg(feature = "std")]
+        {
+            assert_eq!(hash(x), y.hash_object_u64());
+            assert_eq!(hash(x), x.hash_object_u64());
+        }
+
+        #[derive(Hash)]
+        struct Borrowed<'a>(&'a str);
+        let local = String::from("local");
+        let borrowed: Box<dyn HashObj + '_> = Box::new(Borrowed(&local));
+        assert_eq!(hash(&borrowed), hash(Borrowed(&local)));
     }
 
     fn hash<T: Hash>(t: T) -> u64 {
@@ -312,7 +529,159 @@ mod test {
         hasher.finish()
     }
 
+    #[test]
+    fn objects_with_auto_traits() {
+        let set: std::collections::BTreeSet<Box<dyn OrdObj + Send + Sync>> =
+            [3, 1, 2, 1].into_iter().map(|x| Box::new(x) as _).collect();
+        let set = std::thread::spawn(move || set).join().unwrap();
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(&(Box::new(2) as Box<dyn OrdObj + Send + Sync>)));
+
+        let a: Box<dyn PartialEqObj + Send> = Box::new(1.5);
+        assert!(a == Box::new(1.5) as Box<dyn PartialEqObj + Send>);
+        assert!(Obj(a) != Obj(Box::new(f64::NAN) as Box<dyn PartialEqObj + Send>));
+        let h: Box<dyn HashObj + Sync> = Box::new("key");
+        assert_eq!(hash(h), hash("key"));
+    }
+
+    #[test]
+    fn custom_objects_with_auto_traits() {
+        trait Key: EqObj + HashObj {}
+        impl<T: Eq + Hash + 'static> Key for T {}
+        impl_with_auto_traits!(impl_eq: dyn Key);
+        impl_with_auto_traits!(impl_partial_eq: dyn Key);
+        impl_with_auto_traits!(impl_hash: dyn Key);
+
+        let set: std::collections::HashSet<Box<dyn Key + Send>> =
+            [1, 2, 1].into_iter().map(|x| Box::new(x) as _).collect();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&(Box::new(2) as Box<dyn Key + Send>)));
+        let a: &(dyn Key + Sync) = &"a";
+        assert!(*a == *(&"a" as &(dyn Key + Sync)));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn borrowed_objects_in_generic_code() {
+        fn distinct<K: Hash + Eq>(keys: &[K]) -> usize {
+            keys.iter().collect::<std::collections::HashSet<_>>().len()
+        }
+        let (a, b) = (1, 2);
+        let keys: [&dyn KeyObj; 3] = [&a, &b, &a];
+        assert_eq!(distinct(&keys), 2);
+
+        fn sorted<K: Ord>(mut keys: Vec<K>) -> Vec<K> {
+            keys.sort();
+            keys
+        }
+        let (mut c, mut d) = ('c', 'a');
+        let objects: Vec<&mut dyn OrdObj> = vec![&mut c, &mut d];
+        let sorted = sorted(objects);
+        assert_eq!((*sorted[0]).as_any().downcast_ref(), Some(&'a'));
+    }
+
+    #[test]
+    fn ord_is_total_across_types() {
+        let values: Vec<Box<dyn OrdObj>> = vec![
+            Box::new(2),
+            Box::new("b"),
+            Box::new(1),
+            Box::new(()),
+            Box::new("a"),
+            Box::new('c'),
+        ];
+        for a in &values {
+            for b in &values {
+                assert_eq!(a.cmp(b), b.cmp(a).reverse());
+                assert_eq!(a.cmp(b) == Ordering::Equal, a == b);
+                assert_eq!(a.partial_cmp(b), Some(a.cmp(b)));
+            }
+        }
+        let one: Box<dyn OrdObj> = Box::new(1);
+        let two: Box<dyn OrdObj> = Box::new(2);
+        assert!(one < two);
+
+        let set: std::collections::BTreeSet<_> = values.into_iter().map(Obj).collect();
+        assert_eq!(set.len(), 6);
+        let mut ints = set
+            .iter()
+            .filter_map(|x| (***x).as_any().downcast_ref::<i32>());
+        assert_eq!(ints.next(), Some(&1));
+        assert_eq!(ints.next(), Some(&2));
+    }
+
+    #[test]
+    fn partial_ord_preserves_nan() {
+        let nan: Box<dyn PartialOrdObj> = Box::new(f64::NAN);
+        let one: Box<dyn PartialOrdObj> = Box::new(1.0);
+        assert_eq!(nan.partial_cmp_object(one.as_partial_ord_object()), None);
+        assert_eq!(nan.partial_cmp(&nan), None);
+        assert_eq!(
+            [*nan < *one, *nan > *one, *nan <= *one, *nan >= *one],
+            [false; 4]
+        );
+        let (nan, one) = (Obj(nan), Obj(one));
+        assert_eq!(nan.partial_cmp(&one), None);
+        assert_eq!([one < nan, one > nan, one <= nan, one >= nan], [false; 4]);
+    }
+
+    #[test]
+    fn eq_skips_comparison_for_same_object() {
+        #[derive(Eq)]
+        struct NeverCompared;
+        impl PartialEq for NeverCompared {
+            fn eq(&self, _: &Self) -> bool {
+                panic!("should not be compared")
+            }
+        }
+        let x: Box<dyn EqObj> = Box::new(NeverCompared);
+        assert!(*x == *x);
+        let y: Box<dyn OrdObj> = Box::new(0);
+        assert!(*y == *y);
+
+        let nan: Box<dyn PartialEqObj> = Box::new(f64::NAN);
+        assert!(*nan != *nan);
+    }
+
+    #[test]
+    fn ne_is_forwarded() {
+        struct OnlyNe;
+        impl PartialEq for OnlyNe {
+            fn eq(&self, _: &Self) -> bool {
+                panic!("eq should not be called")
+            }
+
+            #[allow(clippy::partialeq_ne_impl)]
+            fn ne(&self, _: &Self) -> bool {
+                true
+            }
+        }
+        let x: Box<dyn PartialEqObj> = Box::new(OnlyNe);
+        let y: Box<dyn PartialEqObj> = Box::new(OnlyNe);
+        assert!(*x != *y);
+        assert!(Obj(x) != Obj(y));
+    }
+
+    #[test]
+    fn eq_for_borrowing_wrappers() {
+        struct View<'a>(&'a dyn EqObj);
+        impl PartialEq for View<'_> {
+            fn eq(&self, other: &Self) -> bool {
+                obj_eq(self.0, other.0)
+            }
+        }
+        impl_eq!(View<'_>);
+
+        fn equal<T: Eq>(a: T, b: T) -> bool {
+            a == b
+        }
+        let (a, b) = (1, 1);
+        assert!(equal(View(&a), View(&b)));
+    }
+
     mod obj_tests {
+        use std::hash::Hash;
+
         use crate::*;
         /// compiler test: hash
         trait MyHash: HashObj {}
@@ -343,6 +712,14 @@ mod test {
             assert_eq!(a, d);
         }
 
+        #[test]
+        fn wrapped_obj_box_dyn_custom_partial_eq() {
+            assert!(
+                MyPartialEqWrapper(Obj(Box::new(0) as Box<dyn MyPartialEq>))
+                    == MyPartialEqWrapper(Obj(Box::new(0) as Box<dyn MyPartialEq>))
+            );
+        }
+
         #[test]
         fn obj_box_dyn_custom_eq() {
             assert_eq!(
@@ -381,6 +758,8 @@ mod test {
     }
 
     mod impl_tests {
+        use std::hash::Hash;
+
         use crate::*;
         trait MyTrait: HashObj + EqObj + PartialEqObj {}
         impl<T> MyTrait for T where T: Hash + Eq + PartialEq + 'static {}
@@ -388,6 +767,10 @@ mod test {
         impl_hash!(dyn MyTrait);
         impl_eq!(dyn MyTrait);
         impl_partial_eq!(dyn MyTrait);
+        impl_partial_eq_concrete!(dyn MyTrait => i32, &'static str, dyn OtherTrait);
+
+        trait OtherTrait: PartialEqObj {}
+        impl<T> OtherTrait for T where T: PartialEq + 'static {}
 
         #[test]
         fn box_dyn_custom_eq() {
@@ -398,6 +781,277 @@ mod test {
                 panic!("should not be equal");
             }
         }
+
+        #[cfg(feature = "alloc")]
+        #[test]
+        fn box_dyn_custom_eq_ref_dyn_custom() {
+            let boxed: Box<dyn MyTrait> = Box::new(0);
+            let zero: &dyn MyTrait = &0;
+            let one: &dyn MyTrait = &1;
+            assert!(boxed == zero);
+            assert!(zero == boxed);
+            assert!(boxed != one);
+            assert!(one != boxed);
+        }
+
+        #[test]
+        fn dyn_custom_eq_concrete() {
+            let boxed: Box<dyn MyTrait> = Box::new(0);
+            assert!(*boxed == 0);
+            assert!(0 == *boxed);
+            assert!(*boxed != 1);
+            assert!(*boxed != "0");
+            assert!("0" != *boxed);
+        }
+
+        #[test]
+        fn dyn_custom_eq_other_dyn() {
+            let mine: &dyn MyTrait = &0;
+            let other: Box<dyn OtherTrait> = Box::new(0);
+            assert!(mine == &*other);
+            assert!(*other == *mine);
+            assert!(mine != &1 as &dyn OtherTrait);
+            assert!(&"0" as &dyn OtherTrait != mine);
+        }
+    }
+
+    /// The impl header syntax of the `impl_*!` macros, for generics that the
+    /// shorthand can't express.
+    mod header_tests {
+        use std::{collections::BTreeSet, hash::Hash, marker::PhantomData, ops::Deref};
+
+        use super::hash;
+        use crate::*;
+
+        trait Key: HashObj + OrdObj {}
+        impl<T> Key for T where T: Hash + Ord + 'static {}
+
+        /// Borrows its object, so it is not `'static`.
+        struct Borrowed<'a, K: ?Sized>(&'a K);
+
+        impl<K: ?Sized> Deref for Borrowed<'_, K> {
+            type Target = K;
+
+            fn deref(&self) -> &K {
+                self.0
+            }
+        }
+
+        impl_hash!(impl<'a, K: ?Sized + crate::HashObj> Borrowed<'a, K>);
+        impl_partial_eq! {
+            impl<'a, K> Borrowed<'a, K> where K: ?Sized + crate::PartialEqObj;
+        }
+
+        struct Tagged<const N: usize>(Box<dyn Key>);
+
+        impl<const N: usize> Deref for Tagged<N> {
+            type Target = dyn Key;
+
+            fn deref(&self) -> &dyn Key {
+                &*self.0
+            }
+        }
+
+        impl_hash! {
+            impl<const N: usize> Tagged<N>;
+        }
+        impl_partial_eq!(impl<const N: usize> Tagged<N>);
+        impl_eq!(impl<const N: usize> Tagged<N>);
+        impl_partial_ord!(impl<const N: usize> Tagged<N>);
+        impl_ord!(impl<const N: usize> Tagged<N>);
+
+        /// Holds the first item of an iterator of keys.
+        struct Head<I: Iterator>(I::Item, PhantomData<I>);
+
+        impl<I: Iterator> Deref for Head<I> {
+            type Target = I::Item;
+
+            fn deref(&self) -> &I::Item {
+                &self.0
+            }
+        }
+
+        impl_hash!(impl<I: Iterator<Item = Box<dyn Key>>> Head<I>);
+        impl_partial_eq! {
+            impl<I> Head<I> where I: Iterator<Item = Box<dyn Key>> + 'static;
+            impl Plain;
+        }
+
+        struct Plain(Box<dyn Key>);
+
+        impl Deref for Plain {
+            type Target = dyn Key;
+
+            fn deref(&self) -> &dyn Key {
+                &*self.0
+            }
+        }
+
+        impl_hash!(impl Plain where Box<dyn Key>: Deref<Target = dyn Key>;);
+
+        /// Uses the shorthand with a generic bound after a `+`.
+        struct Shared<T>(T);
+
+        impl<T: Deref> Deref for Shared<T> {
+            type Target = T::Target;
+
+            fn deref(&self) -> &T::Target {
+                &self.0
+            }
+        }
+
+        impl_hash!(Shared<T> where <T: Clone + Deref<Target=X>, X: HashObj + ?Sized>);
+
+        #[test]
+        fn lifetimes_and_bounds() {
+            let (a, b) = (1, 1);
+            assert!(Borrowed(&a) == Borrowed(&b));
+            assert!(Borrowed(&a) != Borrowed(&2));
+            assert_eq!(hash(Borrowed(&a)), hash(1));
+            #[cfg(feature = "alloc")]
+            {
+                let boxed: Box<Borrowed<'_, i32>> = Box::new(Borrowed(&a));
+                assert!(boxed == &Borrowed(&b));
+            }
+
+            let head = |key: u8| -> Head<std::vec::IntoIter<Box<dyn Key>>> {
+                Head(Box::new(key) as _, PhantomData)
+            };
+            assert!(head(1) == head(1));
+            assert!(head(1) != head(2));
+            assert_eq!(hash(head(1)), hash(1u8));
+        }
+
+        #[test]
+        fn const_generics() {
+            let set: BTreeSet<Tagged<3>> = [2, 1, 2].map(|x| Tagged(Box::new(x))).into();
+            let values: Vec<_> = set.iter().map(hash).collect();
+            assert_eq!(values, [hash(1), hash(2)]);
+        }
+
+        #[test]
+        fn without_generics() {
+            assert_eq!(hash(Plain(Box::new("a"))), hash("a"));
+            assert!(Plain(Box::new("a")) != Plain(Box::new("b")));
+            assert_eq!(hash(Shared(std::rc::Rc::new(1))), hash(1));
+        }
+    }
+
+    /// Randomized checks that hashing and equality give the same results no
+    /// matter how many layers of erasure and wrapping a value is behind.
+    mod consistency_tests {
+        use std::{hash::Hash, rc::Rc};
+
+        use super::hash;
+        use crate::*;
+
+        trait Key: HashObj + EqObj + std::fmt::Debug {}
+        impl<T> Key for T where T: Hash + Eq + std::fmt::Debug + 'static {}
+        impl_hash!(dyn Key);
+        impl_partial_eq!(dyn Key);
+        impl_eq!(dyn Key);
+
+        /// xorshift64, so failures are reproducible without extra dependencies.
+        struct Rng(u64);
+
+        impl Rng {
+            fn next(&mut self) -> u64 {
+                self.0 ^= self.0 << 13;
+                self.0 ^= self.0 >> 7;
+                self.0 ^= self.0 << 17;
+                self.0
+            }
+
+            fn below(&mut self, n: u64) -> u64 {
+                self.next() % n
+            }
+
+            fn string(&mut self) -> String {
+                (0..self.below(4))
+                    .map(|_| ['a', 'b'][self.below(2) as usize])
+                    .collect()
+            }
+        }
+
+        fn random_key(rng: &mut Rng) -> Box<dyn Key> {
+            match rng.below(5) {
+                0 => Box::new(rng.below(4)),
+                1 => Box::new(rng.string()),
+                2 => Box::new((rng.below(3) as u8, Some(rng.below(3) as i16))),
+                3 => Box::new(
+                    (0..rng.below(3))
+                        .map(|_| rng.below(2) as u8)
+                        .collect::<Vec<_>>(),
+                ),
+                _ => Box::new(Obj(random_key(rng))),
+            }
+        }
+
+        fn nest(key: Box<dyn Key>) -> Box<dyn Key> {
+            Box::new(Obj(key))
+        }
+
+        /// Wrapper stacks that should hash like the key itself. Equality is
+        /// only expected to agree between stacks of the same shape, since
+        /// different shapes have different concrete types.
+        fn layers(key: Rc<dyn Key>) -> Vec<Box<dyn Key>> {
+            vec![
+                Box::new(Obj(key.clone())),
+                Box::new(Obj(Box::new(Obj(key.clone())) as Box<dyn Key>)),
+                Box::new(Obj(Rc::new(
+                    Obj(Box::new(Obj(key.clone())) as Box<dyn Key>),
+                ))),
+            ]
+        }
+
+        fn check<T: Hash + Eq + std::fmt::Debug + Clone + 'static>(a: T, b: T) {
+            let direct_hash = hash(&a);
+            let erased: &(dyn HashObj + 'static) = &a;
+            assert_eq!(direct_hash, hash(erased));
+            #[cfg(feature = "alloc")]
+            assert_eq!(direct_hash, hash(&*a.clone().to_hash_object()));
+            assert_eq!(direct_hash, hash(Obj(Box::new(a.clone()) as Box<dyn Key>)));
+            let direct_eq = a == b;
+            assert_eq!(direct_eq, *a.as_eq_object() == *b.as_eq_object());
+            assert_eq!(
+                direct_eq,
+                Obj(Box::new(a.clone())) == Obj(Box::new(b.clone()))
+            );
+
+            let (a, b): (Rc<dyn Key>, Rc<dyn Key>) = (Rc::new(a), Rc::new(b));
+            for (x, y) in layers(a).into_iter().zip(layers(b)) {
+                assert_eq!(direct_hash, hash(&*x), "{x:?}");
+                assert_eq!(direct_eq, *x == *y, "{x:?} {y:?}");
+                let (x, y) = (Obj(x), Obj(y));
+                assert_eq!(direct_eq, x == y, "{x:?} {y:?}");
+            }
+        }
+
+        #[test]
+        fn random_values() {
+            let mut rng = Rng(0x5eed);
+            for _ in 0..500 {
+                check(rng.below(4), rng.below(4));
+                check(rng.string(), rng.string());
+                check((rng.below(3), rng.string()), (rng.below(3), rng.string()));
+            }
+        }
+
+        #[test]
+        fn random_erased_values() {
+            let mut rng = Rng(0xdecaf);
+            for _ in 0..500 {
+                let (a, b) = (random_key(&mut rng), random_key(&mut rng));
+                let eq = *a == *b;
+                assert_eq!(eq, *b == *a, "{a:?} {b:?}");
+                if eq {
+                    assert_eq!(hash(&*a), hash(&*b), "{a:?} {b:?}");
+                }
+                let (a_hash, a, b) = (hash(&*a), nest(a), nest(b));
+                assert_eq!(a_hash, hash(&*a), "{a:?}");
+                assert_eq!(eq, *a == *b, "{a:?} {b:?}");
+            }
+        }
     }
 }
 
@@ -0,0 +1,145 @@
+use std::{
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard},
+};
+
+use crate::{EqObj, HashObj, PartialEqObj};
+
+macro_rules! lock_obj {
+    ($(
+        $(#[$attr:meta])*
+        $Name:ident($Lock:ident, $Guard:ident): $lock:ident;
+    )*) => {$(
+        $(#[$attr])*
+        #[derive(Debug, Default)]
+        pub struct $Name<T>(pub $Lock<T>);
+
+        impl<T> $Name<T> {
+            pub fn new(item: T) -> Self {
+                Self($Lock::new(item))
+            }
+
+            pub fn into_inner(self) -> T {
+                self.0.into_inner().unwrap_or_else(PoisonError::into_inner)
+            }
+
+            /// Locks the object, even if another thread panicked while
+            /// holding the lock, like the comparisons do.
+            pub fn lock(&self) -> $Guard<'_, T> {
+                self.0.$lock().unwrap_or_else(PoisonError::into_inner)
+            }
+        }
+
+        impl<T> Deref for $Name<T> {
+            type Target = $Lock<T>;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl<T> From<T> for $Name<T> {
+            fn from(item: T) -> Self {
+                Self::new(item)
+            }
+        }
+
+        impl<T, X> PartialEq for $Name<T>
+        where
+            T: Deref<Target = X>,
+            X: PartialEqObj + ?Sized,
+        {
+            fn eq(&self, other: &Self) -> bool {
+                if core::ptr::eq(self, other) {
+                    let guard = self.lock();
+                    let target: &X = &guard;
+                    return target.eq_object(target.as_partial_eq_object());
+                }
+                let (first, second) = if (self as *const Self) < (other as *const Self) {
+                    (self.lock(), other.lock())
+                } else {
+                    let second = other.lock();
+                    (self.lock(), second)
+                };
+                let (a, b): (&X, &X) = (&first, &second);
+                a.eq_object(b.as_partial_eq_object())
+            }
+        }
+
+        impl<T, X> Eq for $Name<T>
+        where
+            T: Deref<Target = X>,
+            X: EqObj + ?Sized,
+        {
+        }
+
+        impl<T, X> Hash for $Name<T>
+        where
+            T: Deref<Target = X>,
+            X: HashObj + ?Sized,
+        {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                let guard = self.lock();
+                let target: &X = &guard;
+                target.hash_object(state);
+            }
+        }
+    )*};
+}
+
+lock_obj! {
+    /// Wrapper around a `Mutex` holding a pointer to an object, such as
+    /// `Mutex<Box<dyn MyTrait>>`, which implements `Hash` and `PartialEq` by
+    /// locking the mutex and delegating to the object, like `Obj`.
+    ///
+    /// The comparisons block until they get the locks, and ignore poisoning,
+    /// since the object is only read. Two different mutexes are always
+    /// locked in the order of their addresses, so comparing them from two
+    /// threads at once cannot deadlock, and comparing a wrapper with itself
+    /// only locks it once. Hashing or comparing a wrapper while holding its
+    /// lock still deadlocks, as with any use of the mutex.
+    ///
+    /// Mutating the object changes its hash, so do not mutate it while it is
+    /// a key in a hashed collection.
+    MutexObj(Mutex, MutexGuard): lock;
+
+    /// Wrapper around a `RwLock` holding a pointer to an object, like
+    /// `MutexObj`, whose comparisons take read locks.
+    RwLockObj(RwLock, RwLockReadGuard): read;
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashSet, sync::Arc};
+
+    use crate::*;
+
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn dedup_shared_objects() {
+        let values = [1, 2, 1].map(|x| Arc::new(MutexObj::new(Box::new(x) as Box<dyn KeyObj>)));
+        let set: HashSet<_> = values.iter().cloned().collect();
+        assert_eq!(set.len(), 2);
+        assert!(values[0] == values[0]);
+        *values[2].lock() = Box::new(3);
+        assert!(values[0] != values[2]);
+
+        let threads: Vec<_> = (0..4)
+            .map(|i| {
+                let (a, b) = (values[i % 2].clone(), values[1 - i % 2].clone());
+                std::thread::spawn(move || (0..100).all(|_| a != b))
+            })
+            .collect();
+        assert!(threads.into_iter().all(|t| t.join().unwrap()));
+    }
+
+    #[test]
+    fn rwlock() {
+        let a = RwLockObj::new(Box::new(1.5) as Box<dyn PartialEqObj>);
+        assert!(a == RwLockObj::new(Box::new(1.5)));
+        let nan = RwLockObj::new(Box::new(f64::NAN) as Box<dyn PartialEqObj>);
+        assert!(nan != nan);
+        assert_eq!((*a.into_inner()).as_any().downcast_ref(), Some(&1.5));
+    }
+}
@@ -0,0 +1,151 @@
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use crate::{EqObj, HashObj, OrdObj, PartialEqObj, PartialOrdObj};
+
+macro_rules! marked_obj {
+    ($(
+        $(#[$attr:meta])*
+        $Name:ident: $Marker:ident;
+    )*) => {$(
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $Name<T: $Marker>(T);
+
+        impl<T: $Marker> $Name<T> {
+            pub fn new(item: T) -> Self {
+                Self(item)
+            }
+
+            pub fn into_inner(self) -> T {
+                self.0
+            }
+        }
+
+        impl<T: $Marker> Deref for $Name<T> {
+            type Target = T;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl<T: $Marker> From<T> for $Name<T> {
+            fn from(item: T) -> Self {
+                Self(item)
+            }
+        }
+
+        impl<T, X> PartialEq for $Name<T>
+        where
+            T: $Marker + Deref<Target = X>,
+            X: PartialEqObj + ?Sized,
+        {
+            fn eq(&self, other: &Self) -> bool {
+                let (a, b): (&X, &X) = (&self.0, &other.0);
+                a.eq_object(b.as_partial_eq_object())
+            }
+        }
+
+        impl<T, X> Eq for $Name<T>
+        where
+            T: $Marker + Deref<Target = X>,
+            X: EqObj + ?Sized,
+        {
+        }
+
+        impl<T, X> PartialOrd for $Name<T>
+        where
+            T: $Marker + Deref<Target = X>,
+            X: PartialOrdObj + ?Sized,
+        {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                let (a, b): (&X, &X) = (&self.0, &other.0);
+                a.partial_cmp_object(b.as_partial_ord_object())
+            }
+        }
+
+        impl<T, X> Ord for $Name<T>
+        where
+            T: $Marker + Deref<Target = X>,
+            X: OrdObj + ?Sized,
+        {
+            fn cmp(&self, other: &Self) -> Ordering {
+                let (a, b): (&X, &X) = (&self.0, &other.0);
+                a.cmp_object(b.as_ord_object())
+            }
+        }
+
+        impl<T, X> Hash for $Name<T>
+        where
+            T: $Marker + Deref<Target = X>,
+            X: HashObj + ?Sized,
+        {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                let target: &X = &self.0;
+                target.hash_object(state);
+            }
+        }
+    )*};
+}
+
+marked_obj! {
+    /// Wrapper like `Obj` that can only hold a pointer which is `Send`, such
+    /// as `Box<dyn MyTrait + Send>`, so an API can ask for "a hashable object
+    /// that may move to another thread" with a single type, such as
+    /// `SendObj<Box<dyn HashObj + Send>>`.
+    ///
+    /// ```rust ignore
+    /// let key = SendObj::new(Box::new(5) as Box<dyn HashObj + Send>);
+    /// std::thread::spawn(move || set.insert(key));
+    /// ```
+    SendObj: Send;
+
+    /// Wrapper like `Obj` that can only hold a pointer which is `Sync`, such
+    /// as `&(dyn MyTrait + Sync)` or `Arc<dyn MyTrait + Send + Sync>`, so it
+    /// can be shared between threads. See `SendObj`.
+    SyncObj: Sync;
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(feature = "std")]
+    use std::collections::HashSet;
+    use std::{cmp::Ordering, sync::Arc};
+
+    use crate::*;
+
+    #[cfg(feature = "std")]
+    fn insert_elsewhere(key: SendObj<Box<dyn HashObj + Send>>) -> u64 {
+        std::thread::spawn(move || key.hash_object_u64())
+            .join()
+            .unwrap()
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn send_obj() {
+        let key = SendObj::new(5.to_hash_object_send());
+        assert_eq!(insert_elsewhere(key), 5.hash_object_u64());
+
+        let set: HashSet<SendObj<Box<dyn KeyObj>>> = [1, 2, 1]
+            .map(|x| SendObj::new(Box::new(x) as Box<dyn KeyObj>))
+            .into();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn sync_obj() {
+        let a = SyncObj::new(Arc::new(1) as Arc<dyn OrdObj + Send + Sync>);
+        let b = SyncObj::from(Arc::new(2) as Arc<dyn OrdObj + Send + Sync>);
+        let shared = Arc::new(a.clone());
+        let compared = std::thread::spawn(move || (*shared).cmp(&b))
+            .join()
+            .unwrap();
+        assert_eq!(compared, Ordering::Less);
+        assert!(a == SyncObj::new(Arc::new(1) as Arc<dyn OrdObj + Send + Sync>));
+    }
+}
@@ -0,0 +1,98 @@
+//! Process-wide counters of the comparisons that usually indicate misuse,
+//! such as looking up a key of the wrong type, enabled by the `metrics`
+//! feature.
+//!
+//! Unlike the mismatch hook, the counters cost one atomic increment and
+//! don't run any code, so they can stay enabled in staging environments,
+//! where a rising count can be exported and alerted on.
+//!
+//! ```rust ignore
+//! let before = object_safe::metrics::snapshot();
+//! handle(request);
+//! let delta = object_safe::metrics::snapshot() - before;
+//! if delta.eq_mismatches > 0 { log::warn!("{delta:?}"); }
+//! ```
+//!
+//! The counts from all threads are added up with relaxed atomics, so a
+//! snapshot taken while other threads compare objects may be slightly stale.
+
+use core::{
+    ops::Sub,
+    sync::atomic::{AtomicU64, Ordering::Relaxed},
+};
+
+/// A snapshot of the counters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Metrics {
+    /// Calls to `eq_object` or `ne_object` with objects of different
+    /// concrete types.
+    pub eq_mismatches: u64,
+    /// Calls to `partial_cmp_object` or `cmp_object` with objects of
+    /// different concrete types, which fell back to ordering by type.
+    pub cmp_fallbacks: u64,
+    /// Lookups in the dyn hashed collections that found a stored key with
+    /// the same hash, and so had to call `eq_object`.
+    pub full_eq_after_hash: u64,
+}
+
+/// The counts between two snapshots.
+impl Sub for Metrics {
+    type Output = Metrics;
+
+    fn sub(self, earlier: Metrics) -> Metrics {
+        Metrics {
+            eq_mismatches: self.eq_mismatches.saturating_sub(earlier.eq_mismatches),
+            cmp_fallbacks: self.cmp_fallbacks.saturating_sub(earlier.cmp_fallbacks),
+            full_eq_after_hash: self
+                .full_eq_after_hash
+                .saturating_sub(earlier.full_eq_after_hash),
+        }
+    }
+}
+
+pub(crate) static EQ_MISMATCHES: AtomicU64 = AtomicU64::new(0);
+pub(crate) static CMP_FALLBACKS: AtomicU64 = AtomicU64::new(0);
+pub(crate) static FULL_EQ_AFTER_HASH: AtomicU64 = AtomicU64::new(0);
+
+#[inline]
+pub(crate) fn count(counter: &AtomicU64) {
+    counter.fetch_add(1, Relaxed);
+}
+
+/// Returns the current counts.
+pub fn snapshot() -> Metrics {
+    Metrics {
+        eq_mismatches: EQ_MISMATCHES.load(Relaxed),
+        cmp_fallbacks: CMP_FALLBACKS.load(Relaxed),
+        full_eq_after_hash: FULL_EQ_AFTER_HASH.load(Relaxed),
+    }
+}
+
+/// Sets all the counters back to zero. Prefer subtracting snapshots when
+/// several parts of the program read the counters.
+pub fn reset() {
+    for counter in [&EQ_MISMATCHES, &CMP_FALLBACKS, &FULL_EQ_AFTER_HASH] {
+        counter.store(0, Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{collections::DynHashSet, *};
+
+    #[test]
+    fn counts_misuse() {
+        let before = snapshot();
+        let (a, b): (Box<dyn OrdObj>, Box<dyn OrdObj>) = (Box::new(1u8), Box::new("one"));
+        assert!(*a != *b);
+        let _ = (*a).cmp(&*b);
+        let mut set = DynHashSet::<dyn KeyObj>::new();
+        set.insert(Box::new(1u8));
+        assert!(set.contains(&1u8));
+        let delta = snapshot() - before;
+        assert!(delta.eq_mismatches >= 1);
+        assert!(delta.cmp_fallbacks >= 1);
+        assert!(delta.full_eq_after_hash >= 1);
+    }
+}
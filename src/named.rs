@@ -0,0 +1,136 @@
+use core::{
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
+
+use crate::{EqObj, HashObj, PartialEqObj};
+
+/// Wrapper that pairs an object with a name, such as the name a plugin is
+/// registered under, for logging, routing, or as a serialization tag.
+///
+/// The name is part of the `Hash` and `PartialEq` implementations: two named
+/// objects are only equal if their names and their objects are equal.
+///
+/// ```rust ignore
+/// registry::register_name::<Resize>("resize");
+/// let plugin = NamedObj::registered(Box::new(Resize(2)) as Box<dyn Plugin>).unwrap();
+/// log::info!("loaded {}", plugin.name());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct NamedObj<T> {
+    name: &'static str,
+    inner: T,
+}
+
+impl<T> NamedObj<T> {
+    pub fn new(name: &'static str, inner: T) -> Self {
+        Self { name, inner }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    /// Splits the wrapper into its name and object.
+    pub fn into_parts(self) -> (&'static str, T) {
+        (self.name, self.inner)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, X> NamedObj<T>
+where
+    T: Deref<Target = X>,
+    X: PartialEqObj + ?Sized,
+{
+    /// Names the object with the name registered for its concrete type with
+    /// [`register_name`](crate::registry::register_name), or returns None if
+    /// no name is registered for it.
+    pub fn registered(inner: T) -> Option<Self> {
+        let target: &X = &inner;
+        let name = crate::registry::registered_name(target.as_any())?;
+        Some(Self { name, inner })
+    }
+}
+
+impl<T> Deref for NamedObj<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for NamedObj<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<T, X> Hash for NamedObj<T>
+where
+    T: Deref<Target = X>,
+    X: HashObj + ?Sized,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        let target: &X = &self.inner;
+        target.hash_object(state);
+    }
+}
+
+impl<T, X> PartialEq for NamedObj<T>
+where
+    T: Deref<Target = X>,
+    X: PartialEqObj + ?Sized,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b): (&X, &X) = (&self.inner, &other.inner);
+        self.name == other.name && a.eq_object(b.as_partial_eq_object())
+    }
+}
+
+impl<T, X> Eq for NamedObj<T>
+where
+    T: Deref<Target = X>,
+    X: EqObj + ?Sized,
+{
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::*;
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn name_is_part_of_the_key() {
+        let a = NamedObj::new("a", Box::new(1) as Box<dyn KeyObj>);
+        let b = NamedObj::new("b", Box::new(1) as Box<dyn KeyObj>);
+        assert!(a != b);
+        assert_eq!(a.name(), "a");
+        let set: HashSet<_> = [a, b, NamedObj::new("a", Box::new(1) as Box<dyn KeyObj>)].into();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn registered_names() {
+        #[derive(Hash, PartialEq, Eq)]
+        struct Resize(u32);
+
+        #[derive(Hash, PartialEq, Eq)]
+        struct Crop(u32);
+
+        registry::register_name::<Resize>("resize");
+        let resize = NamedObj::registered(Box::new(Resize(2)) as Box<dyn KeyObj>).unwrap();
+        assert_eq!(resize.name(), "resize");
+        assert!((**resize).as_any().is::<Resize>());
+        assert!(NamedObj::registered(Box::new(Crop(2)) as Box<dyn KeyObj>).is_none());
+    }
+}
@@ -1,8 +1,26 @@
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
 use core::ops::{Deref, DerefMut};
 
 /// Convenient wrapper struct that implements any of the traits supported by
 /// this crate if the contained type derefs to something implementing the
 /// `**Obj` analog trait.
+///
+/// The pointer itself doesn't need to be `'static`, so `Obj` also makes the
+/// borrowed views of standard containers comparable, such as the `Ref` and
+/// `RefMut` of a `RefCell`, lock guards, and `Cow`:
+///
+/// ```rust ignore
+/// let a: RefCell<Box<dyn MyKey>> = RefCell::new(Box::new(5));
+/// let b: RefCell<Box<dyn MyKey>> = RefCell::new(Box::new(5));
+/// assert!(Obj(a.borrow()) == Obj(b.borrow()));
+/// ```
+///
+/// `ManuallyDrop` needs no wrapper, since it has the impls of its contents,
+/// and neither does `Pin` for trait objects with impls from the macros of
+/// this crate. For other objects, `Obj` sees through `Pin` like any pointer,
+/// such as a `Pin<Box<dyn MyTask>>` task key where `MyTask` extends `Future`,
+/// `HashObj` and `EqObj`, without unpinning it.
 #[derive(Clone, Copy, Debug)]
 pub struct Obj<T>(pub T);
 
@@ -12,6 +30,16 @@ impl<T> Obj<T> {
     pub fn new(item: T) -> Self {
         Obj(item)
     }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Obj<T> {
+    fn from(item: T) -> Self {
+        Obj(item)
+    }
 }
 
 impl<T> Deref for Obj<T> {
@@ -27,3 +55,115 @@ impl<T> DerefMut for Obj<T> {
         &mut self.0
     }
 }
+
+/// Postfix constructors for `Obj`, implemented for every sized type, so call
+/// sites read `value.obj()` rather than wrapping and casting by hand.
+///
+/// ```rust ignore
+/// let shared = (Rc::new(5) as Rc<dyn MyTrait>).obj();
+/// let key = 5.to_obj::<dyn OrdObj + Send>();
+/// assert!(key == Box::new(5).into_obj());
+/// ```
+pub trait ToObj: Sized {
+    /// Wraps the value as it is, such as an existing `Rc<dyn MyTrait>`.
+    fn obj(self) -> Obj<Self> {
+        Obj(self)
+    }
+
+    /// Boxes the value as the trait object `D`, like
+    /// [`to_object`](crate::ToMarkedObject::to_object), and wraps the box.
+    #[cfg(feature = "alloc")]
+    fn to_obj<D: ?Sized>(self) -> Obj<Box<D>>
+    where
+        Box<Self>: Into<Box<D>>,
+    {
+        Obj(Box::new(self).into())
+    }
+
+    /// Converts a value that is already boxed, such as a `Box<i32>`, into the
+    /// trait object `D`, reusing its allocation.
+    #[cfg(feature = "alloc")]
+    fn into_obj<D: ?Sized>(self) -> Obj<Box<D>>
+    where
+        Self: Into<Box<D>>,
+    {
+        Obj(self.into())
+    }
+}
+
+impl<T> ToObj for T {}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use std::{
+        borrow::Cow,
+        cell::RefCell,
+        collections::{HashMap, HashSet},
+        hash::Hash,
+        mem::ManuallyDrop,
+        pin::Pin,
+        rc::Rc,
+        sync::{Arc, Mutex},
+    };
+    #[cfg(feature = "std")]
+    use std::{collections::hash_map::DefaultHasher, hash::Hasher};
+
+    use crate::*;
+
+    #[test]
+    fn postfix_constructors() {
+        let rc: Rc<dyn OrdObj> = Rc::new(5);
+        let address = Rc::as_ptr(&rc);
+        let shared = rc.obj();
+        assert!(core::ptr::addr_eq(Rc::as_ptr(&shared), address));
+
+        let key = 5.to_obj::<dyn OrdObj + Send>();
+        let boxed = Box::new(5);
+        let address: *const i32 = &*boxed;
+        let same = boxed.into_obj::<dyn OrdObj + Send>();
+        assert!(core::ptr::addr_eq(&**same, address));
+        assert!(key == same);
+        assert!(key < 6.to_obj());
+    }
+
+    #[test]
+    fn borrowed_views() {
+        let cell: RefCell<Box<dyn KeyObj>> = RefCell::new(Box::new(5));
+        let other: RefCell<Box<dyn KeyObj>> = RefCell::new(Box::new(5));
+        assert!(Obj(cell.borrow()) == Obj(other.borrow()));
+        assert!(Obj(cell.borrow_mut()) == Obj(other.borrow_mut()));
+
+        let a: Mutex<Box<dyn OrdObj>> = Mutex::new(Box::new(1));
+        let b: Mutex<Box<dyn OrdObj>> = Mutex::new(Box::new(2));
+        assert!(Obj(a.lock().unwrap()) < Obj(b.lock().unwrap()));
+
+        let key = 7u32;
+        let borrowed = Cow::Borrowed(&key);
+        let owned: Cow<'_, u32> = Cow::Owned(7);
+        assert!(Obj(borrowed.clone()) == Obj(owned));
+        #[cfg(feature = "std")]
+        {
+            let mut hasher = DefaultHasher::new();
+            Obj(borrowed).hash(&mut hasher);
+            assert_eq!(hasher.finish(), 7u32.hash_object_u64());
+        }
+
+        let kept = ManuallyDrop::new(Box::new(5) as Box<dyn KeyObj>);
+        assert!(kept == ManuallyDrop::new(Box::new(5) as Box<dyn KeyObj>));
+    }
+
+    #[test]
+    fn pinned_objects() {
+        trait Task: HashObj + EqObj {}
+        impl<T: Hash + Eq + 'static> Task for T {}
+
+        let mut tasks: HashMap<Obj<Pin<Box<dyn Task>>>, u8> = HashMap::new();
+        tasks.insert(Obj(Box::pin(1)), 1);
+        tasks.insert(Obj(Box::pin("two")), 2);
+        assert_eq!(tasks[&Obj(Box::pin("two") as Pin<Box<dyn Task>>)], 2);
+
+        let pinned = || -> Pin<Arc<dyn KeyObj>> { Arc::pin(1) };
+        let shared: HashSet<_> = [pinned(), pinned()].into();
+        assert_eq!(shared.len(), 1);
+    }
+}
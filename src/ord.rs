@@ -0,0 +1,176 @@
+//! `PartialOrdObj` and `OrdObj`, the object-safe versions of `PartialOrd` and
+//! `Ord`, and the macros implementing the std traits with them.
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{cmp::Ordering, ops::Deref};
+
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use crate::{impl_with_auto_traits, report_mismatch, upcast_any, EqObj, Obj, PartialEqObj};
+
+/// Object-safe version of PartialOrd
+///
+/// Objects with different concrete types are ordered by their `TypeId`, so
+/// `partial_cmp_object` only returns `None` when the concrete `PartialOrd`
+/// implementation does. See `OrdObj` for the details of the cross-type order.
+///
+/// Values of the same type are compared with their own `PartialOrd`
+/// implementation, so floating point semantics survive erasure: comparing
+/// `f64::NAN` with anything of type `f64` through `dyn PartialOrdObj` or `Obj`
+/// returns `None`, and all of `<`, `<=`, `>` and `>=` are false. If you need
+/// floats in ordered collections, enable the `total-cmp` feature and wrap them
+/// in `TotalCmp`, which implements `Ord` using `total_cmp`.
+pub trait PartialOrdObj: PartialEqObj {
+    fn partial_cmp_object(&self, other: &dyn PartialOrdObj) -> Option<Ordering>;
+    fn as_partial_ord_object(&self) -> &dyn PartialOrdObj;
+    #[cfg(feature = "alloc")]
+    fn to_partial_ord_object(self) -> Box<dyn PartialOrdObj>;
+}
+
+impl<T> PartialOrdObj for T
+where
+    T: PartialOrd + PartialEqObj,
+{
+    fn partial_cmp_object(&self, other: &dyn PartialOrdObj) -> Option<Ordering> {
+        match upcast_any(other).downcast_ref::<Self>() {
+            Some(other) => self.partial_cmp(other),
+            None => Some(cross_partial_cmp(self, other)),
+        }
+    }
+
+    fn as_partial_ord_object(&self) -> &dyn PartialOrdObj {
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    fn to_partial_ord_object(self) -> Box<dyn PartialOrdObj> {
+        Box::new(self)
+    }
+}
+
+/// Orders objects of different concrete types by their `TypeId`, reporting
+/// the mismatch.
+#[cold]
+#[inline(never)]
+fn cross_partial_cmp(left: &dyn PartialOrdObj, right: &dyn PartialOrdObj) -> Ordering {
+    #[cfg(feature = "metrics")]
+    metrics::count(&metrics::CMP_FALLBACKS);
+    report_mismatch!(PartialCmp, left, right);
+    upcast_any(left).type_id().cmp(&upcast_any(right).type_id())
+}
+
+impl<T: Deref<Target = X>, X: PartialOrdObj + ?Sized> PartialOrd for Obj<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.deref()
+            .partial_cmp_object(other.deref().as_partial_ord_object())
+    }
+}
+impl_with_auto_traits!(crate::impl_partial_ord: dyn PartialOrdObj);
+
+macro_rules! impl_total_partial_ord {
+    ($($Type:ty),*$(,)?) => {$(
+        impl PartialOrd for $Type {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+    )*};
+}
+
+impl_with_auto_traits!(impl_total_partial_ord: dyn OrdObj);
+
+#[macro_export]
+macro_rules! impl_partial_ord {
+    (@impl [$($G:tt)*] [$Type:ty] [$($W:tt)*]) => {
+        #[allow(clippy::non_canonical_partial_ord_impl)]
+        impl<$($G)*> ::core::cmp::PartialOrd for $Type where $($W)* {
+            fn partial_cmp(&self, other: &Self) -> Option<::core::cmp::Ordering> {
+                use ::core::ops::Deref as _;
+                self.deref().partial_cmp_object(other.deref().as_partial_ord_object())
+            }
+        }
+    };
+    (impl $($input:tt)*) => {
+        $crate::__impl_header! { $crate::impl_partial_ord; impl $($input)* }
+    };
+    ($($input:tt)*) => {
+        $crate::__legacy_header! { $crate::impl_partial_ord; 'static; $($input)* }
+    };
+}
+
+/// Object-safe version of Ord
+///
+/// Objects with the same concrete type are ordered by that type's `Ord`
+/// implementation. Objects with different concrete types are ordered by their
+/// `TypeId`, which makes the order total over all implementors, so mixed
+/// types can be stored in a `BTreeSet<Box<dyn OrdObj>>`. The relative order of
+/// two different types is arbitrary and may change between builds of your
+/// program, but it is consistent for the lifetime of the process.
+///
+/// Comparators registered in the [`registry`](crate::registry) are not
+/// consulted, here or by `==`, so objects of different types are never equal
+/// and equality agrees with the order.
+pub trait OrdObj: EqObj + PartialOrdObj {
+    fn cmp_object(&self, other: &dyn OrdObj) -> Ordering;
+    fn as_ord_object(&self) -> &dyn OrdObj;
+    #[cfg(feature = "alloc")]
+    fn to_ord_object(self) -> Box<dyn OrdObj>;
+}
+
+impl<T> OrdObj for T
+where
+    T: Ord + EqObj + PartialOrdObj,
+{
+    fn cmp_object(&self, other: &dyn OrdObj) -> Ordering {
+        match upcast_any(other).downcast_ref::<Self>() {
+            Some(other) => self.cmp(other),
+            None => cross_cmp(self, other),
+        }
+    }
+
+    fn as_ord_object(&self) -> &dyn OrdObj {
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    fn to_ord_object(self) -> Box<dyn OrdObj> {
+        Box::new(self)
+    }
+}
+
+/// Orders objects of different concrete types by their `TypeId`, reporting
+/// the mismatch.
+#[cold]
+#[inline(never)]
+fn cross_cmp(left: &dyn OrdObj, right: &dyn OrdObj) -> Ordering {
+    #[cfg(feature = "metrics")]
+    metrics::count(&metrics::CMP_FALLBACKS);
+    report_mismatch!(Cmp, left, right);
+    upcast_any(left).type_id().cmp(&upcast_any(right).type_id())
+}
+
+impl<T: Deref<Target = X>, X: OrdObj + ?Sized> Ord for Obj<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.deref().cmp_object(other.deref().as_ord_object())
+    }
+}
+impl_with_auto_traits!(crate::impl_ord: dyn OrdObj);
+
+#[macro_export]
+macro_rules! impl_ord {
+    (@impl [$($G:tt)*] [$Type:ty] [$($W:tt)*]) => {
+        impl<$($G)*> ::core::cmp::Ord for $Type where $($W)* {
+            fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                use ::core::ops::Deref as _;
+                self.deref().cmp_object(other.deref().as_ord_object())
+            }
+        }
+    };
+    (impl $($input:tt)*) => {
+        $crate::__impl_header! { $crate::impl_ord; impl $($input)* }
+    };
+    ($($input:tt)*) => {
+        $crate::__legacy_header! { $crate::impl_ord; 'static; $($input)* }
+    };
+}
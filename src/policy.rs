@@ -0,0 +1,238 @@
+//! Configurable behavior for comparisons between objects of different
+//! concrete types.
+//!
+//! By default, objects of different types are unequal, and ordered by their
+//! `TypeId`. Wrap an object in [`WithPolicy`] to choose what happens instead.
+
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use crate::{EqObj, HashObj, PartialEqObj, PartialOrdObj, TypeMismatch};
+
+/// Decides the result of comparing objects with different concrete types.
+///
+/// Comparators registered in the [`registry`](crate::registry) still take
+/// precedence over the policy for equality.
+pub trait MismatchPolicy {
+    /// Result of `==` for objects of different types.
+    fn eq(mismatch: TypeMismatch) -> bool;
+
+    /// Result of `partial_cmp` for objects of different types, given the order
+    /// of their `TypeId`s.
+    fn partial_cmp(mismatch: TypeMismatch, type_order: Ordering) -> Option<Ordering>;
+}
+
+/// The default behavior: objects of different types are unequal, and ordered
+/// by their `TypeId`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Unequal;
+
+impl MismatchPolicy for Unequal {
+    fn eq(_: TypeMismatch) -> bool {
+        false
+    }
+
+    fn partial_cmp(_: TypeMismatch, type_order: Ordering) -> Option<Ordering> {
+        Some(type_order)
+    }
+}
+
+/// Objects of different types are unequal, and `partial_cmp` returns `None`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Incomparable;
+
+impl MismatchPolicy for Incomparable {
+    fn eq(_: TypeMismatch) -> bool {
+        false
+    }
+
+    fn partial_cmp(_: TypeMismatch, _: Ordering) -> Option<Ordering> {
+        None
+    }
+}
+
+/// Comparing objects of different types panics.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Panic;
+
+impl MismatchPolicy for Panic {
+    fn eq(mismatch: TypeMismatch) -> bool {
+        panic!("{mismatch}")
+    }
+
+    fn partial_cmp(mismatch: TypeMismatch, _: Ordering) -> Option<Ordering> {
+        panic!("{mismatch}")
+    }
+}
+
+/// Like `Panic` when debug assertions are enabled, and like `Unequal`
+/// otherwise.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DebugPanic;
+
+impl MismatchPolicy for DebugPanic {
+    fn eq(mismatch: TypeMismatch) -> bool {
+        debug_assert!(false, "{mismatch}");
+        Unequal::eq(mismatch)
+    }
+
+    fn partial_cmp(mismatch: TypeMismatch, type_order: Ordering) -> Option<Ordering> {
+        debug_assert!(false, "{mismatch}");
+        Unequal::partial_cmp(mismatch, type_order)
+    }
+}
+
+/// Wrapper that behaves like `Obj`, except that comparisons between objects
+/// of different concrete types are decided by the policy `P`.
+///
+/// ```rust ignore
+/// let a = WithPolicy::<_, Panic>::new(Box::new(1) as Box<dyn MyTrait>);
+/// let b = WithPolicy::<_, Panic>::new(Box::new(1u64) as Box<dyn MyTrait>);
+/// a == b; // panics
+/// ```
+pub struct WithPolicy<T, P = Unequal>(pub T, PhantomData<fn() -> P>);
+
+impl<T, P> WithPolicy<T, P> {
+    pub fn new(item: T) -> Self {
+        WithPolicy(item, PhantomData)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Clone, P> Clone for WithPolicy<T, P> {
+    fn clone(&self) -> Self {
+        Self::new(self.0.clone())
+    }
+}
+
+impl<T: Copy, P> Copy for WithPolicy<T, P> {}
+
+impl<T: fmt::Debug, P> fmt::Debug for WithPolicy<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("WithPolicy").field(&self.0).finish()
+    }
+}
+
+impl<T, P> Deref for WithPolicy<T, P> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T, P> DerefMut for WithPolicy<T, P> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+fn mismatch<X: PartialEqObj + ?Sized>(a: &X, b: &X) -> Option<TypeMismatch> {
+    (a.as_any().type_id() != b.as_any().type_id()).then(|| TypeMismatch {
+        left: a.type_name(),
+        right: b.type_name(),
+    })
+}
+
+impl<T, X, P> PartialEq for WithPolicy<T, P>
+where
+    T: Deref<Target = X>,
+    X: PartialEqObj + ?Sized,
+    P: MismatchPolicy,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b): (&X, &X) = (&self.0, &other.0);
+        match mismatch(a, b) {
+            Some(mismatch) => {
+                if let Some(eq) = crate::registry::cross_eq(a.as_any(), b.as_any()) {
+                    return eq;
+                }
+                P::eq(mismatch)
+            }
+            None => a.eq_object(b.as_partial_eq_object()),
+        }
+    }
+}
+
+impl<T, X, P> Eq for WithPolicy<T, P>
+where
+    T: Deref<Target = X>,
+    X: EqObj + ?Sized,
+    P: MismatchPolicy,
+{
+}
+
+impl<T, X, P> PartialOrd for WithPolicy<T, P>
+where
+    T: Deref<Target = X>,
+    X: PartialOrdObj + ?Sized,
+    P: MismatchPolicy,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        let (a, b): (&X, &X) = (&self.0, &other.0);
+        match mismatch(a, b) {
+            Some(mismatch) => {
+                P::partial_cmp(mismatch, a.as_any().type_id().cmp(&b.as_any().type_id()))
+            }
+            None => a.partial_cmp_object(b.as_partial_ord_object()),
+        }
+    }
+}
+
+impl<T, X, P> Hash for WithPolicy<T, P>
+where
+    T: Deref<Target = X>,
+    X: HashObj + ?Sized,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let target: &X = &self.0;
+        target.hash_object(state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn obj<P>(x: impl PartialOrd + 'static) -> WithPolicy<Box<dyn PartialOrdObj>, P> {
+        WithPolicy::new(Box::new(x))
+    }
+
+    #[test]
+    fn unequal() {
+        assert!(obj::<Unequal>(1) == obj(1));
+        assert!(obj::<Unequal>(1) != obj("1"));
+        assert!(obj::<Unequal>(1).partial_cmp(&obj("1")).is_some());
+    }
+
+    #[test]
+    fn incomparable() {
+        assert!(obj::<Incomparable>(1) != obj("1"));
+        assert_eq!(obj::<Incomparable>(1).partial_cmp(&obj("1")), None);
+        assert_eq!(
+            obj::<Incomparable>(1).partial_cmp(&obj(2)),
+            Some(Ordering::Less)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot compare objects of different types: i32 and &str")]
+    fn panic() {
+        let _ = obj::<Panic>(1) == obj("1");
+    }
+
+    #[test]
+    fn debug_panic() {
+        let result = std::panic::catch_unwind(|| obj::<DebugPanic>(1) == obj("1"));
+        assert_eq!(result.is_err(), cfg!(debug_assertions));
+        assert!(obj::<DebugPanic>(1) == obj(1));
+    }
+}
@@ -0,0 +1,274 @@
+use alloc::{
+    alloc::{alloc, dealloc, handle_alloc_error},
+    vec::Vec,
+};
+use core::{
+    alloc::Layout,
+    cell::RefCell,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+};
+
+use crate::{EqObj, HashObj, OrdObj, PartialEqObj, PartialOrdObj};
+
+/// Pool that recycles the memory of the values it allocates, usually trait
+/// objects, for workloads that create and drop many short-lived objects of
+/// a few types, such as temporary keys built for each request.
+///
+/// Dropping a `PoolBox` drops its value and gives the memory back to the
+/// pool, which reuses it for the next value with the same layout, instead of
+/// freeing it. The memory is only freed by `clear`, or when the pool is
+/// dropped.
+///
+/// ```rust ignore
+/// let pool = ObjPool::new();
+/// for request in requests {
+///     let key: PoolBox<dyn MyKey> = pool.alloc_as(request.key(), |key| key as _);
+///     cache.get(&key);
+/// } // only the first key is actually allocated
+/// ```
+pub struct ObjPool {
+    /// Free blocks, grouped by layout.
+    free: RefCell<Vec<(Layout, Vec<NonNull<u8>>)>>,
+}
+
+/// Owning handle to a value allocated in an `ObjPool`, like a `Box`, which
+/// implements the standard traits through the corresponding traits of this
+/// crate, like `Obj`.
+pub struct PoolBox<'p, X: ?Sized> {
+    value: NonNull<X>,
+    pool: &'p ObjPool,
+}
+
+impl ObjPool {
+    pub fn new() -> Self {
+        Self {
+            free: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Moves the value into memory from the pool.
+    pub fn alloc<T>(&self, value: T) -> PoolBox<'_, T> {
+        self.alloc_as(value, |value| value)
+    }
+
+    /// Moves the value into memory from the pool, and converts it to `X`,
+    /// typically a trait object, with `coerce`, which is usually
+    /// `|value| value as _`, with the type of the result annotated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `coerce` returns a reference to anything else than the
+    /// whole value it is given.
+    pub fn alloc_as<T, X: ?Sized>(
+        &self,
+        value: T,
+        coerce: impl FnOnce(&mut T) -> &mut X,
+    ) -> PoolBox<'_, X> {
+        let layout = Layout::new::<T>();
+        let ptr = self.take(layout).cast::<T>();
+        // SAFETY: `ptr` is valid for writes of a `T`, and unused.
+        unsafe { ptr.as_ptr().write(value) };
+        let mut handle = PoolBox {
+            value: ptr,
+            pool: self,
+        };
+        let target: *mut X = coerce(&mut handle);
+        // SAFETY: `target` was derived from a reference, so it is not null.
+        let target = unsafe { NonNull::new_unchecked(target) };
+        // SAFETY: `target` is valid, since it was just derived from a
+        // reference into the value.
+        let target_layout = Layout::for_value(unsafe { target.as_ref() });
+        assert!(
+            ptr::addr_eq(target.as_ptr(), ptr.as_ptr()) && target_layout == layout,
+            "alloc_as must return the value it is given"
+        );
+        mem::forget(handle);
+        PoolBox {
+            value: target,
+            pool: self,
+        }
+    }
+
+    /// Number of free blocks that the pool keeps for reuse.
+    pub fn free_blocks(&self) -> usize {
+        self.free
+            .borrow()
+            .iter()
+            .map(|(_, blocks)| blocks.len())
+            .sum()
+    }
+
+    /// Frees the blocks that the pool keeps for reuse.
+    pub fn clear(&self) {
+        for (layout, blocks) in self.free.borrow_mut().drain(..) {
+            for block in blocks {
+                // SAFETY: The block was allocated with this layout, and is
+                // not in use.
+                unsafe { dealloc(block.as_ptr(), layout) };
+            }
+        }
+    }
+
+    /// Returns a block for the layout, reusing a free one if there is any.
+    fn take(&self, layout: Layout) -> NonNull<u8> {
+        if layout.size() == 0 {
+            // SAFETY: The alignment is a power of two, so it is not zero.
+            return unsafe { NonNull::new_unchecked(layout.align() as *mut u8) };
+        }
+        let reused = self
+            .free
+            .borrow_mut()
+            .iter_mut()
+            .find(|(free, _)| *free == layout)
+            .and_then(|(_, blocks)| blocks.pop());
+        reused.unwrap_or_else(|| {
+            // SAFETY: The layout has a nonzero size.
+            NonNull::new(unsafe { alloc(layout) }).unwrap_or_else(|| handle_alloc_error(layout))
+        })
+    }
+
+    /// Keeps a block that is no longer in use for reuse.
+    fn give_back(&self, block: NonNull<u8>, layout: Layout) {
+        if layout.size() == 0 {
+            return;
+        }
+        let mut free = self.free.borrow_mut();
+        match free.iter_mut().find(|(free, _)| *free == layout) {
+            Some((_, blocks)) => blocks.push(block),
+            None => free.push((layout, alloc::vec![block])),
+        }
+    }
+}
+
+impl Default for ObjPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ObjPool {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl fmt::Debug for ObjPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ObjPool")
+            .field("free_blocks", &self.free_blocks())
+            .finish()
+    }
+}
+
+impl<X: ?Sized> Drop for PoolBox<'_, X> {
+    fn drop(&mut self) {
+        // SAFETY: The value is valid until it is dropped here.
+        let layout = Layout::for_value(unsafe { self.value.as_ref() });
+        // SAFETY: The handle owns the value, which is never used again.
+        unsafe { ptr::drop_in_place(self.value.as_ptr()) };
+        self.pool.give_back(self.value.cast(), layout);
+    }
+}
+
+impl<X: ?Sized> Deref for PoolBox<'_, X> {
+    type Target = X;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The handle owns the value, which is valid while it lives.
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<X: ?Sized> DerefMut for PoolBox<'_, X> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: The handle owns the value, and is borrowed mutably.
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<X: fmt::Debug + ?Sized> fmt::Debug for PoolBox<'_, X> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PoolBox").field(&&**self).finish()
+    }
+}
+
+impl<X: PartialEqObj + ?Sized> PartialEq for PoolBox<'_, X> {
+    fn eq(&self, other: &Self) -> bool {
+        (**self).eq_object((**other).as_partial_eq_object())
+    }
+}
+
+impl<X: EqObj + ?Sized> Eq for PoolBox<'_, X> {}
+
+impl<X: PartialOrdObj + ?Sized> PartialOrd for PoolBox<'_, X> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (**self).partial_cmp_object((**other).as_partial_ord_object())
+    }
+}
+
+impl<X: OrdObj + ?Sized> Ord for PoolBox<'_, X> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp_object((**other).as_ord_object())
+    }
+}
+
+impl<X: HashObj + ?Sized> Hash for PoolBox<'_, X> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash_object(state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashSet, rc::Rc};
+
+    use super::*;
+    use crate::KeyObj;
+
+    #[test]
+    // The pool's free list trips the lint, but comparisons never use it.
+    #[allow(clippy::mutable_key_type)]
+    fn reuses_memory() {
+        let pool = ObjPool::new();
+        let a: PoolBox<dyn KeyObj> = pool.alloc_as(1u64, |x| x as _);
+        let address = ptr::addr_of!(*a).cast::<()>();
+        drop(a);
+        assert_eq!(pool.free_blocks(), 1);
+        let b: PoolBox<dyn KeyObj> = pool.alloc_as(2i64, |x| x as _);
+        assert!(ptr::addr_eq(&*b, address));
+        assert_eq!(pool.free_blocks(), 0);
+
+        let c: PoolBox<dyn KeyObj> = pool.alloc_as("two", |x| x as _);
+        let set: HashSet<_> = [b, c, pool.alloc_as(2i64, |x| x as _)].into();
+        assert_eq!(set.len(), 2);
+        drop(set);
+        assert_eq!(pool.free_blocks(), 3);
+        pool.clear();
+        assert_eq!(pool.free_blocks(), 0);
+
+        let zero: PoolBox<dyn KeyObj> = pool.alloc_as((), |x| x as _);
+        assert!(zero == pool.alloc_as((), |x| x as _));
+    }
+
+    #[test]
+    fn drops_values() {
+        let pool = ObjPool::new();
+        let shared = Rc::new(());
+        let mut value = pool.alloc(shared.clone());
+        *value = Rc::new(());
+        drop(value);
+        assert_eq!(Rc::strong_count(&shared), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "alloc_as must return the value it is given")]
+    fn rejects_other_references() {
+        let pool = ObjPool::new();
+        let _ = pool.alloc_as((1u32, 2u32), |(_, b)| b);
+    }
+}
@@ -0,0 +1,146 @@
+use core::{
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use crate::{EqObj, HashObj, ObjRefMut, PartialEqObj, Refresh};
+
+/// Key wrapper that hashes the object it points to when it is created, with
+/// `hash_object_u64`, and then feeds hashers only the stored 64-bit hash.
+///
+/// Unlike `CachedHashObj`, the object can only be mutated through the guard
+/// returned by `get_mut`, which rehashes it when it is dropped, so the
+/// wrapper needs no interior mutability. Keys are only equal if their stored hashes are, and then if
+/// `eq_object` says so. Like `CachedHashObj`, it hashes differently from the
+/// bare object, so only look up `PrehashedKey`s with other `PrehashedKey`s.
+///
+/// ```rust ignore
+/// let key = PrehashedKey::new(Box::new(large_document) as Box<dyn Key>);
+/// map.insert(key.clone(), value);
+/// assert!(map.contains_key(&key)); // doesn't traverse the document
+/// ```
+#[derive(Clone, Debug)]
+pub struct PrehashedKey<T> {
+    hash: u64,
+    inner: T,
+}
+
+impl<T, X> PrehashedKey<T>
+where
+    T: Deref<Target = X>,
+    X: HashObj + ?Sized,
+{
+    pub fn new(inner: T) -> Self {
+        Self {
+            hash: inner.hash_object_u64(),
+            inner,
+        }
+    }
+
+    /// Gives mutable access to the pointer, and rehashes the object when the
+    /// guard is dropped.
+    pub fn get_mut(&mut self) -> ObjRefMut<'_, Self> {
+        ObjRefMut::new(self)
+    }
+}
+
+/// Rehashes the object.
+impl<T, X> Refresh for PrehashedKey<T>
+where
+    T: Deref<Target = X>,
+    X: HashObj + ?Sized,
+{
+    type Target = T;
+
+    fn target(&self) -> &T {
+        &self.inner
+    }
+
+    fn target_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    fn refresh(&mut self) {
+        self.hash = self.inner.hash_object_u64();
+    }
+}
+
+impl<T> PrehashedKey<T> {
+    /// Returns the stored hash.
+    pub fn hash_u64(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> Deref for PrehashedKey<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> Hash for PrehashedKey<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+impl<T, X> PartialEq for PrehashedKey<T>
+where
+    T: Deref<Target = X>,
+    X: PartialEqObj + ?Sized,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b): (&X, &X) = (&self.inner, &other.inner);
+        self.hash == other.hash && a.eq_object(b.as_partial_eq_object())
+    }
+}
+
+impl<T, X> Eq for PrehashedKey<T>
+where
+    T: Deref<Target = X>,
+    X: EqObj + ?Sized,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, rc::Rc};
+
+    use super::*;
+    use crate::KeyObj;
+
+    #[test]
+    fn hashes_stored_value() {
+        let key = PrehashedKey::new(Rc::new(String::from("doc")) as Rc<dyn KeyObj>);
+        assert_eq!(key.hash_u64(), "doc".hash_object_u64());
+        let mut map = HashMap::new();
+        map.insert(key.clone(), 1);
+        map.insert(PrehashedKey::new(Rc::new(7u32) as Rc<dyn KeyObj>), 2);
+        assert_eq!(map.get(&key), Some(&1));
+        assert_eq!(
+            map.get(&PrehashedKey::new(
+                Rc::new(String::from("doc")) as Rc<dyn KeyObj>
+            )),
+            Some(&1)
+        );
+        assert_eq!(
+            map.get(&PrehashedKey::new(Rc::new(7u64) as Rc<dyn KeyObj>)),
+            None
+        );
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn rehashes_after_mutation() {
+        let mut key = PrehashedKey::new(Box::new(String::from("doc")));
+        key.get_mut().push_str("s v2");
+        assert_eq!(key.hash_u64(), "docs v2".hash_object_u64());
+        assert!(key == PrehashedKey::new(Box::new(String::from("docs v2"))));
+    }
+}
@@ -0,0 +1,73 @@
+use core::{
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+/// Wrapper that keeps data derived from the object it holds, such as a hash
+/// or a key, which must be brought up to date after the object is mutated
+/// through an `ObjRefMut`. This is implemented for `CachedHashObj`,
+/// `PrehashedKey` and `KeyedObj`, and can be implemented for other wrappers.
+pub trait Refresh {
+    /// What the wrapper holds, usually a pointer to the object.
+    type Target: ?Sized;
+
+    fn target(&self) -> &Self::Target;
+    fn target_mut(&mut self) -> &mut Self::Target;
+
+    /// Recomputes or invalidates the derived data after `target_mut` was
+    /// used.
+    fn refresh(&mut self);
+}
+
+/// Guard giving mutable access to what a wrapper holds, which refreshes the
+/// data the wrapper derives from it when it is dropped, so the wrapper can't
+/// keep a stale hash or key. Get one with the `get_mut` method of the
+/// wrapper.
+///
+/// ```rust ignore
+/// let mut key = PrehashedKey::new(Box::new(document) as Box<dyn Key>);
+/// key.get_mut().set_title("draft"); // rehashed here
+/// map.insert(key, value);
+/// ```
+///
+/// Leaking the guard, such as with `mem::forget`, skips the refresh, which
+/// leaves the wrapper inconsistent, although never unsafe.
+pub struct ObjRefMut<'a, W: Refresh + ?Sized> {
+    wrapper: &'a mut W,
+}
+
+impl<'a, W: Refresh + ?Sized> ObjRefMut<'a, W> {
+    pub fn new(wrapper: &'a mut W) -> Self {
+        Self { wrapper }
+    }
+}
+
+impl<W: Refresh + ?Sized> Deref for ObjRefMut<'_, W> {
+    type Target = W::Target;
+
+    fn deref(&self) -> &Self::Target {
+        self.wrapper.target()
+    }
+}
+
+impl<W: Refresh + ?Sized> DerefMut for ObjRefMut<'_, W> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.wrapper.target_mut()
+    }
+}
+
+impl<W: Refresh + ?Sized> Drop for ObjRefMut<'_, W> {
+    fn drop(&mut self) {
+        self.wrapper.refresh();
+    }
+}
+
+impl<W, T> fmt::Debug for ObjRefMut<'_, W>
+where
+    W: Refresh<Target = T> + ?Sized,
+    T: fmt::Debug + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ObjRefMut").field(&&**self).finish()
+    }
+}
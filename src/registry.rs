@@ -0,0 +1,1054 @@
+//! Opt-in registry of equality comparators between different concrete types.
+//!
+//! `eq_object`, and so `==` on trait objects, returns false whenever the two
+//! objects have different concrete types. Comparators registered here let
+//! you decide that, for example, `3i32` should equal `3i64` when both are
+//! erased behind a trait object, but only where you ask for them: through
+//! `cross_eq`, `obj_eq`, `dyn_eq`, and the `==` of `WithPolicy`.
+//!
+//! Comparators are not used to order or hash objects, so `==` never consults
+//! them, which keeps the `PartialEq`, `Ord` and `Hash` impls of trait objects
+//! consistent with each other. Objects that compare equal through the
+//! registry will generally have different hashes and orders, so do not use
+//! cross-type equality for the keys of collections.
+//!
+//! The registry is initialized on first use, from whichever thread registers
+//! a comparator first. Once every comparator is registered, `freeze` makes
+//! any later registration fail, so the registry stays the same for the rest
+//! of the program.
+//!
+//! Without the `std` feature, the registry has no lock or allocator, so a
+//! fixed number of comparators are stored in static `EqSlot`s, which are
+//! handed to the registry once with `provide_storage`.
+
+use core::any::{Any, TypeId};
+#[cfg(feature = "std")]
+use std::{rc::Rc, sync::Arc};
+
+#[cfg(feature = "std")]
+use crate::dst::BorrowedForm;
+use crate::{AsAny, Error, Result};
+
+/// A registered comparator: `call` downcasts both values and passes them to
+/// `eq`, the type-erased `fn(&A, &B) -> bool` given to `register_eq`.
+#[derive(Clone, Copy)]
+struct Entry {
+    call: fn(*const (), &dyn Any, &dyn Any) -> bool,
+    eq: *const (),
+}
+
+// SAFETY: `eq` is a function pointer, which is neither tied to a thread nor
+// mutable.
+unsafe impl Send for Entry {}
+unsafe impl Sync for Entry {}
+
+impl Entry {
+    fn forward<A: Any, B: Any>(eq: fn(&A, &B) -> bool) -> Self {
+        Self {
+            call: |eq, a, b| match (a.downcast_ref::<A>(), b.downcast_ref::<B>()) {
+                // SAFETY: the entry was created from a `fn(&A, &B) -> bool`.
+                (Some(a), Some(b)) => (unsafe { erased::<A, B>(eq) })(a, b),
+                _ => false,
+            },
+            eq: eq as *const (),
+        }
+    }
+
+    fn backward<A: Any, B: Any>(eq: fn(&A, &B) -> bool) -> Self {
+        Self {
+            call: |eq, b, a| match (a.downcast_ref::<A>(), b.downcast_ref::<B>()) {
+                // SAFETY: the entry was created from a `fn(&A, &B) -> bool`.
+                (Some(a), Some(b)) => (unsafe { erased::<A, B>(eq) })(a, b),
+                _ => false,
+            },
+            eq: eq as *const (),
+        }
+    }
+}
+
+/// # Safety
+/// `eq` must have been cast from a `fn(&A, &B) -> bool`.
+unsafe fn erased<A, B>(eq: *const ()) -> fn(&A, &B) -> bool {
+    unsafe { core::mem::transmute::<*const (), fn(&A, &B) -> bool>(eq) }
+}
+
+/// Registers a comparator used whenever an `A` is compared with a `B` through
+/// the registry, such as by `obj_eq`. The comparator is registered for both argument orders, so the
+/// resulting equality is symmetric. Registering the same pair again replaces
+/// the previous comparator.
+///
+/// # Panics
+/// If the registry is frozen, or, without the `std` feature, if it has no
+/// storage or no room left. Use `try_register_eq` to handle that instead.
+pub fn register_eq<A: Any, B: Any>(eq: fn(&A, &B) -> bool) {
+    if let Err(err) = insert(eq, None) {
+        panic!("cannot register a comparator: {err}");
+    }
+}
+
+/// Registers a comparator like `register_eq`, but returns an error instead of
+/// panicking, and instead of replacing a comparator that is already
+/// registered for the pair:
+/// - `Error::AlreadyRegistered` if the pair already has a comparator.
+/// - `Error::RegistryFrozen` if `freeze` was called.
+/// - `Error::RegistryFull` if there is no room left, without `std`.
+///
+/// Since the registry is initialized lazily, this can be called from any
+/// thread at any time, with no constructor running before `main`.
+pub fn try_register_eq<A: Any, B: Any>(eq: fn(&A, &B) -> bool) -> Result<()> {
+    insert(
+        eq,
+        Some(Error::AlreadyRegistered {
+            left: core::any::type_name::<A>(),
+            right: core::any::type_name::<B>(),
+        }),
+    )
+}
+
+/// Inserts the comparator in both argument orders, failing with `duplicate`
+/// if some is given and the pair already has a comparator.
+fn insert<A: Any, B: Any>(eq: fn(&A, &B) -> bool, duplicate: Option<Error>) -> Result<()> {
+    let forward = ((TypeId::of::<A>(), TypeId::of::<B>()), Entry::forward(eq));
+    let backward = ((TypeId::of::<B>(), TypeId::of::<A>()), Entry::backward(eq));
+    if TypeId::of::<A>() == TypeId::of::<B>() {
+        store::insert(&[forward], duplicate)
+    } else {
+        store::insert(&[forward, backward], duplicate)
+    }
+}
+
+/// Prevents any more comparators from being registered, so the result of
+/// every comparison through the registry stays the same from now on. Call
+/// this once initialization is done, for example before spawning the threads
+/// of a server.
+pub fn freeze() {
+    store::freeze()
+}
+
+/// Whether `freeze` was called.
+pub fn is_frozen() -> bool {
+    store::is_frozen()
+}
+
+/// Compares two values using the comparator registered for their concrete
+/// types, or returns None if no comparator is registered for the pair.
+pub fn cross_eq(a: &dyn Any, b: &dyn Any) -> Option<bool> {
+    let entry = store::get((a.type_id(), b.type_id()))?;
+    Some((entry.call)(entry.eq, a, b))
+}
+
+#[cfg(feature = "std")]
+mod store {
+    use std::{
+        any::TypeId,
+        collections::HashMap,
+        sync::{OnceLock, PoisonError, RwLock},
+    };
+
+    use super::Entry;
+    use crate::{Error, Result};
+
+    #[derive(Default)]
+    struct Registry {
+        comparators: HashMap<(TypeId, TypeId), Entry>,
+        frozen: bool,
+    }
+
+    static EQ_REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+
+    fn write() -> std::sync::RwLockWriteGuard<'static, Registry> {
+        EQ_REGISTRY
+            .get_or_init(Default::default)
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(super) fn insert(
+        entries: &[((TypeId, TypeId), Entry)],
+        duplicate: Option<Error>,
+    ) -> Result<()> {
+        let mut registry = write();
+        if registry.frozen {
+            return Err(Error::RegistryFrozen);
+        }
+        if let Some(err) = duplicate {
+            if registry.comparators.contains_key(&entries[0].0) {
+                return Err(err);
+            }
+        }
+        registry.comparators.extend(entries.iter().copied());
+        Ok(())
+    }
+
+    pub(super) fn get(key: (TypeId, TypeId)) -> Option<Entry> {
+        EQ_REGISTRY
+            .get()?
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .comparators
+            .get(&key)
+            .copied()
+    }
+
+    pub(super) fn freeze() {
+        write().frozen = true;
+    }
+
+    pub(super) fn is_frozen() -> bool {
+        EQ_REGISTRY.get().is_some_and(|registry| {
+            registry
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .frozen
+        })
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub use store::{provide_storage, EqSlot};
+
+/// Without `std`, there is no lock or allocator for the registry, so it keeps
+/// its comparators in slots provided by the caller. Each slot is claimed
+/// atomically and written once, after which it is only ever read.
+#[cfg(not(feature = "std"))]
+mod store {
+    use core::{
+        any::TypeId,
+        cell::UnsafeCell,
+        hint,
+        mem::MaybeUninit,
+        ptr,
+        sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicUsize, Ordering},
+    };
+
+    use super::Entry;
+    use crate::{Error, Result};
+
+    const EMPTY: u8 = 0;
+    const WRITING: u8 = 1;
+    const READY: u8 = 2;
+
+    /// Storage for one comparator of the registry in builds without `std`.
+    /// Registering a comparator between two different types takes two slots,
+    /// one for each argument order.
+    ///
+    /// ```rust ignore
+    /// static SLOTS: [EqSlot; 16] = [const { EqSlot::new() }; 16];
+    /// registry::provide_storage(&SLOTS);
+    /// registry::register_eq(|m: &Meters, cm: &Centimeters| m.0 * 100 == cm.0);
+    /// ```
+    pub struct EqSlot {
+        state: AtomicU8,
+        entry: UnsafeCell<MaybeUninit<Slotted>>,
+    }
+
+    /// A registered comparator, with the position of its registration among
+    /// all the others, since the slots it is stored in are not in order.
+    struct Slotted {
+        key: (TypeId, TypeId),
+        entry: Entry,
+        seq: usize,
+    }
+
+    // SAFETY: the entry is only written by the thread that claimed the slot,
+    // and only read once the slot is marked as ready.
+    unsafe impl Sync for EqSlot {}
+
+    impl EqSlot {
+        pub const fn new() -> Self {
+            Self {
+                state: AtomicU8::new(EMPTY),
+                entry: UnsafeCell::new(MaybeUninit::uninit()),
+            }
+        }
+    }
+
+    impl Default for EqSlot {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    static SLOTS: AtomicPtr<EqSlot> = AtomicPtr::new(ptr::null_mut());
+    static LEN: AtomicUsize = AtomicUsize::new(0);
+    static FROZEN: AtomicBool = AtomicBool::new(false);
+    static SEQ: AtomicUsize = AtomicUsize::new(0);
+
+    /// Registrations and `freeze` take turns, so a registration either
+    /// finishes before `freeze` returns or fails, and a duplicate is always
+    /// seen by the check for it. Without `std`, there is nothing to wait on
+    /// but a spin lock, which is held only while the slots are written.
+    static LOCKED: AtomicBool = AtomicBool::new(false);
+
+    struct Lock;
+
+    impl Lock {
+        fn acquire() -> Self {
+            while LOCKED
+                .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                hint::spin_loop();
+            }
+            Lock
+        }
+    }
+
+    impl Drop for Lock {
+        fn drop(&mut self) {
+            LOCKED.store(false, Ordering::Release);
+        }
+    }
+
+    /// Gives the registry the slots to store its comparators in, returning
+    /// false if it already has storage, in which case `slots` is not used.
+    /// Until this is called, registering a comparator fails.
+    pub fn provide_storage(slots: &'static [EqSlot]) -> bool {
+        let provided = SLOTS.compare_exchange(
+            ptr::null_mut(),
+            slots.as_ptr().cast_mut(),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+        if provided.is_ok() {
+            LEN.store(slots.len(), Ordering::Release);
+        }
+        provided.is_ok()
+    }
+
+    fn slots() -> &'static [EqSlot] {
+        let len = LEN.load(Ordering::Acquire);
+        if len == 0 {
+            return &[];
+        }
+        // SAFETY: `LEN` is only set after `SLOTS`, to the length of the
+        // static slice it points to.
+        unsafe { core::slice::from_raw_parts(SLOTS.load(Ordering::Acquire), len) }
+    }
+
+    /// Claims a slot for every entry before writing any of them, so a pair of
+    /// types is registered in both argument orders or not at all.
+    pub(super) fn insert(
+        entries: &[((TypeId, TypeId), Entry)],
+        duplicate: Option<Error>,
+    ) -> Result<()> {
+        let _lock = Lock::acquire();
+        if FROZEN.load(Ordering::Acquire) {
+            return Err(Error::RegistryFrozen);
+        }
+        if let Some(err) = duplicate {
+            if get(entries[0].0).is_some() {
+                return Err(err);
+            }
+        }
+        let mut claimed: [Option<&EqSlot>; 2] = [None; 2];
+        let mut free = slots().iter().filter(|slot| {
+            slot.state
+                .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        });
+        for claim in &mut claimed[..entries.len()] {
+            *claim = free.next();
+        }
+        if claimed[..entries.len()].iter().any(Option::is_none) {
+            for slot in claimed.into_iter().flatten() {
+                slot.state.store(EMPTY, Ordering::Release);
+            }
+            return Err(Error::RegistryFull);
+        }
+        let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+        for (slot, &(key, entry)) in claimed.into_iter().flatten().zip(entries) {
+            // SAFETY: this thread claimed the slot, so nothing else accesses
+            // the entry until it is marked as ready.
+            unsafe { (*slot.entry.get()).write(Slotted { key, entry, seq }) };
+            slot.state.store(READY, Ordering::Release);
+        }
+        Ok(())
+    }
+
+    /// Finds the comparator registered last for the pair, since registering
+    /// it again takes a new slot rather than replacing the old one.
+    pub(super) fn get(key: (TypeId, TypeId)) -> Option<Entry> {
+        slots()
+            .iter()
+            .filter(|slot| slot.state.load(Ordering::Acquire) == READY)
+            // SAFETY: ready slots are never written again.
+            .map(|slot| unsafe { (*slot.entry.get()).assume_init_ref() })
+            .filter(|slotted| slotted.key == key)
+            .max_by_key(|slotted| slotted.seq)
+            .map(|slotted| slotted.entry)
+    }
+
+    pub(super) fn freeze() {
+        let _lock = Lock::acquire();
+        FROZEN.store(true, Ordering::Release);
+    }
+
+    pub(super) fn is_frozen() -> bool {
+        FROZEN.load(Ordering::Acquire)
+    }
+}
+
+/// Compares two objects using the comparator registered for their concrete
+/// types, like `cross_eq`, returning an error if no comparator is registered
+/// for the pair.
+pub fn try_cross_eq<A: AsAny + ?Sized, B: AsAny + ?Sized>(a: &A, b: &B) -> Result<bool> {
+    cross_eq(a.as_any(), b.as_any()).ok_or(Error::Unregistered {
+        left: a.type_name(),
+        right: b.type_name(),
+    })
+}
+
+#[cfg(feature = "std")]
+/// Registers comparators so that `T`, `Box<T>`, `Rc<T>`, `Arc<T>` and
+/// `&'static T` all compare equal to each other when the values they point to
+/// are equal.
+///
+/// For example, `Box::new(5).to_partial_eq_object()` normally differs from
+/// `5.to_partial_eq_object()`, since `Box<i32>` and `i32` are different types.
+/// Unlike most cross-type comparators, this one is consistent with `Hash`,
+/// because these pointers hash exactly like the values they point to.
+pub fn register_pointer_eq<T: PartialEq + Any>() {
+    register_peeled::<T, T, Box<T>>();
+    register_peeled::<T, T, Rc<T>>();
+    register_peeled::<T, T, Arc<T>>();
+    register_peeled::<T, T, &'static T>();
+    register_peeled::<T, Box<T>, Rc<T>>();
+    register_peeled::<T, Box<T>, Arc<T>>();
+    register_peeled::<T, Box<T>, &'static T>();
+    register_peeled::<T, Rc<T>, Arc<T>>();
+    register_peeled::<T, Rc<T>, &'static T>();
+    register_peeled::<T, Arc<T>, &'static T>();
+}
+
+#[cfg(feature = "std")]
+fn register_peeled<T: PartialEq, A: Peel<T> + Any, B: Peel<T> + Any>() {
+    register_eq::<A, B>(|a, b| a.peel() == b.peel());
+}
+
+#[cfg(feature = "std")]
+/// A value, or a pointer to a value, of type `T`.
+trait Peel<T> {
+    fn peel(&self) -> &T;
+}
+
+#[cfg(feature = "std")]
+impl<T> Peel<T> for T {
+    fn peel(&self) -> &T {
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+macro_rules! impl_peel {
+    ($($Pointer:ty),*) => {$(
+        impl<T> Peel<T> for $Pointer {
+            fn peel(&self) -> &T {
+                self
+            }
+        }
+    )*};
+}
+
+#[cfg(feature = "std")]
+impl_peel!(Box<T>, Rc<T>, Arc<T>, &T);
+
+#[cfg(feature = "std")]
+/// Registers comparators so that all the sized types that borrow as `U`, such
+/// as `String`, `Box<str>` and `Arc<str>` for `str`, compare equal to each
+/// other when their borrowed forms are equal. This is consistent with `Hash`,
+/// since these types all hash like their borrowed form.
+pub fn register_borrowed_eq<U: BorrowedForm + PartialEq + ?Sized>() {
+    U::register_eq();
+}
+
+#[cfg(feature = "std")]
+#[derive(Clone, Copy)]
+struct TypeInfo {
+    name: &'static str,
+    version: u32,
+    type_name: &'static str,
+    /// The fingerprint claimed with `try_register_fingerprinted`, if any.
+    fingerprint: Option<u64>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Names {
+    by_type: std::collections::HashMap<TypeId, TypeInfo>,
+    by_fingerprint: std::collections::HashMap<u64, TypeId>,
+}
+
+#[cfg(feature = "std")]
+static NAMES: std::sync::RwLock<Option<Names>> = std::sync::RwLock::new(None);
+
+/// Registers the name of type `T`, such as the name a plugin is routed or
+/// serialized by, which `NamedObj::registered` then looks up. Registering
+/// another name for the same type replaces the previous one. This is
+/// `register_versioned` with version 0.
+///
+/// # Panics
+/// If the registry is frozen. Use `try_register_versioned` to handle that
+/// instead.
+#[cfg(feature = "std")]
+pub fn register_name<T: Any>(name: &'static str) {
+    register_versioned::<T>(name, 0)
+}
+
+/// Registers the name of type `T` along with the version of its format, so
+/// `check_types` can detect a plugin built against a different version than
+/// the host.
+///
+/// # Panics
+/// If the registry is frozen. Use `try_register_versioned` to handle that
+/// instead.
+#[cfg(feature = "std")]
+pub fn register_versioned<T: Any>(name: &'static str, version: u32) {
+    if let Err(err) = try_register_versioned::<T>(name, version) {
+        panic!("cannot register a name: {err}");
+    }
+}
+
+/// Registers the name and version of type `T` like `register_versioned`,
+/// but returns `Error::RegistryFrozen` instead of panicking if the registry
+/// is frozen.
+#[cfg(feature = "std")]
+pub fn try_register_versioned<T: Any>(name: &'static str, version: u32) -> Result<()> {
+    register_info::<T>(name, version, None)
+}
+
+/// Stable fingerprint of the type registered under `name` and `version`,
+/// which unlike its `TypeId` is the same in every build and process, so it
+/// can identify the type in persisted data and caches shared between
+/// processes. It is the 64-bit FNV-1a hash of the name followed by the
+/// version, and will never change.
+pub const fn fingerprint(name: &str, version: u32) -> u64 {
+    const PRIME: u64 = 0x100_0000_01b3;
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let name = name.as_bytes();
+    let mut i = 0;
+    while i < name.len() {
+        hash = (hash ^ name[i] as u64).wrapping_mul(PRIME);
+        i += 1;
+    }
+    // Separates the name from the version, since names can contain any byte
+    // but 0xff.
+    hash = (hash ^ 0xff).wrapping_mul(PRIME);
+    let version = version.to_le_bytes();
+    let mut i = 0;
+    while i < version.len() {
+        hash = (hash ^ version[i] as u64).wrapping_mul(PRIME);
+        i += 1;
+    }
+    hash
+}
+
+/// Registers the name and version of type `T` like `try_register_versioned`,
+/// and claims the [`fingerprint`] of that name and version for `T`, which
+/// `registered_fingerprint` and `fingerprinted_type` then look up. Returns
+/// the fingerprint, or:
+/// - `Error::FingerprintCollision` if another type already claimed it,
+///   either because it is registered under the same name and version, or
+///   because two names collide. Nothing is registered in that case.
+/// - `Error::RegistryFrozen` if `freeze` was called.
+#[cfg(feature = "std")]
+pub fn try_register_fingerprinted<T: Any>(name: &'static str, version: u32) -> Result<u64> {
+    let fingerprint = fingerprint(name, version);
+    register_info::<T>(name, version, Some(fingerprint))?;
+    Ok(fingerprint)
+}
+
+/// Records the name of `T`, releasing any fingerprint it previously claimed.
+#[cfg(feature = "std")]
+fn register_info<T: Any>(name: &'static str, version: u32, fingerprint: Option<u64>) -> Result<()> {
+    if is_frozen() {
+        return Err(Error::RegistryFrozen);
+    }
+    let mut names = NAMES
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    let names = names.get_or_insert_with(Default::default);
+    let type_id = TypeId::of::<T>();
+    let type_name = core::any::type_name::<T>();
+    if let Some(fingerprint) = fingerprint {
+        match names.by_fingerprint.get(&fingerprint) {
+            Some(&owner) if owner != type_id => {
+                return Err(Error::FingerprintCollision {
+                    fingerprint,
+                    first: names.by_type[&owner].type_name,
+                    second: type_name,
+                });
+            }
+            _ => {}
+        }
+    }
+    let info = TypeInfo {
+        name,
+        version,
+        type_name,
+        fingerprint,
+    };
+    if let Some(previous) = names.by_type.insert(type_id, info) {
+        if let Some(previous) = previous.fingerprint {
+            names.by_fingerprint.remove(&previous);
+        }
+    }
+    if let Some(fingerprint) = fingerprint {
+        names.by_fingerprint.insert(fingerprint, type_id);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+fn type_info(value: &dyn Any) -> Option<TypeInfo> {
+    NAMES
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .as_ref()?
+        .by_type
+        .get(&value.type_id())
+        .copied()
+}
+
+/// Returns the name registered for the concrete type of `value`.
+#[cfg(feature = "std")]
+pub fn registered_name(value: &dyn Any) -> Option<&'static str> {
+    type_info(value).map(|info| info.name)
+}
+
+/// Returns the version registered for the concrete type of `value`.
+#[cfg(feature = "std")]
+pub fn registered_version(value: &dyn Any) -> Option<u32> {
+    type_info(value).map(|info| info.version)
+}
+
+/// Returns the fingerprint claimed for the concrete type of `value` with
+/// `try_register_fingerprinted`.
+#[cfg(feature = "std")]
+pub fn registered_fingerprint(value: &dyn Any) -> Option<u64> {
+    type_info(value)?.fingerprint
+}
+
+/// Returns the type that claimed `fingerprint`, such as one read back from
+/// persisted data.
+#[cfg(feature = "std")]
+pub fn fingerprinted_type(fingerprint: u64) -> Option<TypeId> {
+    NAMES
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .as_ref()?
+        .by_fingerprint
+        .get(&fingerprint)
+        .copied()
+}
+
+/// A problem with the registered names found by `check_types`.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TypeIssue {
+    /// Two different types are registered under the same name.
+    DuplicateName {
+        name: &'static str,
+        first: &'static str,
+        second: &'static str,
+    },
+    /// The type registered under this name has a different version than
+    /// expected.
+    VersionSkew {
+        name: &'static str,
+        expected: u32,
+        found: u32,
+    },
+    /// No type is registered under this name.
+    Missing { name: &'static str },
+}
+
+#[cfg(feature = "std")]
+impl core::fmt::Display for TypeIssue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TypeIssue::DuplicateName {
+                name,
+                first,
+                second,
+            } => write!(f, "{first} and {second} are both registered as {name:?}"),
+            TypeIssue::VersionSkew {
+                name,
+                expected,
+                found,
+            } => write!(f, "{name:?} has version {found}, expected {expected}"),
+            TypeIssue::Missing { name } => write!(f, "no type is registered as {name:?}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::error::Error for TypeIssue {}
+
+/// Validates the registered names against the `(name, version)` pairs the
+/// host expects, such as after loading its plugins and before deserializing
+/// anything. Every name registered for two types is reported, along with each
+/// expected name that is missing or has another version, in a deterministic
+/// order.
+#[cfg(feature = "std")]
+pub fn check_types(expected: &[(&'static str, u32)]) -> core::result::Result<(), Vec<TypeIssue>> {
+    let mut registered: Vec<TypeInfo> = NAMES
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .iter()
+        .flat_map(|names| names.by_type.values().copied())
+        .collect();
+    registered.sort_by_key(|info| (info.name, info.type_name));
+
+    let mut issues: Vec<_> = registered
+        .windows(2)
+        .filter(|pair| pair[0].name == pair[1].name)
+        .map(|pair| TypeIssue::DuplicateName {
+            name: pair[0].name,
+            first: pair[0].type_name,
+            second: pair[1].type_name,
+        })
+        .collect();
+    for &(name, version) in expected {
+        let found = registered.iter().filter(|info| info.name == name);
+        match found.clone().find(|info| info.version != version) {
+            Some(info) => issues.push(TypeIssue::VersionSkew {
+                name,
+                expected: version,
+                found: info.version,
+            }),
+            None if found.count() == 0 => issues.push(TypeIssue::Missing { name }),
+            None => {}
+        }
+    }
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
+
+/// Registers comparators between every pair of primitive integer and float
+/// types, so that numbers compare equal when they represent exactly the same
+/// value, regardless of their concrete type.
+#[cfg(feature = "numeric")]
+pub fn register_numeric_eq() {
+    numeric::register_all();
+}
+
+#[cfg(feature = "numeric")]
+mod numeric {
+    use super::register_eq;
+
+    /// Lossless representation shared by all the primitive numeric types.
+    #[derive(Clone, Copy)]
+    enum Num {
+        Signed(i128),
+        Unsigned(u128),
+        Float(f64),
+    }
+
+    impl PartialEq for Num {
+        fn eq(&self, other: &Self) -> bool {
+            use Num::*;
+            match (*self, *other) {
+                (Signed(a), Signed(b)) => a == b,
+                (Unsigned(a), Unsigned(b)) => a == b,
+                (Float(a), Float(b)) => a == b,
+                (Signed(i), Unsigned(u)) | (Unsigned(u), Signed(i)) => {
+                    u128::try_from(i).is_ok_and(|i| i == u)
+                }
+                (Signed(i), Float(f)) | (Float(f), Signed(i)) => {
+                    f.fract() == 0.0
+                        && f >= i128::MIN as f64
+                        && f < i128::MAX as f64
+                        && f as i128 == i
+                }
+                (Unsigned(u), Float(f)) | (Float(f), Unsigned(u)) => {
+                    f.fract() == 0.0 && f >= 0.0 && f < u128::MAX as f64 && f as u128 == u
+                }
+            }
+        }
+    }
+
+    trait Numeric: 'static {
+        fn to_num(&self) -> Num;
+    }
+
+    macro_rules! numeric {
+        ($Variant:ident as $Repr:ty: $($T:ty),*) => {$(
+            impl Numeric for $T {
+                fn to_num(&self) -> Num {
+                    Num::$Variant(*self as $Repr)
+                }
+            }
+        )*};
+    }
+
+    numeric!(Signed as i128: i8, i16, i32, i64, i128, isize);
+    numeric!(Unsigned as u128: u8, u16, u32, u64, u128, usize);
+    numeric!(Float as f64: f32, f64);
+
+    /// Registers a comparator between the first type and each of the others,
+    /// then does the same for the rest, so each pair is registered once.
+    macro_rules! register_pairs {
+        () => {};
+        ($A:ty $(, $B:ty)*) => {
+            $(register_eq::<$A, $B>(|a, b| a.to_num() == b.to_num());)*
+            register_pairs!($($B),*);
+        };
+    }
+
+    pub fn register_all() {
+        register_pairs!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+    }
+}
+
+/// Registers concrete types into every registry they need, with one entry
+/// per type listing its registrations:
+/// - `name = "..."`, optionally followed by `version = ...`, registers its
+///   name with `register_name` or `register_versioned`.
+/// - `pointer_eq` calls `register_pointer_eq`.
+/// - `eq(Other, |a, b| ...)` registers a comparator with `Other` through
+///   `register_eq`.
+///
+/// Prefix the entries with a function signature to define a function that
+/// registers all of them, which works at module scope, so a plugin can export
+/// a single registration function without running code before `main`.
+/// Without it, the registrations run in place.
+///
+/// ```rust ignore
+/// register_obj_type! {
+///     pub fn register_plugin;
+///     Resize { name = "resize", version = 2, pointer_eq }
+///     Crop { name = "crop", eq(Resize, |c, r| c.0 == r.0) }
+/// }
+/// ```
+#[macro_export]
+macro_rules! register_obj_type {
+    ($vis:vis fn $fn_name:ident; $($T:ty { $($opts:tt)* })*) => {
+        $vis fn $fn_name() {
+            $($crate::register_obj_type!(@opts $T; $($opts)*);)*
+        }
+    };
+    (@opts $T:ty;) => {};
+    (@opts $T:ty; name = $name:literal, version = $version:expr $(, $($rest:tt)*)?) => {
+        $crate::registry::register_versioned::<$T>($name, $version);
+        $crate::register_obj_type!(@opts $T; $($($rest)*)?);
+    };
+    (@opts $T:ty; name = $name:literal $(, $($rest:tt)*)?) => {
+        $crate::registry::register_name::<$T>($name);
+        $crate::register_obj_type!(@opts $T; $($($rest)*)?);
+    };
+    (@opts $T:ty; pointer_eq $(, $($rest:tt)*)?) => {
+        $crate::registry::register_pointer_eq::<$T>();
+        $crate::register_obj_type!(@opts $T; $($($rest)*)?);
+    };
+    (@opts $T:ty; eq($Other:ty, $eq:expr) $(, $($rest:tt)*)?) => {
+        $crate::registry::register_eq::<$T, $Other>($eq);
+        $crate::register_obj_type!(@opts $T; $($($rest)*)?);
+    };
+    ($($T:ty { $($opts:tt)* })*) => {
+        $($crate::register_obj_type!(@opts $T; $($opts)*);)*
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    /// Without `std`, the registry only has room for comparators once it is
+    /// given storage.
+    fn provide_storage() {
+        #[cfg(not(feature = "std"))]
+        {
+            static SLOTS: [registry::EqSlot; 16] = [const { registry::EqSlot::new() }; 16];
+            registry::provide_storage(&SLOTS);
+        }
+    }
+
+    #[test]
+    fn registered_comparator_is_used() {
+        #[derive(PartialEq)]
+        struct Meters(u32);
+        #[derive(PartialEq)]
+        struct Centimeters(u32);
+
+        provide_storage();
+        let m: Box<dyn PartialEqObj> = Box::new(Meters(2));
+        let cm: Box<dyn PartialEqObj> = Box::new(Centimeters(200));
+        assert!(!obj_eq(&*m, &*cm));
+        registry::register_eq(|m: &Meters, cm: &Centimeters| m.0 * 100 == cm.0);
+        assert!(obj_eq(&*m, &*cm));
+        assert!(obj_eq(&*cm, &*m));
+        assert_eq!(dyn_eq(&*m, &*cm), Some(true));
+        assert!(!obj_eq(&*m, &Centimeters(201)));
+    }
+
+    #[test]
+    fn reregistering_replaces_comparator() {
+        provide_storage();
+        #[derive(PartialEq)]
+        struct Grams(u32);
+        #[derive(PartialEq)]
+        struct Kilograms(u32);
+
+        let scaled = |g: &Grams, kg: &Kilograms| g.0 == kg.0 * 1000;
+        assert!(registry::try_register_eq(|_: &Grams, _: &Kilograms| true).is_ok());
+        assert!(matches!(
+            registry::try_register_eq(scaled),
+            Err(Error::AlreadyRegistered { .. })
+        ));
+        assert_eq!(registry::cross_eq(&Grams(1), &Kilograms(2)), Some(true));
+        registry::register_eq(scaled);
+        assert_eq!(registry::cross_eq(&Grams(2000), &Kilograms(2)), Some(true));
+        assert_eq!(registry::cross_eq(&Kilograms(3), &Grams(2000)), Some(false));
+        assert_eq!(registry::cross_eq(&Grams(1), &Grams(1)), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn type_versions() {
+        struct HostV1;
+        struct PluginV2;
+        struct Clash;
+
+        registry::register_versioned::<HostV1>("check-host", 1);
+        assert_eq!(
+            registry::try_register_versioned::<PluginV2>("check-plugin", 2),
+            Ok(())
+        );
+        assert_eq!(registry::registered_version(&HostV1), Some(1));
+        assert_eq!(registry::registered_name(&PluginV2), Some("check-plugin"));
+        assert_eq!(
+            registry::check_types(&[("check-host", 1), ("check-plugin", 2)]),
+            Ok(())
+        );
+
+        registry::register_name::<Clash>("check-host");
+        let issues =
+            registry::check_types(&[("check-plugin", 1), ("check-missing", 0)]).unwrap_err();
+        assert_eq!(
+            issues,
+            [
+                registry::TypeIssue::DuplicateName {
+                    name: "check-host",
+                    first: core::any::type_name::<Clash>(),
+                    second: core::any::type_name::<HostV1>(),
+                },
+                registry::TypeIssue::VersionSkew {
+                    name: "check-plugin",
+                    expected: 1,
+                    found: 2,
+                },
+                registry::TypeIssue::Missing {
+                    name: "check-missing"
+                },
+            ]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn register_obj_type() {
+        #[derive(PartialEq)]
+        struct Resize(u32);
+        #[derive(PartialEq)]
+        struct Crop(u32);
+
+        register_obj_type! {
+            fn register_plugin;
+            Resize { name = "macro-resize", version = 2, pointer_eq }
+            Crop { name = "macro-crop", eq(Resize, |c, r| c.0 == r.0) }
+        }
+        register_plugin();
+        assert_eq!(registry::registered_version(&Resize(1)), Some(2));
+        assert_eq!(registry::registered_name(&Crop(1)), Some("macro-crop"));
+        assert_eq!(
+            registry::cross_eq(&Resize(1), &Box::new(Resize(1))),
+            Some(true)
+        );
+        assert_eq!(registry::cross_eq(&Resize(1), &Crop(1)), Some(true));
+
+        struct Local;
+        register_obj_type! {
+            Local { name = "macro-local" }
+        }
+        assert_eq!(registry::registered_version(&Local), Some(0));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn pointer_eq() {
+        use std::{rc::Rc, sync::Arc};
+
+        #[derive(PartialEq)]
+        struct Id(u32);
+        static ID: Id = Id(1);
+        registry::register_pointer_eq::<Id>();
+
+        let values: Vec<Box<dyn PartialEqObj>> = vec![
+            Id(1).to_partial_eq_object(),
+            Box::new(Id(1)).to_partial_eq_object(),
+            Rc::new(Id(1)).to_partial_eq_object(),
+            Arc::new(Id(1)).to_partial_eq_object(),
+            (&ID).to_partial_eq_object(),
+        ];
+        for a in &values {
+            for b in &values {
+                assert!(obj_eq(&**a, &**b));
+            }
+            assert!(!obj_eq(&**a, &Box::new(Id(2))));
+        }
+    }
+
+    #[test]
+    fn operators_skip_registry() {
+        use std::collections::BTreeSet;
+
+        provide_storage();
+
+        #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+        struct Feet(u32);
+        #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+        struct Yards(u32);
+
+        registry::register_eq(|f: &Feet, y: &Yards| f.0 == y.0 * 3);
+        let feet = || Box::new(Feet(3));
+        let yards = || Box::new(Yards(1));
+        let (a, b): (Box<dyn PartialEqObj>, Box<dyn PartialEqObj>) = (feet(), yards());
+        assert!(obj_eq(&*a, &*b));
+        assert!(*a != *b && Obj(a) != Obj(b));
+        let (a, b): (Box<dyn PartialOrdObj>, Box<dyn PartialOrdObj>) = (feet(), yards());
+        assert!(*a != *b);
+
+        let (a, b): (Box<dyn OrdObj>, Box<dyn OrdObj>) = (feet(), yards());
+        assert!(*a != *b);
+        assert_ne!(a.cmp(&b), core::cmp::Ordering::Equal);
+        assert!(Obj(a) != Obj(b));
+        let (a, b): (Box<dyn FullObj>, Box<dyn FullObj>) = (feet(), yards());
+        assert!(*a != *b && Obj(&*a) != Obj(&*b));
+        assert!(*a == *(Box::new(Feet(3)) as Box<dyn FullObj>));
+
+        let set: BTreeSet<Box<dyn OrdObj>> = [feet() as _, yards() as _].into();
+        assert_eq!(set.len(), 2);
+        assert!(set.first() != set.last());
+    }
+
+    #[cfg(feature = "numeric")]
+    #[test]
+    fn numeric_eq() {
+        registry::register_numeric_eq();
+        assert!(obj_eq(&3i32, &3i64));
+        assert!(obj_eq(&3u8, &3.0f32));
+        assert!(!obj_eq(&u128::MAX, &-1i128));
+        assert!(!obj_eq(&3i32, &3.5f64));
+        assert!(!obj_eq(&f64::NAN, &f32::NAN));
+        assert!(
+            *(Box::new(3i32) as Box<dyn PartialEqObj>)
+                != *(Box::new(3i64) as Box<dyn PartialEqObj>)
+        );
+        assert_eq!(registry::cross_eq(&3i32, &3i32), None);
+    }
+}
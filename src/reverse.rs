@@ -0,0 +1,139 @@
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
+
+use crate::{EqObj, HashObj, OrdObj, PartialEqObj};
+
+/// Wrapper that orders the objects it points to in reverse, like
+/// `std::cmp::Reverse`, but for anything that derefs to an `OrdObj`, such as
+/// `Box<dyn MyTrait>`. Wrap the elements of a `BinaryHeap` to turn it into a
+/// min-heap.
+///
+/// ```rust ignore
+/// let mut heap = BinaryHeap::new();
+/// heap.push(ReverseObj(Box::new(2) as Box<dyn Task>));
+/// heap.push(ReverseObj(Box::new(1) as Box<dyn Task>));
+/// assert_eq!(heap.pop().unwrap().as_any().downcast_ref(), Some(&1));
+/// ```
+///
+/// Without the wrapper, `BinaryHeap<Box<dyn Task>>` is a max-heap, as long as
+/// `Ord` is implemented for `dyn Task` with `impl_ord!`, or
+/// `BinaryHeap<Obj<Box<dyn Task>>>` if it is not.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReverseObj<T>(pub T);
+
+impl<T> ReverseObj<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ReverseObj<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for ReverseObj<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T, X> PartialEq for ReverseObj<T>
+where
+    T: Deref<Target = X>,
+    X: PartialEqObj + ?Sized,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b): (&X, &X) = (&self.0, &other.0);
+        a.eq_object(b.as_partial_eq_object())
+    }
+}
+
+impl<T, X> Eq for ReverseObj<T>
+where
+    T: Deref<Target = X>,
+    X: EqObj + ?Sized,
+{
+}
+
+impl<T, X> PartialOrd for ReverseObj<T>
+where
+    T: Deref<Target = X>,
+    X: OrdObj + ?Sized,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, X> Ord for ReverseObj<T>
+where
+    T: Deref<Target = X>,
+    X: OrdObj + ?Sized,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b): (&X, &X) = (&self.0, &other.0);
+        b.cmp_object(a.as_ord_object())
+    }
+}
+
+impl<T, X> Hash for ReverseObj<T>
+where
+    T: Deref<Target = X>,
+    X: HashObj + ?Sized,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let target: &X = &self.0;
+        target.hash_object(state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BinaryHeap;
+
+    use crate::*;
+
+    trait Task: OrdObj {}
+    impl<T: Ord + 'static> Task for T {}
+    impl_partial_eq!(dyn Task);
+    impl_eq!(dyn Task);
+    impl_partial_ord!(dyn Task);
+    impl_ord!(dyn Task);
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord)]
+    struct Priority(u8);
+
+    fn priority(task: &dyn Task) -> u8 {
+        task.as_any().downcast_ref::<Priority>().unwrap().0
+    }
+
+    #[test]
+    fn heaps() {
+        let tasks = || [3, 1, 2].map(|p| Box::new(Priority(p)) as Box<dyn Task>);
+
+        let mut max: BinaryHeap<Box<dyn Task>> = tasks().into();
+        let popped: Vec<_> = std::iter::from_fn(|| max.pop())
+            .map(|t| priority(&*t))
+            .collect();
+        assert_eq!(popped, [3, 2, 1]);
+
+        let mut min: BinaryHeap<_> = tasks().map(ReverseObj).into();
+        let popped: Vec<_> = std::iter::from_fn(|| min.pop())
+            .map(|t| priority(&**t))
+            .collect();
+        assert_eq!(popped, [1, 2, 3]);
+
+        let mut objs: BinaryHeap<_> = [1, 3, 2]
+            .map(|x| Obj(Box::new(x) as Box<dyn OrdObj>))
+            .into();
+        let top = objs.pop().unwrap();
+        assert_eq!((**top).as_any().downcast_ref::<i32>(), Some(&3));
+    }
+}
@@ -0,0 +1,232 @@
+use core::{
+    cell::{Ref, RefCell},
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+#[cfg(feature = "std")]
+use std::sync::{Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard};
+
+use crate::{EqObj, HashObj, OrdObj, PartialEqObj, PartialOrdObj};
+
+/// Cell or lock that gives shared access to the object it holds, which may be
+/// unsized, such as the `RefCell<dyn MyTrait>` in `Rc<RefCell<dyn MyTrait>>`,
+/// for `SharedObj`. This is implemented for `RefCell`, and with the `std`
+/// feature for `Mutex` and `RwLock`.
+pub trait SharedCell {
+    type Target: ?Sized;
+    type Guard<'a>: Deref<Target = Self::Target>
+    where
+        Self: 'a;
+
+    /// Borrows or locks the object for reading.
+    fn read(&self) -> Self::Guard<'_>;
+}
+
+impl<X: ?Sized> SharedCell for RefCell<X> {
+    type Target = X;
+    type Guard<'a>
+        = Ref<'a, X>
+    where
+        Self: 'a;
+
+    /// Panics if the object is borrowed mutably, like `RefCell::borrow`.
+    fn read(&self) -> Self::Guard<'_> {
+        self.borrow()
+    }
+}
+
+/// Blocks until the lock is acquired, and ignores poisoning.
+#[cfg(feature = "std")]
+impl<X: ?Sized> SharedCell for Mutex<X> {
+    type Target = X;
+    type Guard<'a>
+        = MutexGuard<'a, X>
+    where
+        Self: 'a;
+
+    fn read(&self) -> Self::Guard<'_> {
+        self.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Blocks until a read lock is acquired, and ignores poisoning.
+#[cfg(feature = "std")]
+impl<X: ?Sized> SharedCell for RwLock<X> {
+    type Target = X;
+    type Guard<'a>
+        = RwLockReadGuard<'a, X>
+    where
+        Self: 'a;
+
+    fn read(&self) -> Self::Guard<'_> {
+        self.read().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Wrapper for a shared pointer to a cell or lock holding an object, such as
+/// `Rc<RefCell<dyn MyTrait>>` or `Arc<RwLock<dyn MyTrait>>`, which implements
+/// the standard traits by seeing through both the pointer and the cell to the
+/// object, like `Obj` does through a pointer.
+///
+/// ```rust ignore
+/// let widget: Rc<RefCell<dyn Widget>> = Rc::new(RefCell::new(Button::new("ok")));
+/// let mut focused = HashSet::new();
+/// focused.insert(SharedObj(widget.clone()));
+/// ```
+///
+/// Each comparison or hash borrows the objects only for its own duration,
+/// with `SharedCell::read`:
+/// - a `RefCell` panics if the object is borrowed mutably at that time.
+/// - a `Mutex` or `RwLock` blocks until it gets the lock, and ignores
+///   poisoning, since the object is only read. Two different locks are
+///   always taken in the order of their addresses, so comparing them from
+///   two threads at once cannot deadlock.
+/// - when both sides point to the same cell, it is only borrowed once, so
+///   comparing a `Mutex` with itself does not deadlock. Hashing or comparing
+///   while holding a lock still does, as with any use of the lock.
+///
+/// Mutating the object changes its hash, so do not mutate it while it is a
+/// key in a hashed collection.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SharedObj<P>(pub P);
+
+impl<P> SharedObj<P> {
+    pub fn new(pointer: P) -> Self {
+        Self(pointer)
+    }
+
+    pub fn into_inner(self) -> P {
+        self.0
+    }
+}
+
+impl<P> Deref for SharedObj<P> {
+    type Target = P;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<P> From<P> for SharedObj<P> {
+    fn from(pointer: P) -> Self {
+        Self(pointer)
+    }
+}
+
+/// Reads both objects, borrowing a cell only once if both sides point to it,
+/// and in the order of their addresses otherwise.
+fn read_both<C: SharedCell + ?Sized, R>(
+    a: &C,
+    b: &C,
+    f: impl FnOnce(&C::Target, &C::Target) -> R,
+) -> R {
+    if core::ptr::addr_eq(a, b) {
+        let guard = a.read();
+        return f(&guard, &guard);
+    }
+    let (first, second) = if (a as *const C).cast::<()>() < (b as *const C).cast::<()>() {
+        (a.read(), b.read())
+    } else {
+        let second = b.read();
+        (a.read(), second)
+    };
+    f(&first, &second)
+}
+
+impl<P, C, X> PartialEq for SharedObj<P>
+where
+    P: Deref<Target = C>,
+    C: SharedCell<Target = X> + ?Sized,
+    X: PartialEqObj + ?Sized,
+{
+    fn eq(&self, other: &Self) -> bool {
+        read_both(&*self.0, &*other.0, |a, b| {
+            a.eq_object(b.as_partial_eq_object())
+        })
+    }
+}
+
+impl<P, C, X> Eq for SharedObj<P>
+where
+    P: Deref<Target = C>,
+    C: SharedCell<Target = X> + ?Sized,
+    X: EqObj + ?Sized,
+{
+}
+
+impl<P, C, X> PartialOrd for SharedObj<P>
+where
+    P: Deref<Target = C>,
+    C: SharedCell<Target = X> + ?Sized,
+    X: PartialOrdObj + ?Sized,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        read_both(&*self.0, &*other.0, |a, b| {
+            a.partial_cmp_object(b.as_partial_ord_object())
+        })
+    }
+}
+
+impl<P, C, X> Ord for SharedObj<P>
+where
+    P: Deref<Target = C>,
+    C: SharedCell<Target = X> + ?Sized,
+    X: OrdObj + ?Sized,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        read_both(&*self.0, &*other.0, |a, b| a.cmp_object(b.as_ord_object()))
+    }
+}
+
+impl<P, C, X> Hash for SharedObj<P>
+where
+    P: Deref<Target = C>,
+    C: SharedCell<Target = X> + ?Sized,
+    X: HashObj + ?Sized,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.read().hash_object(state);
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod test {
+    use std::{cell::RefCell, collections::HashSet, rc::Rc};
+    #[cfg(feature = "std")]
+    use std::{
+        collections::BTreeSet,
+        sync::{Arc, Mutex, RwLock},
+    };
+
+    use crate::*;
+
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn see_through_cells() {
+        let a: Rc<RefCell<dyn KeyObj>> = Rc::new(RefCell::new(1));
+        let b: Rc<RefCell<dyn KeyObj>> = Rc::new(RefCell::new("b"));
+        let set: HashSet<_> = [a.clone(), b, Rc::new(RefCell::new(1))]
+            .map(SharedObj)
+            .into();
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&SharedObj(a.clone())));
+        let _borrow = a.borrow();
+        assert!(SharedObj(a.clone()) == SharedObj(a.clone()));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn see_through_locks() {
+        let locked: Arc<Mutex<dyn OrdObj>> = Arc::new(Mutex::new(2));
+        assert!(SharedObj(locked.clone()) == SharedObj(locked.clone()));
+        let set: BTreeSet<_> = [locked, Arc::new(Mutex::new(1))].map(SharedObj).into();
+        let first = set.first().unwrap().lock().unwrap();
+        assert_eq!((*first).as_any().downcast_ref(), Some(&1));
+
+        let read: Arc<RwLock<dyn PartialEqObj>> = Arc::new(RwLock::new(f64::NAN));
+        assert!(SharedObj(read.clone()) != SharedObj(read));
+    }
+}
@@ -0,0 +1,179 @@
+use alloc::boxed::Box;
+use core::{
+    hash::{Hash, Hasher},
+    mem::{self, MaybeUninit},
+    ops::Deref,
+    ptr,
+};
+
+use crate::KeyObj;
+
+/// Key type like `DynKey`, which stores values of up to `N` bytes inline
+/// instead of boxing them. Larger values, and values aligned to more than 8
+/// bytes, are boxed.
+///
+/// Keys are equal if they hold values of the same concrete type that are
+/// equal, and hash exactly like the value they hold, regardless of where the
+/// value is stored.
+///
+/// ```rust ignore
+/// let mut set = HashSet::new();
+/// set.insert(SmallObj::<16>::new(42u64)); // no allocation
+/// set.insert(SmallObj::<16>::new(Uuid::new_v4())); // no allocation
+/// assert!(set.contains(&SmallObj::new(42u64)));
+/// ```
+pub struct SmallObj<const N: usize = 16> {
+    storage: Storage<N>,
+}
+
+enum Storage<const N: usize> {
+    Inline {
+        bytes: Inline<N>,
+        /// Views `bytes` as the value they hold.
+        view: unsafe fn(*const u8) -> *const dyn KeyObj,
+        /// Drops the value that `bytes` hold.
+        drop: unsafe fn(*mut u8),
+    },
+    Boxed(Box<dyn KeyObj>),
+}
+
+#[repr(C, align(8))]
+struct Inline<const N: usize>([MaybeUninit<u8>; N]);
+
+/// # Safety
+///
+/// `ptr` must point to a valid `T`.
+unsafe fn view<T: KeyObj>(ptr: *const u8) -> *const dyn KeyObj {
+    ptr as *const T
+}
+
+/// # Safety
+///
+/// `ptr` must point to a valid `T`, which must not be used afterwards.
+unsafe fn drop<T>(ptr: *mut u8) {
+    ptr::drop_in_place(ptr as *mut T)
+}
+
+impl<const N: usize> SmallObj<N> {
+    pub fn new<T: KeyObj + Sized>(value: T) -> Self {
+        if !Self::fits::<T>() {
+            return Self {
+                storage: Storage::Boxed(Box::new(value)),
+            };
+        }
+        let mut bytes = Inline([MaybeUninit::uninit(); N]);
+        // SAFETY: `T` fits in `bytes`, and `bytes` is aligned enough for it.
+        unsafe { ptr::write(bytes.0.as_mut_ptr() as *mut T, value) };
+        Self {
+            storage: Storage::Inline {
+                bytes,
+                view: view::<T>,
+                drop: drop::<T>,
+            },
+        }
+    }
+
+    /// Returns true if values of type `T` are stored inline.
+    pub const fn fits<T>() -> bool {
+        mem::size_of::<T>() <= N && mem::align_of::<T>() <= mem::align_of::<Inline<N>>()
+    }
+
+    /// Returns true if the value is stored inline rather than boxed.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.storage, Storage::Inline { .. })
+    }
+
+    fn get(&self) -> &dyn KeyObj {
+        match &self.storage {
+            // SAFETY: `view` was created for the type of the value that `new`
+            // wrote to `bytes`, which is still valid since it is only dropped
+            // along with `self`.
+            Storage::Inline { bytes, view, .. } => unsafe { &*view(bytes.0.as_ptr().cast()) },
+            Storage::Boxed(value) => &**value,
+        }
+    }
+}
+
+impl<const N: usize> Drop for SmallObj<N> {
+    fn drop(&mut self) {
+        if let Storage::Inline { bytes, drop, .. } = &mut self.storage {
+            // SAFETY: `drop` was created for the type of the value that `new`
+            // wrote to `bytes`, which is never used again.
+            unsafe { drop(bytes.0.as_mut_ptr().cast()) }
+        }
+    }
+}
+
+impl<const N: usize> Deref for SmallObj<N> {
+    type Target = dyn KeyObj;
+
+    fn deref(&self) -> &Self::Target {
+        self.get()
+    }
+}
+
+impl<const N: usize> PartialEq for SmallObj<N> {
+    fn eq(&self, other: &Self) -> bool {
+        crate::eq_or_same(self.get(), other.get())
+    }
+}
+
+impl<const N: usize> Eq for SmallObj<N> {}
+
+impl<const N: usize> Hash for SmallObj<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.get().hash(state);
+    }
+}
+
+crate::dyn_key::impl_key_accessors!(SmallObj<const N: usize>);
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashSet, hash::BuildHasher, sync::Arc};
+
+    use super::*;
+
+    #[derive(PartialEq, Eq, Hash)]
+    #[repr(align(16))]
+    struct Aligned(u8);
+
+    #[test]
+    fn inline_and_boxed() {
+        let small = SmallObj::<16>::new(7u64);
+        assert!(small.is_inline());
+        assert_eq!(small.downcast_ref::<u64>(), Some(&7));
+        let large = SmallObj::<16>::new([1u64; 4]);
+        assert!(!large.is_inline());
+        assert!(large.is::<[u64; 4]>());
+        assert!(!SmallObj::<16>::fits::<Aligned>());
+        assert_eq!(format!("{small:?}"), "SmallObj(\"u64\")");
+    }
+
+    #[test]
+    fn eq_and_hash_ignore_storage() {
+        let state = std::collections::hash_map::RandomState::new();
+        let inline = SmallObj::<8>::new(2usize);
+        let boxed = SmallObj::<0>::new(2usize);
+        assert!(inline.is_inline() && !boxed.is_inline());
+        assert_eq!(state.hash_one(&inline), state.hash_one(2usize));
+        assert_eq!(state.hash_one(&boxed), state.hash_one(2usize));
+
+        let mut set = HashSet::new();
+        assert!(set.insert(SmallObj::<24>::new(String::from("key"))));
+        assert!(set.insert(SmallObj::<24>::new(3u8)));
+        assert!(!set.insert(SmallObj::<24>::new(String::from("key"))));
+        assert!(set.contains(&SmallObj::new(3u8)));
+        assert!(!set.contains(&SmallObj::new(3u16)));
+    }
+
+    #[test]
+    fn drops_inline_values() {
+        let shared = Arc::new(());
+        let obj = SmallObj::<16>::new(Arc::clone(&shared));
+        assert!(obj.is_inline());
+        assert_eq!(Arc::strong_count(&shared), 2);
+        mem::drop(obj);
+        assert_eq!(Arc::strong_count(&shared), 1);
+    }
+}
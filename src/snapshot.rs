@@ -0,0 +1,118 @@
+//! Debug renderings for snapshot tests, such as with `insta`, which do not
+//! depend on the iteration order of the collection being rendered.
+//!
+//! The iteration order of a `DynHashSet` changes from one run to the next, so
+//! its `Debug` output cannot be compared against a stored snapshot. Wrapping
+//! it in `SortedDebug` renders every key with `Debug` and sorts the results.
+//!
+//! ```rust ignore
+//! insta::assert_debug_snapshot!(SortedDebug(&set));
+//! insta::assert_debug_snapshot!(SortedDebugMap(&map));
+//! ```
+
+use std::fmt;
+
+/// Renders the items of a collection like a `Debug` list, sorted by their
+/// own `Debug` rendering. `I` is typically a reference to the collection.
+#[derive(Clone, Copy)]
+pub struct SortedDebug<I>(pub I);
+
+impl<I> fmt::Debug for SortedDebug<I>
+where
+    I: IntoIterator + Clone,
+    I::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut items: Vec<_> = self
+            .0
+            .clone()
+            .into_iter()
+            .map(|item| render(&item, f.alternate()))
+            .collect();
+        items.sort();
+        f.debug_list().entries(items.iter().map(Raw)).finish()
+    }
+}
+
+/// Renders the entries of a map like a `Debug` map, sorted by the `Debug`
+/// rendering of their keys, and then of their values. `I` is typically a
+/// reference to the map, iterating over `(key, value)` pairs.
+#[derive(Clone, Copy)]
+pub struct SortedDebugMap<I>(pub I);
+
+impl<I, K, V> fmt::Debug for SortedDebugMap<I>
+where
+    I: IntoIterator<Item = (K, V)> + Clone,
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut entries: Vec<_> = self
+            .0
+            .clone()
+            .into_iter()
+            .map(|(key, value)| (render(&key, f.alternate()), render(&value, f.alternate())))
+            .collect();
+        entries.sort();
+        f.debug_map()
+            .entries(entries.iter().map(|(key, value)| (Raw(key), Raw(value))))
+            .finish()
+    }
+}
+
+fn render(item: &impl fmt::Debug, alternate: bool) -> String {
+    if alternate {
+        format!("{item:#?}")
+    } else {
+        format!("{item:?}")
+    }
+}
+
+/// Writes an item that was already rendered.
+struct Raw<'a>(&'a String);
+
+impl fmt::Debug for Raw<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        collections::{DynHashMap, DynHashSet},
+        KeyObj,
+    };
+
+    trait Key: KeyObj + fmt::Debug {}
+    impl<T: KeyObj + fmt::Debug> Key for T {}
+
+    #[test]
+    fn deterministic_order() {
+        let mut map = DynHashMap::<dyn Key, u8>::new();
+        for (i, key) in ["b", "c", "a"].into_iter().enumerate() {
+            map.insert(Box::new(key), i as u8);
+        }
+        map.insert(Box::new(3), 3);
+        assert_eq!(
+            format!("{:?}", SortedDebugMap(&map)),
+            r#"{"a": 2, "b": 0, "c": 1, 3: 3}"#
+        );
+        assert_eq!(
+            format!(
+                "{:?}",
+                SortedDebug(
+                    &map.into_iter()
+                        .map(|(key, _)| key)
+                        .collect::<DynHashSet<_>>()
+                )
+            ),
+            r#"["a", "b", "c", 3]"#
+        );
+        assert_eq!(
+            format!("{:#?}", SortedDebug([2, 1])),
+            "[\n    1,\n    2,\n]"
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Deterministic hashing that produces the same result across processes,
+//! runs, and platforms.
+//!
+//! The hash of a value depends on two things: the data fed to the hasher by
+//! the value's `Hash` implementation, and the hashing algorithm. This module
+//! fixes the algorithm to SipHash-1-3 with zero keys, and encodes integers as
+//! little-endian bytes, with `usize` and `isize` widened to 64 bits, so the
+//! output does not depend on the platform. The algorithm and encoding are
+//! identified by [`STABLE_HASH_VERSION`], which will be incremented if either
+//! ever changes. [`StableHasher128`] uses the same encoding with the 128-bit
+//! variant of SipHash-1-3, for content addresses and other hashes that must
+//! practically never collide.
+//!
+//! The data fed to the hasher is up to each `Hash` implementation, so hashes
+//! are only as stable as the implementations of the hashed types. Types whose
+//! implementations are derived from primitives and strings are typically
+//! stable, but the standard library does not guarantee this across Rust
+//! versions.
+
+use core::hash::Hasher;
+
+use crate::{
+    hasher::{FinishableHasher, Hasher128, SipHasher128, SipHasher13},
+    HashObj,
+};
+
+/// Version of the algorithm and encoding used by [`StableHasher`].
+pub const STABLE_HASH_VERSION: u32 = 1;
+
+/// Hashes an object with [`StableHasher`].
+pub fn stable_hash_object(obj: &dyn HashObj) -> u64 {
+    FinishableHasher::new(StableHasher::new())
+        .write_object(obj)
+        .finish()
+}
+
+/// Hashes an object with [`StableHasher128`], for content addresses and other
+/// persisted hashes that must practically never collide.
+pub fn stable_hash_object128(obj: &dyn HashObj) -> u128 {
+    FinishableHasher::new(StableHasher128::new())
+        .write_object(obj)
+        .finish128()
+}
+
+/// SipHash-1-3 hasher with platform-independent integer encoding. See the
+/// [module documentation](self) for its stability guarantees.
+#[derive(Clone, Debug, Default)]
+pub struct StableHasher(SipHasher13);
+
+/// [`StableHasher`] with a 128-bit output, from `SipHasher128`.
+#[derive(Clone, Debug, Default)]
+pub struct StableHasher128(SipHasher128);
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self::with_keys(0, 0)
+    }
+
+    /// Creates a hasher seeded with the given keys. Hashes are only
+    /// reproducible by hashers with the same keys.
+    pub fn with_keys(k0: u64, k1: u64) -> Self {
+        Self(SipHasher13::new_with_keys(k0, k1))
+    }
+}
+
+impl StableHasher128 {
+    pub fn new() -> Self {
+        Self::with_keys(0, 0)
+    }
+
+    /// Creates a hasher seeded with the given keys. Hashes are only
+    /// reproducible by hashers with the same keys.
+    pub fn with_keys(k0: u64, k1: u64) -> Self {
+        Self(SipHasher128::new_with_keys(k0, k1))
+    }
+}
+
+/// Implements `Hasher` with the stable encoding of integers for a wrapper of
+/// a SipHash hasher.
+macro_rules! impl_stable_hasher {
+    ($Type:ty) => {
+        impl Hasher for $Type {
+            fn write(&mut self, bytes: &[u8]) {
+                self.0.write(bytes);
+            }
+
+            fn write_u16(&mut self, i: u16) {
+                self.write(&i.to_le_bytes());
+            }
+
+            fn write_u32(&mut self, i: u32) {
+                self.write(&i.to_le_bytes());
+            }
+
+            fn write_u64(&mut self, i: u64) {
+                self.write(&i.to_le_bytes());
+            }
+
+            fn write_u128(&mut self, i: u128) {
+                self.write(&i.to_le_bytes());
+            }
+
+            fn write_usize(&mut self, i: usize) {
+                self.write_u64(i as u64);
+            }
+
+            fn write_isize(&mut self, i: isize) {
+                self.write_u64(i as i64 as u64);
+            }
+
+            fn finish(&self) -> u64 {
+                self.0.finish()
+            }
+        }
+    };
+}
+
+impl_stable_hasher!(StableHasher);
+impl_stable_hasher!(StableHasher128);
+
+impl Hasher128 for StableHasher128 {
+    fn finish128(&self) -> u128 {
+        self.0.finish128()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn matches_reference_siphash13() {
+        // std's DefaultHasher is currently SipHash-1-3 with zero keys
+        for input in [&b""[..], b"a", b"hello world", b"0123456789abcdefXYZ"] {
+            let mut stable = StableHasher::new();
+            stable.write(input);
+            let mut reference = std::collections::hash_map::DefaultHasher::new();
+            reference.write(input);
+            assert_eq!(stable.finish(), reference.finish());
+        }
+    }
+
+    #[test]
+    fn stable_hash_values() {
+        // These must never change without incrementing STABLE_HASH_VERSION.
+        assert_eq!(STABLE_HASH_VERSION, 1);
+        assert_eq!(stable_hash_object(&()), 15130871412783076140);
+        assert_eq!(stable_hash_object(&0u8), 7541581120933061747);
+        assert_eq!(stable_hash_object(&1usize), 2206609067086327257);
+        assert_eq!(stable_hash_object(&1u64), stable_hash_object(&1usize));
+        assert_eq!(stable_hash_object(&"hello world"), 8170069951894177743);
+        assert_eq!(
+            stable_hash_object(&(1u32, "a", vec![2i16])),
+            2764190751018076560
+        );
+    }
+
+    #[test]
+    fn stable_hash128_values() {
+        // These must never change without incrementing STABLE_HASH_VERSION.
+        assert_eq!(
+            stable_hash_object128(&()),
+            246054486573677404645197968742568014861
+        );
+        assert_eq!(
+            stable_hash_object128(&"hello world"),
+            308140836635290096798880599757125170523
+        );
+        assert_eq!(stable_hash_object128(&1u64), stable_hash_object128(&1usize));
+        assert_ne!(
+            stable_hash_object128(&"hello world") as u64,
+            stable_hash_object(&"hello world")
+        );
+    }
+}
@@ -0,0 +1,213 @@
+use core::{
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+};
+
+use crate::{EqObj, HashObj, PartialEqObj};
+
+/// Wrapper that includes the concrete type of the contained object in its
+/// `Hash` and `PartialEq` implementations.
+///
+/// The `TypeId` of the erased value is hashed before the value itself, and two
+/// values are only equal when they have the same concrete type, even if a
+/// cross-type comparator is registered for them. This keeps values of
+/// different types that happen to produce the same hash stream from colliding
+/// in hashed collections.
+#[derive(Clone, Copy, Debug)]
+pub struct TypeTagged<T>(pub T);
+
+impl<T> TypeTagged<T> {
+    /// Typically, you should just use TypeTagged(item). This method is for
+    /// compatibility with type aliases.
+    pub fn new(item: T) -> Self {
+        TypeTagged(item)
+    }
+}
+
+impl<T> Deref for TypeTagged<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for TypeTagged<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T, X> Hash for TypeTagged<T>
+where
+    T: Deref<Target = X>,
+    X: HashObj + PartialEqObj + ?Sized,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let target: &X = &self.0;
+        target.as_any().type_id().hash(state);
+        target.hash_object(state);
+    }
+}
+
+impl<T, X> PartialEq for TypeTagged<T>
+where
+    T: Deref<Target = X>,
+    X: PartialEqObj + ?Sized,
+{
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b): (&X, &X) = (&self.0, &other.0);
+        a.as_any().type_id() == b.as_any().type_id() && a.eq_object(b.as_partial_eq_object())
+    }
+}
+
+impl<T, X> Eq for TypeTagged<T>
+where
+    T: Deref<Target = X>,
+    X: EqObj + ?Sized,
+{
+}
+
+/// Wrapper like `TypeTagged`, which hashes the stable fingerprint registered
+/// for the concrete type with `registry::try_register_fingerprinted` instead
+/// of its `TypeId`, so a `StableHasher` gives the same hash in every build,
+/// as long as the value's own `Hash` implementation is stable.
+///
+/// Types without a fingerprint hash their `TypeId`, like `TypeTagged`, so
+/// register every type whose hash is persisted or shared between processes.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct FingerprintTagged<T>(pub T);
+
+#[cfg(feature = "std")]
+impl<T> FingerprintTagged<T> {
+    pub fn new(item: T) -> Self {
+        FingerprintTagged(item)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Deref for FingerprintTagged<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> DerefMut for FingerprintTagged<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, X> Hash for FingerprintTagged<T>
+where
+    T: Deref<Target = X>,
+    X: HashObj + PartialEqObj + ?Sized,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let target: &X = &self.0;
+        match crate::registry::registered_fingerprint(target.as_any()) {
+            Some(fingerprint) => fingerprint.hash(state),
+            None => target.as_any().type_id().hash(state),
+        }
+        target.hash_object(state);
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, X> PartialEq for FingerprintTagged<T>
+where
+    T: Deref<Target = X>,
+    X: PartialEqObj + ?Sized,
+{
+    fn eq(&self, other: &Self) -> bool {
+        TypeTagged(&*self.0) == TypeTagged(&*other.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T, X> Eq for FingerprintTagged<T>
+where
+    T: Deref<Target = X>,
+    X: EqObj + ?Sized,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::hash_map::DefaultHasher, hash::Hasher};
+
+    use super::*;
+
+    #[derive(Hash, PartialEq, Eq)]
+    struct A(u32);
+
+    #[derive(Hash, PartialEq, Eq)]
+    struct B(u32);
+
+    fn hash<T: Hash>(t: T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        t.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn hash_includes_type() {
+        let a: Box<dyn HashObj> = Box::new(A(1));
+        let b: Box<dyn HashObj> = Box::new(B(1));
+        assert_eq!(hash(&*a), hash(&*b));
+
+        trait Key: HashObj + EqObj {}
+        impl<T: Hash + Eq + 'static> Key for T {}
+        let a = TypeTagged(Box::new(A(1)) as Box<dyn Key>);
+        let b = TypeTagged(Box::new(B(1)) as Box<dyn Key>);
+        assert_ne!(hash(&a), hash(&b));
+        assert_eq!(hash(&a), hash(TypeTagged(Box::new(A(1)) as Box<dyn Key>)));
+    }
+
+    #[test]
+    fn eq_requires_same_type() {
+        let a = TypeTagged(Box::new(A(1)) as Box<dyn EqObj>);
+        assert!(a == TypeTagged(Box::new(A(1)) as Box<dyn EqObj>));
+        assert!(a != TypeTagged(Box::new(A(2)) as Box<dyn EqObj>));
+        assert!(a != TypeTagged(Box::new(B(1)) as Box<dyn EqObj>));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn stable_fingerprints() {
+        use crate::{registry, Error, KeyObj};
+
+        #[derive(Hash, PartialEq, Eq)]
+        struct Tagged(u32);
+        #[derive(Hash, PartialEq, Eq)]
+        struct Untagged(u32);
+        #[derive(Hash, PartialEq, Eq)]
+        struct C(u32);
+
+        let fingerprint = registry::try_register_fingerprinted::<Tagged>("tagged-a", 1).unwrap();
+        assert_eq!(fingerprint, registry::fingerprint("tagged-a", 1));
+        assert_ne!(fingerprint, registry::fingerprint("tagged-a", 2));
+        assert_eq!(
+            registry::fingerprinted_type(fingerprint),
+            Some(core::any::TypeId::of::<Tagged>())
+        );
+        assert!(matches!(
+            registry::try_register_fingerprinted::<C>("tagged-a", 1),
+            Err(Error::FingerprintCollision { .. })
+        ));
+        assert_eq!(registry::registered_fingerprint(&C(1)), None);
+
+        let tagged = |a: Tagged| FingerprintTagged(Box::new(a) as Box<dyn KeyObj>);
+        let mut expected = DefaultHasher::new();
+        fingerprint.hash(&mut expected);
+        Tagged(1).hash(&mut expected);
+        assert_eq!(hash(tagged(Tagged(1))), expected.finish());
+        assert!(tagged(Tagged(1)) == tagged(Tagged(1)));
+        assert!(tagged(Tagged(1)) != FingerprintTagged(Box::new(Untagged(1)) as Box<dyn KeyObj>));
+    }
+}
@@ -0,0 +1,291 @@
+//! Checkers for the properties the object-safe traits rely on, so a crate can
+//! validate its own trait objects, or the types it erases behind them, with
+//! one call in its tests.
+//!
+//! ```rust ignore
+//! let values: Vec<Box<dyn MyKey>> = vec![Box::new(1), Box::new("a"), Box::new(1u8)];
+//! testing::check_hash_eq_consistency(&values);
+//! testing::check_erased_agreement(&[1, 2, 3]);
+//! ```
+//!
+//! Every checker takes a slice of pointers to the objects under test, such as
+//! `&[Box<dyn MyKey>]` or `&[&dyn MyKey]`, compares every pair of them, and
+//! panics with the index of the offending values if a property is violated.
+
+use std::{
+    cmp::Ordering,
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use crate::{hasher::FinishableHasher, HashObj, OrdObj, PartialEqObj};
+
+fn hash_object<X: HashObj + ?Sized>(value: &X) -> u64 {
+    FinishableHasher::new(DefaultHasher::new())
+        .write_object(value)
+        .finish()
+}
+
+/// Checks that equality through `eq_object` is symmetric, and that values
+/// which are equal have the same hash, as `HashMap` requires.
+pub fn check_hash_eq_consistency<P, X>(values: &[P])
+where
+    P: Deref<Target = X>,
+    X: HashObj + PartialEqObj + ?Sized,
+{
+    for (i, a) in values.iter().enumerate() {
+        for (j, b) in values.iter().enumerate() {
+            let (a, b): (&X, &X) = (a, b);
+            let eq = a.eq_object(b.as_partial_eq_object());
+            assert_eq!(
+                eq,
+                b.eq_object(a.as_partial_eq_object()),
+                "equality of values {i} and {j} is not symmetric"
+            );
+            if eq {
+                assert_eq!(
+                    hash_object(a),
+                    hash_object(b),
+                    "values {i} and {j} are equal but hash differently"
+                );
+            }
+        }
+    }
+}
+
+/// Checks that `cmp_object` is a total order: antisymmetric, transitive,
+/// equal exactly when `eq_object` is, and agreeing with `partial_cmp_object`.
+pub fn check_ord_total<P, X>(values: &[P])
+where
+    P: Deref<Target = X>,
+    X: OrdObj + ?Sized,
+{
+    let cmp = |a: &P, b: &P| -> Ordering {
+        let (a, b): (&X, &X) = (a, b);
+        a.cmp_object(b.as_ord_object())
+    };
+    for (i, a) in values.iter().enumerate() {
+        for (j, b) in values.iter().enumerate() {
+            let (x, y): (&X, &X) = (a, b);
+            let ordering = cmp(a, b);
+            assert_eq!(
+                ordering,
+                cmp(b, a).reverse(),
+                "order of values {i} and {j} is not antisymmetric"
+            );
+            assert_eq!(
+                ordering == Ordering::Equal,
+                x.eq_object(y.as_partial_eq_object()),
+                "order of values {i} and {j} disagrees with equality"
+            );
+            assert_eq!(
+                Some(ordering),
+                x.partial_cmp_object(y.as_partial_ord_object()),
+                "order of values {i} and {j} disagrees with the partial order"
+            );
+            for (k, c) in values.iter().enumerate() {
+                if ordering != Ordering::Greater && cmp(b, c) != Ordering::Greater {
+                    assert_ne!(
+                        cmp(a, c),
+                        Ordering::Greater,
+                        "order of values {i}, {j} and {k} is not transitive"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Checks that erasing values of type `T` preserves their behavior: comparing
+/// and hashing them through `dyn OrdObj` and `dyn HashObj` gives the same
+/// results, and the same hash stream, as using `T` directly.
+pub fn check_erased_agreement<T: Hash + Ord + 'static>(values: &[T]) {
+    for (i, a) in values.iter().enumerate() {
+        assert_eq!(
+            HasherSpy::of(a).calls(),
+            HasherSpy::of_object(a.as_hash_object()).calls(),
+            "value {i} writes a different hash stream when erased"
+        );
+        for (j, b) in values.iter().enumerate() {
+            let (x, y): (&dyn OrdObj, &dyn OrdObj) = (a, b);
+            assert_eq!(
+                a == b,
+                x.eq_object(y.as_partial_eq_object()),
+                "equality of values {i} and {j} changes when erased"
+            );
+            assert_eq!(
+                a.cmp(b),
+                x.cmp_object(y),
+                "order of values {i} and {j} changes when erased"
+            );
+        }
+    }
+}
+
+/// One call that a `HasherSpy` recorded.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HashWrite {
+    Bytes(Vec<u8>),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    Usize(usize),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    Isize(isize),
+}
+
+/// Hasher that records the exact sequence of `write*` calls it receives, so a
+/// test can assert that two values feed a hasher the same stream, such as a
+/// value and the same value behind `dyn HashObj`.
+///
+/// ```rust ignore
+/// assert_eq!(HasherSpy::of(&value).calls(), HasherSpy::of_object(&value as &dyn MyKey).calls());
+/// ```
+///
+/// `finish` hashes the recorded calls, so it is consistent with them, but it
+/// is not meant to be a good hash function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HasherSpy {
+    calls: Vec<HashWrite>,
+}
+
+impl HasherSpy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records how `value` hashes.
+    pub fn of<T: Hash + ?Sized>(value: &T) -> Self {
+        let mut spy = Self::new();
+        value.hash(&mut spy);
+        spy
+    }
+
+    /// Records how `object` hashes through `hash_object`.
+    pub fn of_object<X: HashObj + ?Sized>(object: &X) -> Self {
+        let mut spy = Self::new();
+        object.hash_object(&mut spy);
+        spy
+    }
+
+    pub fn calls(&self) -> &[HashWrite] {
+        &self.calls
+    }
+
+    pub fn into_calls(self) -> Vec<HashWrite> {
+        self.calls
+    }
+
+    /// Concatenates the bytes of every recorded call, in native byte order,
+    /// which is what a hasher that only implements `write` would see.
+    pub fn bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for call in &self.calls {
+            match call {
+                HashWrite::Bytes(b) => bytes.extend_from_slice(b),
+                HashWrite::U8(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+                HashWrite::U16(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+                HashWrite::U32(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+                HashWrite::U64(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+                HashWrite::U128(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+                HashWrite::Usize(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+                HashWrite::I8(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+                HashWrite::I16(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+                HashWrite::I32(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+                HashWrite::I64(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+                HashWrite::I128(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+                HashWrite::Isize(x) => bytes.extend_from_slice(&x.to_ne_bytes()),
+            }
+        }
+        bytes
+    }
+}
+
+macro_rules! record {
+    ($($method:ident($T:ty) => $Variant:ident),*) => {$(
+        fn $method(&mut self, value: $T) {
+            self.calls.push(HashWrite::$Variant(value));
+        }
+    )*};
+}
+
+impl Hasher for HasherSpy {
+    fn finish(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.calls.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.calls.push(HashWrite::Bytes(bytes.to_vec()));
+    }
+
+    record! {
+        write_u8(u8) => U8,
+        write_u16(u16) => U16,
+        write_u32(u32) => U32,
+        write_u64(u64) => U64,
+        write_u128(u128) => U128,
+        write_usize(usize) => Usize,
+        write_i8(i8) => I8,
+        write_i16(i16) => I16,
+        write_i32(i32) => I32,
+        write_i64(i64) => I64,
+        write_i128(i128) => I128,
+        write_isize(isize) => Isize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn consistent_values_pass() {
+        let values: Vec<Box<dyn OrdObj>> =
+            vec![Box::new((1,)), Box::new("a"), Box::new('a'), Box::new((2,))];
+        check_ord_total(&values);
+        let keys: Vec<&dyn crate::KeyObj> = vec![&(1,), &"a", &'a', &(1,)];
+        check_hash_eq_consistency(&keys);
+        check_erased_agreement(&["b", "a", "c"]);
+    }
+
+    #[test]
+    fn spy_records_calls() {
+        let spy = HasherSpy::of(&(1u8, "ab"));
+        assert_eq!(
+            spy.calls(),
+            [
+                HashWrite::U8(1),
+                HashWrite::Bytes(b"ab".to_vec()),
+                HashWrite::U8(0xff)
+            ]
+        );
+        assert_eq!(spy.bytes(), [1, b'a', b'b', 0xff]);
+        let object: &dyn HashObj = &(1u8, "ab");
+        assert_eq!(HasherSpy::of_object(object), spy);
+        assert_eq!(HasherSpy::of(object).finish(), spy.finish());
+    }
+
+    #[test]
+    #[should_panic(expected = "values 0 and 1 are equal but hash differently")]
+    fn inconsistent_hash_fails() {
+        #[allow(clippy::derived_hash_with_manual_eq)]
+        #[derive(Hash)]
+        struct Loose(u32);
+        impl PartialEq for Loose {
+            fn eq(&self, _: &Self) -> bool {
+                true
+            }
+        }
+        let keys: [&Loose; 2] = [&Loose(1), &Loose(2)];
+        check_hash_eq_consistency(&keys);
+    }
+}
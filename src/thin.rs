@@ -0,0 +1,152 @@
+use alloc::boxed::Box;
+use core::{
+    hash::{Hash, Hasher},
+    ops::Deref,
+    ptr::{self, NonNull},
+};
+
+use crate::{eq_or_same, KeyObj};
+
+/// Key type like `DynKey`, which is a thin pointer: the vtable of the value
+/// is stored in its allocation, in front of the value, rather than next to
+/// the pointer. This makes each key half the size of a `Box<dyn KeyObj>`, so
+/// more of them fit in each cache line of a `HashSet<ThinKey>`, at the cost of
+/// reading the allocation to find the vtable.
+///
+/// ```rust ignore
+/// let mut set = HashSet::new();
+/// set.insert(ThinKey::new(42u64));
+/// assert!(set.contains(&ThinKey::new(42u64)));
+/// assert_eq!(size_of::<ThinKey>(), size_of::<usize>());
+/// ```
+pub struct ThinKey(NonNull<Header>);
+
+/// Start of the allocation of every `ThinKey`.
+struct Header {
+    /// Views the allocation as the value it holds.
+    view: unsafe fn(NonNull<Header>) -> *const dyn KeyObj,
+    /// Drops the value and frees the allocation.
+    drop: unsafe fn(NonNull<Header>),
+}
+
+/// The allocation of a `ThinKey` holding a `T`.
+#[repr(C)]
+struct Block<T> {
+    header: Header,
+    value: T,
+}
+
+/// # Safety
+///
+/// `header` must point to the header of a live `Block<T>`.
+unsafe fn view<T: KeyObj>(header: NonNull<Header>) -> *const dyn KeyObj {
+    ptr::addr_of!((*header.cast::<Block<T>>().as_ptr()).value)
+}
+
+/// # Safety
+///
+/// `header` must point to the header of a live `Block<T>`, allocated by
+/// `ThinKey::new`, which must not be used afterwards.
+unsafe fn drop<T>(header: NonNull<Header>) {
+    core::mem::drop(Box::from_raw(header.cast::<Block<T>>().as_ptr()));
+}
+
+// SAFETY: `ThinKey` owns a value that implements `KeyObj`, which requires
+// `Send` and `Sync`.
+unsafe impl Send for ThinKey {}
+unsafe impl Sync for ThinKey {}
+
+impl ThinKey {
+    pub fn new<T: Hash + Eq + Send + Sync + 'static>(value: T) -> Self {
+        let block = Box::new(Block {
+            header: Header {
+                view: view::<T>,
+                drop: drop::<T>,
+            },
+            value,
+        });
+        // The header is the first field of a `repr(C)` struct, so it starts
+        // at the start of the block.
+        ThinKey(NonNull::from(Box::leak(block)).cast())
+    }
+
+    fn get(&self) -> &dyn KeyObj {
+        // SAFETY: The header always points to the start of a block that was
+        // created along with it by `new`, which is only dropped with `self`.
+        unsafe { &*(self.0.as_ref().view)(self.0) }
+    }
+}
+
+impl Drop for ThinKey {
+    fn drop(&mut self) {
+        // SAFETY: `drop` was created for the block the header starts, which is
+        // never used again.
+        unsafe { (self.0.as_ref().drop)(self.0) }
+    }
+}
+
+impl Deref for ThinKey {
+    type Target = dyn KeyObj;
+
+    fn deref(&self) -> &Self::Target {
+        self.get()
+    }
+}
+
+impl PartialEq for ThinKey {
+    fn eq(&self, other: &Self) -> bool {
+        eq_or_same(self.get(), other.get())
+    }
+}
+
+impl Eq for ThinKey {}
+
+impl Hash for ThinKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.get().hash(state);
+    }
+}
+
+crate::dyn_key::impl_key_accessors!(ThinKey);
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::{hash_map::RandomState, HashSet},
+        hash::BuildHasher,
+        mem::size_of,
+        sync::Arc,
+    };
+
+    use super::*;
+
+    #[test]
+    fn thin_keys() {
+        assert_eq!(size_of::<ThinKey>(), size_of::<usize>());
+        assert_eq!(size_of::<Option<ThinKey>>(), size_of::<usize>());
+
+        let mut set = HashSet::new();
+        assert!(set.insert(ThinKey::new(String::from("key"))));
+        assert!(set.insert(ThinKey::new(3u8)));
+        assert!(!set.insert(ThinKey::new(String::from("key"))));
+        assert!(set.contains(&ThinKey::new(3u8)));
+        assert!(!set.contains(&ThinKey::new((3u8,))));
+
+        let key = ThinKey::new(7u64);
+        let state = RandomState::new();
+        assert_eq!(state.hash_one(&key), state.hash_one(7u64));
+        assert_eq!(key.downcast_ref::<u64>(), Some(&7));
+        assert_eq!(format!("{key:?}"), "ThinKey(\"u64\")");
+    }
+
+    #[test]
+    fn drops_value() {
+        let shared = Arc::new(());
+        let key = ThinKey::new(Arc::clone(&shared));
+        assert_eq!(Arc::strong_count(&shared), 2);
+        std::thread::spawn(move || core::mem::drop(key))
+            .join()
+            .unwrap();
+        assert_eq!(Arc::strong_count(&shared), 1);
+    }
+}
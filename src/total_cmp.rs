@@ -0,0 +1,98 @@
+use core::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
+
+/// Wrapper for floating point numbers that implements `Eq`, `Ord` and `Hash`
+/// using `total_cmp`, so floats can implement `OrdObj` and `HashObj` and be
+/// stored in ordered or hashed collections of trait objects.
+///
+/// Two values are equal only if they have the same bit pattern, so `0.0` and
+/// `-0.0` are distinct, and a NaN is equal to itself.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TotalCmp<F>(pub F);
+
+/// `f64` ordered by `total_cmp`.
+pub type TotalOrdF64 = TotalCmp<f64>;
+
+/// `f32` ordered by `total_cmp`.
+pub type TotalOrdF32 = TotalCmp<f32>;
+
+macro_rules! impl_total_cmp {
+    ($($F:ty),*) => {$(
+        impl PartialEq for TotalCmp<$F> {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.to_bits() == other.0.to_bits()
+            }
+        }
+
+        impl Eq for TotalCmp<$F> {}
+
+        impl PartialOrd for TotalCmp<$F> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for TotalCmp<$F> {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.total_cmp(&other.0)
+            }
+        }
+
+        impl From<$F> for TotalCmp<$F> {
+            fn from(value: $F) -> Self {
+                TotalCmp(value)
+            }
+        }
+
+        impl Hash for TotalCmp<$F> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.to_bits().hash(state);
+            }
+        }
+    )*};
+}
+
+impl_total_cmp!(f32, f64);
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use crate::*;
+
+    #[test]
+    fn floats_in_ordered_set() {
+        let set: BTreeSet<Obj<Box<dyn OrdObj>>> = [f64::NAN, 1.0, -0.0, 0.0, f64::NAN]
+            .into_iter()
+            .map(|f| Obj(TotalCmp(f).to_ord_object()))
+            .collect();
+        assert_eq!(set.len(), 4);
+        let floats: Vec<f64> = set
+            .iter()
+            .map(|x| (***x).as_any().downcast_ref::<TotalCmp<f64>>().unwrap().0)
+            .collect();
+        assert_eq!(floats[..3], [-0.0, 0.0, 1.0]);
+        assert!(floats[3].is_nan());
+    }
+
+    #[test]
+    fn sorting_is_deterministic() {
+        let objs = || -> Vec<Box<dyn OrdObj>> {
+            vec![
+                Box::new(TotalOrdF32::from(2.0)),
+                Box::new(TotalOrdF64::from(f64::NAN)),
+                Box::new(TotalOrdF64::from(-1.0)),
+                Box::new(TotalOrdF32::from(f32::NEG_INFINITY)),
+                Box::new(TotalOrdF64::from(3.0)),
+            ]
+        };
+        let mut forward = objs();
+        let mut reversed = objs();
+        reversed.reverse();
+        sort_objs(&mut forward);
+        sort_objs(&mut reversed);
+        assert!(forward.iter().zip(&reversed).all(|(a, b)| **a == **b));
+    }
+}
@@ -0,0 +1,86 @@
+use core::{fmt, ops::Deref};
+
+use crate::{Obj, PartialEqObj};
+
+/// Strict equality for trait objects, which reports an error instead of
+/// returning false when the concrete types differ.
+///
+/// This is automatically implemented for every `PartialEqObj`, including trait
+/// objects like `dyn MyTrait` where `MyTrait` extends `PartialEqObj`. Since the
+/// types are checked before any comparator is invoked, comparators registered
+/// in the [`registry`](crate::registry) are never used.
+pub trait TryEqObj: PartialEqObj {
+    fn try_eq_object(&self, other: &dyn PartialEqObj) -> Result<bool, TypeMismatch>;
+}
+
+impl<T: PartialEqObj + ?Sized> TryEqObj for T {
+    fn try_eq_object(&self, other: &dyn PartialEqObj) -> Result<bool, TypeMismatch> {
+        if self.as_any().type_id() == other.as_any().type_id() {
+            Ok(self.eq_object(other))
+        } else {
+            Err(TypeMismatch {
+                left: self.type_name(),
+                right: other.type_name(),
+            })
+        }
+    }
+}
+
+impl<T, X> Obj<T>
+where
+    T: Deref<Target = X>,
+    X: PartialEqObj + ?Sized,
+{
+    /// Compares the contained objects like `==`, except that an error is
+    /// returned if their concrete types differ.
+    pub fn try_eq(&self, other: &Self) -> Result<bool, TypeMismatch> {
+        let (a, b): (&X, &X) = (&self.0, &other.0);
+        a.try_eq_object(b.as_partial_eq_object())
+    }
+}
+
+/// Error returned when two objects with different concrete types are compared
+/// strictly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TypeMismatch {
+    pub left: &'static str,
+    pub right: &'static str,
+}
+
+impl fmt::Display for TypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cannot compare objects of different types: {} and {}",
+            self.left, self.right
+        )
+    }
+}
+
+impl core::error::Error for TypeMismatch {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn try_eq_object() {
+        let one: &dyn PartialEqObj = &1;
+        assert_eq!(one.try_eq_object(&1), Ok(true));
+        assert_eq!(one.try_eq_object(&2), Ok(false));
+        assert_eq!(
+            one.try_eq_object(&"1"),
+            Err(TypeMismatch {
+                left: "i32",
+                right: "&str"
+            })
+        );
+    }
+
+    #[test]
+    fn obj_try_eq() {
+        let one = Obj(Box::new(1) as Box<dyn PartialEqObj>);
+        assert_eq!(one.try_eq(&Obj(Box::new(1))), Ok(true));
+        assert!(one.try_eq(&Obj(Box::new(1u64))).is_err());
+    }
+}
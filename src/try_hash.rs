@@ -0,0 +1,95 @@
+use core::{
+    any::type_name,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+/// Object-safe hashing that can fail, for objects that cannot always be
+/// hashed, such as a proxy to a remote object that was detached, or a value
+/// behind a poisoned lock. The dyn collections have `try_*` methods for keys
+/// implementing it, which return the error instead of panicking inside
+/// `Hash`.
+///
+/// This is automatically implemented for every type that implements `Hash`,
+/// which never fails. Implement it by hand for the other types.
+///
+/// ```rust ignore
+/// impl TryHashObj for RemoteProxy {
+///     fn try_hash_object(&self, state: &mut dyn Hasher) -> Result<(), HashError> {
+///         let id = self.id().ok_or(HashError::new::<Self>("the proxy is detached"))?;
+///         id.hash(&mut { state });
+///         Ok(())
+///     }
+/// }
+/// ```
+///
+/// If hashing fails, part of the object may already have been written to the
+/// hasher, so the hasher must not be used for anything else.
+pub trait TryHashObj {
+    fn try_hash_object(&self, state: &mut dyn Hasher) -> Result<(), HashError>;
+}
+
+impl<T: Hash> TryHashObj for T {
+    fn try_hash_object(&self, mut state: &mut dyn Hasher) -> Result<(), HashError> {
+        self.hash(&mut state);
+        Ok(())
+    }
+}
+
+/// Error returned when an object cannot be hashed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HashError {
+    /// Name of the concrete type of the object.
+    pub type_name: &'static str,
+    /// Why the object cannot be hashed.
+    pub reason: &'static str,
+}
+
+impl HashError {
+    /// An error for an object of type `T`.
+    pub fn new<T: ?Sized>(reason: &'static str) -> Self {
+        Self {
+            type_name: type_name::<T>(),
+            reason,
+        }
+    }
+}
+
+impl fmt::Display for HashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot hash {}: {}", self.type_name, self.reason)
+    }
+}
+
+impl core::error::Error for HashError {}
+
+#[cfg(test)]
+mod test {
+    use std::collections::hash_map::DefaultHasher;
+
+    use super::*;
+
+    struct Detached;
+
+    impl TryHashObj for Detached {
+        fn try_hash_object(&self, _: &mut dyn Hasher) -> Result<(), HashError> {
+            Err(HashError::new::<Self>("detached"))
+        }
+    }
+
+    #[test]
+    fn try_hash_object() {
+        let mut hasher = DefaultHasher::new();
+        assert_eq!(5.try_hash_object(&mut hasher), Ok(()));
+        #[cfg(feature = "std")]
+        assert_eq!(hasher.finish(), crate::HashObj::hash_object_u64(&5));
+
+        let error = (&Detached as &dyn TryHashObj)
+            .try_hash_object(&mut DefaultHasher::new())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "cannot hash object_safe::try_hash::test::Detached: detached"
+        );
+    }
+}
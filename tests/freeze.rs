@@ -0,0 +1,36 @@
+//! Freezing the registry lasts for the rest of the process, so it is tested
+//! in a binary of its own rather than next to the other registry tests.
+
+use object_safe::{obj_eq, registry, Error};
+
+#[derive(PartialEq)]
+struct Meters(u32);
+
+#[derive(PartialEq)]
+struct Centimeters(u32);
+
+#[test]
+fn freeze() {
+    #[cfg(not(feature = "std"))]
+    {
+        static SLOTS: [registry::EqSlot; 4] = [const { registry::EqSlot::new() }; 4];
+        registry::provide_storage(&SLOTS);
+    }
+
+    registry::register_eq(|m: &Meters, cm: &Centimeters| m.0 * 100 == cm.0);
+    assert!(!registry::is_frozen());
+    registry::freeze();
+    assert!(registry::is_frozen());
+
+    assert_eq!(
+        registry::try_register_eq(|_: &Centimeters, _: &Meters| true),
+        Err(Error::RegistryFrozen)
+    );
+    assert_eq!(
+        registry::try_register_eq(|_: &Meters, _: &u32| true),
+        Err(Error::RegistryFrozen)
+    );
+    assert!(obj_eq(&Meters(2), &Centimeters(200)));
+    assert!(!obj_eq(&Centimeters(201), &Meters(2)));
+    assert_eq!(registry::cross_eq(&Meters(2), &2u32), None);
+}